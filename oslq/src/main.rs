@@ -1,24 +1,62 @@
 //! oslq - Command-line utility to query OSL shader parameters
 
-use clap::Parser as ClapParser;
-use oslquery_petite::OslQuery;
-use std::io::{self, IsTerminal};
+use clap::{Parser as ClapParser, ValueEnum};
+use oslquery_petite::parser::{ColorChoice, DiagnosticConfig, DiagnosticStream, ParseError};
+use oslquery_petite::text::{TextOptions, TextRole};
+use oslquery_petite::{OslQuery, SearchPaths};
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Read};
+use std::path::Path;
 use std::process;
-use std::time::Instant;
+use std::time::{Duration, Instant};
+use walkdir::WalkDir;
 use yansi::{Paint, Style};
 
+/// Invalid arguments, or a file that could not be read or parsed.
+const EXIT_ERROR: i32 = 1;
+/// The parameter given with `--param` is missing in at least one shader.
+const EXIT_PARAM_NOT_FOUND: i32 = 2;
+/// `--recursive` found no `.oso` files.
+const EXIT_NO_FILES: i32 = 3;
+
+/// The name standard input is shown with.
+const STDIN_NAME: &str = "<stdin>";
+
 #[derive(ClapParser, Debug)]
 #[command(name = "oslq")]
 #[command(about = "Query OSL shader parameters", long_about = None)]
+#[command(after_help = "Exit status:
+  0  Success
+  1  Invalid arguments, or a file could not be read or parsed
+  2  The parameter given with --param was not found
+  3  --recursive found no .oso files")]
 struct Args {
-    /// OSO files to query
+    /// OSO files to query (or directories with --recursive); standard input
+    /// is read if there are none and it is not a terminal
     files: Vec<String>,
 
+    /// Read an OSO file from standard input, shown as `<stdin>` in messages
+    #[arg(long, conflicts_with_all = ["files", "recursive"])]
+    stdin: bool,
+
+    /// Query all .oso files found in directories, recursively
+    #[arg(short, long)]
+    recursive: bool,
+
+    /// Maximum directory depth for --recursive (1 = only the directory itself)
+    #[arg(long, value_name = "N", requires = "recursive")]
+    max_depth: Option<usize>,
+
+    /// Only print the number of parameters of each shader
+    #[arg(long)]
+    count: bool,
+
     /// Show verbose output
     #[arg(short, long)]
     verbose: bool,
 
-    /// Search path for shaders (colon-separated list)
+    /// Search path for shaders (`:`/`;`-separated list; `&` inserts
+    /// $OSLQUERY_SEARCHPATH or $OSL_SHADERS, which are used if omitted)
     #[arg(short = 'p', long)]
     searchpath: Option<String>,
 
@@ -30,6 +68,15 @@ struct Args {
     #[arg(long)]
     json: bool,
 
+    /// Print the shader in another format instead of the listing
+    #[arg(long, value_name = "FORMAT")]
+    export_format: Option<ExportFormat>,
+
+    /// Write an OSL source file with the shader's interface and an empty
+    /// body to OUTPUT_FILE
+    #[arg(long, value_name = "OUTPUT_FILE")]
+    template: Option<String>,
+
     /// Show timing statistics
     #[arg(long)]
     runstats: bool,
@@ -39,510 +86,303 @@ struct Args {
     no_color: bool,
 }
 
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum ExportFormat {
+    /// Single-line OSL shader declaration
+    OslSignature,
+}
+
 fn main() {
-    let args = Args::parse();
+    // Clap exits with 2 on invalid arguments, which is taken by a missing
+    // parameter.
+    let args = Args::try_parse().unwrap_or_else(|e| {
+        let _ = e.print();
+        process::exit(if e.use_stderr() { EXIT_ERROR } else { 0 });
+    });
 
     // Disable colors if requested or if not a terminal
     if args.no_color || !io::stdout().is_terminal() {
         yansi::disable();
     }
 
-    if args.files.is_empty() {
-        eprintln!("Error: No input files specified");
-        eprintln!("Usage: oslq [OPTIONS] <FILES>...");
-        process::exit(1);
-    }
-
-    let searchpath = args.searchpath.as_deref().unwrap_or("");
-
-    for filename in &args.files {
-        let start_time = if args.runstats {
-            Some(Instant::now())
-        } else {
-            None
-        };
-
-        match OslQuery::open_with_searchpath(filename, searchpath) {
+    if args.stdin || (args.files.is_empty() && !args.recursive && !io::stdin().is_terminal()) {
+        let (result, elapsed, source) = parse_stdin();
+        // Without `--stdin`, an empty input more likely means missing files.
+        if !args.stdin && source.as_deref() == Some("") {
+            exit_no_input();
+        }
+        match &result {
             Ok(query) => {
-                if args.json {
-                    print_json(&query, &args);
-                } else {
-                    print_query(&query, &args);
-                }
-
-                if let Some(start) = start_time {
-                    let elapsed = start.elapsed();
-                    eprintln!("Parse time: {:.3}ms", elapsed.as_secs_f64() * 1000.0);
+                if print_shader(STDIN_NAME, query, elapsed, &args) {
+                    process::exit(EXIT_PARAM_NOT_FOUND);
                 }
             }
             Err(e) => {
-                eprintln!("Error reading {}: {}", filename, e);
-                process::exit(1);
+                print_error(STDIN_NAME, source.as_deref(), e, &args);
+                process::exit(EXIT_ERROR);
             }
         }
+        return;
     }
-}
 
-fn print_json(query: &OslQuery, args: &Args) {
-    #[cfg(feature = "serde")]
-    {
-        use serde_json::json;
+    if args.files.is_empty() {
+        exit_no_input();
+    }
 
-        let output = if let Some(ref param_name) = args.param {
-            if let Some(param) = query.param_by_name(param_name) {
-                json!(param)
-            } else {
-                eprintln!("Parameter '{}' not found", param_name);
-                process::exit(1);
+    let search_paths = SearchPaths::parse_or_env(args.searchpath.as_deref().unwrap_or(""));
+
+    let mut files = Vec::new();
+    for filename in &args.files {
+        if Path::new(filename).is_dir() {
+            if !args.recursive {
+                eprintln!(
+                    "Error: {} is a directory (use --recursive to query the shaders in it)",
+                    filename
+                );
+                process::exit(EXIT_ERROR);
             }
+            files.extend(find_oso_files(filename, args.max_depth));
         } else {
-            json!(query)
-        };
+            files.push(filename.clone());
+        }
+    }
 
-        println!("{}", serde_json::to_string_pretty(&output).unwrap());
+    if args.recursive && files.is_empty() {
+        eprintln!("Error: No .oso files found");
+        process::exit(EXIT_NO_FILES);
     }
 
-    #[cfg(not(feature = "serde"))]
-    {
-        eprintln!("JSON output requires the 'json' feature to be enabled");
-        eprintln!("Rebuild with: cargo build --features json");
-        process::exit(1);
+    if args.template.is_some() && files.len() != 1 {
+        eprintln!("Error: --template needs exactly one shader");
+        process::exit(EXIT_ERROR);
     }
-}
 
-fn print_query(query: &OslQuery, args: &Args) {
-    // Set up color styles
-    let styles = ColorStyles {
-        keyword: Style::new().magenta().bold(),
-        type_name: Style::new().cyan(),
-        identifier: Style::new().green(),
-        value: Style::new().yellow(),
-        delimiter: Style::new().white().dim(),
-    };
+    let mut total_params = 0;
+    let mut param_not_found = false;
+    let mut failed = false;
 
-    println!(
-        "{} {} \"{}\"",
-        query.shader_type().paint(styles.keyword),
-        query.shader_name().paint(styles.identifier),
-        query.shader_name()
-    );
+    let report = OslQuery::open_many_with_search_paths(&files, &search_paths);
 
-    // Print global metadata
-    for meta in query.metadata() {
-        print_metadata(meta, "\t");
+    for file in report.files() {
+        let filename = file.path.display().to_string();
+        match &file.result {
+            Ok(query) => {
+                total_params += query.param_count();
+                param_not_found |= print_shader(&filename, query, file.elapsed, &args);
+            }
+            Err(e) => {
+                let source = file
+                    .resolved
+                    .as_deref()
+                    .and_then(|resolved| fs::read_to_string(resolved).ok());
+                print_error(&filename, source.as_deref(), e, &args);
+                // A bad shader should not end a scan of a whole library.
+                if !args.recursive {
+                    process::exit(EXIT_ERROR);
+                }
+                failed = true;
+            }
+        }
     }
 
-    // Calculate max widths for alignment
-    let mut max_name_width = 0;
-    let mut max_type_width = 0;
+    if args.runstats && report.len() > 1 {
+        eprintln!(
+            "Total time: {:.3}ms",
+            report.elapsed().as_secs_f64() * 1000.0
+        );
+    }
 
-    for param in query.params() {
-        // Pre-filter for calculating widths
-        if let Some(ref filter_name) = args.param
-            && param.name.as_str() != filter_name
-        {
-            continue;
-        }
-        max_name_width = max_name_width.max(param.name.len());
-        // For type width, include "output " prefix if needed for verbose mode
-        let type_str = param.typed_param().to_string();
-        let type_len = if args.verbose && param.is_output() {
-            type_str.len() + 7 // "output " is 7 chars
+    if args.recursive {
+        let summary = if args.count {
+            format!(
+                "Found {} .oso files with {} parameters in total",
+                files.len(),
+                total_params
+            )
         } else {
-            type_str.len()
+            format!("Found {} .oso files", files.len())
         };
-        max_type_width = max_type_width.max(type_len);
+        print_status(&summary, &args);
     }
 
-    for param in query.params() {
-        // Filter by parameter name if specified
-        if let Some(ref filter_name) = args.param
-            && param.name.as_str() != filter_name
-        {
-            continue;
-        }
-
-        let typestring = param.typed_param().to_string();
-
-        if args.verbose {
-            // Print with alignment in verbose mode too
-            print!("    \"{}\"", param.name.as_str().paint(styles.identifier));
-
-            // Add padding after the name
-            let name_padding = if max_name_width > param.name.len() {
-                " ".repeat(max_name_width - param.name.len() + 1)
-            } else {
-                " ".to_string()
-            };
-            print!("{} \"", name_padding);
-
-            // Print type with alignment
-            let type_str = if param.is_output() {
-                format!(
-                    "{} {}",
-                    "output".paint(styles.keyword),
-                    typestring.paint(styles.type_name)
-                )
-            } else {
-                format!("{}", typestring.paint(styles.type_name))
-            };
-
-            // Just print the type without internal padding
-            println!("{}\"", type_str);
-        } else {
-            // Print with column alignment
-            // Format: name type value  OR  name output type value
-
-            // Print name with padding first
-            print!(
-                "{:width$} ",
-                param.name.as_str().paint(styles.identifier),
-                width = max_name_width
-            );
-
-            // Print output keyword if needed
-            if param.is_output() {
-                print!("{} ", "output".paint(styles.keyword));
-            }
+    if failed {
+        process::exit(EXIT_ERROR);
+    }
 
-            // Print type with padding
-            let type_padding = if param.is_output() {
-                max_type_width.saturating_sub(typestring.len())
-            } else {
-                max_type_width.saturating_sub(typestring.len()) + 7 // Add 7 for "output " width
-            };
-
-            print!(
-                "{}{} ",
-                typestring.paint(styles.type_name),
-                " ".repeat(type_padding)
-            );
-        }
+    if param_not_found {
+        process::exit(EXIT_PARAM_NOT_FOUND);
+    }
+}
 
-        // Print default values based on the typed parameter
-        print_default_values(param, args.verbose, &styles);
+fn exit_no_input() -> ! {
+    eprintln!("Error: No input files specified");
+    eprintln!("Usage: oslq [OPTIONS] <FILES>...");
+    process::exit(EXIT_ERROR);
+}
 
-        if args.verbose {
-            for meta in &param.metadata {
-                print_metadata(meta, "\t\t");
-            }
+/// Read and parse the shader on standard input.
+///
+/// The time is measured from the first byte read, so it does not include
+/// waiting for the command writing it. The source is returned for showing
+/// errors, unless it could not be read.
+fn parse_stdin() -> (Result<OslQuery, ParseError>, Duration, Option<String>) {
+    let mut stdin = io::stdin().lock();
+    let mut source = String::new();
+    let start = Instant::now();
+    let read = stdin
+        .fill_buf()
+        .map(|_| Instant::now())
+        .and_then(|start| stdin.read_to_string(&mut source).map(|_| start));
+    match read {
+        Ok(start) => {
+            let result = OslQuery::from_string(&source);
+            (result, start.elapsed(), Some(source))
         }
+        Err(e) => (Err(e.into()), start.elapsed(), None),
     }
 }
 
-struct ColorStyles {
-    keyword: Style,
-    type_name: Style,
-    identifier: Style,
-    value: Style,
-    delimiter: Style, // For brackets, quotes, etc.
-}
+/// Print a shader as the options ask for; returns whether the parameter
+/// given with `--param` is missing.
+fn print_shader(filename: &str, query: &OslQuery, elapsed: Duration, args: &Args) -> bool {
+    if args.recursive {
+        print_status(
+            &format!("=== {} ===", filename.paint(Style::new().bold())),
+            args,
+        );
+    }
 
-fn print_default_values(param: &oslquery_petite::Parameter, verbose: bool, styles: &ColorStyles) {
-    use oslquery_petite::TypedParameter;
-
-    // For output parameters or closures, show <no default>
-    if param.is_output() {
-        if verbose {
-            println!(
-                "\t\tDefault value: {}{}{}",
-                " <".paint(styles.delimiter),
-                "no default".paint(styles.value),
-                ">".paint(styles.delimiter)
-            );
-        } else {
-            println!(
-                "  {}{}{}",
-                "<".paint(styles.delimiter),
-                "no default".paint(styles.value),
-                ">".paint(styles.delimiter)
-            );
-        }
-        return;
+    for warning in query.warnings() {
+        eprintln!("Warning: {}: {}", filename, warning);
     }
 
-    match param.typed_param() {
-        TypedParameter::Int { default } => {
-            if let Some(v) = default {
-                if verbose {
-                    println!("\t\tDefault value: {}", v.to_string().paint(styles.value));
-                } else {
-                    println!("   {}", v.to_string().paint(styles.value));
-                }
-            } else {
-                print_no_default(verbose, styles);
-            }
-        }
-        TypedParameter::Float { default } => {
-            if let Some(v) = default {
-                if verbose {
-                    println!("\t\tDefault value: {}", v.to_string().paint(styles.value));
-                } else {
-                    println!("   {}", v.to_string().paint(styles.value));
-                }
-            } else {
-                print_no_default(verbose, styles);
-            }
-        }
-        TypedParameter::String { default } => {
-            if let Some(s) = default {
-                if verbose {
-                    println!(
-                        "\t\tDefault value: {}{}{}",
-                        " \"".paint(styles.delimiter),
-                        escape_string(s).paint(styles.value),
-                        "\"".paint(styles.delimiter)
-                    );
-                } else {
-                    println!(
-                        "  {}{}{}",
-                        "\"".paint(styles.delimiter),
-                        escape_string(s).paint(styles.value),
-                        "\"".paint(styles.delimiter)
-                    );
-                }
-            } else {
-                print_no_default(verbose, styles);
-            }
-        }
-        TypedParameter::Color { default, .. }
-        | TypedParameter::Point { default, .. }
-        | TypedParameter::Vector { default, .. }
-        | TypedParameter::Normal { default, .. } => {
-            if let Some([x, y, z]) = default {
-                if verbose {
-                    println!(
-                        "\t\tDefault value: {}{}{}",
-                        "[".paint(styles.delimiter),
-                        format!("{} {} {}", x, y, z).paint(styles.value),
-                        "]".paint(styles.delimiter)
-                    );
-                } else {
-                    println!(
-                        "  {}{}{}",
-                        "[".paint(styles.delimiter),
-                        format!("{} {} {}", x, y, z).paint(styles.value),
-                        "]".paint(styles.delimiter)
-                    );
-                }
-            } else {
-                print_no_default(verbose, styles);
-            }
-        }
-        TypedParameter::Matrix { default } => {
-            if let Some(m) = default {
-                let vals: Vec<String> = m.iter().map(|v| v.to_string()).collect();
-                if verbose {
-                    println!(
-                        "\t\tDefault value: {}{}{}",
-                        "[".paint(styles.delimiter),
-                        vals.join(" ").paint(styles.value),
-                        "]".paint(styles.delimiter)
-                    );
-                } else {
-                    println!(
-                        "  {}{}{}",
-                        "[".paint(styles.delimiter),
-                        vals.join(" ").paint(styles.value),
-                        "]".paint(styles.delimiter)
-                    );
-                }
-            } else {
-                print_no_default(verbose, styles);
-            }
-        }
-        // Arrays
-        TypedParameter::IntArray { default, .. } | TypedParameter::IntDynamicArray { default } => {
-            if let Some(vals) = default {
-                let s: Vec<String> = vals.iter().map(|v| v.to_string()).collect();
-                if verbose {
-                    println!(
-                        "\t\tDefault value: {}{}{}",
-                        "[".paint(styles.delimiter),
-                        s.join(" ").paint(styles.value),
-                        "]".paint(styles.delimiter)
-                    );
-                } else {
-                    println!(
-                        "  {}{}{}",
-                        "[".paint(styles.delimiter),
-                        s.join(" ").paint(styles.value),
-                        "]".paint(styles.delimiter)
-                    );
-                }
-            } else {
-                print_no_default(verbose, styles);
-            }
-        }
-        TypedParameter::FloatArray { default, .. }
-        | TypedParameter::FloatDynamicArray { default } => {
-            if let Some(vals) = default {
-                let s: Vec<String> = vals.iter().map(|v| v.to_string()).collect();
-                if verbose {
-                    println!(
-                        "\t\tDefault value: {}{}{}",
-                        "[".paint(styles.delimiter),
-                        s.join(" ").paint(styles.value),
-                        "]".paint(styles.delimiter)
-                    );
-                } else {
-                    println!(
-                        "  {}{}{}",
-                        "[".paint(styles.delimiter),
-                        s.join(" ").paint(styles.value),
-                        "]".paint(styles.delimiter)
-                    );
-                }
-            } else {
-                print_no_default(verbose, styles);
-            }
-        }
-        TypedParameter::StringArray { default, .. }
-        | TypedParameter::StringDynamicArray { default } => {
-            if let Some(vals) = default {
-                let s: Vec<String> = vals
-                    .iter()
-                    .map(|v| format!("\"{}\"", escape_string(v)))
-                    .collect();
-                if verbose {
-                    println!(
-                        "\t\tDefault value: {}{}{}",
-                        "[".paint(styles.delimiter),
-                        s.join(" ").paint(styles.value),
-                        "]".paint(styles.delimiter)
-                    );
-                } else {
-                    println!(
-                        "  {}{}{}",
-                        "[".paint(styles.delimiter),
-                        s.join(" ").paint(styles.value),
-                        "]".paint(styles.delimiter)
-                    );
-                }
-            } else {
-                print_no_default(verbose, styles);
-            }
-        }
-        TypedParameter::ColorArray { default, .. }
-        | TypedParameter::PointArray { default, .. }
-        | TypedParameter::VectorArray { default, .. }
-        | TypedParameter::NormalArray { default, .. }
-        | TypedParameter::ColorDynamicArray { default, .. }
-        | TypedParameter::PointDynamicArray { default, .. }
-        | TypedParameter::VectorDynamicArray { default, .. }
-        | TypedParameter::NormalDynamicArray { default, .. } => {
-            if let Some(vals) = default {
-                let s: Vec<String> = vals
-                    .iter()
-                    .map(|[x, y, z]| format!("[{} {} {}]", x, y, z))
-                    .collect();
-                if verbose {
-                    println!(
-                        "\t\tDefault value: {}{}{}",
-                        "[".paint(styles.delimiter),
-                        s.join(" ").paint(styles.value),
-                        "]".paint(styles.delimiter)
-                    );
-                } else {
-                    println!(
-                        " {}{}{}",
-                        "[".paint(styles.delimiter),
-                        s.join(" ").paint(styles.value),
-                        "]".paint(styles.delimiter)
-                    );
-                }
-            } else {
-                print_no_default(verbose, styles);
-            }
-        }
-        TypedParameter::MatrixArray { default, .. }
-        | TypedParameter::MatrixDynamicArray { default } => {
-            if let Some(vals) = default {
-                let s: Vec<String> = vals
-                    .iter()
-                    .map(|m| {
-                        let v: Vec<String> = m.iter().map(|f| f.to_string()).collect();
-                        format!("[{}]", v.join(" "))
-                    })
-                    .collect();
-                if verbose {
-                    println!(
-                        "\t\tDefault value: {}{}{}",
-                        "[".paint(styles.delimiter),
-                        s.join(" ").paint(styles.value),
-                        "]".paint(styles.delimiter)
-                    );
-                } else {
-                    println!(
-                        " {}{}{}",
-                        "[".paint(styles.delimiter),
-                        s.join(" ").paint(styles.value),
-                        "]".paint(styles.delimiter)
-                    );
-                }
-            } else {
-                print_no_default(verbose, styles);
-            }
+    let missing_param = args
+        .param
+        .as_deref()
+        .filter(|name| query.param_by_name(name).is_none());
+
+    if let Some(name) = missing_param {
+        eprintln!("Error: Parameter '{}' not found in {}", name, filename);
+    } else if let Some(output) = &args.template {
+        if let Err(e) = fs::write(output, query.to_osl_stub()) {
+            eprintln!("Error writing {}: {}", output, e);
+            process::exit(EXIT_ERROR);
         }
-        TypedParameter::Closure { .. } => {
-            // Closures never have defaults
-            print_no_default(verbose, styles);
+    } else if args.count {
+        println!("{} parameters", query.param_count());
+    } else if let Some(format) = args.export_format {
+        match format {
+            ExportFormat::OslSignature => println!("{}", query.to_osl_signature()),
         }
+    } else if args.json {
+        print_json(query, args);
+    } else {
+        print_query(query, args);
+    }
+
+    if args.runstats {
+        eprintln!("Parse time: {:.3}ms", elapsed.as_secs_f64() * 1000.0);
     }
+
+    missing_param.is_some()
 }
 
-fn print_no_default(verbose: bool, styles: &ColorStyles) {
-    if verbose {
-        println!(
-            "\t\tDefault value: {}{}{}",
-            " <".paint(styles.delimiter),
-            "no default".paint(styles.value),
-            ">".paint(styles.delimiter)
-        );
+/// Print a line that is not part of a shader's output; with `--json` it goes
+/// to standard error so standard output stays JSON only.
+fn print_status(line: &str, args: &Args) {
+    if args.json {
+        eprintln!("{}", line);
     } else {
-        println!(
-            "  {}{}{}",
-            "<".paint(styles.delimiter),
-            "no default".paint(styles.value),
-            ">".paint(styles.delimiter)
-        );
+        println!("{}", line);
     }
 }
 
-fn print_metadata(meta: &oslquery_petite::Metadata, indent: &str) {
-    use oslquery_petite::MetadataValue;
-
-    print!("{}metadata: ", indent);
-    match &meta.value {
-        MetadataValue::Int(v) => print!("int {} = {}", meta.name, v),
-        MetadataValue::Float(v) => print!("float {} = {}", meta.name, v),
-        MetadataValue::String(v) => print!("string {} = \"{}\"", meta.name, escape_string(v)),
-        MetadataValue::IntArray(v) => {
-            print!("int[] {} =", meta.name);
-            for val in v {
-                print!(" {}", val);
-            }
-        }
-        MetadataValue::FloatArray(v) => {
-            print!("float[] {} =", meta.name);
-            for val in v {
-                print!(" {}", val);
-            }
+/// Print a parse error, showing the offending line of `source` if its
+/// location is known.
+fn print_error(filename: &str, source: Option<&str>, error: &ParseError, args: &Args) {
+    if let Some(source) = source.filter(|source| error.line_column(source).is_some()) {
+        let config = DiagnosticConfig::new()
+            .stream(DiagnosticStream::Stderr)
+            .color(if args.no_color {
+                ColorChoice::Never
+            } else {
+                ColorChoice::Auto
+            });
+        if error.print_with_config(filename, source, &config).is_ok() {
+            return;
         }
-        MetadataValue::StringArray(v) => {
-            print!("string[] {} =", meta.name);
-            for val in v {
-                print!(" \"{}\"", escape_string(val));
+    }
+    eprintln!("Error reading {}: {}", filename, error);
+}
+
+/// Find all `.oso` files below `dir`, sorted by path.
+fn find_oso_files(dir: &str, max_depth: Option<usize>) -> Vec<String> {
+    let mut walker = WalkDir::new(dir);
+    if let Some(depth) = max_depth {
+        walker = walker.max_depth(depth);
+    }
+
+    let mut files: Vec<String> = walker
+        .into_iter()
+        .filter_map(|entry| match entry {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                eprintln!("Warning: {}", e);
+                None
             }
-        }
+        })
+        .filter(|entry| {
+            entry.file_type().is_file() && entry.path().extension().is_some_and(|ext| ext == "oso")
+        })
+        .map(|entry| entry.path().display().to_string())
+        .collect();
+
+    files.sort();
+    files
+}
+
+fn print_json(query: &OslQuery, args: &Args) {
+    #[cfg(feature = "serde")]
+    {
+        use serde_json::json;
+
+        // A missing `--param` was reported before.
+        let output = match args
+            .param
+            .as_deref()
+            .and_then(|name| query.param_by_name(name))
+        {
+            Some(param) => json!(param),
+            None => json!(query),
+        };
+
+        println!("{}", serde_json::to_string_pretty(&output).unwrap());
+    }
+
+    #[cfg(not(feature = "serde"))]
+    {
+        eprintln!("JSON output requires the 'json' feature to be enabled");
+        eprintln!("Rebuild with: cargo build --features json");
+        process::exit(EXIT_ERROR);
     }
-    println!();
 }
 
-fn escape_string(s: &str) -> String {
-    s.replace('\\', "\\\\")
-        .replace('"', "\\\"")
-        .replace('\n', "\\n")
-        .replace('\r', "\\r")
-        .replace('\t', "\\t")
+fn print_query(query: &OslQuery, args: &Args) {
+    let options = TextOptions {
+        verbose: args.verbose,
+        param: args.param.clone(),
+        ..Default::default()
+    };
+
+    for span in query.to_text_spans(&options) {
+        let style = match span.role {
+            TextRole::Plain => Style::new(),
+            TextRole::Keyword => Style::new().magenta().bold(),
+            TextRole::Type => Style::new().cyan(),
+            TextRole::Identifier => Style::new().green(),
+            TextRole::Value => Style::new().yellow(),
+            TextRole::Delimiter => Style::new().white().dim(),
+        };
+        print!("{}", span.text.paint(style));
+    }
 }
@@ -0,0 +1,9 @@
+//! Passes the target triple on to `tests/c_api.rs`, which needs it to find a
+//! C compiler with the `cc` crate.
+
+fn main() {
+    println!(
+        "cargo:rustc-env=OSLQUERY_CAPI_TARGET={}",
+        std::env::var("TARGET").unwrap()
+    );
+}
@@ -0,0 +1,127 @@
+#ifndef OSLQUERY_CAPI_H
+#define OSLQUERY_CAPI_H
+
+/* Generated with cbindgen from src/lib.rs; do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * A parsed shader. Opaque to C.
+ */
+typedef struct OslqShader OslqShader;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Open and parse a compiled shader (`.oso` file).
+ *
+ * `searchpath` is a `:` or `;` separated list of directories to look for
+ * `path` in; if it is NULL or empty, the search path is taken from
+ * `$OSLQUERY_SEARCHPATH` or `$OSL_SHADERS`. The `.oso` extension may be
+ * left out.
+ *
+ * Returns NULL on failure. Free the shader with [`oslq_free`].
+ *
+ * # Safety
+ *
+ * `path` must be a valid NUL-terminated string and `searchpath` NULL or one.
+ */
+OslqShader *oslq_open(const char *path, const char *searchpath);
+
+/**
+ * Free a shader returned by [`oslq_open`]. NULL is ignored.
+ *
+ * # Safety
+ *
+ * `shader` must be NULL or a pointer returned by [`oslq_open`] that was not
+ * freed yet.
+ */
+void oslq_free(OslqShader *shader);
+
+/**
+ * The shader name, or NULL if `shader` is NULL.
+ *
+ * # Safety
+ *
+ * `shader` must be NULL or a live pointer returned by [`oslq_open`].
+ */
+const char *oslq_shader_name(const OslqShader *shader);
+
+/**
+ * The number of parameters, or 0 if `shader` is NULL.
+ *
+ * # Safety
+ *
+ * `shader` must be NULL or a live pointer returned by [`oslq_open`].
+ */
+uintptr_t oslq_param_count(const OslqShader *shader);
+
+/**
+ * The name of parameter `index`, or NULL if it is out of range.
+ *
+ * # Safety
+ *
+ * `shader` must be NULL or a live pointer returned by [`oslq_open`].
+ */
+const char *oslq_param_name(const OslqShader *shader, uintptr_t index);
+
+/**
+ * The type of parameter `index` as `oslinfo` prints it, e.g. `float`,
+ * `color[4]` or `output closure color`; NULL if it is out of range.
+ *
+ * # Safety
+ *
+ * `shader` must be NULL or a live pointer returned by [`oslq_open`].
+ */
+const char *oslq_param_type_string(const OslqShader *shader, uintptr_t index);
+
+/**
+ * The default of parameter `index` as floats, e.g. three for a `color`
+ * or 16 per element of a `matrix[]`.
+ *
+ * Copies up to `capacity` floats to `out` and returns the total number, so
+ * a call with a `capacity` of 0 queries the size. Returns 0 for parameters
+ * without a default and for `int`, `string` and closure types.
+ *
+ * # Safety
+ *
+ * `shader` must be NULL or a live pointer returned by [`oslq_open`], and
+ * `out` must be valid for writing `capacity` floats.
+ */
+uintptr_t oslq_param_default_floats(const OslqShader *shader,
+                                    uintptr_t index,
+                                    float *out,
+                                    uintptr_t capacity);
+
+/**
+ * The value of the string metadata `key` of parameter `index`, or NULL if
+ * there is none.
+ *
+ * # Safety
+ *
+ * `shader` must be NULL or a live pointer returned by [`oslq_open`], and
+ * `key` a valid NUL-terminated string.
+ */
+const char *oslq_param_metadata_string(const OslqShader *shader,
+                                       uintptr_t index,
+                                       const char *key);
+
+/**
+ * The message of the last failure on the calling thread, or NULL if there
+ * was none.
+ *
+ * The string is valid until the next failure on the same thread.
+ */
+const char *oslq_last_error(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* OSLQUERY_CAPI_H */
@@ -0,0 +1,407 @@
+//! C API for [`oslquery_petite`], for C and C++ hosts that would otherwise
+//! link OSL's `liboslquery`.
+//!
+//! The header is `include/oslquery_capi.h`. A shader is opened with
+//! `oslq_open()` and released with `oslq_free()`; strings returned for it
+//! stay valid until then:
+//!
+//! ```c
+//! OslqShader* shader = oslq_open("plastic.oso", NULL);
+//! if (!shader) {
+//!     fprintf(stderr, "%s\n", oslq_last_error());
+//!     return 1;
+//! }
+//! for (size_t i = 0; i < oslq_param_count(shader); ++i)
+//!     printf("%s %s\n", oslq_param_type_string(shader, i), oslq_param_name(shader, i));
+//! oslq_free(shader);
+//! ```
+//!
+//! Functions report failures through their return value and set a message
+//! for [`oslq_last_error`]. Panics are caught and reported the same way, so
+//! they never unwind into the host.
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString, c_char};
+use std::fmt::Display;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use oslquery_petite::{MetadataValue, OslQuery, Parameter, TypedParameter};
+
+/// A parsed shader. Opaque to C.
+pub struct OslqShader {
+    shader_name: CString,
+    params: Vec<Param>,
+}
+
+/// A parameter in the form returned to C.
+struct Param {
+    name: CString,
+    type_string: CString,
+    default_floats: Vec<f32>,
+    /// Names and values of string metadata
+    string_metadata: Vec<(String, CString)>,
+}
+
+impl OslqShader {
+    fn new(query: &OslQuery) -> Self {
+        OslqShader {
+            shader_name: c_string(query.shader_name()),
+            params: query.params().iter().map(Param::new).collect(),
+        }
+    }
+}
+
+impl Param {
+    fn new(param: &Parameter) -> Self {
+        let mut type_string = param.typed_param().oslquery_typestring();
+        if param.is_output() {
+            type_string.insert_str(0, "output ");
+        }
+        Param {
+            name: c_string(&param.name),
+            type_string: c_string(&type_string),
+            default_floats: default_floats(param.typed_param()),
+            string_metadata: param
+                .metadata()
+                .iter()
+                .filter_map(|meta| match &meta.value {
+                    MetadataValue::String(value) => Some((meta.name.to_string(), c_string(value))),
+                    _ => None,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The float components of a default, flattened; empty for `int` and
+/// `string` types.
+fn default_floats(typed_param: &TypedParameter) -> Vec<f32> {
+    match typed_param {
+        TypedParameter::Float { default } => default.iter().copied().collect(),
+        TypedParameter::Color { default, .. }
+        | TypedParameter::Point { default, .. }
+        | TypedParameter::Vector { default, .. }
+        | TypedParameter::Normal { default, .. } => default.iter().flatten().copied().collect(),
+        TypedParameter::Matrix { default } => {
+            default.iter().flat_map(|m| m.iter()).copied().collect()
+        }
+        TypedParameter::FloatArray { default, .. }
+        | TypedParameter::FloatDynamicArray { default } => {
+            default.iter().flatten().copied().collect()
+        }
+        TypedParameter::ColorArray { default, .. }
+        | TypedParameter::PointArray { default, .. }
+        | TypedParameter::VectorArray { default, .. }
+        | TypedParameter::NormalArray { default, .. }
+        | TypedParameter::ColorDynamicArray { default, .. }
+        | TypedParameter::PointDynamicArray { default, .. }
+        | TypedParameter::VectorDynamicArray { default, .. }
+        | TypedParameter::NormalDynamicArray { default, .. } => {
+            default.iter().flatten().flatten().copied().collect()
+        }
+        TypedParameter::MatrixArray { default, .. }
+        | TypedParameter::MatrixDynamicArray { default } => {
+            default.iter().flatten().flatten().copied().collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Convert to a C string, dropping interior NUL bytes.
+fn c_string(s: &str) -> CString {
+    CString::new(s.replace('\0', "")).expect("NUL bytes were removed")
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: impl Display) {
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(c_string(&message.to_string())));
+}
+
+/// Run `f`, turning an `Err` or a panic into `fallback` and a message for
+/// [`oslq_last_error`].
+fn guard<T>(fallback: T, f: impl FnOnce() -> Result<T, String>) -> T {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => value,
+        Ok(Err(message)) => {
+            set_last_error(message);
+            fallback
+        }
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            set_last_error(format!("panic: {}", message));
+            fallback
+        }
+    }
+}
+
+/// # Safety
+///
+/// `s` must be null or a valid NUL-terminated string.
+unsafe fn str_arg<'a>(s: *const c_char, name: &str) -> Result<&'a str, String> {
+    if s.is_null() {
+        return Err(format!("{} is NULL", name));
+    }
+    // SAFETY: guaranteed by the caller.
+    unsafe { CStr::from_ptr(s) }
+        .to_str()
+        .map_err(|_| format!("{} is not valid UTF-8", name))
+}
+
+/// # Safety
+///
+/// `shader` must be null or a pointer returned by [`oslq_open`] that was not
+/// freed yet.
+unsafe fn shader_arg<'a>(shader: *const OslqShader) -> Result<&'a OslqShader, String> {
+    // SAFETY: guaranteed by the caller.
+    unsafe { shader.as_ref() }.ok_or_else(|| "shader is NULL".to_string())
+}
+
+/// # Safety
+///
+/// As for [`shader_arg`].
+unsafe fn param_arg<'a>(shader: *const OslqShader, index: usize) -> Result<&'a Param, String> {
+    // SAFETY: guaranteed by the caller.
+    let shader = unsafe { shader_arg(shader) }?;
+    shader.params.get(index).ok_or_else(|| {
+        format!(
+            "parameter index {} out of range (count {})",
+            index,
+            shader.params.len()
+        )
+    })
+}
+
+/// Open and parse a compiled shader (`.oso` file).
+///
+/// `searchpath` is a `:` or `;` separated list of directories to look for
+/// `path` in; if it is NULL or empty, the search path is taken from
+/// `$OSLQUERY_SEARCHPATH` or `$OSL_SHADERS`. The `.oso` extension may be
+/// left out.
+///
+/// Returns NULL on failure. Free the shader with [`oslq_free`].
+///
+/// # Safety
+///
+/// `path` must be a valid NUL-terminated string and `searchpath` NULL or one.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oslq_open(
+    path: *const c_char,
+    searchpath: *const c_char,
+) -> *mut OslqShader {
+    guard(ptr::null_mut(), || {
+        // SAFETY: guaranteed by the caller.
+        let path = unsafe { str_arg(path, "path") }?;
+        let searchpath = if searchpath.is_null() {
+            ""
+        } else {
+            // SAFETY: guaranteed by the caller.
+            unsafe { str_arg(searchpath, "searchpath") }?
+        };
+        let query = OslQuery::open_with_searchpath(path, searchpath)
+            .map_err(|e| format!("{}: {}", path, e))?;
+        Ok(Box::into_raw(Box::new(OslqShader::new(&query))))
+    })
+}
+
+/// Free a shader returned by [`oslq_open`]. NULL is ignored.
+///
+/// # Safety
+///
+/// `shader` must be NULL or a pointer returned by [`oslq_open`] that was not
+/// freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oslq_free(shader: *mut OslqShader) {
+    guard((), || {
+        if !shader.is_null() {
+            // SAFETY: guaranteed by the caller.
+            drop(unsafe { Box::from_raw(shader) });
+        }
+        Ok(())
+    })
+}
+
+/// The shader name, or NULL if `shader` is NULL.
+///
+/// # Safety
+///
+/// `shader` must be NULL or a live pointer returned by [`oslq_open`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oslq_shader_name(shader: *const OslqShader) -> *const c_char {
+    guard(ptr::null(), || {
+        // SAFETY: guaranteed by the caller.
+        Ok(unsafe { shader_arg(shader) }?.shader_name.as_ptr())
+    })
+}
+
+/// The number of parameters, or 0 if `shader` is NULL.
+///
+/// # Safety
+///
+/// `shader` must be NULL or a live pointer returned by [`oslq_open`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oslq_param_count(shader: *const OslqShader) -> usize {
+    guard(0, || {
+        // SAFETY: guaranteed by the caller.
+        Ok(unsafe { shader_arg(shader) }?.params.len())
+    })
+}
+
+/// The name of parameter `index`, or NULL if it is out of range.
+///
+/// # Safety
+///
+/// `shader` must be NULL or a live pointer returned by [`oslq_open`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oslq_param_name(shader: *const OslqShader, index: usize) -> *const c_char {
+    guard(ptr::null(), || {
+        // SAFETY: guaranteed by the caller.
+        Ok(unsafe { param_arg(shader, index) }?.name.as_ptr())
+    })
+}
+
+/// The type of parameter `index` as `oslinfo` prints it, e.g. `float`,
+/// `color[4]` or `output closure color`; NULL if it is out of range.
+///
+/// # Safety
+///
+/// `shader` must be NULL or a live pointer returned by [`oslq_open`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oslq_param_type_string(
+    shader: *const OslqShader,
+    index: usize,
+) -> *const c_char {
+    guard(ptr::null(), || {
+        // SAFETY: guaranteed by the caller.
+        Ok(unsafe { param_arg(shader, index) }?.type_string.as_ptr())
+    })
+}
+
+/// The default of parameter `index` as floats, e.g. three for a `color`
+/// or 16 per element of a `matrix[]`.
+///
+/// Copies up to `capacity` floats to `out` and returns the total number, so
+/// a call with a `capacity` of 0 queries the size. Returns 0 for parameters
+/// without a default and for `int`, `string` and closure types.
+///
+/// # Safety
+///
+/// `shader` must be NULL or a live pointer returned by [`oslq_open`], and
+/// `out` must be valid for writing `capacity` floats.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oslq_param_default_floats(
+    shader: *const OslqShader,
+    index: usize,
+    out: *mut f32,
+    capacity: usize,
+) -> usize {
+    guard(0, || {
+        // SAFETY: guaranteed by the caller.
+        let floats = &unsafe { param_arg(shader, index) }?.default_floats;
+        let count = floats.len().min(capacity);
+        if count > 0 {
+            if out.is_null() {
+                return Err("out is NULL".to_string());
+            }
+            // SAFETY: `out` has room for `capacity >= count` floats.
+            unsafe { ptr::copy_nonoverlapping(floats.as_ptr(), out, count) };
+        }
+        Ok(floats.len())
+    })
+}
+
+/// The value of the string metadata `key` of parameter `index`, or NULL if
+/// there is none.
+///
+/// # Safety
+///
+/// `shader` must be NULL or a live pointer returned by [`oslq_open`], and
+/// `key` a valid NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oslq_param_metadata_string(
+    shader: *const OslqShader,
+    index: usize,
+    key: *const c_char,
+) -> *const c_char {
+    guard(ptr::null(), || {
+        // SAFETY: guaranteed by the caller.
+        let param = unsafe { param_arg(shader, index) }?;
+        // SAFETY: guaranteed by the caller.
+        let key = unsafe { str_arg(key, "key") }?;
+        Ok(param
+            .string_metadata
+            .iter()
+            .find(|(name, _)| name == key)
+            .map_or(ptr::null(), |(_, value)| value.as_ptr()))
+    })
+}
+
+/// The message of the last failure on the calling thread, or NULL if there
+/// was none.
+///
+/// The string is valid until the next failure on the same thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn oslq_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |s| s.as_ptr()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plastic() -> *mut OslqShader {
+        let path = CString::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/plastic.oso")).unwrap();
+        let shader = unsafe { oslq_open(path.as_ptr(), ptr::null()) };
+        assert!(!shader.is_null());
+        shader
+    }
+
+    fn last_error() -> String {
+        unsafe { CStr::from_ptr(oslq_last_error()) }
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn test_default_floats() {
+        let shader = plastic();
+        let mut out = [0.0; 2];
+        unsafe {
+            // Cs is a color; only the first two components fit.
+            assert_eq!(oslq_param_default_floats(shader, 1, out.as_mut_ptr(), 2), 3);
+            assert_eq!(out, [1.0, 0.5]);
+            // count is an int.
+            assert_eq!(oslq_param_default_floats(shader, 2, out.as_mut_ptr(), 2), 0);
+            oslq_free(shader);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        unsafe {
+            assert!(oslq_param_name(ptr::null(), 0).is_null());
+            assert_eq!(last_error(), "shader is NULL");
+
+            let shader = plastic();
+            assert!(oslq_param_name(shader, 99).is_null());
+            assert_eq!(last_error(), "parameter index 99 out of range (count 5)");
+            oslq_free(shader);
+
+            assert!(oslq_open(ptr::null(), ptr::null()).is_null());
+            assert_eq!(last_error(), "path is NULL");
+        }
+    }
+
+    #[test]
+    fn test_panics_are_caught() {
+        assert_eq!(guard(7, || panic!("boom")), 7);
+        assert_eq!(last_error(), "panic: boom");
+    }
+}
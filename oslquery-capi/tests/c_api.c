@@ -0,0 +1,50 @@
+/* Exercises the C API from C; built and run by tests/c_api.rs. */
+
+#include "oslquery_capi.h"
+
+#include <stdio.h>
+#include <string.h>
+
+#define CHECK(cond)                                                        \
+    do {                                                                   \
+        if (!(cond)) {                                                     \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__,         \
+                    __LINE__, #cond);                                      \
+            return 1;                                                      \
+        }                                                                  \
+    } while (0)
+
+int main(int argc, char** argv)
+{
+    CHECK(argc == 2);
+
+    OslqShader* shader = oslq_open(argv[1], NULL);
+    CHECK(shader != NULL);
+    CHECK(strcmp(oslq_shader_name(shader), "plastic") == 0);
+    CHECK(oslq_param_count(shader) == 5);
+
+    CHECK(strcmp(oslq_param_name(shader, 0), "Kd") == 0);
+    CHECK(strcmp(oslq_param_type_string(shader, 0), "float") == 0);
+    CHECK(strcmp(oslq_param_metadata_string(shader, 0, "label"), "Diffuse") == 0);
+    /* Only string metadata is returned. */
+    CHECK(oslq_param_metadata_string(shader, 0, "min") == NULL);
+
+    float cs[3] = { 0 };
+    CHECK(oslq_param_default_floats(shader, 1, NULL, 0) == 3);
+    CHECK(oslq_param_default_floats(shader, 1, cs, 3) == 3);
+    CHECK(cs[0] == 1.0f && cs[1] == 0.5f && cs[2] == 0.25f);
+
+    CHECK(strcmp(oslq_param_type_string(shader, 3), "float[2]") == 0);
+    CHECK(strcmp(oslq_param_type_string(shader, 4), "output color") == 0);
+    CHECK(oslq_param_default_floats(shader, 4, cs, 3) == 0);
+
+    CHECK(oslq_param_name(shader, 5) == NULL);
+    CHECK(strstr(oslq_last_error(), "out of range") != NULL);
+
+    oslq_free(shader);
+
+    CHECK(oslq_open("does_not_exist.oso", NULL) == NULL);
+    CHECK(strstr(oslq_last_error(), "does_not_exist.oso") != NULL);
+
+    return 0;
+}
@@ -0,0 +1,75 @@
+//! Builds `tests/c_api.c` against the shared library and runs it.
+#![cfg(unix)]
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const TARGET: &str = env!("OSLQUERY_CAPI_TARGET");
+
+/// The directory holding the shared library: the parent of the `deps`
+/// directory this test runs from.
+fn library_dir() -> PathBuf {
+    let exe = env::current_exe().unwrap();
+    let dir = exe.parent().unwrap().parent().unwrap().to_path_buf();
+    let library = format!(
+        "{}oslquery_capi{}",
+        env::consts::DLL_PREFIX,
+        env::consts::DLL_SUFFIX
+    );
+    assert!(
+        dir.join(&library).exists(),
+        "{} not found in {}",
+        library,
+        dir.display()
+    );
+    dir
+}
+
+#[test]
+fn test_c_program() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let library_dir = library_dir();
+    let out_dir = tempdir();
+    let exe = out_dir.join("c_api");
+
+    let compiler = cc::Build::new()
+        .target(TARGET)
+        .host(TARGET)
+        .opt_level(0)
+        .out_dir(&out_dir)
+        .cargo_metadata(false)
+        .get_compiler();
+    let status = compiler
+        .to_command()
+        .arg(manifest_dir.join("tests/c_api.c"))
+        .arg("-I")
+        .arg(manifest_dir.join("include"))
+        .arg("-o")
+        .arg(&exe)
+        .arg("-L")
+        .arg(&library_dir)
+        .arg("-loslquery_capi")
+        .arg(format!("-Wl,-rpath,{}", library_dir.display()))
+        .status()
+        .unwrap();
+    assert!(status.success(), "compiling tests/c_api.c failed");
+
+    let output = Command::new(&exe)
+        .arg(manifest_dir.join("tests/plastic.oso"))
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "tests/c_api.c failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    std::fs::remove_dir_all(&out_dir).ok();
+}
+
+fn tempdir() -> PathBuf {
+    let dir = env::temp_dir().join(format!("oslquery-capi-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
@@ -0,0 +1,292 @@
+//! Python bindings for [`oslquery_petite`].
+//!
+//! ```python
+//! import oslquery_petite
+//!
+//! q = oslquery_petite.open("lambert.oso")
+//! for p in q.params:
+//!     print(p.type, p.name, p.default, p.metadata)
+//! ```
+//!
+//! Defaults become native Python values: `int`, `float` and `str` for
+//! scalars, a list of 3 floats for `color`, `point`, `vector` and `normal`,
+//! a list of 16 floats for `matrix`, and lists of those for (dynamic)
+//! arrays. Parameters without a default, including outputs and closures,
+//! have a default of `None`.
+
+use oslquery_petite::parser::ParseError as OsoParseError;
+use oslquery_petite::types::widen_f32;
+use oslquery_petite::{Metadata, MetadataValue, OslQuery, Parameter, StringValue, TypedParameter};
+use pyo3::create_exception;
+use pyo3::exceptions::{PyIOError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use pyo3::{IntoPyObjectExt, PyErr};
+
+create_exception!(
+    oslquery_petite,
+    ParseError,
+    PyValueError,
+    "Raised when a shader cannot be parsed.\n\n\
+     `line` and `column` are 1-based, or `None` if unknown."
+);
+
+/// Convert a parse error, locating it in `source` if given.
+fn to_py_err(py: Python<'_>, error: OsoParseError, source: Option<&str>) -> PyErr {
+    if let OsoParseError::Io(message) = error {
+        return PyIOError::new_err(message);
+    }
+
+    let (line, column) = match (source, &error) {
+        (Some(source), _) => error.line_column(source).unzip(),
+        (None, OsoParseError::ParseError { line, .. }) => (Some(*line), None),
+        (None, _) => (None, None),
+    };
+    let py_err = ParseError::new_err(error.to_string());
+    let value = py_err.value(py);
+    // Setting attributes on a fresh exception instance cannot fail.
+    let _ = value.setattr("line", line);
+    let _ = value.setattr("column", column);
+    py_err
+}
+
+/// A parsed shader.
+#[pyclass(name = "OslQuery", module = "oslquery_petite", frozen)]
+struct PyOslQuery {
+    query: OslQuery,
+}
+
+#[pymethods]
+impl PyOslQuery {
+    /// The shader name.
+    #[getter]
+    fn shader_name(&self) -> &str {
+        self.query.shader_name()
+    }
+
+    /// The shader type, e.g. `surface`.
+    #[getter]
+    fn shader_type(&self) -> &str {
+        self.query.shader_type()
+    }
+
+    /// The parameters, in declaration order.
+    #[getter]
+    fn params(&self) -> Vec<PyParameter> {
+        self.query
+            .params()
+            .iter()
+            .cloned()
+            .map(|param| PyParameter { param })
+            .collect()
+    }
+
+    /// The shader metadata as a dict.
+    #[getter]
+    fn metadata<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        metadata_dict(py, self.query.metadata())
+    }
+
+    /// The parameter called `name`, or `None`.
+    fn param(&self, name: &str) -> Option<PyParameter> {
+        self.query
+            .param_by_name(name)
+            .cloned()
+            .map(|param| PyParameter { param })
+    }
+
+    fn __len__(&self) -> usize {
+        self.query.param_count()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "<OslQuery {} {} ({} parameters)>",
+            self.query.shader_type(),
+            self.query.shader_name(),
+            self.query.param_count()
+        )
+    }
+}
+
+/// A shader parameter.
+#[pyclass(name = "Parameter", module = "oslquery_petite", frozen)]
+#[derive(Clone)]
+struct PyParameter {
+    param: Parameter,
+}
+
+#[pymethods]
+impl PyParameter {
+    /// The parameter name.
+    #[getter]
+    fn name(&self) -> &str {
+        &self.param.name
+    }
+
+    /// The type as `oslinfo` prints it, e.g. `color[4]` or `closure color`.
+    #[getter(r#type)]
+    fn type_string(&self) -> String {
+        self.param.typed_param().oslquery_typestring()
+    }
+
+    #[getter]
+    fn is_output(&self) -> bool {
+        self.param.is_output()
+    }
+
+    #[getter]
+    fn is_array(&self) -> bool {
+        self.param.is_array()
+    }
+
+    #[getter]
+    fn is_closure(&self) -> bool {
+        self.param.is_closure()
+    }
+
+    /// The coordinate space of a geometric or color default, or `None`.
+    #[getter]
+    fn space(&self) -> Option<String> {
+        self.param
+            .typed_param()
+            .space()
+            .map(|space| space.to_string())
+    }
+
+    /// The default as a native Python value, or `None`.
+    #[getter]
+    fn default<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        default_to_py(py, self.param.typed_param())
+    }
+
+    /// The parameter metadata as a dict.
+    #[getter]
+    fn metadata<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        metadata_dict(py, self.param.metadata())
+    }
+
+    fn __repr__(&self, py: Python<'_>) -> PyResult<String> {
+        let direction = if self.param.is_output() {
+            "output "
+        } else {
+            ""
+        };
+        let mut repr = format!(
+            "<Parameter {}{} {}",
+            direction,
+            self.type_string(),
+            self.name()
+        );
+        let default = self.default(py)?;
+        if !default.is_none() {
+            repr.push_str(&format!(" = {}", default.repr()?));
+        }
+        repr.push('>');
+        Ok(repr)
+    }
+}
+
+fn default_to_py<'py>(
+    py: Python<'py>,
+    typed_param: &TypedParameter,
+) -> PyResult<Bound<'py, PyAny>> {
+    match typed_param {
+        TypedParameter::Int { default } => default.into_bound_py_any(py),
+        TypedParameter::Float { default } => default.map(widen_f32).into_bound_py_any(py),
+        TypedParameter::String { default } => default.as_deref().into_bound_py_any(py),
+        TypedParameter::Color { default, .. }
+        | TypedParameter::Point { default, .. }
+        | TypedParameter::Vector { default, .. }
+        | TypedParameter::Normal { default, .. } => {
+            default.as_ref().map(|v| floats(v)).into_bound_py_any(py)
+        }
+        TypedParameter::Matrix { default } => {
+            default.as_deref().map(|m| floats(m)).into_bound_py_any(py)
+        }
+        TypedParameter::IntArray { default, .. } | TypedParameter::IntDynamicArray { default } => {
+            default.as_deref().into_bound_py_any(py)
+        }
+        TypedParameter::FloatArray { default, .. }
+        | TypedParameter::FloatDynamicArray { default } => {
+            default.as_deref().map(floats).into_bound_py_any(py)
+        }
+        TypedParameter::StringArray { default, .. }
+        | TypedParameter::StringDynamicArray { default } => {
+            default.as_deref().map(strs).into_bound_py_any(py)
+        }
+        TypedParameter::ColorArray { default, .. }
+        | TypedParameter::PointArray { default, .. }
+        | TypedParameter::VectorArray { default, .. }
+        | TypedParameter::NormalArray { default, .. }
+        | TypedParameter::ColorDynamicArray { default, .. }
+        | TypedParameter::PointDynamicArray { default, .. }
+        | TypedParameter::VectorDynamicArray { default, .. }
+        | TypedParameter::NormalDynamicArray { default, .. } => default
+            .as_ref()
+            .map(|v| v.iter().map(|t| floats(t)).collect::<Vec<_>>())
+            .into_bound_py_any(py),
+        TypedParameter::MatrixArray { default, .. }
+        | TypedParameter::MatrixDynamicArray { default } => default
+            .as_ref()
+            .map(|v| v.iter().map(|m| floats(m)).collect::<Vec<_>>())
+            .into_bound_py_any(py),
+        TypedParameter::Closure { .. } => Ok(py.None().into_bound(py)),
+    }
+}
+
+fn metadata_dict<'py>(py: Python<'py>, metadata: &[Metadata]) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new(py);
+    for meta in metadata {
+        let value = match &meta.value {
+            MetadataValue::Int(v) => v.into_bound_py_any(py)?,
+            MetadataValue::Float(v) => widen_f32(*v).into_bound_py_any(py)?,
+            MetadataValue::String(v) => v.as_str().into_bound_py_any(py)?,
+            MetadataValue::IntArray(v) => v.into_bound_py_any(py)?,
+            MetadataValue::FloatArray(v) => floats(v).into_bound_py_any(py)?,
+            MetadataValue::StringArray(v) => strs(v).into_bound_py_any(py)?,
+        };
+        dict.set_item(meta.name.as_str(), value)?;
+    }
+    Ok(dict)
+}
+
+fn floats(values: &[f32]) -> Vec<f64> {
+    values.iter().copied().map(widen_f32).collect()
+}
+
+fn strs(values: &[StringValue]) -> Vec<&str> {
+    values.iter().map(StringValue::as_str).collect()
+}
+
+/// Open and parse a compiled shader (`.oso` file).
+///
+/// `searchpath` is a `:` or `;` separated list of directories to look for
+/// `path` in; if it is empty, the search path is taken from the environment.
+#[pyfunction]
+#[pyo3(signature = (path, searchpath = ""))]
+fn open(py: Python<'_>, path: &str, searchpath: &str) -> PyResult<PyOslQuery> {
+    OslQuery::open_with_searchpath(path, searchpath)
+        .map(|query| PyOslQuery { query })
+        .map_err(|e| to_py_err(py, e, None))
+}
+
+/// Parse the contents of a compiled shader.
+#[pyfunction]
+fn from_string(py: Python<'_>, source: &str) -> PyResult<PyOslQuery> {
+    OslQuery::from_string(source)
+        .map(|query| PyOslQuery { query })
+        .map_err(|e| to_py_err(py, e, Some(source)))
+}
+
+#[pymodule]
+#[pyo3(name = "oslquery_petite")]
+fn oslquery_petite_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    m.add("ParseError", m.py().get_type::<ParseError>())?;
+    m.add_class::<PyOslQuery>()?;
+    m.add_class::<PyParameter>()?;
+    m.add_function(wrap_pyfunction!(open, m)?)?;
+    m.add_function(wrap_pyfunction!(from_string, m)?)?;
+    Ok(())
+}
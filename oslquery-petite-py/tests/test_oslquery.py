@@ -0,0 +1,99 @@
+"""Tests for the Python bindings, over the fixtures of `oslquery-petite`.
+
+Run with `maturin develop && pytest` from this directory.
+"""
+
+from pathlib import Path
+
+import pytest
+
+import oslquery_petite
+
+FIXTURES = Path(__file__).parents[2] / "oslquery-petite" / "tests"
+
+
+def test_open():
+    q = oslquery_petite.open(str(FIXTURES / "ui.oso"))
+    assert q.shader_name == "uiTest"
+    assert q.shader_type == "shader"
+    assert q.metadata == {"help": "Shader exercising UI metadata"}
+    assert len(q) == len(q.params)
+    assert repr(q) == f"<OslQuery shader uiTest ({len(q)} parameters)>"
+
+
+def test_open_with_searchpath():
+    q = oslquery_petite.open("ui", searchpath=str(FIXTURES))
+    assert q.shader_name == "uiTest"
+
+
+def test_scalar_defaults_and_metadata():
+    q = oslquery_petite.open(str(FIXTURES / "ui.oso"))
+
+    gain = q.param("i_gain")
+    assert gain.name == "i_gain"
+    assert gain.type == "float"
+    assert gain.default == 1.0
+    assert gain.metadata == {
+        "page": "Adjust",
+        "label": "Gain",
+        "min": 0.0,
+        "max": 10.0,
+        "help": "Multiplier applied last",
+    }
+    assert repr(gain) == "<Parameter float i_gain = 1.0>"
+
+    assert q.param("filter").default == 1
+    assert q.param("mode").default == "fast"
+    assert q.param("i_tint").default == [1.0, 1.0, 1.0]
+    assert q.param("missing") is None
+
+
+def test_arrays_and_matrices():
+    q = oslquery_petite.open(str(FIXTURES / "all_types.oso"))
+
+    assert q.param("floats").default == [1.0, 2.0, 3.0, 4.0]
+    assert q.param("ints").default == [10, 20, 30]
+    assert q.param("points").default == [[0.0, 1.0, 2.0], [3.0, 4.0, 5.0]]
+    assert q.param("points").is_array
+
+    identity = [1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0]
+    assert q.param("single_matrix").type == "matrix"
+    assert q.param("single_matrix").default == identity
+    matrices = q.param("matrices").default
+    assert len(matrices) == 2 and matrices[0] == identity
+
+
+def test_dynamic_arrays_and_closures():
+    q = oslquery_petite.from_string(
+        "OpenShadingLanguage 1.12\n"
+        "surface s\n"
+        "param\tfloat[]\tweights\t0.1 0.2\n"
+        "param\tstring[]\tnames\n"
+        "oparam\tclosure color\tbsdf\n"
+        "code ___main___\n"
+    )
+
+    weights = q.param("weights")
+    assert weights.type == "float[]"
+    assert weights.default == [0.1, 0.2]
+    assert q.param("names").default is None
+
+    bsdf = q.param("bsdf")
+    assert bsdf.is_closure and bsdf.is_output
+    assert bsdf.type == "closure color"
+    assert bsdf.default is None
+    assert repr(bsdf) == "<Parameter output closure color bsdf>"
+
+
+def test_parse_error():
+    with pytest.raises(oslquery_petite.ParseError) as info:
+        oslquery_petite.from_string("OpenShadingLanguage 1.12\nsurface s\nparam\tbogus\tx\n")
+    assert isinstance(info.value, ValueError)
+    assert info.value.line == 3
+    assert info.value.column == 7
+    assert "bogus" in str(info.value)
+
+
+def test_missing_file():
+    with pytest.raises(OSError):
+        oslquery_petite.open(str(FIXTURES / "does_not_exist.oso"))
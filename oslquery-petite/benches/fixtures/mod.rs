@@ -0,0 +1,40 @@
+//! Generated shader sources, shared by the benches and `tests/allocations.rs`.
+
+use std::fmt::Write;
+
+/// A shader with `count` inputs carrying the page, label, help and range
+/// metadata of UI-heavy shaders, plus one output. Always the same for the
+/// same `count`.
+pub fn metadata_heavy_shader(count: usize) -> String {
+    let mut source = String::from(
+        "OpenShadingLanguage 1.12\n\
+         # Compiled by oslc 1.12.0\n\
+         shader \"metadataHeavy\"\t%meta{string,help,\"Generated benchmark shader\"}\n",
+    );
+    for i in 0..count {
+        let (typespec, default) = match i % 5 {
+            0 => ("float", "0.5"),
+            1 => ("color", "1 0.5 0.25"),
+            2 => ("int", "3"),
+            3 => ("string", "\"default\""),
+            _ => ("float[4]", "0 0.25 0.5 1"),
+        };
+        writeln!(
+            source,
+            "param\t{typespec}\tparam{i}\t{default}\t\t\
+             %meta{{string,page,\"Page {page}\"}} \
+             %meta{{string,label,\"Parameter {i}\"}} \
+             %meta{{string,help,\"Help text for parameter {i}.\"}} \
+             %meta{{float,min,0}} %meta{{float,max,1}} \
+             %read{{0,0}} %write{{2147483647,-1}}",
+            page = i % 12,
+        )
+        .unwrap();
+    }
+    source.push_str(
+        "oparam\tcolor\tCout\t0 0 0\t\t%read{2147483647,-1} %write{0,0}\n\
+         code ___main___\n\
+         \tend\n",
+    );
+    source
+}
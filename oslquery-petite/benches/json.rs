@@ -0,0 +1,23 @@
+use std::hint::black_box;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use oslquery_petite::OslQuery;
+use oslquery_petite::json::NullDefaultBehavior;
+
+mod fixtures;
+
+fn json(c: &mut Criterion) {
+    let query = OslQuery::from_string(&fixtures::metadata_heavy_shader(300)).unwrap();
+
+    let mut group = c.benchmark_group("json");
+    group.bench_function("serialize query", |b| {
+        b.iter(|| serde_json::to_string(black_box(&query)).unwrap())
+    });
+    group.bench_function("parameter defaults", |b| {
+        b.iter(|| black_box(&query).to_json_parameter_defaults(NullDefaultBehavior::Null))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, json);
+criterion_main!(benches);
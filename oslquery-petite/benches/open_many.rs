@@ -0,0 +1,40 @@
+use std::hint::black_box;
+use std::path::PathBuf;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use oslquery_petite::OslQuery;
+
+/// The test fixtures, repeated to make a small shader library.
+fn paths() -> Vec<PathBuf> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let fixtures = [
+        "all_types.oso",
+        "array.oso",
+        "metadata.oso",
+        "points.oso",
+        "renderman.oso",
+        "ui.oso",
+    ];
+    (0..50)
+        .flat_map(|_| fixtures.iter().map(|name| dir.join(name)))
+        .collect()
+}
+
+fn open_many(c: &mut Criterion) {
+    let paths = paths();
+
+    c.bench_function("open serial", |b| {
+        b.iter(|| {
+            for path in &paths {
+                black_box(OslQuery::open(black_box(path)).unwrap());
+            }
+        })
+    });
+
+    c.bench_function("open_many", |b| {
+        b.iter(|| black_box(OslQuery::open_many(black_box(&paths), "")))
+    });
+}
+
+criterion_group!(benches, open_many);
+criterion_main!(benches);
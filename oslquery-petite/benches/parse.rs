@@ -0,0 +1,104 @@
+use std::hint::black_box;
+
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use oslquery_petite::parser::oso::{tokenize_line, tokens};
+use oslquery_petite::parser::{OsoReader, OsoReaderConfig};
+
+mod fixtures;
+
+/// Sources of a small shader library, with the repeated page names, help
+/// texts and defaults typical of real ones.
+fn library() -> Vec<String> {
+    (0..200)
+        .map(|i| {
+            include_str!("../tests/ui.oso").replace("shader uiTest", &format!("shader uiTest{}", i))
+        })
+        .collect()
+}
+
+fn parse(c: &mut Criterion) {
+    let sources = library();
+
+    for (name, config) in [
+        ("parse library", OsoReaderConfig::new()),
+        (
+            "parse library interned",
+            OsoReaderConfig::new().intern_strings(true),
+        ),
+    ] {
+        c.bench_function(name, |b| {
+            b.iter(|| {
+                for source in &sources {
+                    black_box(
+                        OsoReader::with_config(config)
+                            .parse_string(black_box(source))
+                            .unwrap(),
+                    );
+                }
+            })
+        });
+    }
+}
+
+fn parse_single(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse shader");
+    for (name, source) in [
+        ("small", include_str!("../tests/test.oso").to_string()),
+        ("300 params", fixtures::metadata_heavy_shader(300)),
+    ] {
+        group.throughput(Throughput::Bytes(source.len() as u64));
+        group.bench_function(name, |b| {
+            b.iter(|| OsoReader::new().parse_string(black_box(&source)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn parse_metadata(c: &mut Criterion) {
+    let source = fixtures::metadata_heavy_shader(300);
+
+    let mut group = c.benchmark_group("parse metadata");
+    group.throughput(Throughput::Bytes(source.len() as u64));
+    for (name, config) in [
+        ("eager", OsoReaderConfig::new()),
+        ("lazy", OsoReaderConfig::new().lazy_metadata(true)),
+        ("off", OsoReaderConfig::new().parse_metadata(false)),
+    ] {
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                OsoReader::with_config(config)
+                    .parse_string(black_box(&source))
+                    .unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn tokenize(c: &mut Criterion) {
+    let source = fixtures::metadata_heavy_shader(300);
+    let lines: Vec<&str> = source.lines().collect();
+
+    let mut group = c.benchmark_group("tokenize");
+    group.throughput(Throughput::Bytes(source.len() as u64));
+    group.bench_function("300 params", |b| {
+        b.iter(|| {
+            for line in &lines {
+                black_box(tokenize_line(black_box(line)));
+            }
+        })
+    });
+    group.bench_function("300 params, iterator", |b| {
+        b.iter(|| {
+            for line in &lines {
+                tokens(black_box(line)).for_each(|token| {
+                    black_box(token);
+                });
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse, parse_single, parse_metadata, tokenize);
+criterion_main!(benches);
@@ -0,0 +1,33 @@
+use std::hint::black_box;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use oslquery_petite::OslQuery;
+
+mod fixtures;
+
+fn param_by_name(c: &mut Criterion) {
+    let query = OslQuery::from_string(&fixtures::metadata_heavy_shader(300)).unwrap();
+    let names: Vec<String> = query.params().iter().map(|p| p.name.to_string()).collect();
+
+    let mut group = c.benchmark_group("param_by_name");
+    group.bench_function("first", |b| {
+        b.iter(|| query.param_by_name(black_box("param0")))
+    });
+    group.bench_function("last", |b| {
+        b.iter(|| query.param_by_name(black_box("Cout")))
+    });
+    group.bench_function("missing", |b| {
+        b.iter(|| query.param_by_name(black_box("doesNotExist")))
+    });
+    group.bench_function("all", |b| {
+        b.iter(|| {
+            for name in &names {
+                black_box(query.param_by_name(black_box(name)));
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, param_by_name);
+criterion_main!(benches);
@@ -0,0 +1,70 @@
+//! Builds the `liboslcomp` shim for the `compile-osl` feature.
+//!
+//! The OSL installation is looked up in `$OSL_ROOT` (or `$OSL_HOME`) and then
+//! in the usual system prefixes. OpenImageIO headers are expected next to it
+//! or in `$OIIO_ROOT`.
+
+fn main() {
+    #[cfg(feature = "compile-osl")]
+    compile_osl::build();
+}
+
+#[cfg(feature = "compile-osl")]
+mod compile_osl {
+    use std::env;
+    use std::path::{Path, PathBuf};
+
+    const SYSTEM_PREFIXES: [&str; 3] = ["/usr/local", "/usr", "/opt/homebrew"];
+
+    pub fn build() {
+        for var in ["OSL_ROOT", "OSL_HOME", "OIIO_ROOT"] {
+            println!("cargo:rerun-if-env-changed={}", var);
+        }
+        println!("cargo:rerun-if-changed=csrc/oslcomp.cpp");
+
+        let Some(prefix) = find_osl() else {
+            panic!(
+                "\n\nThe `compile-osl` feature needs `liboslcomp`, which was not found.\n\
+                 Set OSL_ROOT to the prefix of your OpenShadingLanguage installation \
+                 (the directory containing `include/OSL/oslcomp.h` and `lib/`), \
+                 or build without the `compile-osl` feature.\n\n"
+            );
+        };
+
+        let mut build = cc::Build::new();
+        build
+            .cpp(true)
+            .std("c++17")
+            .file("csrc/oslcomp.cpp")
+            .include(prefix.join("include"));
+        if let Some(oiio) = env::var_os("OIIO_ROOT") {
+            build.include(Path::new(&oiio).join("include"));
+        }
+        build.compile("oslquery_oslcomp");
+
+        for lib in ["lib", "lib64"] {
+            let dir = prefix.join(lib);
+            if dir.is_dir() {
+                println!("cargo:rustc-link-search=native={}", dir.display());
+            }
+        }
+        println!("cargo:rustc-link-lib=dylib=oslcomp");
+
+        // Let the compiler find `stdosl.h` without configuration.
+        let stdosl = prefix.join("share/OSL/shaders/stdosl.h");
+        if stdosl.exists() {
+            println!("cargo:rustc-env=OSLQUERY_STDOSL_PATH={}", stdosl.display());
+        }
+    }
+
+    fn find_osl() -> Option<PathBuf> {
+        let is_osl = |prefix: &Path| prefix.join("include/OSL/oslcomp.h").exists();
+
+        ["OSL_ROOT", "OSL_HOME"]
+            .iter()
+            .filter_map(env::var_os)
+            .map(PathBuf::from)
+            .chain(SYSTEM_PREFIXES.iter().map(PathBuf::from))
+            .find(|prefix| is_osl(prefix))
+    }
+}
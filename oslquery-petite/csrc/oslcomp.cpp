@@ -0,0 +1,72 @@
+// C shim around `OSL::OSLCompiler::compile_buffer()` for the `compile-osl`
+// feature.
+
+#include <OSL/oslcomp.h>
+
+#include <cstdlib>
+#include <cstring>
+#include <string>
+#include <vector>
+
+namespace {
+
+/// Collects compiler diagnostics instead of printing them.
+class CollectingErrorHandler final : public OIIO::ErrorHandler {
+public:
+    void operator()(int errcode, const std::string& msg) override
+    {
+        if (errcode >= EH_WARNING) {
+            messages += msg;
+            if (!msg.empty() && msg.back() != '\n')
+                messages += '\n';
+        }
+    }
+
+    std::string messages;
+};
+
+char* copy_string(const std::string& s)
+{
+    char* copy = static_cast<char*>(std::malloc(s.size() + 1));
+    if (copy)
+        std::memcpy(copy, s.c_str(), s.size() + 1);
+    return copy;
+}
+
+}  // namespace
+
+extern "C" {
+
+/// Compile `source` to OSO.
+///
+/// On success returns 1 and sets `*oso`; otherwise returns 0 and sets
+/// `*errors`. Either string must be released with `oslq_free_string()`.
+int oslq_compile_buffer(const char* source, const char* stdosl_path,
+                        char** oso, char** errors)
+{
+    *oso    = nullptr;
+    *errors = nullptr;
+
+    try {
+        CollectingErrorHandler handler;
+        OSL::OSLCompiler compiler(&handler);
+        std::string buffer;
+        std::vector<std::string> options;
+        bool ok = compiler.compile_buffer(source, buffer, options,
+                                          stdosl_path ? stdosl_path : "");
+        if (ok) {
+            *oso = copy_string(buffer);
+            return 1;
+        }
+        *errors = copy_string(handler.messages);
+    } catch (const std::exception& e) {
+        *errors = copy_string(e.what());
+    } catch (...) {
+        *errors = copy_string("unknown error");
+    }
+    return 0;
+}
+
+void oslq_free_string(char* s) { std::free(s); }
+
+}  // extern "C"
@@ -0,0 +1,142 @@
+//! Reading shaders from `.zip` archives.
+//!
+//! Shaders are often shipped as a bundle of `.oso` files in one `.zip`.
+//! [`OslQuery::open_from_zip`] parses a shader straight from such a bundle,
+//! without unpacking it to disk.
+
+use std::io::{Read, Seek};
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::path::Path;
+
+use zip::ZipArchive;
+use zip::result::ZipError;
+
+use crate::parser::ParseError;
+use crate::query::OslQuery;
+
+impl OslQuery {
+    /// Open the zip archive at `archive_path` and parse the shader
+    /// `shader_name` in it.
+    ///
+    /// See [`from_zip_reader`](Self::from_zip_reader) for how the entry is
+    /// found.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn open_from_zip<P: AsRef<Path>>(
+        archive_path: P,
+        shader_name: &str,
+    ) -> Result<Self, ParseError> {
+        let archive_path = archive_path.as_ref();
+        let file = std::fs::File::open(archive_path).map_err(|e| {
+            ParseError::Io(format!(
+                "Failed to open archive '{}': {}",
+                archive_path.display(),
+                e
+            ))
+        })?;
+        Self::from_zip_reader(std::io::BufReader::new(file), shader_name)
+    }
+
+    /// Parse the shader `shader_name` from a zip archive read from `reader`,
+    /// e.g. a [`Cursor`](std::io::Cursor) over an archive in memory.
+    ///
+    /// The entry `<shader_name>.oso` at the root of the archive is used. If
+    /// there is none, the first entry of that name in a directory is, as
+    /// bundles often keep their files in a top-level directory.
+    pub fn from_zip_reader<R: Read + Seek>(
+        reader: R,
+        shader_name: &str,
+    ) -> Result<Self, ParseError> {
+        let mut archive = ZipArchive::new(reader).map_err(zip_error)?;
+
+        let entry_name = format!("{}.oso", shader_name);
+        let suffix = format!("/{}", entry_name);
+        let index = archive
+            .index_for_name(&entry_name)
+            .or_else(|| {
+                archive
+                    .file_names()
+                    .find(|name| name.ends_with(&suffix))
+                    .and_then(|name| archive.index_for_name(name))
+            })
+            .ok_or_else(|| ParseError::Io(format!("No entry '{}' in archive", entry_name)))?;
+
+        let mut entry = archive.by_index(index).map_err(zip_error)?;
+        let mut content = Vec::with_capacity(entry.size() as usize);
+        entry
+            .read_to_end(&mut content)
+            .map_err(|e| ParseError::Io(format!("Failed to read '{}': {}", entry.name(), e)))?;
+        Self::from_bytes(&content)
+    }
+}
+
+fn zip_error(error: ZipError) -> ParseError {
+    ParseError::Io(format!("Invalid zip archive: {}", error))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use zip::ZipWriter;
+    use zip::write::SimpleFileOptions;
+
+    use super::*;
+
+    const SHADER: &str = "OpenShadingLanguage 1.12\n\
+                          surface bundled\n\
+                          param\tfloat\tKd\t0.5\n\
+                          code ___main___\n";
+
+    fn zip(entries: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in entries {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_from_zip_reader() {
+        let archive = zip(&[("README.txt", "Shaders"), ("bundled.oso", SHADER)]);
+
+        let query = OslQuery::from_zip_reader(Cursor::new(&archive), "bundled").unwrap();
+        assert_eq!(query.shader_name(), "bundled");
+        assert_eq!(query.param_by_name("Kd").unwrap().name, "Kd");
+
+        assert!(matches!(
+            OslQuery::from_zip_reader(Cursor::new(&archive), "missing"),
+            Err(ParseError::Io(_))
+        ));
+        assert!(matches!(
+            OslQuery::from_zip_reader(Cursor::new(b"not a zip"), "bundled"),
+            Err(ParseError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_from_zip_reader_directory() {
+        let archive = zip(&[("shaders/v1/bundled.oso", SHADER)]);
+        let query = OslQuery::from_zip_reader(Cursor::new(archive), "bundled").unwrap();
+        assert_eq!(query.shader_name(), "bundled");
+    }
+
+    #[test]
+    fn test_open_from_zip() {
+        let path = std::env::temp_dir().join(format!(
+            "oslquery-petite-archive-{}.zip",
+            std::process::id()
+        ));
+        std::fs::write(&path, zip(&[("bundled.oso", SHADER)])).unwrap();
+        let query = OslQuery::open_from_zip(&path, "bundled");
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(query.unwrap().shader_name(), "bundled");
+
+        assert!(matches!(
+            OslQuery::open_from_zip("no/such/archive.zip", "bundled"),
+            Err(ParseError::Io(_))
+        ));
+    }
+}
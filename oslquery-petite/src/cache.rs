@@ -0,0 +1,198 @@
+//! Persistent cache of parsed shaders.
+//!
+//! [`ShaderCache`] keeps one file per source in a cache directory, holding
+//! the source's path, modification time and size, followed by
+//! [`OslQuery::to_cache_bytes`]. A source is only parsed again when one of
+//! these changes. Unreadable or corrupt entries are treated as missing and
+//! rewritten.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::parser::{OsoReader, ParseError};
+use crate::query::{OslQuery, fnv1a};
+
+/// Start of [`OslQuery::to_cache_bytes`]; the digit is the format version.
+const CACHE_MAGIC: &[u8] = b"OSLQCACHE1\n";
+
+/// Extension of cache entry files.
+const ENTRY_EXTENSION: &str = "oslqcache";
+
+impl OslQuery {
+    /// Serialize for [`ShaderCache`].
+    ///
+    /// The format is private to this crate and may change between versions.
+    /// The location of the code section is not kept.
+    pub fn to_cache_bytes(&self) -> Vec<u8> {
+        let mut bytes = CACHE_MAGIC.to_vec();
+        serde_json::to_writer(&mut bytes, self).expect("serializing a query cannot fail");
+        bytes
+    }
+
+    /// Deserialize the output of [`to_cache_bytes`](Self::to_cache_bytes).
+    pub fn from_cache_bytes(bytes: &[u8]) -> Result<Self, ParseError> {
+        let payload = bytes
+            .strip_prefix(CACHE_MAGIC)
+            .ok_or_else(|| ParseError::InvalidFormat("Not a shader cache entry".to_string()))?;
+        serde_json::from_slice(payload).map_err(|e| ParseError::Conversion(e.to_string()))
+    }
+}
+
+/// What a cache entry was created from.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+struct CacheKey {
+    path: PathBuf,
+    /// Modification time since the Unix epoch, in nanoseconds
+    modified: u128,
+    size: u64,
+}
+
+impl CacheKey {
+    fn of(path: &Path) -> io::Result<Self> {
+        let metadata = fs::metadata(path)?;
+        let modified = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        Ok(CacheKey {
+            path: path.to_path_buf(),
+            modified,
+            size: metadata.len(),
+        })
+    }
+}
+
+/// A directory of cached parse results, keyed by source path, modification
+/// time and size.
+#[derive(Debug, Clone)]
+pub struct ShaderCache {
+    dir: PathBuf,
+}
+
+impl ShaderCache {
+    /// Use `dir` as the cache directory. It is created when the first entry
+    /// is written.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        ShaderCache { dir: dir.into() }
+    }
+
+    /// The cache directory.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The file caching `source`.
+    pub fn entry_path(&self, source: impl AsRef<Path>) -> PathBuf {
+        let source = canonical(source.as_ref());
+        self.dir.join(format!(
+            "{:016x}.{}",
+            fnv1a(source.as_os_str().as_encoded_bytes()),
+            ENTRY_EXTENSION
+        ))
+    }
+
+    /// Get the parsed shader at `path`, from the cache if it is up to date.
+    ///
+    /// Otherwise the file is parsed and the cache entry (re)written. Failing
+    /// to write the cache is not an error.
+    pub fn get_or_parse(&self, path: impl AsRef<Path>) -> Result<Arc<OslQuery>, ParseError> {
+        let path = canonical(path.as_ref());
+        let key = CacheKey::of(&path)?;
+        let entry_path = self.entry_path(&path);
+
+        if let Some(query) = read_entry(&entry_path, &key) {
+            return Ok(Arc::new(query));
+        }
+
+        let query = OsoReader::new().parse_file(&path)?;
+        // The cache is only an optimization.
+        let _ = write_entry(&entry_path, &key, &query);
+        Ok(Arc::new(query))
+    }
+
+    /// Remove the cache entry of `path`, if any.
+    pub fn invalidate(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        match fs::remove_file(self.entry_path(path)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Remove entries not written within `max_age`. Returns the number of
+    /// entries removed.
+    pub fn prune(&self, max_age: Duration) -> io::Result<usize> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e),
+        };
+
+        let now = SystemTime::now();
+        let mut removed = 0;
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != ENTRY_EXTENSION) {
+                continue;
+            }
+            let age = fs::metadata(&path)
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|modified| now.duration_since(modified).ok())
+                .unwrap_or_default();
+            if age > max_age {
+                fs::remove_file(&path)?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+/// Read an entry, if it exists, is intact and matches `key`.
+fn read_entry(entry_path: &Path, key: &CacheKey) -> Option<OslQuery> {
+    let bytes = fs::read(entry_path).ok()?;
+    let newline = bytes.iter().position(|&b| b == b'\n')?;
+    let stored: CacheKey = serde_json::from_slice(&bytes[..newline]).ok()?;
+    if &stored != key {
+        return None;
+    }
+    OslQuery::from_cache_bytes(&bytes[newline + 1..]).ok()
+}
+
+/// Write an entry via a temporary file, so readers never see partial ones.
+fn write_entry(entry_path: &Path, key: &CacheKey, query: &OslQuery) -> io::Result<()> {
+    let mut bytes = serde_json::to_vec(key)?;
+    bytes.push(b'\n');
+    bytes.extend(query.to_cache_bytes());
+
+    if let Some(dir) = entry_path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let tmp_path = entry_path.with_extension(format!("{}.tmp", std::process::id()));
+    fs::write(&tmp_path, bytes)?;
+    fs::rename(&tmp_path, entry_path)
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    crate::searchpath::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_bytes_round_trip() {
+        let query = OslQuery::from_string(include_str!("../tests/ui.oso")).unwrap();
+        let restored = OslQuery::from_cache_bytes(&query.to_cache_bytes()).unwrap();
+        assert_eq!(restored.params(), query.params());
+        assert_eq!(restored.metadata(), query.metadata());
+
+        assert!(OslQuery::from_cache_bytes(b"garbage").is_err());
+        assert!(OslQuery::from_cache_bytes(b"OSLQCACHE1\n{").is_err());
+    }
+}
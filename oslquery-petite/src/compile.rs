@@ -0,0 +1,82 @@
+//! In-process compilation of OSL source with `liboslcomp`.
+//!
+//! Enabled by the `compile-osl` feature, which needs an OpenShadingLanguage
+//! installation at build time (see `build.rs`).
+
+use std::ffi::{CStr, CString, c_char, c_int};
+use std::ptr;
+
+use crate::parser::ParseError;
+use crate::query::OslQuery;
+
+unsafe extern "C" {
+    fn oslq_compile_buffer(
+        source: *const c_char,
+        stdosl_path: *const c_char,
+        oso: *mut *mut c_char,
+        errors: *mut *mut c_char,
+    ) -> c_int;
+
+    fn oslq_free_string(s: *mut c_char);
+}
+
+/// Take ownership of a string allocated by the shim.
+///
+/// # Safety
+///
+/// `s` must be null or a string returned by `oslq_compile_buffer()` that was
+/// not freed yet.
+unsafe fn take_string(s: *mut c_char) -> Option<String> {
+    if s.is_null() {
+        return None;
+    }
+    // SAFETY: non-null strings from the shim are NUL-terminated.
+    let string = unsafe { CStr::from_ptr(s) }.to_string_lossy().into_owned();
+    // SAFETY: allocated by the shim and not freed yet.
+    unsafe { oslq_free_string(s) };
+    Some(string)
+}
+
+/// Compile OSL source to OSO.
+///
+/// `stdosl.h` is taken from `$OSLQUERY_STDOSL_PATH` at run time, else from
+/// the OSL installation found at build time.
+pub fn compile_osl(source: &str) -> Result<String, ParseError> {
+    let source = CString::new(source)
+        .map_err(|_| ParseError::Compilation("source contains a NUL byte".to_string()))?;
+    let stdosl_path = std::env::var("OSLQUERY_STDOSL_PATH")
+        .ok()
+        .or_else(|| option_env!("OSLQUERY_STDOSL_PATH").map(String::from))
+        .and_then(|path| CString::new(path).ok());
+
+    let mut oso = ptr::null_mut();
+    let mut errors = ptr::null_mut();
+    // SAFETY: all pointers are valid for the duration of the call; the shim
+    // only writes to `oso` and `errors`.
+    let ok = unsafe {
+        oslq_compile_buffer(
+            source.as_ptr(),
+            stdosl_path.as_ref().map_or(ptr::null(), |p| p.as_ptr()),
+            &mut oso,
+            &mut errors,
+        )
+    };
+    // SAFETY: both are null or owned strings from the shim.
+    let (oso, errors) = unsafe { (take_string(oso), take_string(errors)) };
+
+    match (ok, oso) {
+        (1, Some(oso)) => Ok(oso),
+        _ => Err(ParseError::Compilation(
+            errors
+                .map(|e| e.trim_end().to_string())
+                .unwrap_or_else(|| "unknown error".to_string()),
+        )),
+    }
+}
+
+impl OslQuery {
+    /// Compile OSL source in memory and parse the result.
+    pub fn from_osl_source(osl_source: &str) -> Result<Self, ParseError> {
+        Self::from_string(&compile_osl(osl_source)?)
+    }
+}
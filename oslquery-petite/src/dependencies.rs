@@ -0,0 +1,211 @@
+//! Dependencies between parameters declared in metadata.
+//!
+//! Some shader systems name the parameters a parameter depends on in a
+//! metadata entry, e.g. `%meta{string,depends_on,"enable,mode"}`, so a UI can
+//! disable it until those are set.
+//! [`OslQuery::parameter_dependencies_from_metadata`] reads these lists and
+//! [`OslQuery::parameter_dependency_topological_sort`] orders the parameters
+//! so each comes after the ones it depends on.
+
+use std::collections::{BTreeSet, HashMap};
+
+use thiserror::Error;
+use ustr::Ustr;
+
+use crate::query::OslQuery;
+use crate::types::{MetadataValue, Parameter};
+
+/// The parameters could not be ordered because their dependencies form at
+/// least one cycle.
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+#[error("Parameter dependency cycle between {}", join_names(participants))]
+pub struct CycleError {
+    /// The parameters on a cycle, in declaration order
+    pub participants: Vec<Ustr>,
+}
+
+impl OslQuery {
+    /// Map each parameter with `dep_key` metadata to the parameters it
+    /// names.
+    ///
+    /// The metadata is a comma-separated string, e.g. `"enable, mode"`, or a
+    /// string array. Whitespace around names is ignored, as are empty and
+    /// repeated names. Names are returned as written, whether or not a
+    /// parameter of that name exists.
+    pub fn parameter_dependencies_from_metadata(&self, dep_key: &str) -> HashMap<Ustr, Vec<Ustr>> {
+        self.params()
+            .iter()
+            .filter_map(|param| Some((param.name, dependencies(param, dep_key)?)))
+            .collect()
+    }
+
+    /// Order the parameters so each comes after the parameters its `dep_key`
+    /// metadata names, see
+    /// [`parameter_dependencies_from_metadata`](Self::parameter_dependencies_from_metadata).
+    ///
+    /// Otherwise declaration order is kept. Names that are not parameters
+    /// are ignored.
+    pub fn parameter_dependency_topological_sort(
+        &self,
+        dep_key: &str,
+    ) -> Result<Vec<&Parameter>, CycleError> {
+        let params = self.params();
+        let index: HashMap<Ustr, usize> = params
+            .iter()
+            .enumerate()
+            .map(|(i, param)| (param.name, i))
+            .collect();
+
+        // Kahn's algorithm, with edges from each dependency to its dependents.
+        let mut dependents = vec![Vec::new(); params.len()];
+        let mut unresolved = vec![0usize; params.len()];
+        for (i, param) in params.iter().enumerate() {
+            for name in dependencies(param, dep_key).unwrap_or_default() {
+                if let Some(&dependency) = index.get(&name) {
+                    dependents[dependency].push(i);
+                    unresolved[i] += 1;
+                }
+            }
+        }
+
+        // Always take the first ready parameter in declaration order.
+        let mut ready: BTreeSet<usize> =
+            (0..params.len()).filter(|&i| unresolved[i] == 0).collect();
+        let mut sorted = Vec::with_capacity(params.len());
+        while let Some(i) = ready.pop_first() {
+            sorted.push(&params[i]);
+            for &dependent in &dependents[i] {
+                unresolved[dependent] -= 1;
+                if unresolved[dependent] == 0 {
+                    ready.insert(dependent);
+                }
+            }
+        }
+
+        if sorted.len() == params.len() {
+            return Ok(sorted);
+        }
+
+        // What is left depends on a cycle. Drop the parameters nothing left
+        // depends on until only the cycles remain.
+        let mut remaining: Vec<bool> = unresolved.iter().map(|&n| n > 0).collect();
+        loop {
+            let leaves: Vec<usize> = (0..params.len())
+                .filter(|&i| remaining[i] && !dependents[i].iter().any(|&d| remaining[d]))
+                .collect();
+            if leaves.is_empty() {
+                break;
+            }
+            for i in leaves {
+                remaining[i] = false;
+            }
+        }
+
+        Err(CycleError {
+            participants: (0..params.len())
+                .filter(|&i| remaining[i])
+                .map(|i| params[i].name)
+                .collect(),
+        })
+    }
+}
+
+fn join_names(names: &[Ustr]) -> String {
+    names
+        .iter()
+        .map(Ustr::as_str)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The names in the `dep_key` metadata of `param`, if it has any.
+fn dependencies(param: &Parameter, dep_key: &str) -> Option<Vec<Ustr>> {
+    let names: Vec<&str> = match &param.find_metadata(dep_key)?.value {
+        MetadataValue::String(s) => s.split(',').collect(),
+        MetadataValue::StringArray(values) => values.iter().map(|s| s.as_str()).collect(),
+        _ => return None,
+    };
+
+    let mut deps: Vec<Ustr> = Vec::new();
+    for name in names.into_iter().map(str::trim).filter(|n| !n.is_empty()) {
+        let name = Ustr::from(name);
+        if !deps.contains(&name) {
+            deps.push(name);
+        }
+    }
+    Some(deps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shader(params: &str) -> OslQuery {
+        OslQuery::from_string(&format!(
+            "OpenShadingLanguage 1.12\nsurface deps\n{}code ___main___\n",
+            params
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parameter_dependencies_from_metadata() {
+        let query = shader(
+            "param\tfloat\tamount\t1\t%meta{string,depends_on,\"enable, mode,,enable\"}\n\
+             param\tint\tenable\t0\n\
+             param\tint\tmode\t0\t%meta{string,depends_on,\"enable\"} %meta{int,drives,1}\n\
+             param\tfloat\tother\t0\t%meta{int,depends_on,1}\n",
+        );
+
+        let deps = query.parameter_dependencies_from_metadata("depends_on");
+        assert_eq!(deps.len(), 2);
+        assert_eq!(deps[&Ustr::from("amount")], ["enable", "mode"]);
+        assert_eq!(deps[&Ustr::from("mode")], ["enable"]);
+
+        // Not strings.
+        assert!(
+            query
+                .parameter_dependencies_from_metadata("drives")
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_parameter_dependency_topological_sort() {
+        let query = shader(
+            "param\tfloat\tamount\t1\t%meta{string,depends_on,\"enable,mode,missing\"}\n\
+             param\tint\tenable\t0\n\
+             param\tfloat\tfree\t0\n\
+             param\tint\tmode\t0\t%meta{string,depends_on,\"enable\"}\n",
+        );
+
+        let names: Vec<&str> = query
+            .parameter_dependency_topological_sort("depends_on")
+            .unwrap()
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect();
+        assert_eq!(names, ["enable", "free", "mode", "amount"]);
+    }
+
+    #[test]
+    fn test_dependency_cycle() {
+        let query = shader(
+            "param\tfloat\ta\t0\t%meta{string,depends_on,\"c\"}\n\
+             param\tfloat\tb\t0\t%meta{string,depends_on,\"a\"}\n\
+             param\tfloat\tc\t0\t%meta{string,depends_on,\"b\"}\n\
+             param\tfloat\tafter\t0\t%meta{string,depends_on,\"c\"}\n\
+             param\tfloat\tself\t0\t%meta{string,depends_on,\"self\"}\n\
+             param\tfloat\tfree\t0\n",
+        );
+
+        let error = query
+            .parameter_dependency_topological_sort("depends_on")
+            .unwrap_err();
+        assert_eq!(error.participants, ["a", "b", "c", "self"]);
+        assert_eq!(
+            error.to_string(),
+            "Parameter dependency cycle between a, b, c, self"
+        );
+    }
+}
@@ -0,0 +1,331 @@
+//! Comparing two versions of a shader interface.
+//!
+//! Parameters and metadata are matched by name. Each diff borrows from both
+//! sides, so the entries point into the queries that were compared.
+
+use crate::query::{CANONICAL_EPSILON, OslQuery};
+use crate::types::{Metadata, Parameter};
+
+/// Differences between two lists of metadata.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MetadataDiff<'a> {
+    /// Metadata only in the new list
+    pub added: Vec<&'a Metadata>,
+    /// Metadata only in the old list
+    pub removed: Vec<&'a Metadata>,
+    /// Metadata in both lists with different values, as `(old, new)`
+    pub changed: Vec<(&'a Metadata, &'a Metadata)>,
+}
+
+impl<'a> MetadataDiff<'a> {
+    /// Check if the lists are the same.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// The changed metadata whose type changed, not just its value, as
+    /// `(old, new)`. Hosts that read the value with a fixed type break on
+    /// these.
+    pub fn type_changed(&self) -> impl Iterator<Item = (&'a Metadata, &'a Metadata)> + '_ {
+        self.changed
+            .iter()
+            .copied()
+            .filter(|(old, new)| !old.same_type_as(new))
+    }
+}
+
+/// Differences between two shaders.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ShaderDiff<'a> {
+    /// Parameters only in the new shader
+    pub added: Vec<&'a Parameter>,
+    /// Parameters only in the old shader
+    pub removed: Vec<&'a Parameter>,
+    /// Parameters in both shaders whose direction, type, default or metadata
+    /// differ once in [canonical form](OslQuery::canonical_form), as
+    /// `(old, new)`
+    pub changed: Vec<(&'a Parameter, &'a Parameter)>,
+    /// Differences in the shader's global metadata
+    pub metadata_diff: MetadataDiff<'a>,
+}
+
+impl<'a> ShaderDiff<'a> {
+    /// Check if the shaders have the same interface.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.changed.is_empty()
+            && self.metadata_diff.is_empty()
+    }
+
+    /// The changed parameters that differ only in the space of their
+    /// default, e.g. a color in `sRGB` rather than `linear` with the same
+    /// numbers, as `(old, new)`.
+    pub fn space_changed(&self) -> impl Iterator<Item = (&'a Parameter, &'a Parameter)> + '_ {
+        self.changed
+            .iter()
+            .copied()
+            .filter(|(old, new)| space_only(old, new))
+    }
+
+    /// The changed parameters whose direction, type, default values or
+    /// metadata differ, as `(old, new)`; the others are
+    /// [`space_changed`](Self::space_changed).
+    pub fn value_changed(&self) -> impl Iterator<Item = (&'a Parameter, &'a Parameter)> + '_ {
+        self.changed
+            .iter()
+            .copied()
+            .filter(|(old, new)| !space_only(old, new))
+    }
+}
+
+impl OslQuery {
+    /// Compare the parameters and global metadata with those of `other`,
+    /// which is taken to be the newer version.
+    pub fn diff<'a>(&'a self, other: &'a OslQuery) -> ShaderDiff<'a> {
+        let (added, removed, changed) = diff_by_name(
+            self.params(),
+            other.params(),
+            |p| p.name.as_str(),
+            same_interface,
+        );
+
+        ShaderDiff {
+            added,
+            removed,
+            changed,
+            metadata_diff: self.diff_metadata(other),
+        }
+    }
+
+    /// Compare the global metadata with that of `other`, which is taken to
+    /// be the newer version.
+    pub fn diff_metadata<'a>(&'a self, other: &'a OslQuery) -> MetadataDiff<'a> {
+        metadata_diff(self.metadata(), other.metadata())
+    }
+}
+
+impl Parameter {
+    /// Compare the metadata with that of `other`, which is taken to be the
+    /// newer version.
+    pub fn diff_metadata<'a>(&'a self, other: &'a Parameter) -> MetadataDiff<'a> {
+        metadata_diff(self.metadata(), other.metadata())
+    }
+}
+
+fn metadata_diff<'a>(old: &'a [Metadata], new: &'a [Metadata]) -> MetadataDiff<'a> {
+    let (added, removed, changed) = diff_by_name(old, new, |m| m.name.as_str(), PartialEq::eq);
+    debug_assert!(changed.iter().all(|(old, new)| old.same_name_as(new)));
+    MetadataDiff {
+        added,
+        removed,
+        changed,
+    }
+}
+
+type ItemDiff<'a, T> = (Vec<&'a T>, Vec<&'a T>, Vec<(&'a T, &'a T)>);
+
+/// Check if two versions of a parameter are the same once in canonical form,
+/// see [`OslQuery::canonical_form`]. Metadata order and float noise in the
+/// defaults are not changes.
+fn same_interface(old: &Parameter, new: &Parameter) -> bool {
+    old == new || canonical(old) == canonical(new)
+}
+
+/// Check if two versions of a parameter differ only in the space of their
+/// default, see
+/// [`TypedParameter::eq_ignoring_space`](crate::TypedParameter::eq_ignoring_space).
+fn space_only(old: &Parameter, new: &Parameter) -> bool {
+    let (old, new) = (canonical(old), canonical(new));
+    old.is_output() == new.is_output()
+        && old.metadata() == new.metadata()
+        && old.typed_param().eq_ignoring_space(new.typed_param())
+}
+
+fn canonical(param: &Parameter) -> Parameter {
+    let mut param = param.clone();
+    param.canonicalize(CANONICAL_EPSILON);
+    param
+}
+
+/// Split `old` and `new` into added, removed and changed items, matching them
+/// by `name` and comparing them with `same`. Items are reported in the order
+/// of the list they come from.
+fn diff_by_name<'a, T>(
+    old: &'a [T],
+    new: &'a [T],
+    name: impl Fn(&T) -> &str,
+    same: impl Fn(&T, &T) -> bool,
+) -> ItemDiff<'a, T> {
+    let find = |items: &'a [T], wanted: &str| items.iter().find(|item| name(item) == wanted);
+
+    let mut removed = Vec::new();
+    let mut changed = Vec::new();
+    for old_item in old {
+        match find(new, name(old_item)) {
+            Some(new_item) if !same(old_item, new_item) => changed.push((old_item, new_item)),
+            Some(_) => {}
+            None => removed.push(old_item),
+        }
+    }
+
+    let added = new
+        .iter()
+        .filter(|new_item| find(old, name(new_item)).is_none())
+        .collect();
+
+    (added, removed, changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::MetadataValue;
+
+    fn query(version: i32, help: &str) -> OslQuery {
+        OslQuery::from_string(&format!(
+            "OpenShadingLanguage 1.12\n\
+             surface test\t%meta{{int,version,{}}} %meta{{string,help,\"{}\"}}\n\
+             param\tfloat\tKd\t0.5\t%meta{{string,label,\"Diffuse\"}}\n\
+             code ___main___\n",
+            version, help
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_diff_metadata() {
+        let old = query(1, "Plastic");
+        let mut new = query(2, "Plastic");
+        new.add_metadata(Metadata {
+            name: "author".into(),
+            value: MetadataValue::String("me".into()),
+        });
+
+        let diff = old.diff_metadata(&new);
+        assert_eq!(diff.added, vec![&new.metadata()[2]]);
+        assert!(diff.removed.is_empty());
+        assert_eq!(diff.changed, vec![(&old.metadata()[0], &new.metadata()[0])]);
+
+        let diff = new.diff_metadata(&old);
+        assert_eq!(diff.removed, vec![&new.metadata()[2]]);
+
+        assert!(old.diff_metadata(&query(1, "Plastic")).is_empty());
+        assert_eq!(diff.type_changed().count(), 0);
+    }
+
+    #[test]
+    fn test_metadata_type_changed() {
+        let old = query(1, "Plastic");
+        let new = OslQuery::from_string(
+            "OpenShadingLanguage 1.12\n\
+             surface test\t%meta{string,version,\"2\"} %meta{string,help,\"Plastic\"}\n\
+             code ___main___\n",
+        )
+        .unwrap();
+
+        let diff = old.diff_metadata(&new);
+        assert_eq!(diff.changed.len(), 1);
+        let (old_version, new_version) = diff.type_changed().next().unwrap();
+        assert!(old_version.same_name_as(new_version));
+        assert!(!old_version.is_same_entry(new_version));
+        assert_eq!(old_version.value.variant_name(), "Int");
+        assert_eq!(new_version.value.variant_name(), "String");
+
+        // Same name and type, different value
+        let other = query(3, "Plastic");
+        assert!(old.metadata()[0].is_same_entry(&other.metadata()[0]));
+        assert!(!old.metadata()[0].same_type_as(&old.metadata()[1]));
+    }
+
+    #[test]
+    fn test_parameter_diff_metadata() {
+        let old = Parameter::new_input("Kd", crate::TypedParameter::Float { default: None });
+        let mut new = old.clone();
+        new.add_metadata("label", MetadataValue::String("Diffuse".into()));
+
+        let diff = old.diff_metadata(&new);
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].name.as_str(), "label");
+        assert!(new.diff_metadata(&new).is_empty());
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = query(1, "Plastic");
+        let mut new = query(1, "Shiny plastic");
+        new.add_parameter(Parameter::new_input(
+            "Ks",
+            crate::TypedParameter::Float { default: Some(0.1) },
+        ));
+        new.param_by_name_mut("Kd").unwrap().metadata_mut().clear();
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.added, vec![new.param_by_name("Ks").unwrap()]);
+        assert!(diff.removed.is_empty());
+        assert_eq!(
+            diff.changed,
+            vec![(
+                old.param_by_name("Kd").unwrap(),
+                new.param_by_name("Kd").unwrap()
+            )]
+        );
+        assert_eq!(diff.metadata_diff.changed.len(), 1);
+        assert_eq!(diff.metadata_diff.changed[0].1.name.as_str(), "help");
+
+        assert!(old.diff(&old).is_empty());
+    }
+
+    #[test]
+    fn test_diff_canonical() {
+        let old = OslQuery::from_string(
+            "OpenShadingLanguage 1.12\n\
+             surface test\n\
+             param\tfloat\tKd\t0.5\t%meta{string,label,\"Diffuse\"} %meta{float,min,0}\n\
+             param\tvector\tdir\t0 0 1\n\
+             code ___main___\n",
+        )
+        .unwrap();
+        let new = OslQuery::from_string(
+            "OpenShadingLanguage 1.12\n\
+             surface test\n\
+             param\tvector\tdir\t0.00000001 -0. 1\n\
+             param\tfloat\tKd\t0.5\t%meta{float,min,0} %meta{string,label,\"Diffuse\"}\n\
+             code ___main___\n",
+        )
+        .unwrap();
+
+        // Reordered parameters and metadata and float noise are not changes.
+        assert!(old.diff(&new).is_empty());
+    }
+
+    #[test]
+    fn test_space_changed() {
+        let old = OslQuery::from_string(
+            "OpenShadingLanguage 1.12\n\
+             surface test\n\
+             param\tcolor\ttint\t0.5 0.5 0.5\t%space{\"linear\"}\n\
+             param\tcolor\tbase\t1 1 1\t%space{\"linear\"}\n\
+             param\tpoint\tcenter\t0 0 0\t%space{\"object\"}\n\
+             code ___main___\n",
+        )
+        .unwrap();
+        let new = OslQuery::from_string(
+            "OpenShadingLanguage 1.12\n\
+             surface test\n\
+             param\tcolor\ttint\t0.5 0.5 0.5\t%space{\"sRGB\"}\n\
+             param\tcolor\tbase\t1 0 0\t%space{\"sRGB\"}\n\
+             param\tpoint\tcenter\t0 0 0\t%space{\"object\"}\n\
+             code ___main___\n",
+        )
+        .unwrap();
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.changed.len(), 2);
+        let names = |changes: Vec<(&Parameter, &Parameter)>| -> Vec<&str> {
+            changes.iter().map(|(old, _)| old.name.as_str()).collect()
+        };
+        assert_eq!(names(diff.space_changed().collect()), ["tint"]);
+        assert_eq!(names(diff.value_changed().collect()), ["base"]);
+    }
+}
@@ -0,0 +1,234 @@
+//! CSV/TSV parameter tables.
+//!
+//! One row per parameter, with a header row. Fields containing the
+//! delimiter, quotes or line breaks are quoted as per RFC 4180.
+
+use std::fmt::{self, Write};
+
+use super::{default_string, value_string};
+use crate::query::OslQuery;
+use crate::types::{Parameter, TypedParameter};
+
+/// A column of the table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CsvColumn {
+    /// Parameter name
+    Name,
+    /// `input` or `output`
+    Direction,
+    /// Element type, e.g. `color` for a `color[4]`
+    Type,
+    /// Array length, `-1` for unsized arrays and empty for non-arrays
+    ArrayLen,
+    /// Default value, space-separated components
+    Default,
+    /// `label` metadata
+    Label,
+    /// `page` metadata
+    Page,
+    /// `help` metadata
+    Help,
+    /// `min` metadata
+    Min,
+    /// `max` metadata
+    Max,
+    /// `widget` metadata
+    Widget,
+}
+
+impl CsvColumn {
+    /// All columns in their default order.
+    pub const ALL: [CsvColumn; 11] = [
+        CsvColumn::Name,
+        CsvColumn::Direction,
+        CsvColumn::Type,
+        CsvColumn::ArrayLen,
+        CsvColumn::Default,
+        CsvColumn::Label,
+        CsvColumn::Page,
+        CsvColumn::Help,
+        CsvColumn::Min,
+        CsvColumn::Max,
+        CsvColumn::Widget,
+    ];
+
+    /// The column's header.
+    pub fn header(&self) -> &'static str {
+        match self {
+            CsvColumn::Name => "name",
+            CsvColumn::Direction => "direction",
+            CsvColumn::Type => "type",
+            CsvColumn::ArrayLen => "array_len",
+            CsvColumn::Default => "default",
+            CsvColumn::Label => "label",
+            CsvColumn::Page => "page",
+            CsvColumn::Help => "help",
+            CsvColumn::Min => "min",
+            CsvColumn::Max => "max",
+            CsvColumn::Widget => "widget",
+        }
+    }
+
+    fn value(&self, param: &Parameter) -> String {
+        let metadata = |name| {
+            param
+                .find_metadata(name)
+                .map(|m| value_string(&m.value))
+                .unwrap_or_default()
+        };
+
+        match self {
+            CsvColumn::Name => param.name.to_string(),
+            CsvColumn::Direction => if param.is_output() { "output" } else { "input" }.to_string(),
+            CsvColumn::Type => {
+                let typestring = param.typed_param().to_string();
+                match typestring.split_once('[') {
+                    Some((element, _)) => element.to_string(),
+                    None => typestring,
+                }
+            }
+            CsvColumn::ArrayLen => array_len(param.typed_param())
+                .map(|len| len.to_string())
+                .unwrap_or_default(),
+            CsvColumn::Default => default_string(param.typed_param()).unwrap_or_default(),
+            CsvColumn::Label => metadata("label"),
+            CsvColumn::Page => metadata("page"),
+            CsvColumn::Help => metadata("help"),
+            CsvColumn::Min => metadata("min"),
+            CsvColumn::Max => metadata("max"),
+            CsvColumn::Widget => metadata("widget"),
+        }
+    }
+}
+
+/// Options for [`to_csv`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvOptions {
+    /// Field delimiter; `'\t'` gives TSV.
+    pub delimiter: char,
+    /// Columns to emit, in order.
+    pub columns: Vec<CsvColumn>,
+    /// Include output parameters.
+    pub include_outputs: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            delimiter: ',',
+            columns: CsvColumn::ALL.to_vec(),
+            include_outputs: true,
+        }
+    }
+}
+
+impl CsvOptions {
+    /// Default options with tab as the delimiter.
+    pub fn tsv() -> Self {
+        CsvOptions {
+            delimiter: '\t',
+            ..Default::default()
+        }
+    }
+}
+
+/// Render the parameters of `query` as a CSV (or TSV) table.
+pub fn to_csv(query: &OslQuery, options: &CsvOptions) -> String {
+    let mut out = String::new();
+    write_table(&mut out, query, options).expect("writing to a String cannot fail");
+    out
+}
+
+fn write_table(out: &mut String, query: &OslQuery, options: &CsvOptions) -> fmt::Result {
+    let headers: Vec<String> = options
+        .columns
+        .iter()
+        .map(|column| column.header().to_string())
+        .collect();
+    write_row(out, &headers, options.delimiter)?;
+
+    for param in query.params() {
+        if param.is_output() && !options.include_outputs {
+            continue;
+        }
+        let fields: Vec<String> = options
+            .columns
+            .iter()
+            .map(|column| column.value(param))
+            .collect();
+        write_row(out, &fields, options.delimiter)?;
+    }
+
+    Ok(())
+}
+
+fn write_row(out: &mut String, fields: &[String], delimiter: char) -> fmt::Result {
+    for (index, field) in fields.iter().enumerate() {
+        if index > 0 {
+            out.write_char(delimiter)?;
+        }
+        out.write_str(&quote_field(field, delimiter))?;
+    }
+    out.write_char('\n')
+}
+
+/// Quote a field if it contains the delimiter, a quote or a line break.
+fn quote_field(field: &str, delimiter: char) -> String {
+    if field.contains([delimiter, '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn array_len(typed_param: &TypedParameter) -> Option<i64> {
+    match typed_param {
+        TypedParameter::IntArray { size, .. }
+        | TypedParameter::FloatArray { size, .. }
+        | TypedParameter::StringArray { size, .. }
+        | TypedParameter::ColorArray { size, .. }
+        | TypedParameter::PointArray { size, .. }
+        | TypedParameter::VectorArray { size, .. }
+        | TypedParameter::NormalArray { size, .. }
+        | TypedParameter::MatrixArray { size, .. } => Some(*size as i64),
+        _ if typed_param.is_dynamic_array() => Some(-1),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::MetadataValue;
+
+    #[test]
+    fn test_help_with_comma_and_newline() {
+        let mut query =
+            OslQuery::from_string("OpenShadingLanguage 1.12\nsurface test\ncode ___main___\n")
+                .unwrap();
+        let mut param = Parameter::new_input("scale", TypedParameter::Float { default: Some(2.0) });
+        param.add_metadata(
+            "help",
+            MetadataValue::String("Scale, in \"world\" units.\nDefaults to 2.".into()),
+        );
+        query.add_parameter(param);
+
+        let options = CsvOptions {
+            columns: vec![CsvColumn::Name, CsvColumn::Default, CsvColumn::Help],
+            ..Default::default()
+        };
+        assert_eq!(
+            to_csv(&query, &options),
+            "name,default,help\nscale,2,\"Scale, in \"\"world\"\" units.\nDefaults to 2.\"\n"
+        );
+    }
+
+    #[test]
+    fn test_quote_field() {
+        assert_eq!(quote_field("plain", ','), "plain");
+        assert_eq!(quote_field("a, b", ','), "\"a, b\"");
+        assert_eq!(quote_field("a, b", '\t'), "a, b");
+        assert_eq!(quote_field("say \"hi\"", ','), "\"say \"\"hi\"\"\"");
+        assert_eq!(quote_field("two\nlines", '\t'), "\"two\nlines\"");
+    }
+}
@@ -0,0 +1,320 @@
+//! Maya Attribute Editor template generation.
+//!
+//! Produces the content of an `AE<node>Template.mel` file from the parameter
+//! metadata of a shader:
+//!
+//! - `page` groups controls into collapsible layouts.
+//! - `label` and `help` become the control label and annotation.
+//! - `widget` `"popup"`/`"mapper"` on `int` parameters use an
+//!   `attrEnumOptionMenuGrp` filled from `options`.
+//! - `widget` `"filename"` on `string` parameters gets a file browser.
+//! - `widget` `"null"` suppresses the parameter.
+//!
+//! Output parameters are left to Maya's extra controls.
+
+use std::fmt::{self, Write};
+
+use super::parse_options;
+use crate::query::OslQuery;
+use crate::types::{Parameter, TypedParameter};
+
+/// Options for [`maya_ae_template`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MayaTemplateOptions {
+    /// Maya node type name. Defaults to the shader name.
+    pub node_name: Option<String>,
+    /// Prefixes stripped from parameter names to form attribute names
+    /// (e.g. `i_` turns `i_color` into `color`). The first prefix that
+    /// leaves a non-empty name wins.
+    pub strip_prefixes: Vec<String>,
+}
+
+impl MayaTemplateOptions {
+    /// Map an OSL parameter name to its Maya attribute name.
+    pub fn attribute_name<'a>(&self, param_name: &'a str) -> &'a str {
+        self.strip_prefixes
+            .iter()
+            .find_map(|prefix| {
+                param_name
+                    .strip_prefix(prefix.as_str())
+                    .filter(|name| !name.is_empty())
+            })
+            .unwrap_or(param_name)
+    }
+}
+
+/// How a parameter is presented in the template.
+enum Control<'a> {
+    /// Plain `-addControl`.
+    Default,
+    /// Enum menu with `(value, label)` items.
+    Enum(Vec<(i32, &'a str)>),
+    /// Text field with a file browser button.
+    Filename,
+}
+
+/// Generate the MEL source of an Attribute Editor template for `query`.
+pub fn maya_ae_template(query: &OslQuery, options: &MayaTemplateOptions) -> String {
+    let node = options
+        .node_name
+        .as_deref()
+        .unwrap_or_else(|| query.shader_name());
+
+    let mut out = String::new();
+    write_template(&mut out, query, node, options).expect("writing to a String cannot fail");
+    out
+}
+
+fn write_template(
+    out: &mut String,
+    query: &OslQuery,
+    node: &str,
+    options: &MayaTemplateOptions,
+) -> fmt::Result {
+    // Visible input parameters grouped by page, in order of first appearance
+    let mut pages: Vec<(Option<&str>, Vec<&Parameter>)> = Vec::new();
+    let mut hidden = Vec::new();
+    for param in query.input_params() {
+        if param.is_closure() {
+            continue;
+        }
+        if param.widget() == Some("null") {
+            hidden.push(options.attribute_name(param.name.as_str()));
+            continue;
+        }
+        let page = param.ui_page();
+        match pages.iter_mut().find(|(p, _)| *p == page) {
+            Some((_, params)) => params.push(param),
+            None => pages.push((page, vec![param])),
+        }
+    }
+    // Parameters without a page go first, outside any layout
+    pages.sort_by_key(|(page, _)| page.is_some());
+
+    // Custom control procedures must exist before the template refers to them
+    for (_, params) in &pages {
+        for param in params {
+            let attr = options.attribute_name(param.name.as_str());
+            match control(param) {
+                Control::Default => {}
+                Control::Enum(items) => write_enum_procs(out, node, attr, param, &items)?,
+                Control::Filename => write_filename_procs(out, node, attr, param)?,
+            }
+        }
+    }
+
+    writeln!(out, "global proc AE{}Template(string $nodeName)", node)?;
+    writeln!(out, "{{")?;
+    writeln!(out, "    editorTemplate -beginScrollLayout;")?;
+
+    for (page, params) in &pages {
+        writeln!(out)?;
+        let indent = if let Some(page) = page {
+            writeln!(
+                out,
+                "    editorTemplate -beginLayout \"{}\" -collapse 0;",
+                escape_mel(page)
+            )?;
+            "        "
+        } else {
+            "    "
+        };
+
+        for param in params {
+            let attr = options.attribute_name(param.name.as_str());
+            match control(param) {
+                Control::Default => {
+                    write!(out, "{}editorTemplate", indent)?;
+                    if let Some(label) = param.label() {
+                        write!(out, " -label \"{}\"", escape_mel(label))?;
+                    }
+                    if let Some(help) = param.help() {
+                        write!(out, " -annotation \"{}\"", escape_mel(help))?;
+                    }
+                    writeln!(out, " -addControl \"{}\";", attr)?;
+                }
+                Control::Enum(_) | Control::Filename => {
+                    writeln!(
+                        out,
+                        "{indent}editorTemplate -callCustom \"AE{node}_{attr}_New\" \"AE{node}_{attr}_Replace\" \"{attr}\";"
+                    )?;
+                }
+            }
+        }
+
+        if page.is_some() {
+            writeln!(out, "    editorTemplate -endLayout;")?;
+        }
+    }
+
+    writeln!(out)?;
+    for attr in hidden {
+        writeln!(out, "    editorTemplate -suppress \"{}\";", attr)?;
+    }
+    writeln!(out, "    AEdependNodeTemplate $nodeName;")?;
+    writeln!(out, "    editorTemplate -addExtraControls;")?;
+    writeln!(out, "    editorTemplate -endScrollLayout;")?;
+    writeln!(out, "}}")
+}
+
+fn control(param: &Parameter) -> Control<'_> {
+    match (param.widget(), param.typed_param()) {
+        (Some("popup" | "mapper"), TypedParameter::Int { .. }) => {
+            let items = param
+                .string_metadata("options")
+                .map(parse_options)
+                .unwrap_or_default()
+                .into_iter()
+                .enumerate()
+                .map(|(index, (label, value))| {
+                    let value = value
+                        .and_then(|v| v.trim().parse().ok())
+                        .unwrap_or(index as i32);
+                    (value, label)
+                })
+                .collect::<Vec<_>>();
+            if items.is_empty() {
+                Control::Default
+            } else {
+                Control::Enum(items)
+            }
+        }
+        (Some("filename"), TypedParameter::String { .. }) => Control::Filename,
+        _ => Control::Default,
+    }
+}
+
+fn write_enum_procs(
+    out: &mut String,
+    node: &str,
+    attr: &str,
+    param: &Parameter,
+    items: &[(i32, &str)],
+) -> fmt::Result {
+    let menu = format!("AE{}_{}_Menu", node, attr);
+
+    writeln!(out, "global proc AE{}_{}_New(string $attr)", node, attr)?;
+    writeln!(out, "{{")?;
+    write!(out, "    attrEnumOptionMenuGrp")?;
+    write_label_flags(out, param)?;
+    writeln!(out, " -attribute $attr")?;
+    for (value, label) in items {
+        writeln!(
+            out,
+            "        -enumeratedItem {} \"{}\"",
+            value,
+            escape_mel(label)
+        )?;
+    }
+    writeln!(out, "        {};", menu)?;
+    writeln!(out, "}}")?;
+    writeln!(out)?;
+
+    writeln!(out, "global proc AE{}_{}_Replace(string $attr)", node, attr)?;
+    writeln!(out, "{{")?;
+    writeln!(
+        out,
+        "    attrEnumOptionMenuGrp -edit -attribute $attr {};",
+        menu
+    )?;
+    writeln!(out, "}}")?;
+    writeln!(out)
+}
+
+fn write_filename_procs(
+    out: &mut String,
+    node: &str,
+    attr: &str,
+    param: &Parameter,
+) -> fmt::Result {
+    let field = format!("AE{}_{}_File", node, attr);
+    let browse = format!("AE{}_{}_Browse", node, attr);
+
+    writeln!(out, "global proc AE{}_{}_New(string $attr)", node, attr)?;
+    writeln!(out, "{{")?;
+    writeln!(
+        out,
+        "    setUITemplate -pushTemplate attributeEditorTemplate;"
+    )?;
+    write!(out, "    textFieldButtonGrp")?;
+    write_label_flags(out, param)?;
+    writeln!(out, " -buttonLabel \"...\" {};", field)?;
+    writeln!(out, "    setUITemplate -popTemplate;")?;
+    writeln!(out, "    AE{}_{}_Replace $attr;", node, attr)?;
+    writeln!(out, "}}")?;
+    writeln!(out)?;
+
+    writeln!(out, "global proc AE{}_{}_Replace(string $attr)", node, attr)?;
+    writeln!(out, "{{")?;
+    writeln!(
+        out,
+        "    connectControl -index 2 -fileName {} $attr;",
+        field
+    )?;
+    writeln!(
+        out,
+        "    textFieldButtonGrp -edit -buttonCommand (\"{} \\\"\" + $attr + \"\\\"\") {};",
+        browse, field
+    )?;
+    writeln!(out, "}}")?;
+    writeln!(out)?;
+
+    writeln!(out, "global proc {}(string $attr)", browse)?;
+    writeln!(out, "{{")?;
+    writeln!(out, "    string $files[] = `fileDialog2 -fileMode 1`;")?;
+    writeln!(out, "    if (size($files) > 0)")?;
+    writeln!(out, "        setAttr -type \"string\" $attr $files[0];")?;
+    writeln!(out, "}}")?;
+    writeln!(out)
+}
+
+/// Write ` -label "..."` (falling back to the parameter name, as custom
+/// controls get no automatic label) and ` -annotation "..."`.
+fn write_label_flags(out: &mut String, param: &Parameter) -> fmt::Result {
+    let label = param.label().unwrap_or(param.name.as_str());
+    write!(out, " -label \"{}\"", escape_mel(label))?;
+    if let Some(help) = param.help() {
+        write!(out, " -annotation \"{}\"", escape_mel(help))?;
+    }
+    Ok(())
+}
+
+/// Escape a string for use inside a MEL string literal.
+fn escape_mel(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+        .replace('\t', "\\t")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attribute_name() {
+        let options = MayaTemplateOptions {
+            node_name: None,
+            strip_prefixes: vec!["i_".to_string(), "o_".to_string()],
+        };
+        assert_eq!(options.attribute_name("i_color"), "color");
+        assert_eq!(options.attribute_name("o_outColor"), "outColor");
+        assert_eq!(options.attribute_name("roughness"), "roughness");
+        // Never strip a name down to nothing
+        assert_eq!(options.attribute_name("i_"), "i_");
+
+        // A prefix that would empty the name gives way to later ones
+        let options = MayaTemplateOptions {
+            node_name: None,
+            strip_prefixes: vec!["in_color".to_string(), "in_".to_string()],
+        };
+        assert_eq!(options.attribute_name("in_color"), "color");
+        assert_eq!(options.attribute_name("in_colorRamp"), "Ramp");
+    }
+
+    #[test]
+    fn test_escape_mel() {
+        assert_eq!(escape_mel("say \"hi\"\nnow"), "say \\\"hi\\\"\\nnow");
+    }
+}
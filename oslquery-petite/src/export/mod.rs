@@ -0,0 +1,117 @@
+//! Exporters that turn a parsed shader interface into host-specific formats.
+//!
+//! All exporters are driven by the same conventional OSL parameter metadata
+//! (`page`, `label`, `help`, `widget`, `options`, ...) so a shader annotated
+//! once renders sensibly everywhere.
+
+/// CSV/TSV parameter tables.
+pub mod csv;
+/// Maya Attribute Editor template (MEL) generation.
+pub mod maya;
+/// OSO text.
+pub mod oso;
+/// Python dict literals.
+pub mod python;
+/// RenderMan `.args` file generation.
+pub mod renderman;
+/// OSL shader declarations.
+pub mod signature;
+/// OSL source stubs.
+pub mod stub;
+/// Renderer-agnostic UI descriptor.
+pub mod ui;
+
+pub use csv::{CsvColumn, CsvOptions, to_csv};
+pub use maya::{MayaTemplateOptions, maya_ae_template};
+pub use renderman::renderman_args;
+#[cfg(feature = "json")]
+pub use ui::to_ui_json;
+pub use ui::{
+    UiControl, UiControlKind, UiDescriptor, UiGroup, UiOption, UiRange, UiValue, UiVisibility,
+    ui_descriptor,
+};
+
+use crate::types::{MetadataValue, StringValue, TypedParameter};
+
+/// Split an `options` metadata string into `(label, value)` pairs.
+///
+/// Entries are separated by `|`. An entry may carry an explicit value after a
+/// `:` (`"Linear:0|Smooth:1"`, the `mapper` widget convention); otherwise the
+/// value is `None` and the entry's position is implied.
+pub(crate) fn parse_options(options: &str) -> Vec<(&str, Option<&str>)> {
+    options
+        .split('|')
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| match entry.rsplit_once(':') {
+            Some((label, value)) => (label, Some(value)),
+            None => (entry, None),
+        })
+        .collect()
+}
+
+/// Format a default value as space-separated components.
+///
+/// Aggregates and arrays of them are flattened; strings are not quoted.
+/// Returns `None` if there is no default.
+pub(crate) fn default_string(typed_param: &TypedParameter) -> Option<String> {
+    fn join<T: ToString>(values: &[T]) -> String {
+        values
+            .iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    Some(match typed_param {
+        TypedParameter::Int { default } => default.as_ref()?.to_string(),
+        TypedParameter::Float { default } => default.as_ref()?.to_string(),
+        TypedParameter::String { default } => default.as_ref()?.to_string(),
+        TypedParameter::Color { default, .. }
+        | TypedParameter::Point { default, .. }
+        | TypedParameter::Vector { default, .. }
+        | TypedParameter::Normal { default, .. } => join(default.as_ref()?),
+        TypedParameter::Matrix { default } => join(default.as_deref()?),
+        TypedParameter::IntArray { default, .. } | TypedParameter::IntDynamicArray { default } => {
+            join(default.as_ref()?)
+        }
+        TypedParameter::FloatArray { default, .. }
+        | TypedParameter::FloatDynamicArray { default } => join(default.as_ref()?),
+        TypedParameter::StringArray { default, .. }
+        | TypedParameter::StringDynamicArray { default } => join(default.as_ref()?),
+        TypedParameter::ColorArray { default, .. }
+        | TypedParameter::PointArray { default, .. }
+        | TypedParameter::VectorArray { default, .. }
+        | TypedParameter::NormalArray { default, .. }
+        | TypedParameter::ColorDynamicArray { default, .. }
+        | TypedParameter::PointDynamicArray { default, .. }
+        | TypedParameter::VectorDynamicArray { default, .. }
+        | TypedParameter::NormalDynamicArray { default, .. } => join(&default.as_ref()?.concat()),
+        TypedParameter::MatrixArray { default, .. }
+        | TypedParameter::MatrixDynamicArray { default } => join(&default.as_ref()?.concat()),
+        TypedParameter::Closure { .. } => return None,
+    })
+}
+
+/// Format a metadata value, space-separating array elements.
+pub(crate) fn value_string(value: &MetadataValue) -> String {
+    match value {
+        MetadataValue::Int(v) => v.to_string(),
+        MetadataValue::Float(v) => v.to_string(),
+        MetadataValue::String(v) => v.to_string(),
+        MetadataValue::IntArray(v) => v
+            .iter()
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>()
+            .join(" "),
+        MetadataValue::FloatArray(v) => v
+            .iter()
+            .map(|f| f.to_string())
+            .collect::<Vec<_>>()
+            .join(" "),
+        MetadataValue::StringArray(v) => v
+            .iter()
+            .map(StringValue::as_str)
+            .collect::<Vec<_>>()
+            .join(" "),
+    }
+}
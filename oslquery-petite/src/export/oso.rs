@@ -0,0 +1,191 @@
+//! OSO text.
+//!
+//! [`OslQuery::to_oso`](crate::OslQuery::to_oso) writes the shader interface
+//! back in the format `oslc` produces, with an empty code section. Parsing
+//! the result gives back the same shader type, name, parameters, struct
+//! declarations and metadata, which makes it useful for fixtures, for
+//! trimming shaders down to their interface and for round-trip tests.
+
+use std::fmt::Write;
+
+use crate::export::default_string;
+use crate::parser::MAX_KNOWN_VERSION;
+use crate::query::OslQuery;
+use crate::text::escape_string;
+use crate::types::{Metadata, MetadataValue, Parameter, StructDecl, TypedParameter};
+
+impl OslQuery {
+    /// Write the shader interface as OSO text.
+    ///
+    /// Outputs are written without defaults. Warnings, the location of the
+    /// original code section and shader groups are not kept.
+    pub fn to_oso(&self) -> String {
+        let (major, minor) = MAX_KNOWN_VERSION;
+        let mut oso = format!(
+            "OpenShadingLanguage {}.{:02}\n# Written by oslquery-petite\n{} {}",
+            major,
+            minor,
+            self.shader_type(),
+            self.shader_name()
+        );
+        write_hints(&mut oso, self.metadata(), &[]);
+        oso.push('\n');
+
+        let mut structs_written = vec![false; self.structs().len()];
+        for param in self.params() {
+            // Struct parameters go right before their first field.
+            for (decl, written) in self.structs().iter().zip(&mut structs_written) {
+                if !*written && decl.is_member(param) {
+                    write_struct(&mut oso, decl);
+                    *written = true;
+                }
+            }
+            write_param(&mut oso, param);
+        }
+        for (decl, written) in self.structs().iter().zip(structs_written) {
+            if !written {
+                write_struct(&mut oso, decl);
+            }
+        }
+
+        oso.push_str("code ___main___\n\tend\n");
+        oso
+    }
+}
+
+fn write_struct(oso: &mut String, decl: &StructDecl) {
+    let fields: Vec<&str> = decl.fields.iter().map(|field| field.as_str()).collect();
+    // Writing to a `String` cannot fail.
+    let _ = writeln!(
+        oso,
+        "{}\tstruct {}\t{}\t\t%struct{{\"{}\"}} %structfields{{{}}}",
+        if decl.is_output { "oparam" } else { "param" },
+        decl.struct_name,
+        decl.name,
+        decl.struct_name,
+        fields.join(",")
+    );
+}
+
+fn write_param(oso: &mut String, param: &Parameter) {
+    let typed_param = param.typed_param();
+    let _ = write!(
+        oso,
+        "{}\t{}\t{}\t",
+        if param.is_output() { "oparam" } else { "param" },
+        typed_param.oslquery_typestring(),
+        param.name
+    );
+    if !param.is_output()
+        && let Some(default) = default_tokens(typed_param)
+    {
+        oso.push_str(&default);
+    }
+    oso.push('\t');
+
+    let hints: Vec<String> = typed_param
+        .space()
+        .map(|space| format!("%space{{\"{}\"}}", space))
+        .into_iter()
+        .chain(
+            param
+                .struct_field_of()
+                .map(|parent| format!("%mystruct{{{}}}", parent)),
+        )
+        .collect();
+    write_hints(oso, param.metadata(), &hints);
+    oso.push('\n');
+}
+
+/// Defaults as OSO tokens, with strings quoted.
+fn default_tokens(typed_param: &TypedParameter) -> Option<String> {
+    let quote = |s: &str| format!("\"{}\"", escape_string(s));
+    match typed_param {
+        TypedParameter::String { default } => default.as_deref().map(quote),
+        TypedParameter::StringArray { default, .. }
+        | TypedParameter::StringDynamicArray { default } => default
+            .as_ref()
+            .map(|v| v.iter().map(|s| quote(s)).collect::<Vec<_>>().join(" ")),
+        _ => default_string(typed_param),
+    }
+}
+
+/// Write `%meta{...}` and `extra` hints, each preceded by a tab.
+///
+/// Metadata strings are kept as they appear in the file when parsed, escapes
+/// included, so they are written back unchanged.
+fn write_hints(oso: &mut String, metadata: &[Metadata], extra: &[String]) {
+    for meta in metadata {
+        let (type_name, values) = match &meta.value {
+            MetadataValue::Int(v) => ("int".to_string(), v.to_string()),
+            MetadataValue::Float(v) => ("float".to_string(), v.to_string()),
+            MetadataValue::String(v) => ("string".to_string(), format!("\"{}\"", v)),
+            MetadataValue::IntArray(v) => (format!("int[{}]", v.len()), join(v, |v| v.to_string())),
+            MetadataValue::FloatArray(v) => {
+                (format!("float[{}]", v.len()), join(v, |v| v.to_string()))
+            }
+            MetadataValue::StringArray(v) => (
+                format!("string[{}]", v.len()),
+                join(v, |v| format!("\"{}\"", v)),
+            ),
+        };
+        let _ = write!(oso, "\t%meta{{{},{},{}}}", type_name, meta.name, values);
+    }
+    for hint in extra {
+        oso.push('\t');
+        oso.push_str(hint);
+    }
+}
+
+fn join<T>(values: &[T], to_string: impl Fn(&T) -> String) -> String {
+    values.iter().map(to_string).collect::<Vec<_>>().join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_oso_round_trip() {
+        for source in [
+            include_str!("../../tests/ui.oso"),
+            include_str!("../../tests/all_types.oso"),
+            include_str!("../../tests/struct.oso"),
+            include_str!("../../tests/points.oso"),
+        ] {
+            let query = OslQuery::from_string(source).unwrap();
+            let written = OslQuery::from_string(&query.to_oso()).unwrap();
+
+            assert_eq!(written.shader_type(), query.shader_type());
+            assert_eq!(written.shader_name(), query.shader_name());
+            assert_eq!(written.params(), query.params());
+            assert_eq!(written.structs(), query.structs());
+            assert_eq!(written.metadata(), query.metadata());
+        }
+    }
+
+    #[test]
+    fn test_to_oso() {
+        let query = OslQuery::from_string(
+            "OpenShadingLanguage 1.12\n\
+             surface test\t%meta{string,help,\"A test\"}\n\
+             param\tpoint\tP\t0 1 2\t%space{\"world\"} %meta{float,min,0}\n\
+             param\tstring[2]\tnames\t\"a\" \"b c\"\n\
+             oparam\tcolor\tCout\t0 0 0\n\
+             code ___main___\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            query.to_oso(),
+            "OpenShadingLanguage 1.14\n\
+             # Written by oslquery-petite\n\
+             surface test\t%meta{string,help,\"A test\"}\n\
+             param\tpoint\tP\t0 1 2\t\t%meta{float,min,0}\t%space{\"world\"}\n\
+             param\tstring[2]\tnames\t\"a\" \"b c\"\t\n\
+             oparam\tcolor\tCout\t\t\n\
+             code ___main___\n\
+             \tend\n"
+        );
+    }
+}
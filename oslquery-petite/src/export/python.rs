@@ -0,0 +1,384 @@
+//! Python dict literals.
+//!
+//! [`OslQuery::to_python_dict_string`] writes the shader interface as a
+//! Python expression that DCC scripts can paste or `ast.literal_eval`,
+//! without a Python crate:
+//!
+//! ```text
+//! {'shader_name': 'plastic', 'shader_type': 'surface', 'params': [
+//!     {'name': 'Kd', 'type': 'float', 'output': False, 'default': 0.5, 'label': 'Diffuse'},
+//!     {'name': 'Cout', 'type': 'color', 'output': True, 'default': None}]}
+//! ```
+//!
+//! (shown wrapped; the output is a single line).
+
+use std::fmt::Write;
+
+use crate::query::OslQuery;
+use crate::types::{MetadataValue, Parameter, TypedParameter};
+
+/// The keys of each parameter dict that metadata cannot replace.
+const PARAM_KEYS: [&str; 4] = ["name", "type", "output", "default"];
+
+impl OslQuery {
+    /// Render the shader interface as a single-line Python dict literal.
+    ///
+    /// Each parameter is a dict with its `name`, OSL `type`, whether it is
+    /// an `output`, its `default` and then its metadata by name. Strings are
+    /// single-quoted; colors, points, vectors, normals and matrices are
+    /// tuples and arrays lists. Missing defaults, including those of all
+    /// outputs, are `None`. Infinite floats become `1e999`, which Python
+    /// reads as infinity, and NaN `None`.
+    ///
+    /// Metadata named like one of the fixed keys is left out.
+    pub fn to_python_dict_string(&self) -> String {
+        let params: Vec<String> = self.params().iter().map(param_dict).collect();
+        format!(
+            "{{'shader_name': {}, 'shader_type': {}, 'params': [{}]}}",
+            quote(self.shader_name()),
+            quote(self.shader_type()),
+            params.join(", ")
+        )
+    }
+}
+
+fn param_dict(param: &Parameter) -> String {
+    let typed_param = param.typed_param();
+    let mut dict = format!(
+        "{{'name': {}, 'type': {}, 'output': {}, 'default': {}",
+        quote(&param.name),
+        quote(&typed_param.oslquery_typestring()),
+        if param.is_output() { "True" } else { "False" },
+        default(typed_param).unwrap_or_else(|| "None".to_string())
+    );
+    for metadata in param.metadata() {
+        if !PARAM_KEYS.contains(&metadata.name.as_str()) {
+            let _ = write!(
+                dict,
+                ", {}: {}",
+                quote(&metadata.name),
+                metadata_value(&metadata.value)
+            );
+        }
+    }
+    dict.push('}');
+    dict
+}
+
+/// The default as a Python literal; `None` if there is none.
+fn default(typed_param: &TypedParameter) -> Option<String> {
+    Some(match typed_param {
+        TypedParameter::Int { default } => (*default)?.to_string(),
+        TypedParameter::Float { default } => float((*default)?),
+        TypedParameter::String { default } => quote(default.as_ref()?),
+        TypedParameter::Color { default, .. }
+        | TypedParameter::Point { default, .. }
+        | TypedParameter::Vector { default, .. }
+        | TypedParameter::Normal { default, .. } => tuple(&(*default)?),
+        TypedParameter::Matrix { default } => tuple(default.as_deref()?),
+        TypedParameter::IntArray { default, .. } | TypedParameter::IntDynamicArray { default } => {
+            list(default.as_ref()?.iter().map(i32::to_string))
+        }
+        TypedParameter::FloatArray { default, .. }
+        | TypedParameter::FloatDynamicArray { default } => {
+            list(default.as_ref()?.iter().copied().map(float))
+        }
+        TypedParameter::StringArray { default, .. }
+        | TypedParameter::StringDynamicArray { default } => {
+            list(default.as_ref()?.iter().map(|s| quote(s)))
+        }
+        TypedParameter::ColorArray { default, .. }
+        | TypedParameter::PointArray { default, .. }
+        | TypedParameter::VectorArray { default, .. }
+        | TypedParameter::NormalArray { default, .. }
+        | TypedParameter::ColorDynamicArray { default, .. }
+        | TypedParameter::PointDynamicArray { default, .. }
+        | TypedParameter::VectorDynamicArray { default, .. }
+        | TypedParameter::NormalDynamicArray { default, .. } => {
+            list(default.as_ref()?.iter().map(|v| tuple(v)))
+        }
+        TypedParameter::MatrixArray { default, .. }
+        | TypedParameter::MatrixDynamicArray { default } => {
+            list(default.as_ref()?.iter().map(|m| tuple(m)))
+        }
+        TypedParameter::Closure { .. } => return None,
+    })
+}
+
+fn metadata_value(value: &MetadataValue) -> String {
+    match value {
+        MetadataValue::Int(v) => v.to_string(),
+        MetadataValue::Float(v) => float(*v),
+        MetadataValue::String(v) => quote(v),
+        MetadataValue::IntArray(v) => list(v.iter().map(i32::to_string)),
+        MetadataValue::FloatArray(v) => list(v.iter().copied().map(float)),
+        MetadataValue::StringArray(v) => list(v.iter().map(|s| quote(s))),
+    }
+}
+
+/// A float literal that always has a `.` or an exponent, so Python reads it
+/// as a `float`, not an `int`.
+fn float(v: f32) -> String {
+    if v.is_nan() {
+        "None".to_string()
+    } else if v.is_infinite() {
+        if v > 0.0 { "1e999" } else { "-1e999" }.to_string()
+    } else {
+        format!("{:?}", v)
+    }
+}
+
+/// `(v0, v1, ...)`
+fn tuple(values: &[f32]) -> String {
+    let values: Vec<String> = values.iter().copied().map(float).collect();
+    format!("({})", values.join(", "))
+}
+
+/// `[a, b, ...]`
+fn list(items: impl Iterator<Item = String>) -> String {
+    format!("[{}]", items.collect::<Vec<_>>().join(", "))
+}
+
+/// A single-quoted Python string literal.
+fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('\'');
+    for c in s.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '\'' => quoted.push_str("\\'"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('\'');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A recursive descent parser for the Python literals the exporter
+    /// writes, which fails on anything Python would not read the same way.
+    struct Literal<'a>(&'a str);
+
+    impl Literal<'_> {
+        fn check(s: &str) -> Result<(), String> {
+            let mut literal = Literal(s);
+            literal.value()?;
+            match literal.0 {
+                "" => Ok(()),
+                rest => Err(format!("trailing input: {}", rest)),
+            }
+        }
+
+        fn eat(&mut self, token: &str) -> bool {
+            let found = self.0.starts_with(token);
+            if found {
+                self.0 = &self.0[token.len()..];
+            }
+            found
+        }
+
+        fn expect(&mut self, token: &str) -> Result<(), String> {
+            if self.eat(token) {
+                Ok(())
+            } else {
+                Err(format!("expected {:?} at {:?}", token, self.0))
+            }
+        }
+
+        fn value(&mut self) -> Result<(), String> {
+            if self.eat("{") {
+                self.items("}", |l| {
+                    l.string()?;
+                    l.expect(": ")?;
+                    l.value()
+                })
+            } else if self.eat("[") {
+                self.items("]", Self::value)
+            } else if self.eat("(") {
+                self.items(")", Self::value)
+            } else if self.0.starts_with('\'') {
+                self.string()
+            } else if self.eat("True") || self.eat("False") || self.eat("None") {
+                Ok(())
+            } else {
+                self.number()
+            }
+        }
+
+        fn items(
+            &mut self,
+            close: &str,
+            mut item: impl FnMut(&mut Self) -> Result<(), String>,
+        ) -> Result<(), String> {
+            if self.eat(close) {
+                return Ok(());
+            }
+            loop {
+                item(self)?;
+                if self.eat(close) {
+                    return Ok(());
+                }
+                self.expect(", ")?;
+            }
+        }
+
+        fn string(&mut self) -> Result<(), String> {
+            self.expect("'")?;
+            let mut chars = self.0.char_indices();
+            while let Some((index, c)) = chars.next() {
+                match c {
+                    '\'' => {
+                        self.0 = &self.0[index + 1..];
+                        return Ok(());
+                    }
+                    '\\' => match chars.next() {
+                        Some((_, '\\' | '\'' | 'n' | 'r' | 't')) => {}
+                        Some((_, 'u')) => {
+                            for _ in 0..4 {
+                                chars
+                                    .next()
+                                    .filter(|(_, c)| c.is_ascii_hexdigit())
+                                    .ok_or("bad \\u escape")?;
+                            }
+                        }
+                        other => return Err(format!("bad escape {:?}", other)),
+                    },
+                    c if c.is_control() => return Err(format!("raw control {:?}", c)),
+                    _ => {}
+                }
+            }
+            Err("unterminated string".to_string())
+        }
+
+        fn number(&mut self) -> Result<(), String> {
+            let end = self
+                .0
+                .find(|c: char| !(c.is_ascii_alphanumeric() || "+-.".contains(c)))
+                .unwrap_or(self.0.len());
+            let (number, rest) = self.0.split_at(end);
+            let digits = number.strip_prefix('-').unwrap_or(number);
+            let is_digits = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+            // Python rejects leading zeros on ints, and has no `inf` or `NaN`.
+            let valid = match digits.split_once('e') {
+                _ if !digits.contains(['.', 'e']) => {
+                    is_digits(digits) && (digits == "0" || !digits.starts_with('0'))
+                }
+                Some((mantissa, exponent)) => {
+                    is_digits(mantissa.replacen('.', "", 1).as_str())
+                        && is_digits(exponent.strip_prefix(['+', '-']).unwrap_or(exponent))
+                }
+                None => digits.split_once('.').is_some_and(|(int, frac)| {
+                    is_digits(int) && (frac.is_empty() || is_digits(frac))
+                }),
+            };
+            if !valid {
+                return Err(format!("bad number {:?}", number));
+            }
+            self.0 = rest;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_literal_checker() {
+        for valid in ["{'a': [1, -2.5, (1.0, 2e-8)], 'b': None}", "'it\\'s'", "[]"] {
+            assert_eq!(Literal::check(valid), Ok(()), "{}", valid);
+        }
+        for invalid in [
+            "{\"a\": 1}",
+            "{'a': true}",
+            "[1,2]",
+            "'it's'",
+            "inf",
+            "01",
+            "'a\nb'",
+        ] {
+            assert!(Literal::check(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_to_python_dict_string() {
+        let query = OslQuery::from_string(
+            "OpenShadingLanguage 1.12\n\
+             surface MyShader\n\
+             param\tfloat\tKd\t0.5\t%meta{string,label,\"Diffuse\"} %meta{float,max,1}\n\
+             param\tcolor\tbaseColor\t1 0 0\n\
+             param\tint[3]\tids\t1 2 3\t%meta{int[],options,1 2 3}\n\
+             param\tstring\tname\t\"it's \\\"b\\\"\\n\"\n\
+             param\tfloat[]\tweights\n\
+             oparam\tcolor\tresult\t0 0 0\n\
+             code ___main___\n",
+        )
+        .unwrap();
+
+        let python = query.to_python_dict_string();
+        assert_eq!(
+            python,
+            "{'shader_name': 'MyShader', 'shader_type': 'surface', 'params': [\
+             {'name': 'Kd', 'type': 'float', 'output': False, 'default': 0.5, \
+             'label': 'Diffuse', 'max': 1.0}, \
+             {'name': 'baseColor', 'type': 'color', 'output': False, 'default': (1.0, 0.0, 0.0)}, \
+             {'name': 'ids', 'type': 'int[3]', 'output': False, 'default': [1, 2, 3], \
+             'options': [1, 2, 3]}, \
+             {'name': 'name', 'type': 'string', 'output': False, 'default': 'it\\'s \"b\"\\n'}, \
+             {'name': 'weights', 'type': 'float[]', 'output': False, 'default': None}, \
+             {'name': 'result', 'type': 'color', 'output': True, 'default': None}]}"
+        );
+        assert_eq!(Literal::check(&python), Ok(()));
+    }
+
+    #[test]
+    fn test_python_literals() {
+        let mut query = OslQuery::new();
+        query.add_parameter(
+            Parameter::new_input(
+                "ramp",
+                TypedParameter::ColorDynamicArray {
+                    default: Some(vec![[0.0; 3], [1.0, 0.5, 1e-8]]),
+                    space: Some("rgb".into()),
+                },
+            )
+            .with_metadata("type", MetadataValue::String("ignored".into()))
+            .with_metadata("help", MetadataValue::String("tab\tbell\u{7}".into())),
+        );
+        query.add_parameter(Parameter::new_input(
+            "xform",
+            TypedParameter::Matrix {
+                default: Some(Box::new([1.0; 16])),
+            },
+        ));
+        query.add_parameter(Parameter::new_input(
+            "limits",
+            TypedParameter::FloatArray {
+                size: 3,
+                default: Some(vec![f32::INFINITY, f32::NEG_INFINITY, f32::NAN]),
+            },
+        ));
+        query.add_parameter(Parameter::new_output(
+            "bsdf",
+            TypedParameter::Closure {
+                closure_type: "color".into(),
+            },
+        ));
+
+        let python = query.to_python_dict_string();
+        assert_eq!(Literal::check(&python), Ok(()), "{}", python);
+        assert!(python.starts_with("{'shader_name': '', 'shader_type': '', 'params': [{"));
+        assert!(python.contains("'default': [(0.0, 0.0, 0.0), (1.0, 0.5, 1e-8)]"));
+        assert!(!python.contains("ignored"));
+        assert!(python.contains("'help': 'tab\\tbell\\u0007'"));
+        assert!(python.contains(&format!("'default': ({})", ["1.0"; 16].join(", "))));
+        assert!(python.contains("'default': [1e999, -1e999, None]"));
+        assert!(python.contains("{'name': 'bsdf', 'type': 'closure color', 'output': True"));
+    }
+}
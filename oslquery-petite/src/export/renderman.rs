@@ -0,0 +1,387 @@
+//! RenderMan `.args` file generation.
+//!
+//! Follows the RenderMan for Maya/Houdini/Katana conventions:
+//!
+//! - Shader metadata named `rfm_<key>` becomes a `<key>` attribute of the
+//!   `<rfmdata>` element (e.g. `rfm_nodeid`, `rfm_classification`).
+//! - `page` groups parameters into `<page>` elements.
+//! - `label`, `min`, `max`, `slidermin`, `slidermax`, `digits`,
+//!   `vstructmember` and `vstructConditionalExpr` map to the attributes of the
+//!   same name, `help` to a `<help>` child.
+//! - `widget` is mapped to the Args widget names (`filename` becomes
+//!   `fileInput`); `mapper` and `popup` options become a `<hintdict>` or
+//!   `<hintlist>`.
+//! - `connectable` becomes `connectable="True"`/`"False"`.
+//! - `tags` (a `|`-separated string or string array) and `tag` become
+//!   `<tags>` children; outputs tagged `vstruct` are virtual struct outputs.
+//! - Struct parameters become virtual structs: a `vstruct`-tagged `struct`
+//!   param or output before their first field, and each input field gets a
+//!   `vstructmember` unless its metadata sets one.
+//! - Closure inputs have no Args type; they are left out with an XML comment
+//!   saying so.
+//!
+//! Attributes whose source metadata is missing or cannot be read are omitted
+//! rather than guessed.
+
+use std::fmt::{self, Write};
+
+use super::{default_string, parse_options, value_string};
+use crate::query::OslQuery;
+use crate::types::{Metadata, MetadataValue, Parameter, StringValue, StructDecl, TypedParameter};
+
+/// Numeric metadata copied verbatim to attributes of the same name.
+const NUMERIC_ATTRIBUTES: [&str; 5] = ["min", "max", "slidermin", "slidermax", "digits"];
+
+/// Generate the content of a RenderMan `.args` file for `query`.
+pub fn renderman_args(query: &OslQuery) -> String {
+    let mut out = String::new();
+    write_args(&mut out, query).expect("writing to a String cannot fail");
+    out
+}
+
+fn write_args(out: &mut String, query: &OslQuery) -> fmt::Result {
+    writeln!(out, "<args format=\"1.0\">")?;
+
+    let rfmdata: Vec<_> = query
+        .metadata()
+        .iter()
+        .filter_map(|m| Some((m.name.as_str().strip_prefix("rfm_")?, m)))
+        .collect();
+    if !rfmdata.is_empty() {
+        write!(out, "    <rfmdata")?;
+        for (key, meta) in rfmdata {
+            write!(
+                out,
+                " {}=\"{}\"",
+                key,
+                escape_xml(&value_string(&meta.value))
+            )?;
+        }
+        writeln!(out, "/>")?;
+    }
+
+    if let Some(Metadata {
+        value: MetadataValue::String(help),
+        ..
+    }) = query.find_metadata("help")
+    {
+        writeln!(out, "    <help>{}</help>", escape_xml(help))?;
+    }
+
+    // Input parameters grouped by page, in order of first appearance
+    let mut pages: Vec<(Option<&str>, Vec<&Parameter>)> = Vec::new();
+    for param in query.input_params() {
+        let page = param.ui_page();
+        match pages.iter_mut().find(|(p, _)| *p == page) {
+            Some((_, params)) => params.push(param),
+            None => pages.push((page, vec![param])),
+        }
+    }
+
+    let mut vstructs_written = vec![false; query.structs().len()];
+    for (page, params) in &pages {
+        let indent = if let Some(page) = page {
+            writeln!(
+                out,
+                "    <page name=\"{}\" open=\"False\">",
+                escape_xml(page)
+            )?;
+            "        "
+        } else {
+            "    "
+        };
+        for param in params {
+            let decl = struct_decl(query, param);
+            if let Some((index, decl)) = decl
+                && !std::mem::replace(&mut vstructs_written[index], true)
+            {
+                write_vstruct(out, "param", decl, indent)?;
+            }
+            if param.is_closure() {
+                writeln!(
+                    out,
+                    "{}<!-- Skipped closure parameter \"{}\": Args files have no closure type -->",
+                    indent,
+                    escape_xml(&param.name)
+                )?;
+                continue;
+            }
+            let vstructmember = decl.map(|_| param.name.as_str());
+            write_param(out, param, indent, vstructmember)?;
+        }
+        if page.is_some() {
+            writeln!(out, "    </page>")?;
+        }
+    }
+
+    for param in query.output_params() {
+        if let Some((index, decl)) = struct_decl(query, param)
+            && !std::mem::replace(&mut vstructs_written[index], true)
+        {
+            write_vstruct(out, "output", decl, "    ")?;
+        }
+        let tags = tags(param);
+        if tags.is_empty() {
+            writeln!(out, "    <output name=\"{}\"/>", escape_xml(&param.name))?;
+        } else {
+            writeln!(out, "    <output name=\"{}\">", escape_xml(&param.name))?;
+            write_tags(out, &tags, "        ")?;
+            writeln!(out, "    </output>")?;
+        }
+    }
+
+    writeln!(out, "</args>")
+}
+
+/// Write the virtual struct standing for struct parameter `decl` as a
+/// `param` or `output` element.
+fn write_vstruct(out: &mut String, element: &str, decl: &StructDecl, indent: &str) -> fmt::Result {
+    write!(
+        out,
+        "{}<{} name=\"{}\"",
+        indent,
+        element,
+        escape_xml(&decl.name)
+    )?;
+    if element == "param" {
+        write!(out, " type=\"struct\"")?;
+    }
+    writeln!(out, ">")?;
+    write_tags(out, &["vstruct"], &format!("{}    ", indent))?;
+    writeln!(out, "{}</{}>", indent, element)
+}
+
+/// Find the declaration, and its index, of the struct parameter the parser
+/// found `param` to be a field of.
+fn struct_decl<'a>(query: &'a OslQuery, param: &Parameter) -> Option<(usize, &'a StructDecl)> {
+    let parent = param.struct_field_of()?;
+    query
+        .structs()
+        .iter()
+        .enumerate()
+        .find(|(_, decl)| decl.name == parent)
+}
+
+/// Read a `connectable` value: a number, `"true"`/`"false"`/`"1"`/`"0"`, or
+/// an array holding one of those.
+fn connectable(value: &MetadataValue) -> Option<bool> {
+    fn from_str(v: &str) -> Option<bool> {
+        if v.eq_ignore_ascii_case("true") || v == "1" {
+            Some(true)
+        } else if v.eq_ignore_ascii_case("false") || v == "0" {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    match value {
+        MetadataValue::Int(v) => Some(*v != 0),
+        MetadataValue::Float(v) => Some(*v != 0.0),
+        MetadataValue::String(v) => from_str(v),
+        MetadataValue::IntArray(v) => match v.as_slice() {
+            [v] => Some(*v != 0),
+            _ => None,
+        },
+        MetadataValue::FloatArray(v) => match v.as_slice() {
+            [v] => Some(*v != 0.0),
+            _ => None,
+        },
+        MetadataValue::StringArray(v) => match v.as_slice() {
+            [v] => from_str(v),
+            _ => None,
+        },
+    }
+}
+
+fn write_param(
+    out: &mut String,
+    param: &Parameter,
+    indent: &str,
+    vstructmember: Option<&str>,
+) -> fmt::Result {
+    let typed_param = param.typed_param();
+
+    write!(out, "{}<param name=\"{}\"", indent, escape_xml(&param.name))?;
+    if let Some(label) = param.label() {
+        write!(out, " label=\"{}\"", escape_xml(label))?;
+    }
+    write!(
+        out,
+        " type=\"{}\"",
+        typed_param.type_name().trim_end_matches("[]")
+    )?;
+    match typed_param {
+        _ if typed_param.is_dynamic_array() => write!(out, " isDynamicArray=\"1\"")?,
+        TypedParameter::IntArray { size, .. }
+        | TypedParameter::FloatArray { size, .. }
+        | TypedParameter::StringArray { size, .. }
+        | TypedParameter::ColorArray { size, .. }
+        | TypedParameter::PointArray { size, .. }
+        | TypedParameter::VectorArray { size, .. }
+        | TypedParameter::NormalArray { size, .. }
+        | TypedParameter::MatrixArray { size, .. } => write!(out, " arraySize=\"{}\"", size)?,
+        _ => {}
+    }
+    if let Some(default) = default_string(typed_param) {
+        write!(out, " default=\"{}\"", escape_xml(&default))?;
+    }
+    for name in NUMERIC_ATTRIBUTES {
+        if let Some(meta) = param.find_metadata(name) {
+            write!(
+                out,
+                " {}=\"{}\"",
+                name,
+                escape_xml(&value_string(&meta.value))
+            )?;
+        }
+    }
+
+    let widget = param.widget();
+    if let Some(widget) = widget {
+        let widget = match widget {
+            "filename" => "fileInput",
+            other => other,
+        };
+        write!(out, " widget=\"{}\"", escape_xml(widget))?;
+    }
+    if let Some(connectable) = param
+        .find_metadata("connectable")
+        .and_then(|meta| connectable(&meta.value))
+    {
+        let connectable = if connectable { "True" } else { "False" };
+        write!(out, " connectable=\"{}\"", connectable)?;
+    }
+    if let Some(member) = param.string_metadata("vstructmember").or(vstructmember) {
+        write!(out, " vstructmember=\"{}\"", escape_xml(member))?;
+    }
+    if let Some(value) = param.string_metadata("vstructConditionalExpr") {
+        write!(out, " vstructConditionalExpr=\"{}\"", escape_xml(value))?;
+    }
+
+    let help = param.help();
+    let options = match widget {
+        Some("mapper" | "popup") => param.string_metadata("options").map(parse_options),
+        _ => None,
+    }
+    .filter(|options| !options.is_empty());
+    let tags = tags(param);
+
+    if help.is_none() && options.is_none() && tags.is_empty() {
+        return writeln!(out, "/>");
+    }
+    writeln!(out, ">")?;
+
+    let child_indent = format!("{}    ", indent);
+    if let Some(help) = help {
+        writeln!(out, "{}<help>{}</help>", child_indent, escape_xml(help))?;
+    }
+    if let Some(options) = options {
+        if widget == Some("mapper") {
+            writeln!(out, "{}<hintdict name=\"options\">", child_indent)?;
+            for (index, (label, value)) in options.iter().enumerate() {
+                let value = value.map(str::to_string).unwrap_or(index.to_string());
+                writeln!(
+                    out,
+                    "{}    <string name=\"{}\" value=\"{}\"/>",
+                    child_indent,
+                    escape_xml(label),
+                    escape_xml(&value)
+                )?;
+            }
+            writeln!(out, "{}</hintdict>", child_indent)?;
+        } else {
+            writeln!(out, "{}<hintlist name=\"options\">", child_indent)?;
+            for (label, _) in options {
+                writeln!(
+                    out,
+                    "{}    <string value=\"{}\"/>",
+                    child_indent,
+                    escape_xml(label)
+                )?;
+            }
+            writeln!(out, "{}</hintlist>", child_indent)?;
+        }
+    }
+    write_tags(out, &tags, &child_indent)?;
+
+    writeln!(out, "{}</param>", indent)
+}
+
+fn write_tags(out: &mut String, tags: &[&str], indent: &str) -> fmt::Result {
+    if tags.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "{}<tags>", indent)?;
+    for tag in tags {
+        writeln!(out, "{}    <tag value=\"{}\"/>", indent, escape_xml(tag))?;
+    }
+    writeln!(out, "{}</tags>", indent)
+}
+
+/// Collect the tags of a parameter from its `tags` and `tag` metadata.
+fn tags(param: &Parameter) -> Vec<&str> {
+    let mut tags = Vec::new();
+    for meta in param
+        .metadata()
+        .iter()
+        .filter(|m| m.name.as_str() == "tags" || m.name.as_str() == "tag")
+    {
+        match &meta.value {
+            MetadataValue::String(s) => tags.extend(s.split('|').filter(|t| !t.is_empty())),
+            MetadataValue::StringArray(v) => tags.extend(v.iter().map(StringValue::as_str)),
+            _ => {}
+        }
+    }
+    tags
+}
+
+/// Escape a string for use in XML text and attribute values.
+fn escape_xml(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' => escaped.push_str("&#10;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_xml() {
+        assert_eq!(
+            escape_xml(r#"a < b && "c" > 'd'"#),
+            "a &lt; b &amp;&amp; &quot;c&quot; &gt; &apos;d&apos;"
+        );
+        assert_eq!(escape_xml("two\nlines"), "two&#10;lines");
+        assert_eq!(escape_xml("plain"), "plain");
+    }
+
+    #[test]
+    fn test_escaped_param() {
+        let query = OslQuery::from_string(
+            r#"
+OpenShadingLanguage 1.12
+shader test
+param string label "<none> & \"more\"" %meta{string,label,"A & B"}
+code ___main___
+"#,
+        )
+        .unwrap();
+
+        let args = renderman_args(&query);
+        assert!(args.contains(
+            r#"<param name="label" label="A &amp; B" type="string" default="&lt;none&gt; &amp; &quot;more&quot;"/>"#
+        ));
+    }
+}
@@ -0,0 +1,210 @@
+//! OSL shader declarations.
+//!
+//! [`OslQuery::to_osl_signature`] writes the shader's interface back as the
+//! declaration it would have in OSL source, for documentation or for tools
+//! that match connections by signature:
+//!
+//! ```text
+//! surface plastic(float Kd = 0.5, color Cs = color(1, 0, 0), output color Cout)
+//! ```
+
+use crate::query::OslQuery;
+use crate::text::escape_string;
+use crate::types::{Parameter, TypedParameter};
+
+impl OslQuery {
+    /// Render the shader interface as a single-line OSL shader declaration.
+    ///
+    /// Parameters without a default, including all outputs, are declared
+    /// without an initializer. Geometric defaults with a space use the
+    /// constructor with a space argument, e.g. `point("world", 0, 0, 0)`.
+    pub fn to_osl_signature(&self) -> String {
+        let params: Vec<String> = self.params().iter().map(param_declaration).collect();
+        format!(
+            "{} {}({})",
+            self.shader_type(),
+            self.shader_name(),
+            params.join(", ")
+        )
+    }
+}
+
+fn param_declaration(param: &Parameter) -> String {
+    let typed_param = param.typed_param();
+    let typestring = typed_param.oslquery_typestring();
+    let (element_type, array_suffix) = split_typestring(&typestring);
+
+    let mut declaration = format!("{} {}{}", element_type, param.name, array_suffix);
+    if param.is_output() {
+        declaration.insert_str(0, "output ");
+    } else if let Some(default) = initializer(typed_param) {
+        declaration.push_str(" = ");
+        declaration.push_str(&default);
+    }
+    declaration
+}
+
+/// Split a type string into the element type and the array length, e.g.
+/// `float[3]` into `float` and `[3]`, which goes after the name in
+/// declarations.
+pub(super) fn split_typestring(typestring: &str) -> (&str, &str) {
+    match typestring.find('[') {
+        Some(index) => typestring.split_at(index),
+        None => (typestring, ""),
+    }
+}
+
+/// The default as an OSL initializer; `None` if there is none.
+pub(super) fn initializer(typed_param: &TypedParameter) -> Option<String> {
+    Some(match typed_param {
+        TypedParameter::Int { default } => (*default)?.to_string(),
+        TypedParameter::Float { default } => (*default)?.to_string(),
+        TypedParameter::String { default } => quote(default.as_ref()?),
+        TypedParameter::Color { default, space } => {
+            constructor("color", space.as_deref(), &(*default)?)
+        }
+        TypedParameter::Point { default, space } => {
+            constructor("point", space.as_deref(), &(*default)?)
+        }
+        TypedParameter::Vector { default, space } => {
+            constructor("vector", space.as_deref(), &(*default)?)
+        }
+        TypedParameter::Normal { default, space } => {
+            constructor("normal", space.as_deref(), &(*default)?)
+        }
+        TypedParameter::Matrix { default } => constructor("matrix", None, default.as_deref()?),
+        TypedParameter::IntArray { default, .. } | TypedParameter::IntDynamicArray { default } => {
+            list(default.as_ref()?.iter().map(i32::to_string))
+        }
+        TypedParameter::FloatArray { default, .. }
+        | TypedParameter::FloatDynamicArray { default } => {
+            list(default.as_ref()?.iter().map(f32::to_string))
+        }
+        TypedParameter::StringArray { default, .. }
+        | TypedParameter::StringDynamicArray { default } => {
+            list(default.as_ref()?.iter().map(|s| quote(s)))
+        }
+        TypedParameter::ColorArray { default, space, .. }
+        | TypedParameter::ColorDynamicArray { default, space } => list(
+            default
+                .as_ref()?
+                .iter()
+                .map(|v| constructor("color", space.as_deref(), v)),
+        ),
+        TypedParameter::PointArray { default, space, .. }
+        | TypedParameter::PointDynamicArray { default, space } => list(
+            default
+                .as_ref()?
+                .iter()
+                .map(|v| constructor("point", space.as_deref(), v)),
+        ),
+        TypedParameter::VectorArray { default, space, .. }
+        | TypedParameter::VectorDynamicArray { default, space } => list(
+            default
+                .as_ref()?
+                .iter()
+                .map(|v| constructor("vector", space.as_deref(), v)),
+        ),
+        TypedParameter::NormalArray { default, space, .. }
+        | TypedParameter::NormalDynamicArray { default, space } => list(
+            default
+                .as_ref()?
+                .iter()
+                .map(|v| constructor("normal", space.as_deref(), v)),
+        ),
+        TypedParameter::MatrixArray { default, .. }
+        | TypedParameter::MatrixDynamicArray { default } => list(
+            default
+                .as_ref()?
+                .iter()
+                .map(|m| constructor("matrix", None, m)),
+        ),
+        TypedParameter::Closure { .. } => return None,
+    })
+}
+
+/// `name(v0, v1, ...)`, with the space as a leading string argument.
+fn constructor(name: &str, space: Option<&str>, values: &[f32]) -> String {
+    let args: Vec<String> = space
+        .map(quote)
+        .into_iter()
+        .chain(values.iter().map(f32::to_string))
+        .collect();
+    format!("{}({})", name, args.join(", "))
+}
+
+/// `{a, b, ...}`
+fn list(items: impl Iterator<Item = String>) -> String {
+    format!("{{{}}}", items.collect::<Vec<_>>().join(", "))
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", escape_string(s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_osl_signature() {
+        let query = OslQuery::from_string(
+            "OpenShadingLanguage 1.12\n\
+             surface MyShader\n\
+             param\tfloat\tKd\t0.5\n\
+             param\tcolor\tbaseColor\t1 0 0\n\
+             param\tint[3]\tids\t1 2 3\n\
+             param\tstring\tname\t\"a \\\"b\\\"\"\n\
+             param\tmatrix\txform\t1 0 0 0 0 1 0 0 0 0 1 0 0 0 0 1\n\
+             param\tfloat[]\tweights\n\
+             oparam\tcolor\tresult\t0 0 0\n\
+             code ___main___\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            query.to_osl_signature(),
+            "surface MyShader(\
+             float Kd = 0.5, \
+             color baseColor = color(1, 0, 0), \
+             int ids[3] = {1, 2, 3}, \
+             string name = \"a \\\"b\\\"\", \
+             matrix xform = matrix(1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1), \
+             float weights[], \
+             output color result)"
+        );
+    }
+
+    #[test]
+    fn test_spaces_arrays_and_closures() {
+        let mut query = OslQuery::new();
+        query.add_parameter(Parameter::new_input(
+            "P",
+            TypedParameter::Point {
+                default: Some([0.0, 1.0, 2.0]),
+                space: Some("world".into()),
+            },
+        ));
+        query.add_parameter(Parameter::new_input(
+            "ramp",
+            TypedParameter::ColorDynamicArray {
+                default: Some(vec![[0.0; 3], [1.0; 3]]),
+                space: None,
+            },
+        ));
+
+        query.add_parameter(Parameter::new_output(
+            "bsdf",
+            TypedParameter::Closure {
+                closure_type: "color".into(),
+            },
+        ));
+
+        assert_eq!(
+            query.to_osl_signature(),
+            " (point P = point(\"world\", 0, 1, 2), \
+             color ramp[] = {color(0, 0, 0), color(1, 1, 1)}, \
+             output closure color bsdf)"
+        );
+    }
+}
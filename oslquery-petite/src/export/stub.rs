@@ -0,0 +1,280 @@
+//! OSL source stubs.
+//!
+//! [`OslQuery::to_osl_stub`] writes the interface of a compiled shader as OSL
+//! source with an empty body, for recovering the interface of a shader whose
+//! source is lost:
+//!
+//! ```text
+//! surface plastic
+//!     [[ string help = "A plastic surface" ]]
+//! (
+//!     float Kd = 0.5
+//!         [[ float min = 0, float max = 1 ]],
+//!     output color Cout = 0
+//! )
+//! {
+//! }
+//! ```
+
+use std::fmt::Write;
+
+use crate::export::signature::{initializer, split_typestring};
+use crate::query::OslQuery;
+use crate::types::{CollapsedParam, Metadata, MetadataValue, Parameter, StructParam};
+
+impl OslQuery {
+    /// Write the shader interface as OSL source with an empty shader body.
+    ///
+    /// Parameters keep their defaults and metadata, which becomes `[[ ... ]]`
+    /// annotations. Outputs and parameters without a default, which OSL does
+    /// not allow, are initialized to zero or an empty string. Struct
+    /// parameters get a `struct` declaration before the shader; the metadata
+    /// of their fields is not kept.
+    pub fn to_osl_stub(&self) -> String {
+        let mut osl = format!(
+            "// Interface of {} {}, without its code.\n\n",
+            self.shader_type(),
+            self.shader_name()
+        );
+
+        let params = self.params_collapsed();
+        let mut declared: Vec<&str> = Vec::new();
+        for param in &params {
+            if let CollapsedParam::Struct(struct_param) = param
+                && !declared.contains(&struct_param.struct_name())
+            {
+                declared.push(struct_param.struct_name());
+                write_struct(&mut osl, struct_param);
+            }
+        }
+
+        osl.push_str(self.shader_type());
+        osl.push(' ');
+        osl.push_str(self.shader_name());
+        osl.push('\n');
+        if !self.metadata().is_empty() {
+            let _ = writeln!(osl, "    {}", annotations(self.metadata()));
+        }
+
+        let declarations: Vec<String> = params
+            .iter()
+            .map(|param| match param {
+                CollapsedParam::Scalar(param) => param_declaration(param),
+                CollapsedParam::Struct(struct_param) => struct_declaration(struct_param),
+            })
+            .collect();
+        if declarations.is_empty() {
+            osl.push_str("()\n");
+        } else {
+            let _ = writeln!(osl, "(\n{}\n)", declarations.join(",\n"));
+        }
+
+        osl.push_str("{\n}\n");
+        osl
+    }
+}
+
+/// `struct S { float a; color b; };` with the field types of `struct_param`.
+fn write_struct(osl: &mut String, struct_param: &StructParam) {
+    let _ = writeln!(osl, "struct {} {{", struct_param.struct_name());
+    for (name, field) in struct_fields(struct_param) {
+        let typestring = field.typed_param().oslquery_typestring();
+        let (element_type, array_suffix) = split_typestring(&typestring);
+        let _ = writeln!(osl, "    {} {}{};", element_type, name, array_suffix);
+    }
+    osl.push_str("};\n\n");
+}
+
+/// The fields of `struct_param` with their names, e.g. `Kd` for `p.Kd`.
+fn struct_fields<'a>(struct_param: &StructParam<'a>) -> Vec<(&'a str, &'a Parameter)> {
+    let prefix_len = struct_param.name().len() + 1;
+    struct_param
+        .fields
+        .iter()
+        .map(|&field| (&field.name[prefix_len..], field))
+        .collect()
+}
+
+fn param_declaration(param: &Parameter) -> String {
+    let typestring = param.typed_param().oslquery_typestring();
+    let (element_type, array_suffix) = split_typestring(&typestring);
+
+    let mut declaration = format!(
+        "    {}{} {}{} = {}",
+        if param.is_output() { "output " } else { "" },
+        element_type,
+        param.name,
+        array_suffix,
+        value(param)
+    );
+    if !param.metadata().is_empty() {
+        let _ = write!(declaration, "\n        {}", annotations(param.metadata()));
+    }
+    declaration
+}
+
+fn struct_declaration(struct_param: &StructParam) -> String {
+    let values: Vec<String> = struct_fields(struct_param)
+        .into_iter()
+        .map(|(_, field)| value(field))
+        .collect();
+    format!(
+        "    {}{} {} = {{ {} }}",
+        if struct_param.decl.is_output {
+            "output "
+        } else {
+            ""
+        },
+        struct_param.struct_name(),
+        struct_param.name(),
+        values.join(", ")
+    )
+}
+
+/// The default of an input, or zero.
+fn value(param: &Parameter) -> String {
+    let typed_param = param.typed_param();
+    if !param.is_output()
+        && let Some(default) = initializer(typed_param)
+    {
+        return default;
+    }
+
+    let typestring = typed_param.oslquery_typestring();
+    let (element_type, array_suffix) = split_typestring(&typestring);
+    let zero = if element_type == "string" {
+        "\"\""
+    } else {
+        "0"
+    };
+    if array_suffix.is_empty() {
+        return zero.to_string();
+    }
+    // An unsized array needs at least one element.
+    let len = array_suffix
+        .trim_matches(['[', ']'])
+        .parse()
+        .unwrap_or(1usize);
+    format!("{{{}}}", vec![zero; len].join(", "))
+}
+
+/// `[[ int a = 1, string b = "x" ]]`
+///
+/// Metadata strings are kept as they appear in the compiled shader, escapes
+/// included, so they are written unchanged.
+fn annotations(metadata: &[Metadata]) -> String {
+    fn list<T: ToString>(values: &[T]) -> String {
+        let values: Vec<String> = values.iter().map(T::to_string).collect();
+        format!("{{{}}}", values.join(", "))
+    }
+
+    let entries: Vec<String> = metadata
+        .iter()
+        .map(|meta| match &meta.value {
+            MetadataValue::Int(v) => format!("int {} = {}", meta.name, v),
+            MetadataValue::Float(v) => format!("float {} = {}", meta.name, v),
+            MetadataValue::String(v) => format!("string {} = \"{}\"", meta.name, v),
+            MetadataValue::IntArray(v) => {
+                format!("int {}[{}] = {}", meta.name, v.len(), list(v))
+            }
+            MetadataValue::FloatArray(v) => {
+                format!("float {}[{}] = {}", meta.name, v.len(), list(v))
+            }
+            MetadataValue::StringArray(v) => {
+                let values: Vec<String> = v.iter().map(|s| format!("\"{}\"", s)).collect();
+                format!(
+                    "string {}[{}] = {{{}}}",
+                    meta.name,
+                    v.len(),
+                    values.join(", ")
+                )
+            }
+        })
+        .collect();
+    format!("[[ {} ]]", entries.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_osl_stub() {
+        let query = OslQuery::from_string(
+            "OpenShadingLanguage 1.12\n\
+             surface plastic\t%meta{string,help,\"A \\\"plastic\\\" surface\"}\n\
+             param\tfloat\tKd\t0.5\t%meta{float,min,0} %meta{int,max,1}\n\
+             param\tstring\ttexture\t\"\"\t%meta{string,widget,\"filename\"}\n\
+             param\tint\tmode\t0\t%meta{string,options,\"Off|On\"} %meta{int[2],enumvalues,0 1}\n\
+             param\tpoint\tP\t0 1 2\t%space{\"world\"}\n\
+             param\tfloat[]\tweights\n\
+             param\tcolor[2]\tramp\n\
+             oparam\tcolor\tCout\t1 1 1\n\
+             oparam\tclosure color\tbsdf\n\
+             code ___main___\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            query.to_osl_stub(),
+            "// Interface of surface plastic, without its code.\n\
+             \n\
+             surface plastic\n\
+             \x20   [[ string help = \"A \\\"plastic\\\" surface\" ]]\n\
+             (\n\
+             \x20   float Kd = 0.5\n\
+             \x20       [[ float min = 0, int max = 1 ]],\n\
+             \x20   string texture = \"\"\n\
+             \x20       [[ string widget = \"filename\" ]],\n\
+             \x20   int mode = 0\n\
+             \x20       [[ string options = \"Off|On\", int enumvalues[2] = {0, 1} ]],\n\
+             \x20   point P = point(\"world\", 0, 1, 2),\n\
+             \x20   float weights[] = {0},\n\
+             \x20   color ramp[2] = {0, 0},\n\
+             \x20   output color Cout = 0,\n\
+             \x20   output closure color bsdf = 0\n\
+             )\n\
+             {\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn test_to_osl_stub_structs() {
+        let query = OslQuery::from_string(include_str!("../fixtures/struct.oso")).unwrap();
+
+        // One declaration for both parameters of the struct type.
+        assert_eq!(
+            query.to_osl_stub(),
+            "// Interface of surface structTest, without its code.\n\
+             \n\
+             struct Layer {\n\
+             \x20   float weight;\n\
+             \x20   color tint;\n\
+             \x20   string name;\n\
+             };\n\
+             \n\
+             surface structTest\n\
+             \x20   [[ string help = \"Struct parameters and their fields.\" ]]\n\
+             (\n\
+             \x20   Layer base = { 1, color(0.8, 0.8, 0.8), \"base\" },\n\
+             \x20   Layer coat = { 0, color(1, 1, 1), \"coat\" },\n\
+             \x20   float gain = 1,\n\
+             \x20   output color Cout = 0\n\
+             )\n\
+             {\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn test_to_osl_stub_empty() {
+        let query =
+            OslQuery::from_string("OpenShadingLanguage 1.12\nshader empty\ncode ___main___\n")
+                .unwrap();
+        assert_eq!(
+            query.to_osl_stub(),
+            "// Interface of shader empty, without its code.\n\nshader empty\n()\n{\n}\n"
+        );
+    }
+}
@@ -0,0 +1,328 @@
+//! Renderer-agnostic UI description of a shader interface.
+//!
+//! [`ui_descriptor`] condenses the conventional parameter metadata into a
+//! list of control groups that a front-end can lay out without knowing any
+//! OSL conventions itself:
+//!
+//! - `page` becomes the group, `label`/`help` the control's texts.
+//! - `widget` selects the control kind (`checkBox`, `popup`/`mapper` with
+//!   `options`, paired with `enumvalues` if present, `filename`, `null` to
+//!   hide), otherwise it follows the type.
+//! - `min`/`max` and `slidermin`/`slidermax` form the range.
+//! - `conditionalVisOp`/`conditionalVisPath`/`conditionalVisValue` form the
+//!   visibility condition.
+
+use super::parse_options;
+use crate::query::OslQuery;
+use crate::types::{MetadataValue, Parameter, TypedParameter};
+
+/// UI description of a whole shader.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UiDescriptor {
+    /// Shader name
+    pub shader: String,
+    /// Shader type (surface, displacement, ...)
+    pub shader_type: String,
+    /// Shader `help` metadata
+    pub help: Option<String>,
+    /// Control groups in order of first appearance
+    pub groups: Vec<UiGroup>,
+}
+
+/// A group of controls, usually a page.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UiGroup {
+    /// Page name, `None` for parameters without a page
+    pub name: Option<String>,
+    /// Controls in parameter order
+    pub controls: Vec<UiControl>,
+}
+
+/// A single control bound to a shader parameter.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UiControl {
+    /// Name of the underlying shader parameter
+    pub param: String,
+    /// Kind of control to present
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub kind: UiControlKind,
+    /// Display label, the parameter name if there is no `label` metadata
+    pub label: String,
+    /// Tooltip text
+    pub help: Option<String>,
+    /// Default value
+    pub default: Option<UiValue>,
+    /// Value range
+    pub range: Option<UiRange>,
+    /// Condition under which the control is shown
+    pub visibility: Option<UiVisibility>,
+}
+
+/// The kind of control used for a parameter.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "kind", rename_all = "snake_case")
+)]
+pub enum UiControlKind {
+    /// Numeric value with a range
+    Slider,
+    /// Numeric value without a range
+    Number,
+    /// On/off toggle
+    Checkbox,
+    /// Color swatch
+    Color,
+    /// Point, vector or normal triple
+    Vector,
+    /// 4x4 matrix
+    Matrix,
+    /// Choice from a fixed list
+    Dropdown { options: Vec<UiOption> },
+    /// File path with a browser
+    Filepath,
+    /// Free text
+    Text,
+    /// Array of values
+    Array,
+}
+
+/// An entry of a dropdown.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UiOption {
+    pub label: String,
+    pub value: UiValue,
+}
+
+/// A value shown in the UI.
+///
+/// Aggregates (colors, points, matrices and arrays of them) are flattened
+/// into their components.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(untagged)
+)]
+pub enum UiValue {
+    Int(i32),
+    Float(f32),
+    String(String),
+    IntArray(Vec<i32>),
+    FloatArray(Vec<f32>),
+    StringArray(Vec<String>),
+}
+
+/// Hard and soft (slider) limits of a numeric control.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UiRange {
+    pub min: Option<f32>,
+    pub max: Option<f32>,
+    pub soft_min: Option<f32>,
+    pub soft_max: Option<f32>,
+}
+
+/// Show a control only when another parameter satisfies a condition.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UiVisibility {
+    /// Parameter the condition refers to
+    pub param: String,
+    /// Comparison operator, e.g. `equalTo`
+    pub op: String,
+    /// Value to compare with
+    pub value: String,
+}
+
+/// Build the UI description of `query`.
+///
+/// Output parameters, closures and parameters with a `null` widget are left
+/// out.
+pub fn ui_descriptor(query: &OslQuery) -> UiDescriptor {
+    let mut groups: Vec<UiGroup> = Vec::new();
+
+    for param in query.input_params() {
+        if param.is_closure() || param.widget() == Some("null") {
+            continue;
+        }
+
+        let control = ui_control(param);
+        let page = param.ui_page();
+        match groups.iter_mut().find(|g| g.name.as_deref() == page) {
+            Some(group) => group.controls.push(control),
+            None => groups.push(UiGroup {
+                name: page.map(String::from),
+                controls: vec![control],
+            }),
+        }
+    }
+
+    let help = match query.find_metadata("help").map(|m| &m.value) {
+        Some(MetadataValue::String(s)) => Some(s.to_string()),
+        _ => None,
+    };
+
+    UiDescriptor {
+        shader: query.shader_name().to_string(),
+        shader_type: query.shader_type().to_string(),
+        help,
+        groups,
+    }
+}
+
+/// Serialize the UI description of `query` as pretty-printed JSON.
+#[cfg(feature = "json")]
+pub fn to_ui_json(query: &OslQuery) -> String {
+    serde_json::to_string_pretty(&ui_descriptor(query))
+        .expect("UI descriptor serialization cannot fail")
+}
+
+fn ui_control(param: &Parameter) -> UiControl {
+    let range = UiRange {
+        min: param.float_metadata("min"),
+        max: param.float_metadata("max"),
+        soft_min: param.float_metadata("slidermin"),
+        soft_max: param.float_metadata("slidermax"),
+    };
+    let range = (range != UiRange::default()).then_some(range);
+
+    UiControl {
+        param: param.name.to_string(),
+        kind: control_kind(param, range.is_some()),
+        label: param.label().unwrap_or(param.name.as_str()).to_string(),
+        help: param.help().map(String::from),
+        default: default_value(param.typed_param()),
+        range,
+        visibility: visibility(param),
+    }
+}
+
+fn control_kind(param: &Parameter, has_range: bool) -> UiControlKind {
+    let typed_param = param.typed_param();
+
+    match param.widget() {
+        Some("checkBox" | "boolean") => return UiControlKind::Checkbox,
+        Some("popup" | "mapper") => {
+            if let Some(options) = dropdown_options(param) {
+                return UiControlKind::Dropdown { options };
+            }
+        }
+        Some("filename") if matches!(typed_param, TypedParameter::String { .. }) => {
+            return UiControlKind::Filepath;
+        }
+        _ => {}
+    }
+
+    match typed_param {
+        TypedParameter::Int { .. } | TypedParameter::Float { .. } if has_range => {
+            UiControlKind::Slider
+        }
+        TypedParameter::Int { .. } | TypedParameter::Float { .. } => UiControlKind::Number,
+        TypedParameter::String { .. } => UiControlKind::Text,
+        TypedParameter::Color { .. } => UiControlKind::Color,
+        TypedParameter::Point { .. }
+        | TypedParameter::Vector { .. }
+        | TypedParameter::Normal { .. } => UiControlKind::Vector,
+        TypedParameter::Matrix { .. } => UiControlKind::Matrix,
+        _ => UiControlKind::Array,
+    }
+}
+
+fn dropdown_options(param: &Parameter) -> Option<Vec<UiOption>> {
+    let is_int = matches!(param.typed_param(), TypedParameter::Int { .. });
+    if is_int && let Some(options) = param.enum_options() {
+        let options = options
+            .into_iter()
+            .map(|(label, value)| UiOption {
+                label,
+                value: UiValue::Int(value),
+            })
+            .collect::<Vec<_>>();
+        return (!options.is_empty()).then_some(options);
+    }
+
+    let entries = parse_options(param.string_metadata("options")?);
+
+    let options: Vec<UiOption> = entries
+        .into_iter()
+        .enumerate()
+        .map(|(index, (label, value))| {
+            let value = if is_int {
+                UiValue::Int(
+                    value
+                        .and_then(|v| v.trim().parse().ok())
+                        .unwrap_or(index as i32),
+                )
+            } else {
+                UiValue::String(value.unwrap_or(label).to_string())
+            };
+            UiOption {
+                label: label.to_string(),
+                value,
+            }
+        })
+        .collect();
+
+    (!options.is_empty()).then_some(options)
+}
+
+fn default_value(typed_param: &TypedParameter) -> Option<UiValue> {
+    Some(match typed_param {
+        TypedParameter::Int { default } => UiValue::Int((*default)?),
+        TypedParameter::Float { default } => UiValue::Float((*default)?),
+        TypedParameter::String { default } => UiValue::String(default.as_ref()?.to_string()),
+        TypedParameter::Color { default, .. }
+        | TypedParameter::Point { default, .. }
+        | TypedParameter::Vector { default, .. }
+        | TypedParameter::Normal { default, .. } => UiValue::FloatArray(default.as_ref()?.to_vec()),
+        TypedParameter::Matrix { default } => UiValue::FloatArray(default.as_ref()?.to_vec()),
+        TypedParameter::IntArray { default, .. } | TypedParameter::IntDynamicArray { default } => {
+            UiValue::IntArray(default.clone()?)
+        }
+        TypedParameter::FloatArray { default, .. }
+        | TypedParameter::FloatDynamicArray { default } => UiValue::FloatArray(default.clone()?),
+        TypedParameter::StringArray { default, .. }
+        | TypedParameter::StringDynamicArray { default } => {
+            UiValue::StringArray(default.as_ref()?.iter().map(|s| s.to_string()).collect())
+        }
+        TypedParameter::ColorArray { default, .. }
+        | TypedParameter::PointArray { default, .. }
+        | TypedParameter::VectorArray { default, .. }
+        | TypedParameter::NormalArray { default, .. }
+        | TypedParameter::ColorDynamicArray { default, .. }
+        | TypedParameter::PointDynamicArray { default, .. }
+        | TypedParameter::VectorDynamicArray { default, .. }
+        | TypedParameter::NormalDynamicArray { default, .. } => {
+            UiValue::FloatArray(default.as_ref()?.concat())
+        }
+        TypedParameter::MatrixArray { default, .. }
+        | TypedParameter::MatrixDynamicArray { default } => {
+            UiValue::FloatArray(default.as_ref()?.concat())
+        }
+        TypedParameter::Closure { .. } => return None,
+    })
+}
+
+fn visibility(param: &Parameter) -> Option<UiVisibility> {
+    let path = param.string_metadata("conditionalVisPath")?;
+    let op = param.string_metadata("conditionalVisOp")?;
+    let value = match &param.find_metadata("conditionalVisValue")?.value {
+        MetadataValue::Int(v) => v.to_string(),
+        MetadataValue::Float(v) => v.to_string(),
+        MetadataValue::String(v) => v.to_string(),
+        _ => return None,
+    };
+
+    Some(UiVisibility {
+        // Paths are relative to the parameter's node, e.g. `../enable`
+        param: path.trim_start_matches("../").to_string(),
+        op: op.to_string(),
+        value,
+    })
+}
@@ -0,0 +1,44 @@
+//! A small corpus of `.oso` files for tests.
+//!
+//! Enabled by the `test-fixtures` feature. The shaders are written the way
+//! `oslc` writes them, including comments, bytecode hints and a code
+//! section, so downstream crates can test against them without an OSL
+//! installation.
+//!
+//! ```
+//! use oslquery_petite::{OslQuery, fixtures};
+//!
+//! for (name, source) in fixtures::all() {
+//!     let query = OslQuery::from_string(source).unwrap();
+//!     assert!(query.param_count() > 0, "{}", name);
+//! }
+//! ```
+
+/// A Lambert surface: color, float and int defaults, an output, and a
+/// `normal` parameter whose default is an init expression.
+pub const LAMBERT_OSO: &str = include_str!("lambert.oso");
+
+/// A surface whose parameters carry pages, labels, help texts, widgets,
+/// options, ranges and conditional visibility, as production materials do.
+pub const METADATA_HEAVY_OSO: &str = include_str!("metadata_heavy.oso");
+
+/// Two struct parameters of the same struct type, followed by their fields
+/// and a plain parameter.
+pub const STRUCT_OSO: &str = include_str!("struct.oso");
+
+/// Closure inputs and outputs next to plain parameters.
+pub const CLOSURE_OSO: &str = include_str!("closure.oso");
+
+/// Fixed-size and dynamic arrays of every type, with and without defaults.
+pub const ARRAYS_OSO: &str = include_str!("arrays.oso");
+
+/// All fixtures as `(name, source)` pairs, named after their shader.
+pub fn all() -> &'static [(&'static str, &'static str)] {
+    &[
+        ("lambert", LAMBERT_OSO),
+        ("metadataHeavy", METADATA_HEAVY_OSO),
+        ("structTest", STRUCT_OSO),
+        ("closureTest", CLOSURE_OSO),
+        ("arrayTest", ARRAYS_OSO),
+    ]
+}
@@ -0,0 +1,277 @@
+//! Shader groups.
+//!
+//! A shader group is a network of shader layers rather than a leaf shader.
+//! Renderers exchange groups in OSL's serialized form, the text accepted by
+//! `ShadingSystem::ShaderGroupBegin()`:
+//!
+//! ```text
+//! param float Kd 0.75 ;
+//! param string texturename "grid.tx" ;
+//! shader texture tex1 ;
+//! shader plastic layer1 ;
+//! connect tex1.Cout layer1.Cs ;
+//! ```
+//!
+//! `param` statements set instance values on the next `shader` statement,
+//! which names the shader and its layer. `connect` statements wire an output
+//! of one layer to an input of a later one.
+//!
+//! With the `json` feature, groups can also be given as JSON in the shape
+//! [`ShaderGroup`] serializes to, with `layers` and `connections`.
+//!
+//! Groups are not `.oso` files; they are read with [`parse_group`],
+//! [`OslQuery::from_group_string`](crate::OslQuery::from_group_string) or
+//! [`OslQuery::open_group`](crate::OslQuery::open_group).
+
+use crate::parser::ParseError;
+use crate::parser::oso::{self, offset_in, parse_default_token, parse_typespec};
+use crate::parser::types::ParsedParameter;
+use crate::types::Parameter;
+
+/// A parsed shader group.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ShaderGroup {
+    /// Layers in evaluation order; the last one is the group's root
+    pub layers: Vec<ShaderLayer>,
+    /// Connections between layers
+    pub connections: Vec<Connection>,
+}
+
+/// A shader instance within a group.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ShaderLayer {
+    /// Name of the referenced shader
+    pub shader: String,
+    /// Name of the layer
+    pub layer: String,
+    /// Instance values set on the layer
+    pub params: Vec<Parameter>,
+}
+
+/// A connection from an output of one layer to an input of another.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Connection {
+    pub src_layer: String,
+    pub src_param: String,
+    pub dst_layer: String,
+    pub dst_param: String,
+}
+
+impl ShaderGroup {
+    /// Find a layer by name.
+    pub fn layer(&self, name: &str) -> Option<&ShaderLayer> {
+        self.layers.iter().find(|l| l.layer == name)
+    }
+
+    /// The root layer, i.e. the last one.
+    pub fn root(&self) -> Option<&ShaderLayer> {
+        self.layers.last()
+    }
+
+    /// Names of the shaders referenced by the group, in layer order.
+    pub fn shader_names(&self) -> impl Iterator<Item = &str> {
+        self.layers.iter().map(|l| l.shader.as_str())
+    }
+}
+
+/// Check whether `content` looks like a serialized shader group rather than
+/// an OSO file, by looking at its first statement.
+///
+/// This is a guess for callers choosing between [`parse_group`] and the OSO
+/// parser; neither calls it.
+pub fn is_group_source(content: &str) -> bool {
+    let first = content
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .and_then(|line| line.split_whitespace().next());
+
+    matches!(first, Some("param" | "shader" | "connect"))
+}
+
+/// Parse a serialized shader group, or with the `json` feature one in JSON.
+pub fn parse_group(content: &str) -> Result<ShaderGroup, ParseError> {
+    #[cfg(feature = "json")]
+    if content.trim_start().starts_with('{') {
+        return serde_json::from_str(content)
+            .map_err(|error| ParseError::InvalidFormat(error.to_string()));
+    }
+
+    let mut group = ShaderGroup::default();
+    let mut pending_params = Vec::new();
+
+    for (line, statement) in statements(content) {
+        let tokens = tokens_without_hints(statement);
+        let Some((&keyword, args)) = tokens.split_first() else {
+            continue;
+        };
+
+        let error = |message: String| ParseError::ParseError {
+            line,
+            message,
+            token_info: Some((keyword.to_string(), offset_in(content, keyword))),
+        };
+
+        match keyword {
+            "param" => {
+                let [typename, name, values @ ..] = args else {
+                    return Err(error("Expected `param <type> <name> <values>`".to_string()));
+                };
+                let Ok((_, typespec)) = parse_typespec(typename) else {
+                    return Err(error(format!("Invalid type specification: {}", typename)));
+                };
+
+                let mut parsed = ParsedParameter::new(unquote(name), typespec.simpletype);
+                for value in values {
+                    let Some(default) = parse_default_token(value) else {
+                        return Err(error(format!("Invalid value for `{}`: {}", name, value)));
+                    };
+                    parsed.push_default(default);
+                }
+                pending_params.push(Parameter::try_from(parsed).map_err(error)?);
+            }
+            "shader" => {
+                let [shader, layer] = args else {
+                    return Err(error("Expected `shader <name> <layer>`".to_string()));
+                };
+                group.layers.push(ShaderLayer {
+                    shader: unquote(shader).to_string(),
+                    layer: unquote(layer).to_string(),
+                    params: std::mem::take(&mut pending_params),
+                });
+            }
+            "connect" => {
+                let endpoints = match args {
+                    [src, dst] => unquote(src)
+                        .split_once('.')
+                        .zip(unquote(dst).split_once('.')),
+                    _ => None,
+                };
+                let Some(((src_layer, src_param), (dst_layer, dst_param))) = endpoints else {
+                    return Err(error(
+                        "Expected `connect <layer>.<param> <layer>.<param>`".to_string(),
+                    ));
+                };
+                group.connections.push(Connection {
+                    src_layer: src_layer.to_string(),
+                    src_param: src_param.to_string(),
+                    dst_layer: dst_layer.to_string(),
+                    dst_param: dst_param.to_string(),
+                });
+            }
+            _ => return Err(error(format!("Unknown statement: {}", keyword))),
+        }
+    }
+
+    if !pending_params.is_empty() {
+        return Err(ParseError::Incomplete(
+            "`param` statements without a following `shader`".to_string(),
+        ));
+    }
+
+    Ok(group)
+}
+
+/// Split `content` into `;`-terminated statements, with the 1-based line
+/// each one starts on.
+fn statements(content: &str) -> Vec<(usize, &str)> {
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (i, c) in content.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            ';' if !in_string => {
+                ranges.push(start..i);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    ranges.push(start..content.len());
+
+    ranges
+        .into_iter()
+        .filter_map(|range| {
+            let raw = &content[range.clone()];
+            let statement = raw.trim();
+            if statement.is_empty() {
+                return None;
+            }
+            let offset = range.start + raw.len() - raw.trim_start().len();
+            let line = content[..offset].matches('\n').count() + 1;
+            Some((line, statement))
+        })
+        .collect()
+}
+
+/// Tokenize a statement, dropping `[[ ... ]]` hint blocks.
+fn tokens_without_hints(statement: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut in_hints = false;
+
+    for token in oso::tokens(statement) {
+        if token.starts_with("[[") {
+            in_hints = true;
+        }
+        if !in_hints {
+            tokens.push(token);
+        }
+        if token.ends_with("]]") {
+            in_hints = false;
+        }
+    }
+
+    tokens
+}
+
+fn unquote(s: &str) -> &str {
+    s.strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .unwrap_or(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_group_source() {
+        assert!(is_group_source(
+            "\nparam float Kd 0.5 ;\nshader matte layer1 ;"
+        ));
+        assert!(is_group_source("shader matte layer1;"));
+        assert!(!is_group_source("OpenShadingLanguage 1.12\nsurface test\n"));
+    }
+
+    #[test]
+    fn test_statements() {
+        assert_eq!(
+            statements("param string s \"a;b\" ;\n\nshader x y;"),
+            vec![(1, "param string s \"a;b\""), (3, "shader x y")]
+        );
+    }
+
+    #[test]
+    fn test_parse_group_errors() {
+        assert!(matches!(
+            parse_group("shader matte ;"),
+            Err(ParseError::ParseError { line: 1, .. })
+        ));
+        assert!(matches!(
+            parse_group("shader a b ;\nconnect a.out b ;"),
+            Err(ParseError::ParseError { line: 2, .. })
+        ));
+        assert!(matches!(
+            parse_group("param float Kd 1 ;"),
+            Err(ParseError::Incomplete(_))
+        ));
+    }
+}
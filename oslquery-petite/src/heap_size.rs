@@ -0,0 +1,284 @@
+//! Estimates of the heap memory held by parsed shaders.
+//!
+//! [`OslQuery::heap_size_estimate`] and [`Parameter::heap_size_estimate`]
+//! add up the heap allocations a query owns: parameter lists, defaults,
+//! metadata and strings. Allocator overhead and spare `Vec` capacity are not
+//! counted, so the real footprint is somewhat larger.
+//!
+//! Parameter names and other [`Ustr`] strings live in a global interner and
+//! are shared by all queries. [`InternedStrings`] says whether to leave them
+//! out, e.g. to budget a library whose shaders share most names, or to count
+//! each distinct one once.
+
+use std::collections::HashSet;
+use std::mem::size_of;
+
+use ustr::Ustr;
+
+use crate::query::OslQuery;
+use crate::types::{Metadata, MetadataValue, Parameter, StringValue, StructDecl, TypedParameter};
+
+/// How [`Ustr`] strings, which are interned and shared, are counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InternedStrings {
+    /// Leave them out.
+    #[default]
+    Exclude,
+    /// Count each distinct string once.
+    CountOnce,
+}
+
+impl OslQuery {
+    /// Estimate the heap memory this query owns, in bytes, leaving out
+    /// interned strings.
+    ///
+    /// See the [module documentation](crate::heap_size).
+    pub fn heap_size_estimate(&self) -> usize {
+        self.heap_size_estimate_with(InternedStrings::Exclude)
+    }
+
+    /// Estimate the heap memory this query owns, in bytes, counting interned
+    /// strings as given by `interned`.
+    pub fn heap_size_estimate_with(&self, interned: InternedStrings) -> usize {
+        let mut sizer = HeapSizer::new(interned);
+        sizer.query(self)
+    }
+}
+
+impl Parameter {
+    /// Estimate the heap memory this parameter owns, in bytes, leaving out
+    /// interned strings.
+    ///
+    /// See the [module documentation](crate::heap_size).
+    pub fn heap_size_estimate(&self) -> usize {
+        self.heap_size_estimate_with(InternedStrings::Exclude)
+    }
+
+    /// Estimate the heap memory this parameter owns, in bytes, counting
+    /// interned strings as given by `interned`.
+    pub fn heap_size_estimate_with(&self, interned: InternedStrings) -> usize {
+        let mut sizer = HeapSizer::new(interned);
+        sizer.parameter(self)
+    }
+}
+
+/// Walks a query, remembering the interned strings already counted.
+struct HeapSizer {
+    interned: InternedStrings,
+    seen: HashSet<Ustr>,
+}
+
+impl HeapSizer {
+    fn new(interned: InternedStrings) -> Self {
+        HeapSizer {
+            interned,
+            seen: HashSet::new(),
+        }
+    }
+
+    fn query(&mut self, query: &OslQuery) -> usize {
+        let mut size = query.shader_name().len()
+            + query.shader_type().len()
+            + query.format_identifier().len()
+            + slice::<Parameter>(query.params().len())
+            + slice::<StructDecl>(query.structs().len())
+            + self.metadata(query.metadata())
+            + slice::<String>(query.warnings().len())
+            + query.warnings().iter().map(String::len).sum::<usize>()
+            + slice::<std::path::PathBuf>(query.resolution_candidates().len())
+            + query
+                .resolution_candidates()
+                .iter()
+                .map(|path| path.as_os_str().len())
+                .sum::<usize>();
+        for param in query.params() {
+            size += self.parameter(param);
+        }
+        for decl in query.structs() {
+            size += self.ustr(decl.name)
+                + self.ustr(decl.struct_name)
+                + slice::<Ustr>(decl.fields.len());
+            for &field in &decl.fields {
+                size += self.ustr(field);
+            }
+        }
+        if let Some(group) = query.group() {
+            size += slice::<crate::group::ShaderLayer>(group.layers.len());
+            for layer in &group.layers {
+                size +=
+                    layer.shader.len() + layer.layer.len() + slice::<Parameter>(layer.params.len());
+                for param in &layer.params {
+                    size += self.parameter(param);
+                }
+            }
+            size += slice::<crate::group::Connection>(group.connections.len());
+            for connection in &group.connections {
+                size += connection.src_layer.len()
+                    + connection.src_param.len()
+                    + connection.dst_layer.len()
+                    + connection.dst_param.len();
+            }
+        }
+        size
+    }
+
+    fn parameter(&mut self, param: &Parameter) -> usize {
+        let (raw_size, metadata) = param.metadata_storage();
+        self.ustr(param.name)
+            + self.typed_param(param.typed_param())
+            + raw_size
+            + metadata.map_or(0, |metadata| self.metadata(metadata))
+    }
+
+    fn typed_param(&mut self, typed_param: &TypedParameter) -> usize {
+        let size = match typed_param {
+            TypedParameter::String { default } => {
+                default.as_ref().map_or(0, |s| self.string_value(s))
+            }
+            TypedParameter::Matrix { default } => {
+                default.as_ref().map_or(0, |_| size_of::<[f32; 16]>())
+            }
+            TypedParameter::IntArray { default, .. }
+            | TypedParameter::IntDynamicArray { default } => {
+                default.as_ref().map_or(0, |v| slice::<i32>(v.len()))
+            }
+            TypedParameter::FloatArray { default, .. }
+            | TypedParameter::FloatDynamicArray { default } => {
+                default.as_ref().map_or(0, |v| slice::<f32>(v.len()))
+            }
+            TypedParameter::StringArray { default, .. }
+            | TypedParameter::StringDynamicArray { default } => default.as_ref().map_or(0, |v| {
+                slice::<StringValue>(v.len())
+                    + v.iter().map(|s| self.string_value(s)).sum::<usize>()
+            }),
+            TypedParameter::ColorArray { default, .. }
+            | TypedParameter::PointArray { default, .. }
+            | TypedParameter::VectorArray { default, .. }
+            | TypedParameter::NormalArray { default, .. }
+            | TypedParameter::ColorDynamicArray { default, .. }
+            | TypedParameter::PointDynamicArray { default, .. }
+            | TypedParameter::VectorDynamicArray { default, .. }
+            | TypedParameter::NormalDynamicArray { default, .. } => {
+                default.as_ref().map_or(0, |v| slice::<[f32; 3]>(v.len()))
+            }
+            TypedParameter::MatrixArray { default, .. }
+            | TypedParameter::MatrixDynamicArray { default } => {
+                default.as_ref().map_or(0, |v| slice::<[f32; 16]>(v.len()))
+            }
+            _ => 0,
+        };
+        let space = typed_param.space().map_or(0, |space| self.ustr(space));
+        let closure_type = typed_param
+            .closure_type()
+            .map_or(0, |closure_type| self.ustr(closure_type));
+        size + space + closure_type
+    }
+
+    fn metadata(&mut self, metadata: &[Metadata]) -> usize {
+        let mut size = slice::<Metadata>(metadata.len());
+        for meta in metadata {
+            size += self.ustr(meta.name);
+            size += match &meta.value {
+                MetadataValue::Int(_) | MetadataValue::Float(_) => 0,
+                MetadataValue::String(s) => self.string_value(s),
+                MetadataValue::IntArray(v) => slice::<i32>(v.len()),
+                MetadataValue::FloatArray(v) => slice::<f32>(v.len()),
+                MetadataValue::StringArray(v) => {
+                    slice::<StringValue>(v.len())
+                        + v.iter().map(|s| self.string_value(s)).sum::<usize>()
+                }
+            };
+        }
+        size
+    }
+
+    fn string_value(&mut self, s: &StringValue) -> usize {
+        match s {
+            StringValue::Owned(s) => s.len(),
+            StringValue::Interned(s) => self.ustr(*s),
+        }
+    }
+
+    /// An interned string, with the length and hash stored next to it.
+    fn ustr(&mut self, s: Ustr) -> usize {
+        match self.interned {
+            InternedStrings::Exclude => 0,
+            InternedStrings::CountOnce if self.seen.insert(s) => 2 * size_of::<usize>() + s.len(),
+            InternedStrings::CountOnce => 0,
+        }
+    }
+}
+
+/// Bytes taken by `len` elements of `T`.
+fn slice<T>(len: usize) -> usize {
+    len * size_of::<T>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_query() {
+        let query = OslQuery::new();
+        assert_eq!(query.heap_size_estimate(), 0);
+        assert_eq!(query.heap_size_estimate_with(InternedStrings::CountOnce), 0);
+    }
+
+    #[test]
+    fn test_large_default() {
+        let mut query = OslQuery::new();
+        query.set_shader_info("surface", "big".to_string());
+        query.add_parameter(Parameter::new_input(
+            "Kd",
+            TypedParameter::Float { default: Some(0.5) },
+        ));
+        let before = query.heap_size_estimate();
+        assert!(before > 0);
+
+        let weights = Parameter::new_input(
+            "weights",
+            TypedParameter::FloatDynamicArray {
+                default: Some(vec![0.0; 10_000]),
+            },
+        );
+        let weights_size = weights.heap_size_estimate();
+        assert!(weights_size >= 10_000 * size_of::<f32>());
+
+        query.add_parameter(weights);
+        assert_eq!(
+            query.heap_size_estimate(),
+            before + size_of::<Parameter>() + weights_size
+        );
+    }
+
+    #[test]
+    fn test_interned_strings() {
+        let query_with = |intern: bool| {
+            let mut query = OslQuery::new();
+            for name in ["a", "b"] {
+                let mut param = Parameter::new_input(name, TypedParameter::Float { default: None })
+                    .with_metadata("label", MetadataValue::String("Label".into()));
+                if intern {
+                    param.intern_strings();
+                }
+                query.add_parameter(param);
+            }
+            query
+        };
+
+        let owned = query_with(false);
+        let excluded = owned.heap_size_estimate();
+        // `a`, `b` and `label`, which both parameters share.
+        let counted = owned.heap_size_estimate_with(InternedStrings::CountOnce);
+        assert_eq!(counted, excluded + 3 * 2 * size_of::<usize>() + 1 + 1 + 5);
+
+        // An interned label is counted once, owned ones every time.
+        let interned = query_with(true);
+        assert_eq!(interned.heap_size_estimate(), excluded - 2 * "Label".len());
+        assert_eq!(
+            interned.heap_size_estimate_with(InternedStrings::CountOnce),
+            counted - 2 * "Label".len() + 2 * size_of::<usize>() + "Label".len()
+        );
+    }
+}
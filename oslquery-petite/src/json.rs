@@ -0,0 +1,439 @@
+//! Parameter defaults as plain JSON objects.
+//!
+//! Scene formats that store parameter values next to a shader reference
+//! only need the values, not the types:
+//!
+//! ```json
+//! { "Kd": 0.5, "baseColor": [1.0, 0.0, 0.0], "count": 42 }
+//! ```
+//!
+//! Aggregates become arrays of their components, arrays of aggregates
+//! arrays of such arrays.
+
+use serde_json::{Map, Value};
+
+use crate::query::OslQuery;
+use crate::types::{Parameter, ParameterKind, StringValue, TypedParameter, widen_f32};
+
+/// How [`OslQuery::to_json_parameter_defaults`] treats parameters without a
+/// default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum NullDefaultBehavior {
+    /// Leave them out.
+    #[default]
+    Omit,
+    /// Include them as `null`.
+    Null,
+}
+
+impl OslQuery {
+    /// Map the names of input parameters to their defaults.
+    ///
+    /// Closures are always left out as they cannot have defaults.
+    pub fn to_json_parameter_defaults(&self, nulls: NullDefaultBehavior) -> Value {
+        let mut object = Map::new();
+
+        for param in self.input_params() {
+            if param.is_closure() {
+                continue;
+            }
+            match param.default_as_json() {
+                Some(value) => {
+                    object.insert(param.name.to_string(), value);
+                }
+                None if nulls == NullDefaultBehavior::Null => {
+                    object.insert(param.name.to_string(), Value::Null);
+                }
+                None => {}
+            }
+        }
+
+        Value::Object(object)
+    }
+
+    /// Return a copy with the defaults replaced by the values in `json`.
+    ///
+    /// `json` must be an object mapping input parameter names to values in
+    /// the form produced by
+    /// [`to_json_parameter_defaults`](Self::to_json_parameter_defaults);
+    /// `null` clears a default. Parameters not mentioned keep their
+    /// defaults. Fixed-size arrays need exactly as many elements as their
+    /// size.
+    ///
+    /// All problems (unknown parameters, outputs, mismatched values) are
+    /// collected and returned together.
+    pub fn apply_json_defaults(&self, json: &Value) -> Result<OslQuery, Vec<String>> {
+        let Value::Object(object) = json else {
+            return Err(vec!["Expected a JSON object".to_string()]);
+        };
+
+        let mut query = self.clone();
+        let mut errors = Vec::new();
+
+        for (name, value) in object {
+            let Some(param) = query.param_by_name_mut(name) else {
+                errors.push(format!("Unknown parameter '{}'", name));
+                continue;
+            };
+            match &mut param.kind {
+                ParameterKind::Input(typed_param) => {
+                    if let Err(e) = set_default(typed_param, value) {
+                        errors.push(format!("Parameter '{}': {}", name, e));
+                    }
+                }
+                ParameterKind::Output(_) => {
+                    errors.push(format!("Parameter '{}' is an output", name));
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(query)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+impl OslQuery {
+    /// Serialize to pretty-printed JSON that is byte-identical for the same
+    /// shader, e.g. for manifests kept under source control.
+    ///
+    /// Metadata is sorted by name, and floats are written in the shortest
+    /// form that reads back as the same `f32`, e.g. `0.1`. Parameters stay
+    /// in declaration order.
+    pub fn to_canonical_json(&self) -> String {
+        let mut query = self.clone();
+        query.sort_metadata();
+        // serde_json writes `f32`s with ryu, which gives the shortest
+        // round-trip representation.
+        serde_json::to_string_pretty(&query).expect("serializing a query cannot fail")
+    }
+}
+
+impl Parameter {
+    /// The default as a bare JSON value, in the form used by
+    /// [`OslQuery::to_json_parameter_defaults`].
+    ///
+    /// `None` if there is no default, e.g. for outputs and closures.
+    pub fn default_as_json(&self) -> Option<Value> {
+        default_to_json(self.typed_param())
+    }
+}
+
+fn default_to_json(typed_param: &TypedParameter) -> Option<Value> {
+    fn floats(values: &[f32]) -> Value {
+        Value::Array(values.iter().copied().map(float).collect())
+    }
+
+    Some(match typed_param {
+        TypedParameter::Int { default } => Value::from((*default)?),
+        TypedParameter::Float { default } => float((*default)?),
+        TypedParameter::String { default } => Value::from(default.as_ref()?.as_str()),
+        TypedParameter::Color { default, .. }
+        | TypedParameter::Point { default, .. }
+        | TypedParameter::Vector { default, .. }
+        | TypedParameter::Normal { default, .. } => floats(default.as_ref()?),
+        TypedParameter::Matrix { default } => floats(default.as_deref()?),
+        TypedParameter::IntArray { default, .. } | TypedParameter::IntDynamicArray { default } => {
+            Value::from(default.clone()?)
+        }
+        TypedParameter::FloatArray { default, .. }
+        | TypedParameter::FloatDynamicArray { default } => floats(default.as_ref()?),
+        TypedParameter::StringArray { default, .. }
+        | TypedParameter::StringDynamicArray { default } => Value::from(default.clone()?),
+        TypedParameter::ColorArray { default, .. }
+        | TypedParameter::PointArray { default, .. }
+        | TypedParameter::VectorArray { default, .. }
+        | TypedParameter::NormalArray { default, .. }
+        | TypedParameter::ColorDynamicArray { default, .. }
+        | TypedParameter::PointDynamicArray { default, .. }
+        | TypedParameter::VectorDynamicArray { default, .. }
+        | TypedParameter::NormalDynamicArray { default, .. } => {
+            Value::Array(default.as_ref()?.iter().map(|v| floats(v)).collect())
+        }
+        TypedParameter::MatrixArray { default, .. }
+        | TypedParameter::MatrixDynamicArray { default } => {
+            Value::Array(default.as_ref()?.iter().map(|m| floats(m)).collect())
+        }
+        TypedParameter::Closure { .. } => return None,
+    })
+}
+
+/// Convert with [`widen_f32`]; non-finite values become `null`.
+pub(crate) fn float(value: f32) -> Value {
+    Value::from(widen_f32(value))
+}
+
+fn set_default(typed_param: &mut TypedParameter, value: &Value) -> Result<(), String> {
+    let size = match typed_param {
+        TypedParameter::IntArray { size, .. }
+        | TypedParameter::FloatArray { size, .. }
+        | TypedParameter::StringArray { size, .. }
+        | TypedParameter::ColorArray { size, .. }
+        | TypedParameter::PointArray { size, .. }
+        | TypedParameter::VectorArray { size, .. }
+        | TypedParameter::NormalArray { size, .. }
+        | TypedParameter::MatrixArray { size, .. } => Some(*size),
+        _ => None,
+    };
+    let expected = typed_param.to_string();
+    let mismatch = || format!("expected a value of type {}, got {}", expected, value);
+
+    if let (Some(size), Value::Array(values)) = (size, value)
+        && values.len() != size
+    {
+        return Err(format!("expected {} elements, got {}", size, values.len()));
+    }
+
+    let null = value.is_null();
+    let ok = match typed_param {
+        TypedParameter::Int { default } => assign(default, null, || int(value)),
+        TypedParameter::Float { default } => assign(default, null, || float_from(value)),
+        TypedParameter::String { default } => {
+            assign(default, null, || value.as_str().map(Into::into))
+        }
+        TypedParameter::Color { default, .. }
+        | TypedParameter::Point { default, .. }
+        | TypedParameter::Vector { default, .. }
+        | TypedParameter::Normal { default, .. } => assign(default, null, || components(value)),
+        TypedParameter::Matrix { default } => {
+            assign(default, null, || components(value).map(Box::new))
+        }
+        TypedParameter::IntArray { default, .. } | TypedParameter::IntDynamicArray { default } => {
+            assign(default, null, || list(value, int))
+        }
+        TypedParameter::FloatArray { default, .. }
+        | TypedParameter::FloatDynamicArray { default } => {
+            assign(default, null, || list(value, float_from))
+        }
+        TypedParameter::StringArray { default, .. }
+        | TypedParameter::StringDynamicArray { default } => assign(default, null, || {
+            list(value, |v| v.as_str().map(StringValue::from))
+        }),
+        TypedParameter::ColorArray { default, .. }
+        | TypedParameter::PointArray { default, .. }
+        | TypedParameter::VectorArray { default, .. }
+        | TypedParameter::NormalArray { default, .. }
+        | TypedParameter::ColorDynamicArray { default, .. }
+        | TypedParameter::PointDynamicArray { default, .. }
+        | TypedParameter::VectorDynamicArray { default, .. }
+        | TypedParameter::NormalDynamicArray { default, .. } => {
+            assign(default, null, || list(value, components))
+        }
+        TypedParameter::MatrixArray { default, .. }
+        | TypedParameter::MatrixDynamicArray { default } => {
+            assign(default, null, || list(value, components))
+        }
+        TypedParameter::Closure { .. } => return Err("closures cannot have defaults".to_string()),
+    };
+
+    if ok { Ok(()) } else { Err(mismatch()) }
+}
+
+/// Set `default` to `None` for `null`, otherwise to the converted value.
+/// Returns `false` if the conversion failed.
+fn assign<T>(default: &mut Option<T>, null: bool, convert: impl FnOnce() -> Option<T>) -> bool {
+    if null {
+        *default = None;
+        return true;
+    }
+    match convert() {
+        Some(value) => {
+            *default = Some(value);
+            true
+        }
+        None => false,
+    }
+}
+
+fn int(value: &Value) -> Option<i32> {
+    value.as_i64().and_then(|i| i32::try_from(i).ok())
+}
+
+fn float_from(value: &Value) -> Option<f32> {
+    value.as_f64().map(|f| f as f32)
+}
+
+fn components<const N: usize>(value: &Value) -> Option<[f32; N]> {
+    list(value, float_from)?.try_into().ok()
+}
+
+fn list<T>(value: &Value, convert: impl Fn(&Value) -> Option<T>) -> Option<Vec<T>> {
+    value.as_array()?.iter().map(convert).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn query() -> OslQuery {
+        OslQuery::from_string(
+            r#"
+OpenShadingLanguage 1.12
+surface test
+param	float	Kd	0.1
+param	color	baseColor	1 0 0
+param	int	count	42
+param	string	name	"wood"
+param	float[2]	weights	0.25 0.75
+param	matrix	xform	1 0 0 0 0 1 0 0 0 0 1 0 0 0 0 1
+param	float	unset
+oparam	color	Cout	0 0 0
+code ___main___
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_to_json_parameter_defaults() {
+        let query = query();
+
+        let defaults = query.to_json_parameter_defaults(NullDefaultBehavior::Omit);
+        assert_eq!(
+            defaults,
+            json!({
+                "Kd": 0.1,
+                "baseColor": [1.0, 0.0, 0.0],
+                "count": 42,
+                "name": "wood",
+                "weights": [0.25, 0.75],
+                "xform": [1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0],
+            })
+        );
+
+        let defaults = query.to_json_parameter_defaults(NullDefaultBehavior::Null);
+        assert_eq!(defaults["unset"], Value::Null);
+        assert!(defaults.get("Cout").is_none());
+    }
+
+    #[test]
+    fn test_default_as_json() {
+        let query = query();
+        let default = |name: &str| query.param_by_name(name).unwrap().default_as_json();
+
+        assert_eq!(default("count"), Some(json!(42)));
+        assert_eq!(default("baseColor"), Some(json!([1.0, 0.0, 0.0])));
+        assert_eq!(default("unset"), None);
+        assert_eq!(default("Cout"), None);
+
+        let textures = Parameter::new_input(
+            "textures",
+            TypedParameter::StringDynamicArray {
+                default: Some(vec!["a.tx".into(), "b.tx".into()]),
+            },
+        );
+        assert_eq!(textures.default_as_json(), Some(json!(["a.tx", "b.tx"])));
+    }
+
+    #[test]
+    fn test_apply_json_defaults_round_trip() {
+        let query = query();
+        let defaults = query.to_json_parameter_defaults(NullDefaultBehavior::Null);
+        assert_eq!(query.apply_json_defaults(&defaults), Ok(query));
+    }
+
+    #[test]
+    fn test_apply_json_defaults() {
+        let query = query()
+            .apply_json_defaults(&json!({
+                "Kd": 1,
+                "baseColor": [0, 0.5, 1],
+                "weights": null,
+            }))
+            .unwrap();
+
+        assert_eq!(
+            query.param_by_name("Kd").unwrap().typed_param(),
+            &TypedParameter::Float { default: Some(1.0) }
+        );
+        assert!(matches!(
+            query.param_by_name("baseColor").unwrap().typed_param(),
+            TypedParameter::Color {
+                default: Some([0.0, 0.5, 1.0]),
+                ..
+            }
+        ));
+        assert_eq!(
+            query.param_by_name("weights").unwrap().typed_param(),
+            &TypedParameter::FloatArray {
+                size: 2,
+                default: None,
+            }
+        );
+        // Untouched
+        assert_eq!(
+            query.param_by_name("count").unwrap().typed_param(),
+            &TypedParameter::Int { default: Some(42) }
+        );
+    }
+
+    #[test]
+    fn test_apply_json_defaults_errors() {
+        let errors = query()
+            .apply_json_defaults(&json!({
+                "count": 1.5,
+                "baseColor": [1, 0],
+                "weights": [1, 2, 3],
+                "Cout": [0, 0, 0],
+                "missing": 1,
+            }))
+            .unwrap_err();
+
+        assert_eq!(errors.len(), 5);
+        assert!(errors.contains(&"Unknown parameter 'missing'".to_string()));
+        assert!(errors.contains(&"Parameter 'Cout' is an output".to_string()));
+        assert!(errors.contains(&"Parameter 'weights': expected 2 elements, got 3".to_string()));
+
+        assert!(query().apply_json_defaults(&json!([1, 2])).is_err());
+    }
+
+    #[test]
+    fn test_to_canonical_json() {
+        let source = "OpenShadingLanguage 1.12\n\
+             surface canonical\t%meta{string,help,\"Test\"} %meta{string,author,\"me\"}\n\
+             param\tfloat\tKd\t0.1\t%meta{string,label,\"Diffuse\"} %meta{float,max,0.3}\n\
+             param\tcolor\ttint\t0.1 0.2 0.7\n\
+             code ___main___\n";
+        let query = OslQuery::from_string(source).unwrap();
+
+        let json = query.to_canonical_json();
+        assert_eq!(json, query.to_canonical_json());
+        assert_eq!(
+            json,
+            OslQuery::from_string(source).unwrap().to_canonical_json()
+        );
+
+        // Shortest round-trip floats.
+        assert!(json.contains("\"default\": 0.1\n"));
+        assert!(json.contains("0.7"));
+        assert!(!json.contains("0.10000000149011612"));
+
+        // Metadata sorted by name.
+        let value: Value = serde_json::from_str(&json).unwrap();
+        let names = |metadata: &Value| -> Vec<String> {
+            metadata
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|m| m["name"].as_str().unwrap().to_string())
+                .collect()
+        };
+        assert_eq!(names(&value["metadata"]), ["author", "help"]);
+        assert_eq!(names(&value["parameters"][0]["metadata"]), ["label", "max"]);
+
+        // The order metadata was declared in doesn't matter.
+        let reordered = source.replace(
+            "%meta{string,label,\"Diffuse\"} %meta{float,max,0.3}",
+            "%meta{float,max,0.3} %meta{string,label,\"Diffuse\"}",
+        );
+        assert_eq!(
+            OslQuery::from_string(&reordered)
+                .unwrap()
+                .to_canonical_json(),
+            json
+        );
+    }
+}
@@ -28,10 +28,68 @@
 //! # Ok(())
 //! # }
 //! ```
+//!
+//! # WebAssembly
+//!
+//! On `wasm32-unknown-unknown` there is no file system, so everything that
+//! reads files (`OslQuery::open()`, `OsoReader::parse_file()`, `library`
+//! and `cache`) is left out. Parse with [`OslQuery::from_string`] or
+//! [`OslQuery::from_bytes`] instead. The `wasm` feature adds JavaScript
+//! bindings in the `wasm` module.
 
+#[cfg(feature = "archive")]
+pub mod archive;
+#[cfg(all(
+    feature = "json",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+pub mod cache;
+#[cfg(feature = "compile-osl")]
+pub mod compile;
+pub mod dependencies;
+pub mod diff;
+pub mod export;
+#[cfg(feature = "test-fixtures")]
+pub mod fixtures;
+pub mod group;
+pub mod heap_size;
+#[cfg(feature = "json")]
+pub mod json;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub mod library;
+pub mod lint;
+pub mod metadata_schema;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub mod open_many;
 pub mod parser;
 pub mod query;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub mod query_cache;
+pub mod searchpath;
+pub mod source;
+#[cfg(feature = "test-util")]
+pub mod test_util;
+pub mod text;
+mod trace;
 pub mod types;
+pub mod validate;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
+pub use diff::{MetadataDiff, ShaderDiff};
+pub use group::{ShaderGroup, parse_group};
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub use library::ShaderLibrary;
+pub use lint::{LintFinding, LintRule, LintRuleset};
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub use open_many::{OpenManyReport, OpenedFile};
 pub use query::OslQuery;
-pub use types::{Metadata, MetadataValue, Parameter, ParameterKind, TypedParameter};
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub use query_cache::QueryCache;
+pub use searchpath::SearchPaths;
+pub use source::{ShaderSource, StdFs};
+pub use types::{
+    CollapsedParam, Metadata, MetadataValue, Parameter, ParameterDirection, ParameterKind,
+    StringValue, StructDecl, StructParam, TypeFilter, TypedParameter,
+};
+pub use validate::{RuleId, Severity, StructFieldError, StructRegistry, ValidationIssue};
@@ -0,0 +1,526 @@
+//! Indexing directories of compiled shaders.
+//!
+//! [`ShaderLibrary::scan`] only reads the header of each `.oso` file to learn
+//! the shader's name and type; the file is parsed in full the first time the
+//! shader is requested. [`ShaderLibrary::refresh`] picks up changed files,
+//! and with the `watch` feature [`ShaderLibrary::watch`] does so whenever
+//! the directories change.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock, PoisonError, RwLock};
+use std::time::SystemTime;
+
+use thiserror::Error;
+
+use crate::parser::{OsoReader, ParseError, oso};
+use crate::query::OslQuery;
+use crate::searchpath::SearchPaths;
+use crate::source::{ShaderSource, StdFs};
+use crate::types::MetadataValue;
+
+/// Errors when looking up a shader in a [`ShaderLibrary`].
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum LibraryError {
+    #[error("Shader not found: {0}")]
+    NotFound(String),
+
+    #[error("Failed to parse {path}: {error}")]
+    Parse { path: PathBuf, error: ParseError },
+}
+
+/// Options for [`ShaderLibrary::scan`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanOptions {
+    /// Descend into subdirectories.
+    pub recursive: bool,
+}
+
+/// A shader found while scanning.
+#[derive(Debug)]
+pub struct ShaderEntry {
+    name: String,
+    shader_type: String,
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    size: u64,
+    query: OnceLock<Result<Arc<OslQuery>, ParseError>>,
+    source: Arc<dyn ShaderSource>,
+}
+
+impl ShaderEntry {
+    /// Shader name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Shader type (surface, displacement, ...).
+    pub fn shader_type(&self) -> &str {
+        &self.shader_type
+    }
+
+    /// Path of the `.oso` file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Modification time of the file when it was scanned.
+    pub fn modified(&self) -> Option<SystemTime> {
+        self.modified
+    }
+
+    /// Parse the file, or return the result of the first parse.
+    pub fn query(&self) -> Result<Arc<OslQuery>, LibraryError> {
+        self.query
+            .get_or_init(|| {
+                OsoReader::new()
+                    .parse_source(&self.path, &*self.source)
+                    .map(Arc::new)
+            })
+            .clone()
+            .map_err(|error| LibraryError::Parse {
+                path: self.path.clone(),
+                error,
+            })
+    }
+
+    /// Check if the file is still the one that was scanned.
+    fn is_unchanged(&self, modified: Option<SystemTime>, size: u64) -> bool {
+        self.modified.is_some() && self.modified == modified && self.size == size
+    }
+}
+
+/// Shader names whose file changed in a [`ShaderLibrary::refresh`].
+///
+/// Only the shader each name resolves to counts: a change to a shadowed
+/// duplicate is not reported. All lists are sorted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LibraryChange {
+    /// Shaders that were not in the library before.
+    pub added: Vec<String>,
+    /// Shaders whose file was rewritten or that now resolve to another file.
+    pub updated: Vec<String>,
+    /// Shaders that are no longer in the library.
+    pub removed: Vec<String>,
+}
+
+impl LibraryChange {
+    /// Check if nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// The shaders found by one scan.
+#[derive(Debug, Default)]
+struct Index {
+    /// All shaders in scan order
+    entries: Vec<Arc<ShaderEntry>>,
+    /// Indices into `entries` by shader name
+    by_name: BTreeMap<String, Vec<usize>>,
+    /// Files whose header could not be read, in scan order
+    failures: Vec<LibraryError>,
+}
+
+impl Index {
+    fn entry(&self, name: &str) -> Option<&Arc<ShaderEntry>> {
+        let index = *self.by_name.get(name)?.first()?;
+        Some(&self.entries[index])
+    }
+}
+
+/// An index of the shaders found in a set of directories.
+///
+/// If a shader name occurs more than once, the first one found wins, i.e.
+/// directories given earlier take precedence, like a search path.
+///
+/// The library can be shared between threads. [`refresh`](Self::refresh)
+/// builds a new index and swaps it in; lookups running at the same time see
+/// either the old or the new one.
+#[derive(Debug)]
+pub struct ShaderLibrary {
+    /// Directories given to `scan`
+    roots: Vec<PathBuf>,
+    options: ScanOptions,
+    source: Arc<dyn ShaderSource>,
+    index: RwLock<Arc<Index>>,
+}
+
+impl Default for ShaderLibrary {
+    fn default() -> Self {
+        ShaderLibrary {
+            roots: Vec::new(),
+            options: ScanOptions::default(),
+            source: Arc::new(StdFs),
+            index: RwLock::default(),
+        }
+    }
+}
+
+impl ShaderLibrary {
+    /// Scan `paths` for `.oso` files.
+    ///
+    /// Files in each directory are visited in alphabetical order. Files that
+    /// contain no shader declaration are skipped, and files that cannot be
+    /// read are listed in [`failures`](Self::failures). Symbolic links to
+    /// directories are not followed. With the `rayon` feature, the files are
+    /// read in parallel.
+    pub fn scan<P: AsRef<Path>>(
+        paths: impl IntoIterator<Item = P>,
+        options: &ScanOptions,
+    ) -> Result<Self, ParseError> {
+        Self::scan_with_source(paths, options, StdFs)
+    }
+
+    /// Like [`scan`](Self::scan), with the directories and files in
+    /// `source` instead of the file system.
+    ///
+    /// The source needs to implement [`ShaderSource::is_dir`] and
+    /// [`ShaderSource::read_dir`]. [`refresh`](Self::refresh) only keeps the
+    /// entries of unchanged files if it implements
+    /// [`ShaderSource::modified_and_len`].
+    pub fn scan_with_source<P: AsRef<Path>>(
+        paths: impl IntoIterator<Item = P>,
+        options: &ScanOptions,
+        source: impl ShaderSource + 'static,
+    ) -> Result<Self, ParseError> {
+        let roots: Vec<PathBuf> = paths
+            .into_iter()
+            .map(|path| path.as_ref().to_path_buf())
+            .collect();
+        let source: Arc<dyn ShaderSource> = Arc::new(source);
+        let index = build_index(&roots, options, &source, &Index::default())?;
+        Ok(ShaderLibrary {
+            roots,
+            options: options.clone(),
+            source,
+            index: RwLock::new(Arc::new(index)),
+        })
+    }
+
+    /// Scan the directories of a search path. Directories that do not exist
+    /// are skipped.
+    pub fn scan_search_paths(
+        search_paths: &SearchPaths,
+        options: &ScanOptions,
+    ) -> Result<Self, ParseError> {
+        Self::scan(search_paths.iter().filter(|dir| dir.is_dir()), options)
+    }
+
+    /// Scan the directories again and pick up added, rewritten and deleted
+    /// files.
+    ///
+    /// Files whose modification time and size are unchanged keep their
+    /// entry, including the parsed shader. On error the library is left as
+    /// it was.
+    pub fn refresh(&self) -> Result<LibraryChange, ParseError> {
+        let old = self.snapshot();
+        let new = Arc::new(build_index(&self.roots, &self.options, &self.source, &old)?);
+
+        let mut change = LibraryChange::default();
+        for name in new.by_name.keys() {
+            match (old.entry(name), new.entry(name)) {
+                (None, _) => change.added.push(name.clone()),
+                (Some(before), Some(after)) if !Arc::ptr_eq(before, after) => {
+                    change.updated.push(name.clone())
+                }
+                _ => {}
+            }
+        }
+        change.removed = old
+            .by_name
+            .keys()
+            .filter(|name| !new.by_name.contains_key(*name))
+            .cloned()
+            .collect();
+
+        *self.index.write().unwrap_or_else(PoisonError::into_inner) = new;
+        Ok(change)
+    }
+
+    /// Watch the scanned directories and [`refresh`](Self::refresh) the
+    /// library when `.oso` files in them change.
+    ///
+    /// `callback` is called from the watcher thread after each refresh that
+    /// changed something. This watches the file system, also for a library
+    /// scanned from another [`ShaderSource`]. Watching stops when the returned [`LibraryWatcher`]
+    /// is dropped.
+    #[cfg(feature = "watch")]
+    pub fn watch(
+        self: &Arc<Self>,
+        mut callback: impl FnMut(&LibraryChange) + Send + 'static,
+    ) -> Result<LibraryWatcher, notify::Error> {
+        use notify::{EventKind, RecursiveMode, Watcher};
+
+        let library = Arc::clone(self);
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                let Ok(event) = event else {
+                    return;
+                };
+                if matches!(event.kind, EventKind::Access(_))
+                    || !event
+                        .paths
+                        .iter()
+                        .any(|path| path.extension().is_some_and(|ext| ext == "oso"))
+                {
+                    return;
+                }
+                match library.refresh() {
+                    Ok(change) if !change.is_empty() => callback(&change),
+                    Ok(_) => {}
+                    Err(_error) => {
+                        crate::trace::error!(error = %_error, "failed to refresh shader library");
+                    }
+                }
+            })?;
+
+        let mode = if self.options.recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+        for root in &self.roots {
+            watcher.watch(root, mode)?;
+        }
+
+        Ok(LibraryWatcher { _watcher: watcher })
+    }
+
+    /// Get the parsed shader `name`, parsing it on first access.
+    pub fn get(&self, name: &str) -> Result<Arc<OslQuery>, LibraryError> {
+        self.entry(name)
+            .ok_or_else(|| LibraryError::NotFound(name.to_string()))?
+            .query()
+    }
+
+    /// Get the entry of shader `name`.
+    pub fn entry(&self, name: &str) -> Option<Arc<ShaderEntry>> {
+        self.snapshot().entry(name).cloned()
+    }
+
+    /// Names of all shaders, sorted.
+    pub fn names(&self) -> impl Iterator<Item = String> {
+        self.snapshot()
+            .by_name
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    /// All shaders found, including shadowed duplicates, in scan order.
+    pub fn iter(&self) -> impl Iterator<Item = Arc<ShaderEntry>> {
+        self.snapshot().entries.clone().into_iter()
+    }
+
+    /// Number of shaders found, including shadowed duplicates.
+    pub fn len(&self) -> usize {
+        self.snapshot().entries.len()
+    }
+
+    /// Check if no shaders were found.
+    pub fn is_empty(&self) -> bool {
+        self.snapshot().entries.is_empty()
+    }
+
+    /// Estimate the heap memory held by the index and the shaders parsed so
+    /// far, in bytes, leaving out interned strings.
+    ///
+    /// Shaders that were not accessed yet only count their index entry. See
+    /// [`OslQuery::heap_size_estimate`].
+    pub fn total_heap_size(&self) -> usize {
+        self.snapshot()
+            .entries
+            .iter()
+            .map(|entry| {
+                let query = match entry.query.get() {
+                    Some(Ok(query)) => size_of::<OslQuery>() + query.heap_size_estimate(),
+                    _ => 0,
+                };
+                size_of::<ShaderEntry>()
+                    + entry.name.len()
+                    + entry.shader_type.len()
+                    + entry.path.as_os_str().len()
+                    + query
+            })
+            .sum()
+    }
+
+    /// Shader names found in more than one file, across all directories,
+    /// with all their files in priority order: the first is the one
+    /// [`get`](Self::get) returns.
+    pub fn collisions(&self) -> Vec<(String, Vec<PathBuf>)> {
+        let index = self.snapshot();
+        index
+            .by_name
+            .iter()
+            .filter(|(_, indices)| indices.len() > 1)
+            .map(|(name, indices)| {
+                let paths = indices
+                    .iter()
+                    .map(|&i| index.entries[i].path.clone())
+                    .collect();
+                (name.clone(), paths)
+            })
+            .collect()
+    }
+
+    /// Shaders whose shader-level metadata `key` has the value `value`.
+    ///
+    /// Numbers are compared by their textual form. This parses every shader
+    /// not parsed yet; shaders that fail to parse are skipped.
+    pub fn find_by_metadata(&self, key: &str, value: &str) -> Vec<Arc<OslQuery>> {
+        let index = self.snapshot();
+        index
+            .by_name
+            .keys()
+            .filter_map(|name| index.entry(name)?.query().ok())
+            .filter(|query| {
+                query
+                    .find_metadata(key)
+                    .is_some_and(|meta| match &meta.value {
+                        MetadataValue::Int(v) => v.to_string() == value,
+                        MetadataValue::Float(v) => v.to_string() == value,
+                        MetadataValue::String(v) => v == value,
+                        _ => false,
+                    })
+            })
+            .collect()
+    }
+
+    /// The `.oso` files that could not be read in the last scan or refresh,
+    /// e.g. because they are not UTF-8, as [`LibraryError::Parse`] with the
+    /// path of each file.
+    pub fn failures(&self) -> Vec<LibraryError> {
+        self.snapshot().failures.clone()
+    }
+
+    /// The current index. The lock is only held while cloning the `Arc`.
+    fn snapshot(&self) -> Arc<Index> {
+        Arc::clone(&self.index.read().unwrap_or_else(PoisonError::into_inner))
+    }
+}
+
+/// Keeps a [`ShaderLibrary`] refreshed; see [`ShaderLibrary::watch`].
+#[cfg(feature = "watch")]
+#[derive(Debug)]
+pub struct LibraryWatcher {
+    _watcher: notify::RecommendedWatcher,
+}
+
+/// Scan `roots`, reusing the entries of `previous` whose file is unchanged.
+fn build_index(
+    roots: &[PathBuf],
+    options: &ScanOptions,
+    source: &Arc<dyn ShaderSource>,
+    previous: &Index,
+) -> Result<Index, ParseError> {
+    let mut files = Vec::new();
+    for root in roots {
+        collect_files(root, options, &**source, &mut files)?;
+    }
+
+    let unchanged: HashMap<&Path, &Arc<ShaderEntry>> = previous
+        .entries
+        .iter()
+        .map(|entry| (entry.path.as_path(), entry))
+        .collect();
+    let stats: Vec<_> = files
+        .iter()
+        .map(|path| source.modified_and_len(path))
+        .collect();
+
+    let read = |(path, &(modified, size)): (&PathBuf, &(Option<SystemTime>, u64))| match unchanged
+        .get(path.as_path())
+    {
+        Some(entry) if entry.is_unchanged(modified, size) => Ok(Some(Arc::clone(entry))),
+        _ => read_header(path, &**source).map(|header| {
+            header.map(|(shader_type, name)| {
+                Arc::new(ShaderEntry {
+                    name,
+                    shader_type,
+                    path: path.clone(),
+                    modified,
+                    size,
+                    query: OnceLock::new(),
+                    source: Arc::clone(source),
+                })
+            })
+        }),
+    };
+
+    #[cfg(feature = "rayon")]
+    let entries: Vec<_> = {
+        use rayon::prelude::*;
+        files.par_iter().zip(&stats).map(read).collect()
+    };
+    #[cfg(not(feature = "rayon"))]
+    let entries: Vec<_> = files.iter().zip(&stats).map(read).collect();
+
+    let mut index = Index::default();
+    for (path, entry) in files.into_iter().zip(entries) {
+        let entry = match entry {
+            Ok(Some(entry)) => entry,
+            Ok(None) => continue,
+            Err(error) => {
+                crate::trace::debug!(path = %path.display(), error = %error, "skipping unreadable shader");
+                index.failures.push(LibraryError::Parse { path, error });
+                continue;
+            }
+        };
+        index
+            .by_name
+            .entry(entry.name.clone())
+            .or_default()
+            .push(index.entries.len());
+        index.entries.push(entry);
+    }
+
+    Ok(index)
+}
+
+/// Collect the `.oso` files in `dir`, sorted per directory.
+fn collect_files(
+    dir: &Path,
+    options: &ScanOptions,
+    source: &dyn ShaderSource,
+    files: &mut Vec<PathBuf>,
+) -> Result<(), ParseError> {
+    let mut paths = source.read_dir(dir)?;
+    paths.sort();
+
+    for path in paths {
+        if source.is_dir(&path) {
+            // Links back to a parent would otherwise be scanned forever.
+            if options.recursive && !source.is_symlink(&path) {
+                collect_files(&path, options, source, files)?;
+            }
+        } else if path.extension().is_some_and(|ext| ext == "oso") {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Read the shader type and name from the declaration line of an OSO file.
+fn read_header(
+    path: &Path,
+    source: &dyn ShaderSource,
+) -> Result<Option<(String, String)>, ParseError> {
+    let reader = BufReader::new(source.open(path)?);
+    for line in reader.lines() {
+        let line = line?;
+        if oso::is_shader_declaration(&line) {
+            return Ok(oso::parse_shader(&line)
+                .ok()
+                .map(|(_, (shader_type, name))| (shader_type.to_string(), name)));
+        }
+        if line.starts_with("code") {
+            break;
+        }
+    }
+    Ok(None)
+}
@@ -0,0 +1,404 @@
+//! Checking a shader's UI metadata against authoring standards.
+//!
+//! Unlike [`OslQuery::validate`], which finds interfaces that contradict
+//! themselves, [`OslQuery::lint`] reports parameters that are consistent but
+//! poorly described for artists: no label, no help text, no page, and so on.
+//! Only inputs are linted; outputs are not shown as controls.
+
+use ustr::Ustr;
+
+use crate::query::OslQuery;
+use crate::types::Parameter;
+
+/// A lint rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum LintRule {
+    /// The parameter has no `label`.
+    MissingLabel,
+    /// The `label` is just the parameter name.
+    WeakLabel,
+    /// The parameter has no `help`.
+    MissingHelp,
+    /// The parameter has no `page`.
+    MissingPage,
+    /// An `int` or `float` parameter lacks `min` or `max`. Ints shown as
+    /// checkboxes are exempt.
+    MissingRange,
+    /// A string parameter whose name suggests a file does not use the
+    /// `filename` widget.
+    FilenameWidget,
+    /// A parameter with `options` has no `widget` to show them with.
+    OptionsWithoutWidget,
+}
+
+impl LintRule {
+    /// All rules, in the order they are checked.
+    pub const ALL: [LintRule; 7] = [
+        LintRule::MissingLabel,
+        LintRule::WeakLabel,
+        LintRule::MissingHelp,
+        LintRule::MissingPage,
+        LintRule::MissingRange,
+        LintRule::FilenameWidget,
+        LintRule::OptionsWithoutWidget,
+    ];
+
+    /// The rule's id in reports, e.g. `missing_label`.
+    pub fn id(&self) -> &'static str {
+        match self {
+            LintRule::MissingLabel => "missing_label",
+            LintRule::WeakLabel => "weak_label",
+            LintRule::MissingHelp => "missing_help",
+            LintRule::MissingPage => "missing_page",
+            LintRule::MissingRange => "missing_range",
+            LintRule::FilenameWidget => "filename_widget",
+            LintRule::OptionsWithoutWidget => "options_without_widget",
+        }
+    }
+}
+
+/// The rules [`OslQuery::lint`] checks.
+///
+/// The default enables all of them.
+///
+/// ```
+/// use oslquery_petite::lint::{LintRule, LintRuleset};
+///
+/// let ruleset = LintRuleset::new().disable(LintRule::MissingPage);
+/// assert!(!ruleset.is_enabled(LintRule::MissingPage));
+/// assert!(ruleset.is_enabled(LintRule::MissingLabel));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintRuleset {
+    enabled: Vec<LintRule>,
+}
+
+impl Default for LintRuleset {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LintRuleset {
+    /// Create a ruleset with all rules enabled.
+    pub fn new() -> Self {
+        LintRuleset {
+            enabled: LintRule::ALL.to_vec(),
+        }
+    }
+
+    /// Create a ruleset with no rules enabled, to [`enable`](Self::enable)
+    /// single ones.
+    pub fn none() -> Self {
+        LintRuleset {
+            enabled: Vec::new(),
+        }
+    }
+
+    /// Enable `rule`.
+    pub fn enable(mut self, rule: LintRule) -> Self {
+        if !self.enabled.contains(&rule) {
+            self.enabled.push(rule);
+        }
+        self
+    }
+
+    /// Disable `rule`.
+    pub fn disable(mut self, rule: LintRule) -> Self {
+        self.enabled.retain(|enabled| *enabled != rule);
+        self
+    }
+
+    /// Check if `rule` is enabled.
+    pub fn is_enabled(&self, rule: LintRule) -> bool {
+        self.enabled.contains(&rule)
+    }
+}
+
+/// A finding of [`OslQuery::lint`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LintFinding {
+    /// Name of the parameter
+    pub param: Ustr,
+    /// The rule that found it
+    pub rule: LintRule,
+    /// A description for the shader author
+    pub message: String,
+}
+
+impl OslQuery {
+    /// Check the metadata of the input parameters against the enabled rules
+    /// of `ruleset`.
+    ///
+    /// Findings are reported in parameter order, and for each parameter in
+    /// the order of [`LintRule::ALL`].
+    pub fn lint(&self, ruleset: &LintRuleset) -> Vec<LintFinding> {
+        let mut findings = Vec::new();
+        for param in self.params().iter().filter(|param| !param.is_output()) {
+            for rule in LintRule::ALL {
+                if ruleset.is_enabled(rule)
+                    && let Some(message) = check(rule, param)
+                {
+                    findings.push(LintFinding {
+                        param: param.name,
+                        rule,
+                        message,
+                    });
+                }
+            }
+        }
+        findings
+    }
+}
+
+/// Check `param` against `rule`, returning the message if it fails.
+fn check(rule: LintRule, param: &Parameter) -> Option<String> {
+    let name = &param.name;
+    let label = param.label().filter(|label| !label.trim().is_empty());
+    let widget = param.widget();
+    match rule {
+        LintRule::MissingLabel => label
+            .is_none()
+            .then(|| format!("\"{}\" has no label", name)),
+        LintRule::WeakLabel => label
+            .filter(|label| label.trim() == name.as_str())
+            .map(|_| format!("the label of \"{}\" is just its name", name)),
+        LintRule::MissingHelp => param
+            .help()
+            .is_none_or(|help| help.trim().is_empty())
+            .then(|| format!("\"{}\" has no help text", name)),
+        LintRule::MissingPage => param
+            .ui_page()
+            .is_none()
+            .then(|| format!("\"{}\" is on no page", name)),
+        LintRule::MissingRange => {
+            let numeric = matches!(
+                param.typed_param().type_name(),
+                "int" | "float" | "int[]" | "float[]"
+            );
+            let checkbox = matches!(widget, Some("checkBox" | "boolean"));
+            let missing: Vec<&str> = ["min", "max"]
+                .into_iter()
+                .filter(|bound| param.find_metadata(bound).is_none())
+                .collect();
+            (numeric && !checkbox && !missing.is_empty())
+                .then(|| format!("\"{}\" has no {}", name, missing.join(" or ")))
+        }
+        LintRule::FilenameWidget => {
+            let lowercase = name.to_lowercase();
+            let file_like = ["file", "path", "texture", "map"]
+                .iter()
+                .any(|word| lowercase.contains(word));
+            (param.typed_param().type_name() == "string" && file_like && widget != Some("filename"))
+                .then(|| format!("\"{}\" looks like a file but has no filename widget", name))
+        }
+        LintRule::OptionsWithoutWidget => (param.find_metadata("options").is_some()
+            && widget.is_none())
+        .then(|| format!("\"{}\" has options but no widget to show them", name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{MetadataValue, ParameterKind, TypedParameter};
+
+    fn string(value: &str) -> MetadataValue {
+        MetadataValue::String(value.into())
+    }
+
+    /// A float input that passes every rule.
+    fn complete(name: &str) -> Parameter {
+        Parameter::new_input(name, TypedParameter::Float { default: Some(0.5) })
+            .with_metadata("label", string("Diffuse"))
+            .with_metadata("help", string("How much light is scattered."))
+            .with_metadata("page", string("Diffuse"))
+            .with_metadata("min", MetadataValue::Float(0.0))
+            .with_metadata("max", MetadataValue::Float(1.0))
+    }
+
+    /// Lint a query with the single parameter `param` with all rules.
+    fn lint(param: Parameter) -> Vec<LintFinding> {
+        let mut query = OslQuery::new();
+        query.add_parameter(param);
+        query.lint(&LintRuleset::default())
+    }
+
+    fn rules(findings: &[LintFinding]) -> Vec<LintRule> {
+        findings.iter().map(|finding| finding.rule).collect()
+    }
+
+    /// `complete("Kd")` without the metadata `name`.
+    fn without(name: &str) -> Parameter {
+        let mut param = complete("Kd");
+        param
+            .metadata_mut()
+            .retain(|metadata| metadata.name != name);
+        param
+    }
+
+    #[test]
+    fn test_complete() {
+        assert_eq!(lint(complete("Kd")), []);
+        // Outputs are not linted.
+        assert_eq!(
+            lint(Parameter::new_output(
+                "result",
+                TypedParameter::Float { default: None }
+            )),
+            []
+        );
+    }
+
+    #[test]
+    fn test_missing_label() {
+        let findings = lint(without("label"));
+        assert_eq!(
+            findings,
+            [LintFinding {
+                param: "Kd".into(),
+                rule: LintRule::MissingLabel,
+                message: "\"Kd\" has no label".to_string(),
+            }]
+        );
+        let param = without("label").with_metadata("label", string(" "));
+        assert_eq!(rules(&lint(param)), [LintRule::MissingLabel]);
+    }
+
+    #[test]
+    fn test_weak_label() {
+        let param = without("label").with_metadata("label", string("Kd"));
+        assert_eq!(rules(&lint(param)), [LintRule::WeakLabel]);
+    }
+
+    #[test]
+    fn test_missing_help() {
+        assert_eq!(rules(&lint(without("help"))), [LintRule::MissingHelp]);
+    }
+
+    #[test]
+    fn test_missing_page() {
+        assert_eq!(rules(&lint(without("page"))), [LintRule::MissingPage]);
+    }
+
+    #[test]
+    fn test_missing_range() {
+        let findings = lint(without("max"));
+        assert_eq!(rules(&findings), [LintRule::MissingRange]);
+        assert_eq!(findings[0].message, "\"Kd\" has no max");
+
+        let mut param = without("min");
+        param
+            .metadata_mut()
+            .retain(|metadata| metadata.name != "max");
+        assert_eq!(lint(param)[0].message, "\"Kd\" has no min or max");
+
+        // Checkboxes and non-numeric types need no range.
+        let mut checkbox = without("min").with_metadata("widget", string("checkBox"));
+        checkbox.kind = ParameterKind::Input(TypedParameter::Int { default: Some(1) });
+        assert_eq!(lint(checkbox), []);
+        let mut color = without("min");
+        color.kind = ParameterKind::Input(TypedParameter::Color {
+            default: None,
+            space: None,
+        });
+        assert_eq!(lint(color), []);
+    }
+
+    #[test]
+    fn test_filename_widget() {
+        let texture = |name: &str| {
+            let mut param = complete(name);
+            param
+                .metadata_mut()
+                .retain(|metadata| !["min", "max", "label"].contains(&metadata.name.as_str()));
+            param.kind = ParameterKind::Input(TypedParameter::String {
+                default: Some("".into()),
+            });
+            param.with_metadata("label", string("Texture"))
+        };
+        assert_eq!(
+            rules(&lint(texture("diffuseTexture"))),
+            [LintRule::FilenameWidget]
+        );
+        assert_eq!(
+            rules(&lint(texture("FilePath"))),
+            [LintRule::FilenameWidget]
+        );
+        assert_eq!(
+            lint(texture("diffuseTexture").with_metadata("widget", string("filename"))),
+            []
+        );
+        assert_eq!(lint(texture("projection")), []);
+    }
+
+    #[test]
+    fn test_options_without_widget() {
+        let param = complete("Kd").with_metadata("options", string("Low:0.2|High:0.8"));
+        assert_eq!(
+            rules(&lint(param.clone())),
+            [LintRule::OptionsWithoutWidget]
+        );
+        assert_eq!(lint(param.with_metadata("widget", string("mapper"))), []);
+    }
+
+    #[test]
+    fn test_ruleset() {
+        let bare = Parameter::new_input("Kd", TypedParameter::Float { default: Some(0.5) });
+        assert_eq!(
+            rules(&lint(bare.clone())),
+            [
+                LintRule::MissingLabel,
+                LintRule::MissingHelp,
+                LintRule::MissingPage,
+                LintRule::MissingRange
+            ]
+        );
+
+        let mut query = OslQuery::new();
+        query.add_parameter(bare);
+        let ruleset = LintRuleset::new()
+            .disable(LintRule::MissingHelp)
+            .disable(LintRule::MissingPage);
+        assert_eq!(
+            rules(&query.lint(&ruleset)),
+            [LintRule::MissingLabel, LintRule::MissingRange]
+        );
+
+        let ruleset = LintRuleset::none().enable(LintRule::MissingPage);
+        assert_eq!(rules(&query.lint(&ruleset)), [LintRule::MissingPage]);
+        assert_eq!(query.lint(&LintRuleset::none()), []);
+        assert_eq!(
+            LintRuleset::none()
+                .enable(LintRule::WeakLabel)
+                .enable(LintRule::WeakLabel),
+            LintRuleset::none().enable(LintRule::WeakLabel)
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_finding_json() {
+        let finding = &lint(without("help"))[0];
+        let json = serde_json::to_value(finding).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "param": "Kd",
+                "rule": "missing_help",
+                "message": "\"Kd\" has no help text",
+            })
+        );
+        assert_eq!(json["rule"], finding.rule.id());
+        assert_eq!(
+            &serde_json::from_value::<LintFinding>(json).unwrap(),
+            finding
+        );
+    }
+}
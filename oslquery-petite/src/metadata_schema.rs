@@ -0,0 +1,347 @@
+//! The conventional metadata keys and the types their values should have.
+//!
+//! The OSL specification and renderer documentation agree on a set of
+//! parameter metadata, e.g. `label` is a string and `min` a number of the
+//! parameter's own type. [`check_metadata`] compares a parameter's metadata
+//! against [`KNOWN_KEYS`], and [`OslQuery::validate`](crate::OslQuery::validate)
+//! reports what it finds.
+
+use ustr::Ustr;
+
+use crate::types::{MetadataValue, Parameter};
+
+/// The type a known metadata key should have.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpectedType {
+    /// A single string
+    String,
+    /// A single int
+    Int,
+    /// A `|`-separated string, or an array of strings or ints
+    Options,
+    /// Numbers of the parameter's own type: ints for `int` parameters and
+    /// floats for float-based ones, single or as an array
+    ParamType,
+}
+
+/// A conventional metadata key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KnownKey {
+    /// Name of the metadata
+    pub name: &'static str,
+    /// The type its value should have
+    pub expected: ExpectedType,
+}
+
+const fn key(name: &'static str, expected: ExpectedType) -> KnownKey {
+    KnownKey { name, expected }
+}
+
+/// The metadata keys of the OSL specification and common renderer
+/// conventions.
+pub const KNOWN_KEYS: [KnownKey; 15] = [
+    key("label", ExpectedType::String),
+    key("help", ExpectedType::String),
+    key("page", ExpectedType::String),
+    key("widget", ExpectedType::String),
+    key("units", ExpectedType::String),
+    key("URL", ExpectedType::String),
+    key("options", ExpectedType::Options),
+    key("min", ExpectedType::ParamType),
+    key("max", ExpectedType::ParamType),
+    key("slidermin", ExpectedType::ParamType),
+    key("slidermax", ExpectedType::ParamType),
+    key("sensitivity", ExpectedType::ParamType),
+    key("digits", ExpectedType::Int),
+    key("slider", ExpectedType::Int),
+    key("connectable", ExpectedType::Int),
+];
+
+/// Find a known metadata key by name.
+pub fn known_key(name: &str) -> Option<&'static KnownKey> {
+    KNOWN_KEYS.iter().find(|key| key.name == name)
+}
+
+/// What is wrong with a metadata entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataIssueKind {
+    /// A known key has a value of the wrong type, e.g. an int `label`.
+    WrongType,
+    /// An unknown key is close to a known one, e.g. `lable`.
+    PossibleTypo {
+        /// The known key it is close to
+        suggestion: &'static str,
+    },
+    /// A [`ParamType`](ExpectedType::ParamType) key is a number of the
+    /// other type, e.g. an int `min` of a float parameter.
+    RangeTypeMismatch,
+}
+
+/// A finding of [`check_metadata`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetadataIssue {
+    /// Name of the metadata
+    pub key: Ustr,
+    /// What is wrong with it
+    pub kind: MetadataIssueKind,
+    /// A description of the issue
+    pub message: String,
+}
+
+/// Check the metadata of `param` against [`KNOWN_KEYS`].
+///
+/// Unknown keys are fine unless they look like a misspelled known key.
+/// Issues are reported in metadata order.
+pub fn check_metadata(param: &Parameter) -> Vec<MetadataIssue> {
+    param
+        .metadata()
+        .iter()
+        .filter_map(|metadata| {
+            let kind = match known_key(&metadata.name) {
+                Some(known) => type_issue(param, known.expected, &metadata.value)?,
+                None => MetadataIssueKind::PossibleTypo {
+                    suggestion: suggestion(&metadata.name)?,
+                },
+            };
+            Some(MetadataIssue {
+                key: metadata.name,
+                kind,
+                message: message(param, &metadata.name, kind, &metadata.value),
+            })
+        })
+        .collect()
+}
+
+fn type_issue(
+    param: &Parameter,
+    expected: ExpectedType,
+    value: &MetadataValue,
+) -> Option<MetadataIssueKind> {
+    use MetadataValue as V;
+
+    let typed = param.typed_param();
+    let wrong = match expected {
+        ExpectedType::String => !matches!(value, V::String(_)),
+        ExpectedType::Int => !matches!(value, V::Int(_)),
+        ExpectedType::Options => {
+            !matches!(value, V::String(_) | V::StringArray(_) | V::IntArray(_))
+        }
+        ExpectedType::ParamType => {
+            let (is_int, is_float) = match value {
+                V::Int(_) | V::IntArray(_) => (true, false),
+                V::Float(_) | V::FloatArray(_) => (false, true),
+                _ => return Some(MetadataIssueKind::WrongType),
+            };
+            // Ranges of strings and closures mean nothing; leave them be.
+            if (typed.is_float_type() && is_int) || (typed.is_integer_type() && is_float) {
+                return Some(MetadataIssueKind::RangeTypeMismatch);
+            }
+            false
+        }
+    };
+    wrong.then_some(MetadataIssueKind::WrongType)
+}
+
+fn message(param: &Parameter, key: &str, kind: MetadataIssueKind, value: &MetadataValue) -> String {
+    match kind {
+        MetadataIssueKind::WrongType => format!(
+            "\"{}\" metadata of \"{}\" should not be {}",
+            key,
+            param.name,
+            value_type(value)
+        ),
+        MetadataIssueKind::PossibleTypo { suggestion } => format!(
+            "\"{}\" metadata of \"{}\" is unknown; did you mean \"{}\"?",
+            key, param.name, suggestion
+        ),
+        MetadataIssueKind::RangeTypeMismatch => format!(
+            "\"{}\" metadata of {} parameter \"{}\" is {}",
+            key,
+            param.typed_param().type_name().trim_end_matches("[]"),
+            param.name,
+            value_type(value)
+        ),
+    }
+}
+
+/// The OSL type of a metadata value with an article, e.g. `an int`.
+fn value_type(value: &MetadataValue) -> &'static str {
+    match value {
+        MetadataValue::Int(_) => "an int",
+        MetadataValue::Float(_) => "a float",
+        MetadataValue::String(_) => "a string",
+        MetadataValue::IntArray(_) => "an int array",
+        MetadataValue::FloatArray(_) => "a float array",
+        MetadataValue::StringArray(_) => "a string array",
+    }
+}
+
+/// The known key `name` is most likely a misspelling of, if any.
+///
+/// Keys that differ only in case always match; otherwise one edit is allowed
+/// for short keys and two for keys of five or more characters, with swapped
+/// neighbors counting as one edit.
+fn suggestion(name: &str) -> Option<&'static str> {
+    let name = name.to_lowercase();
+    let allowed = if name.chars().count() >= 5 { 2 } else { 1 };
+    KNOWN_KEYS
+        .iter()
+        .map(|key| (edit_distance(&name, &key.name.to_lowercase()), key.name))
+        .filter(|&(distance, _)| distance <= allowed)
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, name)| name)
+}
+
+/// The optimal string alignment distance: insertions, deletions,
+/// substitutions and transpositions of adjacent characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // Rows for the two previous prefixes of `a` and the current one.
+    let mut before: Vec<usize> = vec![0; b.len() + 1];
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for i in 1..=a.len() {
+        current[0] = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            current[j] = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(before[j - 2] + 1);
+            }
+        }
+        std::mem::swap(&mut before, &mut previous);
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::TypedParameter;
+
+    fn float() -> Parameter {
+        Parameter::new_input("Kd", TypedParameter::Float { default: Some(0.5) })
+    }
+
+    fn kinds(param: &Parameter) -> Vec<MetadataIssueKind> {
+        check_metadata(param)
+            .iter()
+            .map(|issue| issue.kind)
+            .collect()
+    }
+
+    #[test]
+    fn test_correct() {
+        let param = float()
+            .with_metadata("label", MetadataValue::String("Diffuse".into()))
+            .with_metadata("min", MetadataValue::Float(0.0))
+            .with_metadata("max", MetadataValue::FloatArray(vec![1.0]))
+            .with_metadata("digits", MetadataValue::Int(2))
+            .with_metadata("connectable", MetadataValue::Int(0))
+            .with_metadata("options", MetadataValue::String("Low:0.2|High:0.8".into()))
+            .with_metadata("tags", MetadataValue::StringArray(vec!["shading".into()]))
+            .with_metadata("vendor_hint", MetadataValue::Int(1));
+        assert_eq!(check_metadata(&param), []);
+
+        let int = Parameter::new_input("mode", TypedParameter::Int { default: Some(0) })
+            .with_metadata("min", MetadataValue::Int(0))
+            .with_metadata("options", MetadataValue::IntArray(vec![0, 1]));
+        assert_eq!(check_metadata(&int), []);
+
+        // Strings have no range to check.
+        let string = Parameter::new_input("name", TypedParameter::String { default: None })
+            .with_metadata("max", MetadataValue::Int(0));
+        assert_eq!(check_metadata(&string), []);
+    }
+
+    #[test]
+    fn test_wrong_type() {
+        let param = float()
+            .with_metadata("label", MetadataValue::Int(1))
+            .with_metadata("connectable", MetadataValue::String("no".into()))
+            .with_metadata("min", MetadataValue::String("0".into()));
+        let issues = check_metadata(&param);
+        assert_eq!(
+            kinds(&param),
+            [MetadataIssueKind::WrongType; 3],
+            "{:?}",
+            issues
+        );
+        assert_eq!(issues[0].key, "label");
+        assert_eq!(
+            issues[0].message,
+            "\"label\" metadata of \"Kd\" should not be an int"
+        );
+        assert_eq!(issues[2].key, "min");
+    }
+
+    #[test]
+    fn test_range_type_mismatch() {
+        let param = float().with_metadata("max", MetadataValue::Int(1));
+        let issues = check_metadata(&param);
+        assert_eq!(kinds(&param), [MetadataIssueKind::RangeTypeMismatch]);
+        assert_eq!(
+            issues[0].message,
+            "\"max\" metadata of float parameter \"Kd\" is an int"
+        );
+
+        let ints = Parameter::new_input(
+            "ids",
+            TypedParameter::IntArray {
+                size: 2,
+                default: None,
+            },
+        )
+        .with_metadata("slidermin", MetadataValue::Float(0.5));
+        assert_eq!(
+            check_metadata(&ints)[0].message,
+            "\"slidermin\" metadata of int parameter \"ids\" is a float"
+        );
+    }
+
+    #[test]
+    fn test_possible_typo() {
+        let param = float()
+            .with_metadata("lable", MetadataValue::String("Diffuse".into()))
+            .with_metadata("Help", MetadataValue::String("Diffuse".into()))
+            .with_metadata("sliderrmax", MetadataValue::Float(1.0))
+            .with_metadata("mn", MetadataValue::Float(0.0))
+            .with_metadata("tag", MetadataValue::String("shading".into()))
+            .with_metadata("colorspace", MetadataValue::String("srgb".into()));
+        let issues = check_metadata(&param);
+        assert_eq!(
+            kinds(&param),
+            [
+                MetadataIssueKind::PossibleTypo {
+                    suggestion: "label"
+                },
+                MetadataIssueKind::PossibleTypo { suggestion: "help" },
+                MetadataIssueKind::PossibleTypo {
+                    suggestion: "slidermax"
+                },
+                MetadataIssueKind::PossibleTypo { suggestion: "min" },
+            ],
+            "{:?}",
+            issues
+        );
+        assert_eq!(
+            issues[0].message,
+            "\"lable\" metadata of \"Kd\" is unknown; did you mean \"label\"?"
+        );
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("label", "label"), 0);
+        assert_eq!(edit_distance("lable", "label"), 1);
+        assert_eq!(edit_distance("labl", "label"), 1);
+        assert_eq!(edit_distance("", "max"), 3);
+        assert_eq!(edit_distance("page", "tag"), 2);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+}
@@ -0,0 +1,198 @@
+//! Opening an explicit list of shader files.
+//!
+//! [`OslQuery::open_many`] opens every file of a list and returns an
+//! [`OpenManyReport`] saying which files were opened, which failed and why,
+//! which file each input resolved to and how long it all took.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::parser::ParseError;
+use crate::query::OslQuery;
+use crate::searchpath::SearchPaths;
+
+/// One input of [`OslQuery::open_many`].
+#[derive(Debug)]
+pub struct OpenedFile {
+    /// The path as given
+    pub path: PathBuf,
+    /// The file the path resolved to, `None` if it was not found
+    pub resolved: Option<PathBuf>,
+    /// The parsed shader, or why it could not be opened
+    pub result: Result<OslQuery, ParseError>,
+    /// Time taken to resolve, read and parse the file
+    pub elapsed: Duration,
+}
+
+/// The outcome of [`OslQuery::open_many`]: one [`OpenedFile`] per input, in
+/// the order of the inputs.
+#[derive(Debug)]
+pub struct OpenManyReport {
+    files: Vec<OpenedFile>,
+    elapsed: Duration,
+}
+
+impl OpenManyReport {
+    /// All inputs, in the order they were given.
+    pub fn files(&self) -> &[OpenedFile] {
+        &self.files
+    }
+
+    /// Take the inputs, in the order they were given.
+    pub fn into_files(self) -> Vec<OpenedFile> {
+        self.files
+    }
+
+    /// The shaders that were opened, keyed by the path as given.
+    pub fn ok_iter(&self) -> impl Iterator<Item = (&Path, &OslQuery)> {
+        self.files
+            .iter()
+            .filter_map(|file| Some((file.path.as_path(), file.result.as_ref().ok()?)))
+    }
+
+    /// The inputs that could not be opened, keyed by the path as given.
+    pub fn errors(&self) -> impl Iterator<Item = (&Path, &ParseError)> {
+        self.files
+            .iter()
+            .filter_map(|file| Some((file.path.as_path(), file.result.as_ref().err()?)))
+    }
+
+    /// Check if every input was opened.
+    pub fn is_all_ok(&self) -> bool {
+        self.files.iter().all(|file| file.result.is_ok())
+    }
+
+    /// Number of inputs.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Check if there were no inputs.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Wall-clock time taken to open all inputs.
+    ///
+    /// With the `rayon` feature this is less than the sum of the
+    /// [`OpenedFile::elapsed`] times.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+}
+
+impl OslQuery {
+    /// Open and parse each of `paths`, looking for them in `searchpath` if
+    /// they are not found directly.
+    ///
+    /// See [`open_with_searchpath`](Self::open_with_searchpath). Every path
+    /// is tried, whether or not others failed. With the `rayon` feature the
+    /// files are parsed in parallel.
+    pub fn open_many<P: AsRef<Path>>(
+        paths: impl IntoIterator<Item = P>,
+        searchpath: &str,
+    ) -> OpenManyReport {
+        Self::open_many_with_search_paths(paths, &SearchPaths::parse_or_env(searchpath))
+    }
+
+    /// Like [`open_many`](Self::open_many), with search paths that were
+    /// parsed before.
+    pub fn open_many_with_search_paths<P: AsRef<Path>>(
+        paths: impl IntoIterator<Item = P>,
+        search_paths: &SearchPaths,
+    ) -> OpenManyReport {
+        let start = Instant::now();
+        let paths: Vec<PathBuf> = paths
+            .into_iter()
+            .map(|path| path.as_ref().to_path_buf())
+            .collect();
+
+        let open = |path: PathBuf| {
+            let start = Instant::now();
+            let (resolved, result) = Self::resolve_and_open(&path, search_paths);
+            OpenedFile {
+                path,
+                resolved,
+                result,
+                elapsed: start.elapsed(),
+            }
+        };
+
+        #[cfg(feature = "rayon")]
+        let files = {
+            use rayon::prelude::*;
+            paths.into_par_iter().map(open).collect()
+        };
+        #[cfg(not(feature = "rayon"))]
+        let files = paths.into_iter().map(open).collect();
+
+        OpenManyReport {
+            files,
+            elapsed: start.elapsed(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_many() {
+        let tests_dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests");
+        let report = OslQuery::open_many(
+            ["ui", "missing", "tests/struct.oso", "no/such/file.oso"],
+            tests_dir,
+        );
+
+        assert_eq!(report.len(), 4);
+        assert!(!report.is_all_ok());
+
+        let opened: Vec<(&Path, &str)> = report
+            .ok_iter()
+            .map(|(path, query)| (path, query.shader_name()))
+            .collect();
+        assert_eq!(
+            opened,
+            [
+                (Path::new("ui"), "uiTest"),
+                (Path::new("tests/struct.oso"), "structTest")
+            ]
+        );
+
+        let failed: Vec<&Path> = report.errors().map(|(path, _)| path).collect();
+        assert_eq!(
+            failed,
+            [Path::new("missing"), Path::new("no/such/file.oso")]
+        );
+        assert!(
+            report
+                .errors()
+                .all(|(_, error)| matches!(error, ParseError::Io(_)))
+        );
+
+        // Found in the search path, found directly, not found.
+        let resolved: Vec<Option<&Path>> = report
+            .files()
+            .iter()
+            .map(|file| file.resolved.as_deref())
+            .collect();
+        assert_eq!(
+            resolved,
+            [
+                Some(Path::new(tests_dir).join("ui.oso").as_path()),
+                None,
+                Some(Path::new("tests/struct.oso")),
+                None
+            ]
+        );
+        assert!(report.files().iter().all(|f| f.elapsed <= report.elapsed()));
+    }
+
+    #[test]
+    fn test_open_many_empty() {
+        let report = OslQuery::open_many(Vec::<PathBuf>::new(), "");
+        assert!(report.is_empty() && report.is_all_ok());
+        assert_eq!(report.ok_iter().count(), 0);
+    }
+}
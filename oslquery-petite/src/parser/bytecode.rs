@@ -0,0 +1,68 @@
+//! Code (bytecode) section handling for OSO files.
+//!
+//! An OSO file ends with one or more `code` blocks holding the shader's
+//! compiled instructions:
+//!
+//! ```text
+//! code ___main___
+//!     assign      ss $const1  %filename{"metadata.osl"} %line{15} %argrw{"wr"}
+//!     end
+//! ```
+//!
+//! Each block starts with a `code <name>` line (`___main___` for the shader
+//! body, otherwise the name of a parameter whose default is computed by an
+//! init expression). Instructions follow on indented lines as an opcode, its
+//! argument symbols and optional `%hint{...}` blocks. The final instruction of
+//! the file is `end`.
+//!
+//! Querying parameters does not need any of this, so the reader stops at the
+//! first `code` line. This module only locates the section so its extent is
+//! known to callers.
+
+/// Line range of the code section in an OSO source.
+///
+/// Line numbers are 1-based, like the ones reported in
+/// [`ParseError::ParseError`](super::ParseError::ParseError).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BytecodeSection {
+    /// Line of the first `code` directive.
+    pub start: usize,
+    /// Line one past the last line of the section.
+    pub end: usize,
+}
+
+impl BytecodeSection {
+    /// Skip over the code section.
+    ///
+    /// Scans `content` from line `after_code_line` (the line following the
+    /// `code` directive) and returns the line one past the final `end`
+    /// instruction, or one past the last line if there is none.
+    pub fn skip(content: &str, after_code_line: usize) -> usize {
+        let mut line_count = 0;
+
+        for (index, line) in content.lines().enumerate() {
+            line_count = index + 1;
+            if line_count >= after_code_line && line.trim() == "end" {
+                return line_count + 1;
+            }
+        }
+
+        line_count.max(after_code_line.saturating_sub(1)) + 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skip_code_section() {
+        let content =
+            "OpenShadingLanguage 1.12\nshader test\ncode ___main___\n\tassign a b\n\tend\n";
+        assert_eq!(BytecodeSection::skip(content, 4), 6);
+
+        // No `end` instruction - the section runs to the end of the file
+        let content = "OpenShadingLanguage 1.12\nshader test\ncode ___main___\n\tassign a b\n";
+        assert_eq!(BytecodeSection::skip(content, 4), 5);
+    }
+}
@@ -0,0 +1,379 @@
+//! Configuration of parse error diagnostics.
+//!
+//! [`ParseError::print_with_config`](super::ParseError::print_with_config)
+//! and [`ParseError::format_with_source`](super::ParseError::format_with_source)
+//! take a [`DiagnosticConfig`] saying where the report goes and whether it
+//! is colored:
+//!
+//! ```
+//! use oslquery_petite::parser::{ColorChoice, DiagnosticConfig, DiagnosticStream};
+//!
+//! // Plain text on stderr, e.g. for a build tool whose output is logged.
+//! let config = DiagnosticConfig::new()
+//!     .color(ColorChoice::Never)
+//!     .stream(DiagnosticStream::Stderr);
+//! ```
+//!
+//! With the default `pretty-errors` feature reports are drawn with
+//! [`ariadne`](https://docs.rs/ariadne). Without it a plain renderer prints
+//! the offending line with the token underlined:
+//!
+//! ```text
+//! Error: Parse error: Unknown type 'bogus'
+//!  --> s.oso:3:7
+//!   |
+//! 3 | param bogus x
+//!   |       ^^^^^ Unknown type 'bogus'
+//! ```
+
+use std::io::{self, IsTerminal, Write};
+use std::ops::Range;
+
+/// Colors of the labels in a report.
+#[cfg(feature = "pretty-errors")]
+pub use ariadne::Color;
+
+/// Colors of the labels in a report, the same as those of `ariadne` with the
+/// `pretty-errors` feature.
+#[cfg(not(feature = "pretty-errors"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Color {
+    /// The terminal's default color
+    Primary,
+    /// One of 256 colors
+    Fixed(u8),
+    /// A 24-bit color
+    Rgb(u8, u8, u8),
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    BrightBlack,
+    BrightRed,
+    BrightGreen,
+    BrightYellow,
+    BrightBlue,
+    BrightMagenta,
+    BrightCyan,
+    BrightWhite,
+}
+
+/// When diagnostics are colored with ANSI escape sequences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorChoice {
+    /// Always color.
+    Always,
+    /// Color if the stream is a terminal.
+    #[default]
+    Auto,
+    /// Never color.
+    Never,
+}
+
+/// Where diagnostics are printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DiagnosticStream {
+    /// Standard output
+    #[default]
+    Stdout,
+    /// Standard error
+    Stderr,
+}
+
+impl DiagnosticStream {
+    fn is_terminal(self) -> bool {
+        match self {
+            DiagnosticStream::Stdout => io::stdout().is_terminal(),
+            DiagnosticStream::Stderr => io::stderr().is_terminal(),
+        }
+    }
+
+    pub(super) fn writer(self) -> Box<dyn Write> {
+        match self {
+            DiagnosticStream::Stdout => Box::new(io::stdout().lock()),
+            DiagnosticStream::Stderr => Box::new(io::stderr().lock()),
+        }
+    }
+}
+
+/// How parse errors are printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiagnosticConfig {
+    color: ColorChoice,
+    pub(super) stream: DiagnosticStream,
+    pub(super) label_color: Option<Color>,
+}
+
+impl Default for DiagnosticConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DiagnosticConfig {
+    /// Create the default configuration: colored if standard output is a
+    /// terminal, with red labels.
+    pub fn new() -> Self {
+        DiagnosticConfig {
+            color: ColorChoice::Auto,
+            stream: DiagnosticStream::Stdout,
+            label_color: Some(Color::Red),
+        }
+    }
+
+    /// Set when to use colors (default: [`ColorChoice::Auto`]).
+    pub fn color(mut self, color: ColorChoice) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Set the stream to print to (default: [`DiagnosticStream::Stdout`]).
+    ///
+    /// [`ColorChoice::Auto`] checks whether this stream is a terminal, also
+    /// when the report is only formatted.
+    pub fn stream(mut self, stream: DiagnosticStream) -> Self {
+        self.stream = stream;
+        self
+    }
+
+    /// Set the color of the labels pointing at the offending token (default:
+    /// red). With `None` the labels are not colored, while the rest of the
+    /// report still is unless colors are off.
+    pub fn label_color(mut self, color: Option<Color>) -> Self {
+        self.label_color = color;
+        self
+    }
+
+    /// Check if reports are colored, resolving [`ColorChoice::Auto`].
+    pub fn use_color(&self) -> bool {
+        match self.color {
+            ColorChoice::Always => true,
+            ColorChoice::Auto => self.stream.is_terminal(),
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+/// What a report says and where in the source it points, the same for both
+/// renderers.
+pub(super) struct Diagnostic {
+    /// The headline
+    pub(super) message: String,
+    /// The byte range of the offending token or line, with its label
+    pub(super) label: Option<(Range<usize>, String)>,
+    /// A hint below the source excerpt
+    pub(super) note: Option<String>,
+}
+
+/// Write `diagnostic` about `source`, read from `filename`, to `out`.
+pub(super) fn render(
+    diagnostic: &Diagnostic,
+    filename: &str,
+    source: &str,
+    config: &DiagnosticConfig,
+    out: impl Write,
+) -> io::Result<()> {
+    #[cfg(feature = "pretty-errors")]
+    return render_pretty(diagnostic, filename, source, config, out);
+    #[cfg(not(feature = "pretty-errors"))]
+    return render_plain(diagnostic, filename, source, config, out);
+}
+
+#[cfg(feature = "pretty-errors")]
+fn render_pretty(
+    diagnostic: &Diagnostic,
+    filename: &str,
+    source: &str,
+    config: &DiagnosticConfig,
+    out: impl Write,
+) -> io::Result<()> {
+    use ariadne::{Config, Label, Report, ReportKind, Source};
+
+    let span = diagnostic
+        .label
+        .as_ref()
+        .map_or(0..0, |(span, _)| span.clone());
+    let mut report = Report::build(ReportKind::Error, (filename, span))
+        .with_config(Config::default().with_color(config.use_color()))
+        .with_message(&diagnostic.message);
+    if let Some((span, message)) = &diagnostic.label {
+        let label = Label::new((filename, span.clone())).with_message(message);
+        report = report.with_label(match config.label_color {
+            Some(color) => label.with_color(color),
+            None => label,
+        });
+    }
+    if let Some(note) = &diagnostic.note {
+        report = report.with_note(note);
+    }
+    report.finish().write((filename, Source::from(source)), out)
+}
+
+/// Print the line of the label with the label underlined, in the style of
+/// `rustc`.
+#[cfg(any(test, not(feature = "pretty-errors")))]
+fn render_plain(
+    diagnostic: &Diagnostic,
+    filename: &str,
+    source: &str,
+    config: &DiagnosticConfig,
+    mut out: impl Write,
+) -> io::Result<()> {
+    let use_color = config.use_color();
+    let paint = |text: &str, color: Option<Color>| match color {
+        Some(color) if use_color => format!("\x1b[{}m{}\x1b[0m", ansi_code(color), text),
+        _ => text.to_string(),
+    };
+
+    writeln!(
+        out,
+        "{}: {}",
+        paint("Error", Some(Color::Red)),
+        diagnostic.message
+    )?;
+
+    let mut gutter = " ".to_string();
+    if let Some((span, label)) = &diagnostic.label {
+        let start = span.start.min(source.len());
+        let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[start..]
+            .find('\n')
+            .map_or(source.len(), |i| start + i);
+        let line = source[line_start..line_end].trim_end_matches('\r');
+        let line_number = source[..start].matches('\n').count() + 1;
+        let column = source[line_start..start].chars().count() + 1;
+
+        // Keep tabs before the token so the underline lines up with it.
+        let indent: String = source[line_start..start]
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let underline_len = source
+            .get(start..span.end.clamp(start, line_start + line.len()))
+            .map_or(0, |token| token.chars().count())
+            .max(1);
+
+        gutter = " ".repeat(line_number.to_string().len());
+        writeln!(out, "{}--> {}:{}:{}", gutter, filename, line_number, column)?;
+        writeln!(out, "{} |", gutter)?;
+        writeln!(out, "{} | {}", line_number, line)?;
+        writeln!(
+            out,
+            "{} | {}{}",
+            gutter,
+            indent,
+            paint(
+                &format!("{} {}", "^".repeat(underline_len), label),
+                config.label_color
+            )
+        )?;
+    }
+
+    if let Some(note) = &diagnostic.note {
+        writeln!(out, "{} = note: {}", gutter, note)?;
+    }
+    Ok(())
+}
+
+/// The SGR parameters that set `color` as the foreground.
+#[cfg(any(test, not(feature = "pretty-errors")))]
+fn ansi_code(color: Color) -> String {
+    let basic = match color {
+        Color::Primary => 39,
+        Color::Fixed(n) => return format!("38;5;{}", n),
+        Color::Rgb(r, g, b) => return format!("38;2;{};{};{}", r, g, b),
+        Color::Black => 30,
+        Color::Red => 31,
+        Color::Green => 32,
+        Color::Yellow => 33,
+        Color::Blue => 34,
+        Color::Magenta => 35,
+        Color::Cyan => 36,
+        Color::White => 37,
+        Color::BrightBlack => 90,
+        Color::BrightRed => 91,
+        Color::BrightGreen => 92,
+        Color::BrightYellow => 93,
+        Color::BrightBlue => 94,
+        Color::BrightMagenta => 95,
+        Color::BrightCyan => 96,
+        Color::BrightWhite => 97,
+    };
+    basic.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plain(diagnostic: &Diagnostic, source: &str, config: &DiagnosticConfig) -> String {
+        let mut out = Vec::new();
+        render_plain(diagnostic, "s.oso", source, config, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_render_plain() {
+        let source = "OpenShadingLanguage 1.12\nsurface s\nparam\tbogus\tx\n";
+        let diagnostic = Diagnostic {
+            message: "Parse error: Unknown type 'bogus'".to_string(),
+            label: Some((41..46, "Unknown type 'bogus'".to_string())),
+            note: None,
+        };
+        let config = DiagnosticConfig::new().color(ColorChoice::Never);
+        assert_eq!(
+            plain(&diagnostic, source, &config),
+            "Error: Parse error: Unknown type 'bogus'\n\
+             \x20--> s.oso:3:7\n\
+             \x20 |\n\
+             3 | param\tbogus\tx\n\
+             \x20 | \x20    \t^^^^^ Unknown type 'bogus'\n"
+        );
+
+        let colored = plain(&diagnostic, source, &config.color(ColorChoice::Always));
+        assert!(colored.starts_with("\x1b[31mError\x1b[0m: "), "{}", colored);
+        assert!(colored.contains("\t\x1b[31m^^^^^ Unknown"), "{}", colored);
+        let uncolored_labels = plain(
+            &diagnostic,
+            source,
+            &config
+                .color(ColorChoice::Always)
+                .label_color(Some(Color::Fixed(208))),
+        );
+        assert!(uncolored_labels.contains("\x1b[38;5;208m^^^^^"));
+    }
+
+    #[test]
+    fn test_render_plain_note() {
+        // An empty span at the end of the source still gets a caret.
+        let source = "OpenShadingLanguage 0.9";
+        let diagnostic = Diagnostic {
+            message: "Unsupported OSO version: 0.9".to_string(),
+            label: Some((23..23, "here".to_string())),
+            note: Some("Upgrade".to_string()),
+        };
+        let config = DiagnosticConfig::new().color(ColorChoice::Never);
+        assert_eq!(
+            plain(&diagnostic, source, &config),
+            "Error: Unsupported OSO version: 0.9\n\
+             \x20--> s.oso:1:24\n\
+             \x20 |\n\
+             1 | OpenShadingLanguage 0.9\n\
+             \x20 | \x20                      ^ here\n\
+             \x20 = note: Upgrade\n"
+        );
+
+        let diagnostic = Diagnostic {
+            label: None,
+            ..diagnostic
+        };
+        assert_eq!(
+            plain(&diagnostic, source, &config),
+            "Error: Unsupported OSO version: 0.9\n  = note: Upgrade\n"
+        );
+    }
+}
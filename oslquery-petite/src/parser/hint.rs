@@ -4,14 +4,14 @@ use nom::IResult;
 use ustr::Ustr;
 
 use super::types::{BaseType, ParsedParameter, TypeDesc};
+use crate::types::Metadata;
 
 /// Parse a metadata hint like: %meta{type name value} or %meta{type,name,value}.
 pub(super) fn parse_metadata_hint(input: &str) -> IResult<&str, ParsedParameter> {
     // Skip the %meta{ prefix if present
     let input = input.strip_prefix("%meta{").unwrap_or(input);
 
-    // Find the closing brace
-    let end = input.find('}').unwrap_or(input.len());
+    let end = closing_brace(input).unwrap_or(input.len());
     let content = &input[..end];
     let rest = if end < input.len() {
         &input[end + 1..]
@@ -26,12 +26,41 @@ pub(super) fn parse_metadata_hint(input: &str) -> IResult<&str, ParsedParameter>
     Ok((rest, meta))
 }
 
+/// Parse a metadata hint into [`Metadata`]; `None` if it is malformed or has
+/// no value.
+pub(crate) fn parse_metadata(input: &str) -> Option<Metadata> {
+    parse_metadata_hint(input)
+        .ok()
+        .and_then(|(_, meta)| Metadata::from_parsed(meta))
+}
+
+/// Find the `}` closing a hint, skipping any in quoted strings.
+fn closing_brace(input: &str) -> Option<usize> {
+    let mut in_quotes = false;
+    let mut escaped = false;
+    for (i, c) in input.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            '}' if !in_quotes => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
 /// Parse metadata content: "type name value" or "type,name,value"
 fn parse_metadata_content(input: &str) -> Result<ParsedParameter, String> {
     // Try comma-separated format first
     if input.contains(',') {
         let parts: Vec<&str> = input.split(',').map(|s| s.trim()).collect();
         if parts.len() >= 3 {
+            // Array elements are split and unquoted one by one.
+            if parts[0].contains('[') {
+                let value = input.splitn(3, ',').nth(2).unwrap_or_default();
+                return parse_metadata_parts(parts[0], parts[1], value.trim());
+            }
             // Strip quotes from the value if present
             let value = parts[2..].join(",");
             let value = value.trim().trim_matches('"');
@@ -106,6 +135,28 @@ fn parse_metadata_parts(
     name: &str,
     value: &str,
 ) -> Result<ParsedParameter, String> {
+    // Arrays, e.g. `int[3]` or `string[]`.
+    if let Some((base, len)) = type_str.split_once('[') {
+        let basetype = base.trim().parse::<BaseType>().unwrap_or(BaseType::String);
+        let arraylen = len.trim_end_matches(']').trim().parse().unwrap_or(-1);
+        let mut param = ParsedParameter::new(name, TypeDesc::new_array(basetype, arraylen));
+        param.valid_default = true;
+
+        let elements = split_array_values(value);
+        match basetype {
+            BaseType::Int => match elements.iter().map(|e| e.parse()).collect() {
+                Ok(values) => param.idefault = values,
+                Err(_) => param.sdefault.push(value.to_string()),
+            },
+            BaseType::String => param.sdefault = elements,
+            _ => match elements.iter().map(|e| e.parse()).collect() {
+                Ok(values) => param.fdefault = values,
+                Err(_) => param.sdefault.push(value.to_string()),
+            },
+        }
+        return Ok(param);
+    }
+
     let basetype = type_str.parse::<BaseType>().unwrap_or(BaseType::String);
     let type_desc = TypeDesc::new(basetype);
 
@@ -114,6 +165,23 @@ fn parse_metadata_parts(
 
     // Parse the value based on type
     match basetype {
+        BaseType::Color
+        | BaseType::Point
+        | BaseType::Vector
+        | BaseType::Normal
+        | BaseType::Matrix => {
+            // Aggregates may use the constructor form, e.g. color(0.1,0.2,0.3)
+            let components = parse_constructor(value).and_then(|components| {
+                components
+                    .iter()
+                    .map(|c| c.parse::<f32>().ok())
+                    .collect::<Option<Vec<_>>>()
+            });
+            match components {
+                Some(components) => param.fdefault.extend(components),
+                None => param.sdefault.push(value.to_string()),
+            }
+        }
         BaseType::Int => {
             if let Ok(val) = value.parse::<i32>() {
                 param.idefault.push(val);
@@ -137,6 +205,44 @@ fn parse_metadata_parts(
     Ok(param)
 }
 
+/// Split array metadata values separated by commas or whitespace, unquoting
+/// strings. Escapes in strings are kept, as for scalar string metadata.
+fn split_array_values(input: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut in_quotes = false;
+    let mut escaped = false;
+
+    for ch in input.chars() {
+        match ch {
+            _ if escaped => {
+                current.push(ch);
+                escaped = false;
+            }
+            '\\' if in_quotes => {
+                current.push(ch);
+                escaped = true;
+            }
+            '"' => {
+                in_quotes = !in_quotes;
+                quoted = true;
+            }
+            ',' | ' ' | '\t' if !in_quotes => {
+                if quoted || !current.is_empty() {
+                    values.push(std::mem::take(&mut current));
+                }
+                quoted = false;
+            }
+            _ => current.push(ch),
+        }
+    }
+    if quoted || !current.is_empty() {
+        values.push(current);
+    }
+    values
+}
+
 /// Parse struct fields hint: structfields{field1,field2,field3}.
 pub(super) fn parse_structfields_hint(input: &str) -> Option<Vec<Ustr>> {
     // Find the content between braces
@@ -182,7 +288,7 @@ pub(super) fn parse_struct_hint(input: &str) -> Option<Ustr> {
 }
 
 /// Parse space name hint: space{"spacename"}.
-pub(super) fn parse_space_hint(input: &str) -> Option<String> {
+pub(super) fn parse_space_hint(input: &str) -> Option<&str> {
     // Find the content between braces
     if let Some(start) = input.find('{') {
         if let Some(end) = input.rfind('}') {
@@ -190,11 +296,7 @@ pub(super) fn parse_space_hint(input: &str) -> Option<String> {
 
             // Remove quotes if present
             let space = content.trim().trim_matches('"');
-            if !space.is_empty() {
-                Some(space.to_string())
-            } else {
-                None
-            }
+            if !space.is_empty() { Some(space) } else { None }
         } else {
             None
         }
@@ -214,19 +316,22 @@ pub(super) fn parse_default_hint(input: &str) -> Option<Vec<String>> {
         return None;
     }
 
-    // Check if it's an array
-    let values = if content.starts_with('[') && content.ends_with(']') {
+    // Check if it's a constructor or an array
+    let values = if let Some(components) = parse_constructor(content) {
+        components.iter().map(|c| c.to_string()).collect()
+    } else if content.starts_with('[') && content.ends_with(']') {
         let array_content = &content[1..content.len() - 1];
 
         // Parse array elements
         array_content
             .split(',')
-            .map(|elem| elem.trim().trim_matches('"').to_string())
-            .filter(|s| !s.is_empty())
+            .map(|elem| elem.trim())
+            .filter(|elem| !elem.is_empty())
+            .map(|elem| unquote(elem).to_string())
             .collect()
     } else {
         // Single value
-        vec![content.trim_matches('"').to_string()]
+        vec![unquote(content).to_string()]
     };
 
     if values.is_empty() {
@@ -236,6 +341,28 @@ pub(super) fn parse_default_hint(input: &str) -> Option<Vec<String>> {
     }
 }
 
+/// Strip one pair of surrounding double quotes, if present.
+///
+/// Unlike `trim_matches('"')` this keeps an empty string literal `""` intact
+/// as an empty value and leaves unbalanced quotes alone.
+fn unquote(input: &str) -> &str {
+    input
+        .strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .unwrap_or(input)
+}
+
+/// Split a type constructor like `color(0.1,0.2,0.3)` into its arguments.
+///
+/// Returns `None` if `input` is not a constructor of a known OSL type.
+fn parse_constructor(input: &str) -> Option<Vec<&str>> {
+    let (typename, rest) = input.trim().split_once('(')?;
+    let arguments = rest.strip_suffix(')')?;
+    typename.trim().parse::<BaseType>().ok()?;
+
+    Some(arguments.split(',').map(|arg| arg.trim()).collect())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -258,6 +385,61 @@ mod tests {
         assert_eq!(meta.idefault[0], 100);
     }
 
+    #[test]
+    fn test_parse_metadata_hint_quoted_braces() {
+        let input = "%meta{string,tip,\"use } carefully\"} %read{0,0}";
+        let (rest, meta) = parse_metadata_hint(input).unwrap();
+        assert_eq!(meta.name.as_str(), "tip");
+        assert_eq!(meta.sdefault[0], "use } carefully");
+        assert_eq!(rest, " %read{0,0}");
+
+        let input = "%meta{string,tip,\"a \\\" } b\"}";
+        let (rest, meta) = parse_metadata_hint(input).unwrap();
+        assert_eq!(meta.sdefault[0], "a \\\" } b");
+        assert_eq!(rest, "");
+
+        // Unbalanced input takes the rest of the line.
+        let (rest, meta) = parse_metadata_hint("%meta{string,tip,\"open }").unwrap();
+        assert_eq!(meta.name.as_str(), "tip");
+        assert_eq!(rest, "");
+    }
+
+    #[test]
+    fn test_parse_array_metadata() {
+        let (_, meta) = parse_metadata_hint("%meta{int[],enumvalues,0 1 2}").unwrap();
+        assert_eq!(meta.name.as_str(), "enumvalues");
+        assert!(meta.type_desc.is_unsized_array());
+        assert_eq!(meta.idefault, vec![0, 1, 2]);
+
+        let (_, meta) = parse_metadata_hint("%meta{int[3],enumvalues,4,5,6}").unwrap();
+        assert_eq!(meta.type_desc.arraylen, 3);
+        assert_eq!(meta.idefault, vec![4, 5, 6]);
+
+        let (_, meta) = parse_metadata_hint("%meta{float[2] range 0.5 1}").unwrap();
+        assert_eq!(meta.fdefault, vec![0.5, 1.0]);
+
+        let (_, meta) = parse_metadata_hint("%meta{string[3],labels,\"a, b\",\"\",\"c\"}").unwrap();
+        assert_eq!(meta.sdefault, vec!["a, b", "", "c"]);
+
+        // Allowed values of an enum parameter.
+        let (_, meta) = parse_metadata_hint("%meta{int[],options,0 1 2 3}").unwrap();
+        assert_eq!(meta.name.as_str(), "options");
+        assert!(meta.type_desc.is_unsized_array());
+        assert_eq!(meta.idefault, vec![0, 1, 2, 3]);
+        assert!(meta.sdefault.is_empty());
+
+        let (_, meta) =
+            parse_metadata_hint("%meta{string[],enum,\"foo\" \"bar\" \"baz\"}").unwrap();
+        assert_eq!(meta.name.as_str(), "enum");
+        assert_eq!(meta.type_desc.basetype, BaseType::String);
+        assert_eq!(meta.sdefault, vec!["foo", "bar", "baz"]);
+
+        // Values that are not numbers are kept as a string.
+        let (_, meta) = parse_metadata_hint("%meta{int[2],ids,1 x}").unwrap();
+        assert!(meta.idefault.is_empty());
+        assert_eq!(meta.sdefault, vec!["1 x"]);
+    }
+
     #[test]
     fn test_parse_structfields() {
         let input = "structfields{x,y,z}";
@@ -288,4 +470,47 @@ mod tests {
         let name = parse_struct_hint(input);
         assert_eq!(name.unwrap().as_str(), "Point3");
     }
+
+    #[test]
+    fn test_parse_constructor() {
+        let values = parse_default_hint("%default{color(0.5,0.5,0.5)}").unwrap();
+        assert_eq!(values, vec!["0.5", "0.5", "0.5"]);
+
+        let values = parse_default_hint("%default{point( 1, 2 ,3 )}").unwrap();
+        assert_eq!(values, vec!["1", "2", "3"]);
+
+        // Not a type constructor
+        assert!(parse_constructor("noise(1,2)").is_none());
+
+        let (_, meta) = parse_metadata_hint("%meta{color,c,color(0.1,0.2,0.3)}").unwrap();
+        assert_eq!(meta.name.as_str(), "c");
+        assert_eq!(meta.fdefault, vec![0.1, 0.2, 0.3]);
+        assert!(meta.sdefault.is_empty());
+    }
+
+    #[test]
+    fn test_parse_default_hint() {
+        // Numeric values are passed through untouched
+        assert_eq!(
+            parse_default_hint("%default{0.0}"),
+            Some(vec!["0.0".to_string()])
+        );
+
+        // An empty string literal is a valid default
+        assert_eq!(
+            parse_default_hint("%default{\"\"}"),
+            Some(vec![String::new()])
+        );
+
+        assert_eq!(
+            parse_default_hint("%default{\"hello\"}"),
+            Some(vec!["hello".to_string()])
+        );
+        assert_eq!(
+            parse_default_hint("%default{[\"a\", \"\"]}"),
+            Some(vec!["a".to_string(), String::new()])
+        );
+
+        assert_eq!(parse_default_hint("%default{}"), None);
+    }
 }
@@ -4,6 +4,10 @@
 //! The parser uses a line-by-line, token-based approach that matches the behavior
 //! of OpenShadingLanguage's C++ parser.
 
+/// Code section location; the bytecode itself is not parsed.
+pub mod bytecode;
+/// Configuration of parse error diagnostics.
+pub mod diagnostic;
 /// Hint parsing utilities for metadata extraction.
 pub mod hint;
 /// Core OSO tokenization and parsing functions.
@@ -13,9 +17,16 @@ pub mod reader;
 /// Intermediate types for parsing.
 pub mod types;
 
-pub use reader::OsoReader;
+pub use bytecode::BytecodeSection;
+pub use diagnostic::{ColorChoice, DiagnosticConfig, DiagnosticStream};
+pub use reader::{
+    MAX_KNOWN_VERSION, OsoReader, OsoReaderConfig, parse_param_line, parse_raw, parse_stream,
+};
+pub use types::ParsedParameter;
 
-use ariadne::{Color, Label, Report, ReportKind, Source};
+use std::io::Write;
+
+use diagnostic::Diagnostic;
 use thiserror::Error;
 
 /// Errors that can occur during OSO file parsing.
@@ -38,7 +49,8 @@ pub enum ParseError {
     ParseError {
         line: usize,
         message: String,
-        /// Optional token that caused the error and its position in the line
+        /// Optional token that caused the error and its byte offset in the
+        /// source
         token_info: Option<(String, usize)>,
     },
 
@@ -47,6 +59,10 @@ pub enum ParseError {
 
     #[error("Conversion error: {0}")]
     Conversion(String),
+
+    #[cfg(feature = "compile-osl")]
+    #[error("OSL compilation failed: {0}")]
+    Compilation(String),
 }
 
 // Manual Hash implementation for ParseError when hash feature is enabled
@@ -72,6 +88,8 @@ impl std::hash::Hash for ParseError {
             }
             ParseError::Incomplete(s) => s.hash(state),
             ParseError::Conversion(s) => s.hash(state),
+            #[cfg(feature = "compile-osl")]
+            ParseError::Compilation(s) => s.hash(state),
         }
     }
 }
@@ -84,14 +102,100 @@ impl From<std::io::Error> for ParseError {
 }
 
 impl ParseError {
-    /// Print the error with ariadne for nice formatting.
-    pub fn print_with_source(&self, filename: &str, source: &str) -> std::io::Result<()> {
+    /// The 1-based line and column of the error in `source`, if known.
+    ///
+    /// The column is that of the offending token, in characters, or `1` if
+    /// the token is unknown.
+    pub fn line_column(&self, source: &str) -> Option<(usize, usize)> {
+        match self {
+            ParseError::ParseError { line, .. } => {
+                source.lines().nth(line.checked_sub(1)?)?;
+                let column = self.token_span(source).map_or(1, |span| {
+                    let line_start = source[..span.start].rfind('\n').map_or(0, |i| i + 1);
+                    source[line_start..span.start].chars().count() + 1
+                });
+                Some((*line, column))
+            }
+            ParseError::UnsupportedVersion { .. } => {
+                let start = version_span(source)?.start;
+                let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+                Some((
+                    source[..start].matches('\n').count() + 1,
+                    source[line_start..start].chars().count() + 1,
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    /// The byte range of the offending token in `source`.
+    ///
+    /// `None` if the error has no token or `source` is not the text that was
+    /// parsed.
+    pub fn token_span(&self, source: &str) -> Option<std::ops::Range<usize>> {
         match self {
             ParseError::ParseError {
-                line,
-                message,
-                token_info,
+                token_info: Some((token, offset)),
+                ..
             } => {
+                let span = *offset..offset + token.len();
+                (source.get(span.clone()) == Some(token.as_str())).then_some(span)
+            }
+            _ => None,
+        }
+    }
+
+    /// Print the error with the offending token of `source` marked, with the
+    /// default [`DiagnosticConfig`].
+    ///
+    /// See [`diagnostic`] for how the report looks.
+    pub fn print_with_source(&self, filename: &str, source: &str) -> std::io::Result<()> {
+        self.print_with_config(filename, source, &DiagnosticConfig::new())
+    }
+
+    /// Print the error to the stream and with the colors of `config`.
+    pub fn print_with_config(
+        &self,
+        filename: &str,
+        source: &str,
+        config: &DiagnosticConfig,
+    ) -> std::io::Result<()> {
+        self.write_with_source(filename, source, config, config.stream.writer())
+    }
+
+    /// Format the error as [`print_with_config`](Self::print_with_config)
+    /// would print it.
+    pub fn format_with_source(
+        &self,
+        filename: &str,
+        source: &str,
+        config: &DiagnosticConfig,
+    ) -> String {
+        let mut out = Vec::new();
+        // Writing to a `Vec` cannot fail.
+        let _ = self.write_with_source(filename, source, config, &mut out);
+        String::from_utf8_lossy(&out).into_owned()
+    }
+
+    /// Write the error to `out`, with the colors of `config`.
+    pub fn write_with_source(
+        &self,
+        filename: &str,
+        source: &str,
+        config: &DiagnosticConfig,
+        mut out: impl Write,
+    ) -> std::io::Result<()> {
+        match self.diagnostic(source) {
+            Some(diagnostic) => diagnostic::render(&diagnostic, filename, source, config, out),
+            // Other errors have no source location.
+            None => writeln!(out, "Error: {}", self),
+        }
+    }
+
+    /// What to report about the error in `source`, if it has a location.
+    fn diagnostic(&self, source: &str) -> Option<Diagnostic> {
+        match self {
+            ParseError::ParseError { line, message, .. } => {
                 // Calculate byte offset from line number
                 let mut line_start_offset = 0;
                 let mut current_line = 1;
@@ -108,46 +212,175 @@ impl ParseError {
                 // Get the line content
                 let line_content = source[line_start_offset..].lines().next().unwrap_or("");
 
-                // Calculate the span for the error
-                let (start_offset, end_offset) = if let Some((token, _token_pos)) = token_info {
-                    // Find the token in the line and highlight just that token
-                    if let Some(token_idx) = line_content.find(token.as_str()) {
-                        let token_start = line_start_offset + token_idx;
-                        let token_end = token_start + token.len();
-                        (token_start, token_end)
-                    } else {
-                        // Fallback to whole line if token not found
-                        let line_end = line_start_offset + line_content.len();
-                        (line_start_offset, line_end)
-                    }
-                } else {
-                    // No token info, highlight whole line
-                    let line_end = line_start_offset + line_content.len();
-                    (line_start_offset, line_end)
-                };
-
-                Report::build(ReportKind::Error, (filename, start_offset..end_offset))
-                    .with_message(format!("Parse error: {}", message))
-                    .with_label(
-                        Label::new((filename, start_offset..end_offset))
-                            .with_message(message)
-                            .with_color(Color::Red),
-                    )
-                    .finish()
-                    .print((filename, Source::from(source)))
+                // Highlight just the token, or the whole line if unknown
+                let span = self
+                    .token_span(source)
+                    .unwrap_or(line_start_offset..line_start_offset + line_content.len());
+
+                Some(Diagnostic {
+                    message: format!("Parse error: {}", message),
+                    label: Some((span, message.clone())),
+                    note: None,
+                })
             }
             ParseError::UnsupportedVersion { major, minor } => {
-                Report::build(ReportKind::Error, (filename, 0..0))
-                    .with_message(format!("Unsupported OSO version: {}.{}", major, minor))
-                    .with_note("This parser supports OSO version 1.11 and above")
-                    .finish()
-                    .print((filename, Source::from(source)))
+                let message = format!("Unsupported OSO version: {}.{}", major, minor);
+                Some(Diagnostic {
+                    label: version_span(source).map(|span| (span, message.clone())),
+                    message,
+                    note: Some(format!(
+                        "This parser supports OSO versions 1.00 to {}.{:02}",
+                        MAX_KNOWN_VERSION.0, MAX_KNOWN_VERSION.1
+                    )),
+                })
             }
-            _ => {
-                // For other errors, print without source location
-                eprintln!("Error: {}", self);
-                Ok(())
+            _ => None,
+        }
+    }
+}
+
+/// Find the byte range of the version number in the `OpenShadingLanguage x.y`
+/// header of `source`.
+fn version_span(source: &str) -> Option<std::ops::Range<usize>> {
+    let mut line_start = 0;
+    for line in source.split_inclusive('\n') {
+        if let Some(rest) = line.trim_start().strip_prefix("OpenShadingLanguage") {
+            let version = rest.trim_start();
+            let version_len = version.find(char::is_whitespace).unwrap_or(version.len());
+            if version_len == 0 {
+                return None;
             }
+            let start = line_start + (line.len() - version.len());
+            return Some(start..start + version_len);
         }
+        line_start += line.len();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_span() {
+        let source = "OpenShadingLanguage 0.9\nshader test\n";
+        let span = version_span(source).unwrap();
+        assert_eq!(&source[span], "0.9");
+
+        // Header after leading comments and blank lines
+        let source = "# Compiled by oslc\n\nOpenShadingLanguage\t0.5 \nshader test\n";
+        let span = version_span(source).unwrap();
+        assert_eq!(&source[span], "0.5");
+
+        assert!(version_span("shader test\n").is_none());
+    }
+
+    #[test]
+    fn test_line_column() {
+        let source = "OpenShadingLanguage 1.12\nsurface s\nparam\tbogus\tx\n";
+        let error = OsoReader::new().parse_string(source).unwrap_err();
+        assert_eq!(error.line_column(source), Some((3, 7)));
+
+        let source = "# Compiled by oslc\nOpenShadingLanguage 0.9\nsurface s\n";
+        let error = OsoReader::new().parse_string(source).unwrap_err();
+        assert_eq!(error.line_column(source), Some((2, 21)));
+
+        assert_eq!(ParseError::Io("gone".to_string()).line_column(source), None);
+    }
+
+    #[test]
+    fn test_token_span() {
+        // The bad type `par` also occurs in `param`; the second one is meant.
+        let source = "OpenShadingLanguage 1.12\nsurface s\nparam\tpar\tx\n";
+        let error = OsoReader::new().parse_string(source).unwrap_err();
+        let span = error.token_span(source).unwrap();
+        assert_eq!(span, 41..44);
+        assert_eq!(&source[span], "par");
+        assert_eq!(error.line_column(source), Some((3, 7)));
+
+        // Closure default `0`, after the `0` in the parameter name.
+        let source = "OpenShadingLanguage 1.12\nsurface s\nparam\tclosure color\tbsdf0\t0\n";
+        let error = OsoReader::with_config(OsoReaderConfig::new().strict_defaults(true))
+            .parse_string(source)
+            .unwrap_err();
+        assert_eq!(error.token_span(source), Some(61..62));
+        assert_eq!(error.line_column(source), Some((3, 27)));
+
+        // Not the parsed source.
+        assert_eq!(error.token_span("something else"), None);
+        assert_eq!(ParseError::Io("gone".to_string()).token_span(source), None);
+    }
+
+    #[test]
+    fn test_diagnostic_colors() {
+        let source = "OpenShadingLanguage 1.12\nsurface s\nparam\tbogus\tx\n";
+        let error = OsoReader::new().parse_string(source).unwrap_err();
+
+        let plain = error.format_with_source(
+            "s.oso",
+            source,
+            &DiagnosticConfig::new().color(ColorChoice::Never),
+        );
+        assert!(!plain.contains('\x1b'), "{}", plain);
+        assert!(plain.contains("s.oso:3:7"), "{}", plain);
+        assert!(plain.contains("bogus"), "{}", plain);
+
+        let colored = error.format_with_source(
+            "s.oso",
+            source,
+            &DiagnosticConfig::new().color(ColorChoice::Always),
+        );
+        assert!(colored.contains('\x1b'));
+
+        let uncolored_labels = error.format_with_source(
+            "s.oso",
+            source,
+            &DiagnosticConfig::new()
+                .color(ColorChoice::Always)
+                .label_color(None),
+        );
+        // Still colored, but with fewer red parts.
+        let red = |s: &str| s.matches("\x1b[31m").count();
+        assert!(uncolored_labels.contains('\x1b'));
+        assert!(red(&uncolored_labels) < red(&colored));
+
+        // Without source location.
+        let error = ParseError::Io("gone".to_string());
+        let config = DiagnosticConfig::new().color(ColorChoice::Never);
+        assert_eq!(
+            error.format_with_source("s.oso", source, &config),
+            "Error: IO error: gone\n"
+        );
+    }
+
+    #[cfg(feature = "pretty-errors")]
+    #[test]
+    fn test_pretty_errors() {
+        let source = "OpenShadingLanguage 1.12\nsurface s\nparam\tbogus\tx\n";
+        let error = OsoReader::new().parse_string(source).unwrap_err();
+        let config = DiagnosticConfig::new().color(ColorChoice::Never);
+        let report = error.format_with_source("s.oso", source, &config);
+        assert!(report.contains("╭─[ s.oso:3:7 ]"), "{}", report);
+    }
+
+    #[cfg(not(feature = "pretty-errors"))]
+    #[test]
+    fn test_plain_errors() {
+        let source = "# Compiled by oslc\nOpenShadingLanguage 0.9\nsurface s\n";
+        let error = OsoReader::new().parse_string(source).unwrap_err();
+        let config = DiagnosticConfig::new().color(ColorChoice::Never);
+        assert_eq!(
+            error.format_with_source("s.oso", source, &config),
+            format!(
+                "Error: Unsupported OSO version: 0.9\n\
+                 \x20--> s.oso:2:21\n\
+                 \x20 |\n\
+                 2 | OpenShadingLanguage 0.9\n\
+                 \x20 |                     ^^^ Unsupported OSO version: 0.9\n\
+                 \x20 = note: This parser supports OSO versions 1.00 to {}.{:02}\n",
+                MAX_KNOWN_VERSION.0, MAX_KNOWN_VERSION.1
+            )
+        );
     }
 }
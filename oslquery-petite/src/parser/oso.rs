@@ -3,19 +3,20 @@
 use nom::{
     IResult, Parser,
     branch::alt,
-    bytes::complete::{tag, take_while, take_while1},
-    character::complete::{char, digit1, space1},
+    bytes::complete::{tag, tag_no_case, take_while, take_while1},
+    character::complete::{alpha1, char, digit1, space1},
     combinator::{map, map_res, opt, recognize, value},
-    sequence::{delimited, pair, preceded, separated_pair, terminated},
+    sequence::{delimited, pair, separated_pair, terminated},
 };
 
 use super::types::{BaseType, SymType, TypeDesc, TypeSpec};
 
-/// Parse a C-style identifier (including $ and . as allowed in OSO).
+/// Parse a C-style identifier (including $, . and the @ of global symbols
+/// as allowed in OSO).
 pub(crate) fn parse_identifier(input: &str) -> IResult<&str, &str> {
     recognize(pair(
-        take_while1(|c: char| c.is_ascii_alphabetic() || c == '_' || c == '$'),
-        take_while(|c: char| c.is_ascii_alphanumeric() || c == '_' || c == '$' || c == '.'),
+        take_while1(|c: char| c.is_ascii_alphabetic() || matches!(c, '_' | '$' | '@')),
+        take_while(|c: char| c.is_ascii_alphanumeric() || matches!(c, '_' | '$' | '@' | '.')),
     ))
     .parse(input)
 }
@@ -30,26 +31,47 @@ pub(crate) fn parse_int(input: &str) -> IResult<&str, i32> {
 }
 
 /// Parse a quoted string.
+///
+/// A backslash escapes the next character, so `\"` does not end the string
+/// but `\\"` does. `\n`, `\t` and `\r` become newline, tab and carriage
+/// return; other escapes are kept as written.
 pub(crate) fn parse_string(input: &str) -> IResult<&str, String> {
-    delimited(
-        char('"'),
-        map(take_while(|c: char| c != '"'), |s: &str| {
-            // Handle escape sequences
-            s.replace("\\n", "\n")
-                .replace("\\t", "\t")
-                .replace("\\r", "\r")
-                .replace("\\\"", "\"")
-                .replace("\\\\", "\\")
-        }),
-        char('"'),
-    )
-    .parse(input)
+    let (rest, _) = char('"').parse(input)?;
+    let mut value = String::new();
+    let mut chars = rest.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok((&rest[i + 1..], value)),
+            '\\' => match chars.next() {
+                Some((_, 'n')) => value.push('\n'),
+                Some((_, 't')) => value.push('\t'),
+                Some((_, 'r')) => value.push('\r'),
+                Some((_, c @ ('"' | '\\'))) => value.push(c),
+                Some((_, c)) => {
+                    value.push('\\');
+                    value.push(c);
+                }
+                None => break,
+            },
+            c => value.push(c),
+        }
+    }
+    // No closing quote.
+    Err(nom::Err::Error(nom::error::Error::new(
+        &rest[rest.len()..],
+        nom::error::ErrorKind::Char,
+    )))
 }
 
-/// Parse OSL version directive.
-pub(super) fn parse_version(input: &str) -> IResult<&str, (i32, i32)> {
-    preceded(
-        (tag("OpenShadingLanguage"), space1),
+/// Keyword of the version directive of OSO files.
+pub(crate) const OSL_FORMAT_IDENTIFIER: &str = "OpenShadingLanguage";
+
+/// Parse the version directive, e.g. `OpenShadingLanguage 1.12`, into the
+/// format keyword and the version.
+pub(super) fn parse_version(input: &str) -> IResult<&str, (&str, (i32, i32))> {
+    separated_pair(
+        alpha1,
+        space1,
         separated_pair(
             parse_int,
             char('.'),
@@ -59,8 +81,17 @@ pub(super) fn parse_version(input: &str) -> IResult<&str, (i32, i32)> {
     .parse(input)
 }
 
+/// Keywords that start a shader declaration.
+const SHADER_TYPES: [&str; 4] = ["shader", "surface", "displacement", "volume"];
+
+/// Check if a line is a shader declaration, e.g. `surface "name"`.
+pub(crate) fn is_shader_declaration(line: &str) -> bool {
+    line.split_once(' ')
+        .is_some_and(|(keyword, _)| SHADER_TYPES.contains(&keyword))
+}
+
 /// Parse shader type and name - handles both quoted and unquoted names.
-pub(super) fn parse_shader(input: &str) -> IResult<&str, (&str, String)> {
+pub(crate) fn parse_shader(input: &str) -> IResult<&str, (&str, String)> {
     let (input, shader_type) = terminated(parse_identifier, space1).parse(input)?;
 
     // Try to parse either a quoted string or an unquoted identifier
@@ -82,54 +113,107 @@ pub(super) fn parse_symtype(input: &str) -> IResult<&str, SymType> {
     .parse(input)
 }
 
+/// Match the keyword `word`, in any case if `ignore_case` is set.
+fn keyword<'a>(
+    word: &'static str,
+    ignore_case: bool,
+) -> impl Fn(&'a str) -> IResult<&'a str, &'a str> {
+    move |input| {
+        if ignore_case {
+            tag_no_case(word).parse(input)
+        } else {
+            tag(word).parse(input)
+        }
+    }
+}
+
 /// Parse type name (int, float, string, color, etc.).
-pub(crate) fn parse_typename(input: &str) -> IResult<&str, BaseType> {
+fn parse_typename(input: &str, ignore_case: bool) -> IResult<&str, BaseType> {
+    let keyword = |word| keyword(word, ignore_case);
     alt((
-        value(BaseType::Int, tag("int")),
-        value(BaseType::Float, tag("float")),
-        value(BaseType::String, tag("string")),
-        value(BaseType::Color, tag("color")),
-        value(BaseType::Point, tag("point")),
-        value(BaseType::Vector, tag("vector")),
-        value(BaseType::Normal, tag("normal")),
-        value(BaseType::Matrix, tag("matrix")),
+        value(BaseType::Int, keyword("int")),
+        value(BaseType::Float, keyword("float")),
+        value(BaseType::String, keyword("string")),
+        value(BaseType::Color, keyword("color")),
+        value(BaseType::Point, keyword("point")),
+        value(BaseType::Vector, keyword("vector")),
+        value(BaseType::Normal, keyword("normal")),
+        value(BaseType::Matrix, keyword("matrix")),
+    ))
+    .parse(input)
+}
+
+/// Parse an array specification, `[]` or e.g. `[3]`; -1 for `[]`.
+fn parse_array_spec(input: &str) -> IResult<&str, i32> {
+    alt((
+        value(-1, tag("[]")),
+        delimited(char('['), parse_int, char(']')),
     ))
     .parse(input)
 }
 
 /// Parse closure type.
-pub(crate) fn parse_closure(input: &str) -> IResult<&str, TypeDesc> {
-    preceded((tag("closure"), space1, tag("color")), |input| {
-        let mut type_desc = TypeDesc::new(BaseType::Color);
-        type_desc.is_closure = true;
-
-        // Check for array specification
-        let (input, array_spec) = opt(alt((
-            value(-1, tag("[]")),
-            delimited(char('['), parse_int, char(']')),
-        )))
+fn parse_closure(input: &str, ignore_case: bool) -> IResult<&str, TypeDesc> {
+    let (input, _) = (
+        keyword("closure", ignore_case),
+        space1,
+        keyword("color", ignore_case),
+    )
         .parse(input)?;
+    let mut type_desc = TypeDesc::new(BaseType::Color);
+    type_desc.is_closure = true;
 
-        if let Some(arraylen) = array_spec {
-            type_desc.arraylen = arraylen;
-        }
+    // Check for array specification
+    let (input, array_spec) = opt(parse_array_spec).parse(input)?;
+    if let Some(arraylen) = array_spec {
+        type_desc.arraylen = arraylen;
+    }
 
-        Ok((input, type_desc))
-    })
-    .parse(input)
+    Ok((input, type_desc))
+}
+
+/// Parse struct type, e.g. `struct MyParams` or `struct MyParams[2]`.
+///
+/// Only whether it is a struct is kept; its name comes from the `%struct`
+/// hint.
+fn parse_struct(input: &str, ignore_case: bool) -> IResult<&str, TypeSpec> {
+    let (input, _) = (keyword("struct", ignore_case), space1, parse_identifier).parse(input)?;
+    let mut type_desc = TypeDesc::new(BaseType::None);
+
+    // Check for array specification
+    let (input, array_spec) = opt(parse_array_spec).parse(input)?;
+    if let Some(arraylen) = array_spec {
+        type_desc.arraylen = arraylen;
+    }
+
+    Ok((
+        input,
+        TypeSpec {
+            simpletype: type_desc,
+            structure: 1,
+        },
+    ))
 }
 
 /// Parse type specification.
-pub(super) fn parse_typespec(input: &str) -> IResult<&str, TypeSpec> {
+pub(crate) fn parse_typespec(input: &str) -> IResult<&str, TypeSpec> {
+    parse_typespec_with(input, false)
+}
+
+/// Like [`parse_typespec`], also accepting the type keywords in any case,
+/// e.g. `Color` or `CLOSURE Color`. Struct names keep their case.
+pub(crate) fn parse_typespec_ignore_case(input: &str) -> IResult<&str, TypeSpec> {
+    parse_typespec_with(input, true)
+}
+
+fn parse_typespec_with(input: &str, ignore_case: bool) -> IResult<&str, TypeSpec> {
     alt((
-        map(parse_closure, TypeSpec::new),
+        map(|input| parse_closure(input, ignore_case), TypeSpec::new),
+        |input| parse_struct(input, ignore_case),
         map(
             pair(
-                parse_typename,
-                opt(alt((
-                    value(-1, tag("[]")),
-                    delimited(char('['), parse_int, char(']')),
-                ))),
+                |input| parse_typename(input, ignore_case),
+                opt(parse_array_spec),
             ),
             |(basetype, array_spec)| {
                 let mut type_desc = TypeDesc::new(basetype);
@@ -143,8 +227,105 @@ pub(super) fn parse_typespec(input: &str) -> IResult<&str, TypeSpec> {
     .parse(input)
 }
 
+/// The byte offset of `inner` in `outer`, which it must be a slice of.
+pub(crate) fn offset_in(outer: &str, inner: &str) -> usize {
+    let offset = (inner.as_ptr() as usize).wrapping_sub(outer.as_ptr() as usize);
+    debug_assert!(
+        offset + inner.len() <= outer.len(),
+        "not a slice of `outer`"
+    );
+    offset
+}
+
+/// Split a line into whitespace-separated tokens, preserving quoted strings
+/// and `%hint{...}` blocks.
+///
+/// Like [`tokenize_line`], but lazy and without allocating.
+pub fn tokens(line: &str) -> Tokens<'_> {
+    Tokens {
+        line,
+        chars: line.char_indices(),
+    }
+}
+
+/// Iterator over the tokens of a line; see [`tokens`].
+#[derive(Debug, Clone)]
+pub struct Tokens<'a> {
+    line: &'a str,
+    chars: std::str::CharIndices<'a>,
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let line = self.line;
+        let mut start = None;
+
+        while let Some((i, ch)) = self.chars.next() {
+            match ch {
+                '"' => {
+                    // Quoted string - the token ends at the closing quote
+                    let start = *start.get_or_insert(i);
+                    for (j, c) in self.chars.by_ref() {
+                        if c == '"' && !line[..j].ends_with('\\') {
+                            return Some(&line[start..=j]);
+                        }
+                    }
+                }
+                '%' => {
+                    // Hint - ends at its balanced closing brace, or at
+                    // whitespace if it has no braces
+                    let start = *start.get_or_insert(i);
+                    let mut brace_count = 0;
+                    // Braces in quoted strings inside the hint don't count
+                    let mut in_quotes = false;
+                    let mut escaped = false;
+
+                    for (j, c) in self.chars.by_ref() {
+                        if in_quotes {
+                            match c {
+                                _ if escaped => escaped = false,
+                                '\\' => escaped = true,
+                                '"' => in_quotes = false,
+                                _ => {}
+                            }
+                        } else if c == '"' && brace_count > 0 {
+                            in_quotes = true;
+                        } else if c == '{' {
+                            brace_count += 1;
+                        } else if c == '}' {
+                            brace_count -= 1;
+                            if brace_count == 0 {
+                                return Some(&line[start..=j]);
+                            }
+                        } else if brace_count == 0 && matches!(c, ' ' | '\t' | '\r' | '\n') {
+                            return Some(&line[start..j]);
+                        }
+                    }
+                }
+                ' ' | '\t' | '\r' | '\n' => {
+                    if let Some(start) = start {
+                        return Some(&line[start..i]);
+                    }
+                }
+                _ => {
+                    start.get_or_insert(i);
+                }
+            }
+        }
+
+        // The last token runs to the end of the line.
+        start.map(|start| &line[start..])
+    }
+}
+
+impl std::iter::FusedIterator for Tokens<'_> {}
+
 /// Tokenize a line into whitespace-separated tokens, preserving quoted strings and %hint{...} blocks.
-pub(super) fn tokenize_line(line: &str) -> Vec<&str> {
+///
+/// See [`tokens`] for a version that doesn't allocate.
+pub fn tokenize_line(line: &str) -> Vec<&str> {
     let mut tokens = Vec::new();
     let mut chars = line.char_indices().peekable();
     let mut current_start = 0;
@@ -180,10 +361,22 @@ pub(super) fn tokenize_line(line: &str) -> Vec<&str> {
                 // Check if followed by identifier and brace
                 let mut brace_count = 0;
                 let mut hint_end = i;
+                // Braces in quoted strings inside the hint don't count
+                let mut in_quotes = false;
+                let mut escaped = false;
 
                 for (j, c) in chars.by_ref() {
                     hint_end = j;
-                    if c == '{' {
+                    if in_quotes {
+                        match c {
+                            _ if escaped => escaped = false,
+                            '\\' => escaped = true,
+                            '"' => in_quotes = false,
+                            _ => {}
+                        }
+                    } else if c == '"' && brace_count > 0 {
+                        in_quotes = true;
+                    } else if c == '{' {
                         brace_count += 1;
                     } else if c == '}' {
                         brace_count -= 1;
@@ -240,7 +433,7 @@ pub(super) fn tokenize_line(line: &str) -> Vec<&str> {
 
 /// Default value parsed from a token.
 #[derive(Debug, Clone, PartialEq)]
-pub(super) enum DefaultValue {
+pub(crate) enum DefaultValue {
     Int(i32),
     Float(f32),
     String(String),
@@ -260,7 +453,7 @@ impl std::hash::Hash for DefaultValue {
 }
 
 /// Parse a default value token.
-pub(super) fn parse_default_token(token: &str) -> Option<DefaultValue> {
+pub(crate) fn parse_default_token(token: &str) -> Option<DefaultValue> {
     // Try to parse as string (quoted)
     if token.starts_with('"') && token.ends_with('"') {
         let content = &token[1..token.len() - 1];
@@ -300,6 +493,8 @@ mod tests {
         assert_eq!(parse_identifier("Point2"), Ok(("", "Point2")));
         assert_eq!(parse_identifier("$special"), Ok(("", "$special")));
         assert_eq!(parse_identifier("some.thing"), Ok(("", "some.thing")));
+        assert_eq!(parse_identifier("@time\t0"), Ok(("\t0", "@time")));
+        assert_eq!(parse_identifier("@P"), Ok(("", "@P")));
     }
 
     #[test]
@@ -312,12 +507,52 @@ mod tests {
             parse_string("\"test\\nline\""),
             Ok(("", "test\nline".to_string()))
         );
+        assert_eq!(
+            parse_string(r#""quote\"in middle" rest"#),
+            Ok((" rest", "quote\"in middle".to_string()))
+        );
+        assert_eq!(
+            parse_string(r#""unknown\q""#),
+            Ok(("", "unknown\\q".to_string()))
+        );
+        assert!(parse_string(r#""unterminated\""#).is_err());
+        assert!(parse_string("unquoted").is_err());
+    }
+
+    #[test]
+    fn test_parse_string_escaped_backslashes() {
+        // An escaped backslash right before the closing quote.
+        assert_eq!(
+            parse_string(r#""trailing\\backslash\\""#),
+            Ok(("", r"trailing\backslash\".to_string()))
+        );
+        // Not a `\n` escape.
+        assert_eq!(
+            parse_string(r#""double\\\\backslash\\n""#),
+            Ok(("", r"double\\backslash\n".to_string()))
+        );
+        // The quote after an escaped backslash ends the string.
+        assert_eq!(
+            parse_string(r#""quote\\"in middle\\""#),
+            Ok((r#"in middle\\""#, r"quote\".to_string()))
+        );
     }
 
     #[test]
     fn test_parse_version() {
-        assert_eq!(parse_version("OpenShadingLanguage 1.12"), Ok(("", (1, 12))));
-        assert_eq!(parse_version("OpenShadingLanguage 1.00"), Ok(("", (1, 0))));
+        assert_eq!(
+            parse_version("OpenShadingLanguage 1.12"),
+            Ok(("", ("OpenShadingLanguage", (1, 12))))
+        );
+        assert_eq!(
+            parse_version("OpenShadingLanguage 1.00"),
+            Ok(("", ("OpenShadingLanguage", (1, 0))))
+        );
+        assert_eq!(
+            parse_version("MaterialX 0.3"),
+            Ok(("", ("MaterialX", (0, 3))))
+        );
+        assert!(parse_version("param\tfloat\tKd\t0.5").is_err());
     }
 
     #[test]
@@ -333,6 +568,59 @@ mod tests {
         assert_eq!(name, "_3DelightMaterial");
     }
 
+    #[test]
+    fn test_tokenize_quoted_braces() {
+        let tokens = tokenize_line(
+            "param\tstring\ttip\t\"\"\t%meta{string,tip,\"use } carefully\"} %read{0,0}",
+        );
+        assert_eq!(
+            tokens,
+            [
+                "param",
+                "string",
+                "tip",
+                "\"\"",
+                "%meta{string,tip,\"use } carefully\"}",
+                "%read{0,0}"
+            ]
+        );
+
+        // Unbalanced braces take the rest of the line.
+        let tokens = tokenize_line("param\tint\tn\t1\t%meta{int,a,1 %meta{int,b,2}");
+        assert_eq!(
+            tokens,
+            ["param", "int", "n", "1", "%meta{int,a,1 %meta{int,b,2}"]
+        );
+    }
+
+    #[test]
+    fn test_tokens_match_tokenize_line() {
+        for line in [
+            "",
+            "   \t ",
+            "param float Kd 0.5",
+            "param\tcolor\tcoating_color\t1\t1\t1  \t",
+            r#"param string name "hello world" %meta{...}"#,
+            "param\tstring\ttip\t\"\"\t%meta{string,tip,\"use } carefully\"} %read{0,0}",
+            "param\tint\tn\t1\t%meta{int,a,1 %meta{int,b,2}",
+            r#"param string s "escaped \" quote" "unterminated"#,
+            r#"glued"quoted part"after %hint %"#,
+            "%meta{string,label,\"a b\"}%read{1,2}\tx",
+            "param\tfloat\tKd\t0.5\t%meta{float,min,0} }",
+            "oparam\tclosure color\tbsdf\t\t%read{2,3} %write{0,1}\r",
+        ] {
+            assert_eq!(
+                tokens(line).collect::<Vec<_>>(),
+                tokenize_line(line),
+                "{:?}",
+                line
+            );
+        }
+
+        // Lines may end in a non-ASCII character.
+        assert_eq!(tokens("param string ünïcödé").last(), Some("ünïcödé"));
+    }
+
     #[test]
     fn test_tokenize_line() {
         // Test simple space-separated tokens
@@ -383,6 +671,31 @@ mod tests {
         let (_, ts) = parse_typespec("string[]").unwrap();
         assert_eq!(ts.simpletype.basetype, BaseType::String);
         assert_eq!(ts.simpletype.arraylen, -1);
+
+        let (_, ts) = parse_typespec("struct MyParams").unwrap();
+        assert!(ts.is_structure());
+        assert_eq!(ts.simpletype.arraylen, 0);
+    }
+
+    #[test]
+    fn test_parse_typespec_ignore_case() {
+        assert!(parse_typespec("Color").is_err());
+        assert!(parse_typespec("Float[3]").is_err());
+
+        let (_, ts) = parse_typespec_ignore_case("Color").unwrap();
+        assert_eq!(ts.simpletype.basetype, BaseType::Color);
+        let (_, ts) = parse_typespec_ignore_case("FLOAT[3]").unwrap();
+        assert_eq!(ts.simpletype.basetype, BaseType::Float);
+        assert_eq!(ts.simpletype.arraylen, 3);
+        let (_, ts) = parse_typespec_ignore_case("Closure Color").unwrap();
+        assert!(ts.simpletype.is_closure);
+        let (rest, ts) = parse_typespec_ignore_case("Struct MyParams").unwrap();
+        assert!(ts.is_structure());
+        assert_eq!(rest, "");
+        assert_eq!(
+            parse_typespec_ignore_case("color[]").unwrap(),
+            parse_typespec("color[]").unwrap()
+        );
     }
 
     #[test]
@@ -1,11 +1,158 @@
 //! OSO file reader that orchestrates the parsing
 
-use std::fs;
+use std::io::BufRead;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
 use std::path::Path;
 
-use super::types::{BaseType, ParsedParameter, SymType, TypeSpec};
+use super::bytecode::BytecodeSection;
+use super::types::{ParsedParameter, SymType, TypeSpec};
 use super::{ParseError, hint, oso};
 use crate::query::OslQuery;
+use crate::trace;
+use crate::types::{Parameter, StructDecl};
+use ustr::Ustr;
+
+/// Newest OSO format version this parser knows about.
+///
+/// Files from `1.00` up to this version are supported. Newer files are parsed
+/// with a warning, or rejected with [`OsoReaderConfig::strict_version`].
+pub const MAX_KNOWN_VERSION: (i32, i32) = (1, 14);
+
+/// Configuration for [`OsoReader`].
+///
+/// Built with chained setters:
+///
+/// ```
+/// use oslquery_petite::parser::{OsoReader, OsoReaderConfig};
+///
+/// let reader = OsoReader::with_config(OsoReaderConfig::new().parse_metadata(false));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OsoReaderConfig {
+    /// Whether `%meta{...}` hints are collected
+    parse_metadata: bool,
+    /// Whether parameter `%meta{...}` hints are parsed on first access
+    lazy_metadata: bool,
+    /// Whether string defaults and metadata are interned
+    intern_strings: bool,
+    /// Whether defaults may continue on the following lines
+    multiline_defaults: bool,
+    /// Newest OSO version parsed without a warning
+    max_version: (i32, i32),
+    /// Whether versions newer than `max_version` are errors
+    strict_version: bool,
+    /// Whether default values of closure parameters are errors
+    strict_defaults: bool,
+    /// Whether type keywords match in any case
+    ignore_type_case: bool,
+}
+
+impl Default for OsoReaderConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OsoReaderConfig {
+    /// Create the default configuration.
+    pub fn new() -> Self {
+        OsoReaderConfig {
+            parse_metadata: true,
+            lazy_metadata: false,
+            intern_strings: true,
+            multiline_defaults: false,
+            max_version: MAX_KNOWN_VERSION,
+            strict_version: false,
+            strict_defaults: false,
+            ignore_type_case: false,
+        }
+    }
+
+    /// Set whether shader and parameter metadata is collected (default: `true`).
+    ///
+    /// Disabling this keeps parsed queries small when only types and defaults
+    /// are needed, e.g. when indexing large shader libraries.
+    pub fn parse_metadata(mut self, enabled: bool) -> Self {
+        self.parse_metadata = enabled;
+        self
+    }
+
+    /// Set whether parameter metadata is kept as the raw `%meta{...}` hints
+    /// and only parsed on first access (default: `false`).
+    ///
+    /// This speeds up loading shader libraries of which only the types and
+    /// defaults of most shaders are looked at. Reading the metadata of a
+    /// parameter, including by serializing or comparing it, parses all of
+    /// its hints once. Shader metadata is always parsed right away.
+    ///
+    /// Has no effect if [`parse_metadata`](Self::parse_metadata) is off.
+    pub fn lazy_metadata(mut self, enabled: bool) -> Self {
+        self.lazy_metadata = enabled;
+        self
+    }
+
+    /// Set whether string defaults and string metadata, including arrays, are
+    /// interned (default: `true`).
+    ///
+    /// Identical strings then share storage, which saves memory when many
+    /// shaders repeat the same `page` names, help texts or defaults. Interned
+    /// strings are never freed, so turn this off when parsing many unrelated,
+    /// short-lived shaders. See [`StringValue`](crate::types::StringValue).
+    pub fn intern_strings(mut self, enabled: bool) -> Self {
+        self.intern_strings = enabled;
+        self
+    }
+
+    /// Set whether a line starting with a default value continues the
+    /// defaults of the parameter above it (default: `false`).
+    ///
+    /// `oslc` writes each symbol on a single line, but some tools wrap large
+    /// array defaults onto continuation lines.
+    pub fn multiline_defaults(mut self, enabled: bool) -> Self {
+        self.multiline_defaults = enabled;
+        self
+    }
+
+    /// Set the newest OSO version parsed without a warning (default:
+    /// [`MAX_KNOWN_VERSION`]).
+    pub fn max_version(mut self, major: i32, minor: i32) -> Self {
+        self.max_version = (major, minor);
+        self
+    }
+
+    /// Set whether files newer than [`max_version`](Self::max_version) fail
+    /// with [`ParseError::UnsupportedVersion`] instead of being parsed with a
+    /// warning (default: `false`).
+    ///
+    /// See [`OslQuery::warnings`].
+    pub fn strict_version(mut self, enabled: bool) -> Self {
+        self.strict_version = enabled;
+        self
+    }
+
+    /// Set whether default values on a closure parameter fail with
+    /// [`ParseError::ParseError`] instead of being skipped with a warning
+    /// (default: `false`).
+    ///
+    /// `oslc` never writes defaults for closures, so they point to a
+    /// miscompiled or hand-edited shader. Outputs of other types do have
+    /// defaults, their initial values.
+    pub fn strict_defaults(mut self, enabled: bool) -> Self {
+        self.strict_defaults = enabled;
+        self
+    }
+
+    /// Set whether the type keywords of symbols match in any case, e.g.
+    /// `param Color c 1 0 0` (default: `false`).
+    ///
+    /// `oslc` writes them in lowercase, as OSL requires, but hand-edited
+    /// files sometimes capitalize them. Otherwise such a line fails with
+    /// [`ParseError::ParseError`].
+    pub fn ignore_type_case(mut self, enabled: bool) -> Self {
+        self.ignore_type_case = enabled;
+        self
+    }
+}
 
 /// OSO file reader that parses OSO format line by line.
 ///
@@ -13,12 +160,19 @@ use crate::query::OslQuery;
 /// is first tokenized, then tokens are parsed sequentially. This matches
 /// the behavior of the OpenShadingLanguage C++ parser.
 pub struct OsoReader {
+    /// Reader configuration
+    config: OsoReaderConfig,
     /// Current line number for error reporting
     line_no: usize,
+    /// Byte offset of the current line in the source
+    line_start: usize,
     /// Current parameter being read
     current_param: Option<ParsedParameter>,
     /// Whether we're reading a parameter
     reading_param: bool,
+    /// Copies of the parameters before conversion, when collected for
+    /// [`parse_string_raw`](Self::parse_string_raw)
+    raw_params: Option<Vec<ParsedParameter>>,
 }
 
 impl Default for OsoReader {
@@ -30,53 +184,74 @@ impl Default for OsoReader {
 impl OsoReader {
     /// Create a new OSO reader
     pub fn new() -> Self {
+        Self::with_config(OsoReaderConfig::default())
+    }
+
+    /// Create a new OSO reader with the given configuration
+    pub fn with_config(config: OsoReaderConfig) -> Self {
         OsoReader {
+            config,
             line_no: 1,
+            line_start: 0,
             current_param: None,
             reading_param: false,
+            raw_params: None,
         }
     }
 
     /// Parse an OSO file from disk
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
     pub fn parse_file<P: AsRef<Path>>(self, path: P) -> Result<OslQuery, ParseError> {
-        let content = fs::read_to_string(path)?;
-        self.parse_string(&content)
+        self.parse_source(path.as_ref(), &crate::source::StdFs)
     }
 
     /// Parse OSO content from a string
     pub fn parse_string(mut self, content: &str) -> Result<OslQuery, ParseError> {
+        self.parse_content(content)
+    }
+
+    /// Parse OSO content from a string, and also return the parameters as
+    /// they were read, before conversion to [`Parameter`]s.
+    ///
+    /// See [`parse_raw`].
+    pub fn parse_string_raw(
+        mut self,
+        content: &str,
+    ) -> Result<(OslQuery, Vec<ParsedParameter>), ParseError> {
+        self.raw_params = Some(Vec::new());
+        let query = self.parse_content(content)?;
+        Ok((query, self.raw_params.unwrap_or_default()))
+    }
+
+    fn parse_content(&mut self, content: &str) -> Result<OslQuery, ParseError> {
+        trace::enter_span!("parse", bytes = content.len());
         let mut query = OslQuery::new();
         let lines = content.lines();
 
         for line in lines {
+            self.line_start = oso::offset_in(content, line);
             // Don't trim the line - preserve tabs for proper parsing
 
             // Skip empty lines and comments (# at start of line)
             if line.trim().is_empty() || line.trim_start().starts_with('#') {
+                trace::debug!(line = self.line_no, "skipped empty or comment line");
                 self.line_no += 1;
                 continue;
             }
 
             // Try to parse different directives
-            if let Ok((_, version)) = oso::parse_version(line) {
-                // Check version compatibility - support 1.00 and above
-                if version.0 < 1 {
-                    return Err(ParseError::UnsupportedVersion {
-                        major: version.0,
-                        minor: version.1,
-                    });
+            if let Ok((_, (identifier, version))) = oso::parse_version(line) {
+                query.set_format_identifier(identifier);
+                // Other formats have their own versioning.
+                if identifier == oso::OSL_FORMAT_IDENTIFIER {
+                    self.check_version(&mut query, version)?;
                 }
-            } else if line.starts_with("shader ")
-                || line.starts_with("surface ")
-                || line.starts_with("displacement ")
-                || line.starts_with("volume ")
-            {
+            } else if oso::is_shader_declaration(line) {
                 // Parse shader declaration - handles both "shader name" and "surface name" formats
                 if let Ok((rest, (shader_type, shader_name))) = oso::parse_shader(line) {
                     query.set_shader_info(shader_type, shader_name);
                     // Parse any hints on the same line
-                    let rest_tokens = oso::tokenize_line(rest);
-                    for token in rest_tokens {
+                    for token in oso::tokens(rest) {
                         if token.starts_with('%') {
                             self.handle_hint(&mut query, token)?;
                         }
@@ -84,15 +259,22 @@ impl OsoReader {
                 }
             } else if self.try_parse_symbol_line(&mut query, line)? {
                 // Symbol line was successfully parsed
+            } else if self.config.multiline_defaults
+                && self.try_parse_continuation_line(&mut query, line)?
+            {
+                // More defaults for the current parameter
             } else if line.starts_with("code") {
                 // End of current parameter, start of code section
                 self.finish_current_param(&mut query);
-                // For now, we stop parsing at code section
-                // In a full implementation, we'd parse bytecode here
+                // We stop parsing at the code section and only record where it is
+                let end = BytecodeSection::skip(content, self.line_no + 1);
+                query.set_code_section(self.line_no, end);
                 break;
             } else if line.starts_with('%') {
                 // Standalone hint line (metadata for shader or current param)
                 self.handle_hint(&mut query, line)?;
+            } else {
+                trace::debug!(line = self.line_no, "skipped unrecognized line");
             }
 
             self.line_no += 1;
@@ -101,36 +283,35 @@ impl OsoReader {
         // Make sure to add the last parameter if any
         self.finish_current_param(&mut query);
 
+        if self.config.intern_strings {
+            query.intern_strings();
+        }
+
         Ok(query)
     }
 
-    /// Handle symbol declaration
-    fn handle_symbol(
-        &mut self,
+    /// Reject versions before 1.00, and warn about (or reject) versions
+    /// newer than configured.
+    fn check_version(
+        &self,
         query: &mut OslQuery,
-        symtype: SymType,
-        typespec: TypeSpec,
-        name: &str,
+        (major, minor): (i32, i32),
     ) -> Result<(), ParseError> {
-        // Finish any previous parameter
-        self.finish_current_param(query);
-
-        match symtype {
-            SymType::Param | SymType::OutputParam => {
-                let mut param = ParsedParameter::new(name, typespec.simpletype);
-                param.is_output = symtype == SymType::OutputParam;
-                param.is_struct = typespec.is_structure();
-                param.varlen_array = typespec.is_unsized_array();
-
-                self.current_param = Some(param);
-                self.reading_param = true;
-            }
-            _ => {
-                // Not a parameter, ignore for now
-                self.reading_param = false;
+        let unsupported = ParseError::UnsupportedVersion { major, minor };
+        if major < 1 {
+            return Err(unsupported);
+        }
+        if (major, minor) > self.config.max_version {
+            if self.config.strict_version {
+                return Err(unsupported);
             }
+            let (max_major, max_minor) = self.config.max_version;
+            query.add_warning(format!(
+                "OSO version {}.{:02} is newer than {}.{:02}, the newest known version; \
+                 parameters may be missing or wrong",
+                major, minor, max_major, max_minor
+            ));
         }
-
         Ok(())
     }
 
@@ -140,255 +321,651 @@ impl OsoReader {
         query: &mut OslQuery,
         line: &str,
     ) -> Result<bool, ParseError> {
-        let tokens = oso::tokenize_line(line);
-        if tokens.is_empty() {
+        let Some(header) = parse_symbol_header(
+            line,
+            self.line_no,
+            self.line_start,
+            self.config.ignore_type_case,
+        )?
+        else {
             return Ok(false);
-        }
-
-        // Check if first token is a valid symtype
-        let symtype = match oso::parse_symtype(tokens[0]) {
-            Ok((_, st)) => st,
-            _ => return Ok(false),
         };
 
-        // Need at least 3 tokens: symtype, typespec, identifier
-        if tokens.len() < 3 {
-            return Ok(false);
-        }
-
-        // Parse typespec from second token(s)
-        // Handle "closure color" as two tokens
-        let (typespec, next_token_idx) = if tokens[1] == "closure" {
-            // Need at least 4 tokens for closure: symtype, "closure", typename, identifier
-            if tokens.len() < 4 {
-                return Err(ParseError::ParseError {
-                    line: self.line_no,
-                    message: "Incomplete closure type specification".to_string(),
-                    token_info: Some((tokens[1].to_string(), 1)),
-                });
-            }
-            // Parse "closure typename" as a single typespec
-            let closure_spec = format!("{} {}", tokens[1], tokens[2]);
-            match oso::parse_typespec(&closure_spec) {
-                Ok((_, ts)) => (ts, 3), // Next token is at index 3
-                _ => {
-                    return Err(ParseError::ParseError {
-                        line: self.line_no,
-                        message: format!("Invalid closure type: {} {}", tokens[1], tokens[2]),
-                        token_info: Some((tokens[1].to_string(), 1)),
-                    });
-                }
-            }
-        } else {
-            // Regular single-token typespec
-            match oso::parse_typespec(tokens[1]) {
-                Ok((_, ts)) => (ts, 2), // Next token is at index 2
-                _ => {
-                    return Err(ParseError::ParseError {
-                        line: self.line_no,
-                        message: format!("Invalid type specification: {}", tokens[1]),
-                        token_info: Some((tokens[1].to_string(), 1)),
-                    });
-                }
-            }
-        };
+        // Finish any previous parameter; other symbols are ignored for now
+        self.finish_current_param(query);
+        self.current_param = header.new_param();
+        self.reading_param = self.current_param.is_some();
 
-        // Next token is the identifier
-        let name = tokens[next_token_idx];
+        // Process remaining tokens as default values and hints
+        self.parse_defaults_and_hints(query, line, header.rest)?;
 
-        // Handle the symbol
-        self.handle_symbol(query, symtype, typespec, name)?;
+        Ok(true)
+    }
 
-        // Process remaining tokens as default values and hints
-        let mut token_idx = next_token_idx + 1;
+    /// Try to parse a line continuing the defaults of the current parameter
+    fn try_parse_continuation_line(
+        &mut self,
+        query: &mut OslQuery,
+        line: &str,
+    ) -> Result<bool, ParseError> {
+        if !self.reading_param {
+            return Ok(false);
+        }
 
-        // Parse default values (everything until we hit a % token)
-        while token_idx < tokens.len() && !tokens[token_idx].starts_with('%') {
-            if let Some(default) = oso::parse_default_token(tokens[token_idx])
-                && let Some(ref mut param) = self.current_param
-            {
-                match default {
-                    oso::DefaultValue::Int(i) => {
-                        // For float-based types (float, color, point, vector, normal, matrix),
-                        // store integers as floats
-                        match param.type_desc.basetype {
-                            BaseType::Float
-                            | BaseType::Color
-                            | BaseType::Point
-                            | BaseType::Vector
-                            | BaseType::Normal
-                            | BaseType::Matrix => {
-                                param.fdefault.push(i as f32);
-                            }
-                            _ => {
-                                param.idefault.push(i);
-                            }
-                        }
-                    }
-                    oso::DefaultValue::Float(f) => {
-                        param.fdefault.push(f);
-                    }
-                    oso::DefaultValue::String(s) => {
-                        param.sdefault.push(s);
-                    }
-                }
-                param.valid_default = true;
+        match oso::tokens(line).next() {
+            Some(token) if oso::parse_default_token(token).is_some() => {
+                self.parse_defaults_and_hints(query, line, oso::tokens(line))?;
+                Ok(true)
             }
-            token_idx += 1;
+            _ => Ok(false),
         }
+    }
+
+    /// Parse default values and hints (tokens starting with `%`) from
+    /// `tokens`, which are (the rest of) those of `line`
+    fn parse_defaults_and_hints<'a>(
+        &mut self,
+        query: &mut OslQuery,
+        line: &'a str,
+        tokens: impl Iterator<Item = &'a str>,
+    ) -> Result<(), ParseError> {
+        let Some(param) = self.current_param.as_mut().filter(|_| self.reading_param) else {
+            // The hints of other symbols go to the shader
+            for token in tokens.filter(|token| token.starts_with('%')) {
+                self.handle_hint(query, token)?;
+            }
+            return Ok(());
+        };
 
-        // Process hint tokens
-        while token_idx < tokens.len() {
-            if tokens[token_idx].starts_with('%') {
-                self.handle_hint(query, tokens[token_idx])?;
+        if let Some(token) = apply_defaults_and_hints(param, tokens, &self.config) {
+            let message = format!("Closure parameter {} has default values", param.name);
+            if self.config.strict_defaults {
+                return Err(ParseError::ParseError {
+                    line: self.line_no,
+                    message,
+                    token_info: Some(token_info(line, self.line_start, token)),
+                });
             }
-            token_idx += 1;
+            query.add_warning(format!(
+                "{} on line {}; ignoring them",
+                message, self.line_no
+            ));
         }
 
-        Ok(true)
+        Ok(())
     }
 
     /// Handle hint directive
     fn handle_hint(&mut self, query: &mut OslQuery, hint_str: &str) -> Result<(), ParseError> {
-        // Parse metadata hints
-        if hint_str.starts_with("%meta{") {
-            self.parse_metadata(query, hint_str)?;
-        } else if self.reading_param && hint_str.starts_with("%structfields{") {
-            self.parse_struct_fields(hint_str)?;
-        } else if self.reading_param && hint_str.starts_with("%struct{") {
-            self.parse_struct_name(hint_str)?;
-        } else if self.reading_param && hint_str.starts_with("%space{") {
-            self.parse_space_hint(hint_str)?;
-        } else if self.reading_param && hint_str.starts_with("%default{") {
-            self.parse_default_hint(hint_str)?;
-        } else if self.reading_param
-            && hint_str == "%initexpr"
-            && let Some(ref mut param) = self.current_param
-        {
-            param.valid_default = false;
+        match self.current_param {
+            Some(ref mut param) if self.reading_param => {
+                apply_param_hint(param, hint_str, &self.config);
+            }
+            // Shader metadata
+            _ => {
+                if self.config.parse_metadata
+                    && hint_str.starts_with("%meta{")
+                    && let Some(meta) = hint::parse_metadata(hint_str)
+                {
+                    query.add_metadata(meta);
+                }
+            }
         }
-        // Ignore other hints like %read{...} %write{...} which are bytecode related
-
         Ok(())
     }
 
-    /// Parse metadata hint
-    fn parse_metadata(&mut self, query: &mut OslQuery, hint_str: &str) -> Result<(), ParseError> {
-        if let Ok((_, meta)) = hint::parse_metadata_hint(hint_str) {
-            if self.reading_param {
-                if let Some(ref mut param) = self.current_param {
-                    param.metadata.push(meta);
-                }
-            } else {
-                // Convert ParsedParameter metadata to Metadata
-                use crate::types::MetadataValue;
-                let value = if !meta.idefault.is_empty() {
-                    if meta.idefault.len() == 1 {
-                        MetadataValue::Int(meta.idefault[0])
-                    } else {
-                        MetadataValue::IntArray(meta.idefault)
-                    }
-                } else if !meta.fdefault.is_empty() {
-                    if meta.fdefault.len() == 1 {
-                        MetadataValue::Float(meta.fdefault[0])
-                    } else {
-                        MetadataValue::FloatArray(meta.fdefault)
-                    }
-                } else if !meta.sdefault.is_empty() {
-                    if meta.sdefault.len() == 1 {
-                        MetadataValue::String(meta.sdefault[0].clone())
-                    } else {
-                        MetadataValue::StringArray(meta.sdefault)
-                    }
-                } else {
-                    return Ok(());
-                };
-                query.add_metadata(crate::types::Metadata {
-                    name: meta.name,
-                    value,
+    /// Finish processing the current parameter and add it to the query
+    fn finish_current_param(&mut self, query: &mut OslQuery) {
+        if let Some(parsed_param) = self.current_param.take() {
+            if let Some(raw_params) = &mut self.raw_params {
+                raw_params.push(parsed_param.clone());
+            }
+
+            // Struct parameters only group their members, which follow as
+            // separate parameters
+            if parsed_param.is_struct {
+                query.add_struct(StructDecl {
+                    name: parsed_param.name,
+                    struct_name: parsed_param.structname.unwrap_or_default(),
+                    fields: parsed_param.fields,
+                    is_output: parsed_param.is_output,
                 });
+                self.reading_param = false;
+                return;
+            }
+
+            // Files without `%mystruct` hints still name the fields in the
+            // `%structfields` of the struct parameter, which comes first.
+            let mut parsed_param = parsed_param;
+            if parsed_param.mystruct.is_none() {
+                parsed_param.mystruct = query
+                    .structs()
+                    .iter()
+                    .find(|decl| {
+                        parsed_param
+                            .name
+                            .strip_prefix(decl.name.as_str())
+                            .and_then(|rest| rest.strip_prefix('.'))
+                            .is_some_and(|field| decl.fields.iter().any(|f| f.as_str() == field))
+                    })
+                    .map(|decl| decl.name);
+            }
+
+            // Convert ParsedParameter to final Parameter type
+            trace::enter_span!("convert", param = %parsed_param.name);
+            match parsed_param.try_into() {
+                Ok(param) => query.add_parameter(param),
+                #[cfg(feature = "tracing")]
+                Err(e) => tracing::error!(error = %e, "failed to convert parameter"),
+                #[cfg(not(feature = "tracing"))]
+                Err(e) => eprintln!("Failed to convert parameter: {}", e),
             }
         }
-        Ok(())
+        self.reading_param = false;
     }
+}
 
-    /// Parse struct fields hint
-    fn parse_struct_fields(&mut self, hint_str: &str) -> Result<(), ParseError> {
-        if let Some(ref mut param) = self.current_param
-            && let Some(fields) = hint::parse_structfields_hint(hint_str)
+/// Parse OSO content, and also return the parameters as they were read,
+/// before conversion to [`Parameter`]s.
+///
+/// The [`ParsedParameter`]s hold the defaults exactly as they appear in the
+/// file, which helps to tell a conversion problem from a parse problem.
+/// Struct parameters are included; shader groups have none.
+///
+/// ```
+/// use oslquery_petite::parser::parse_raw;
+///
+/// let (query, raw) = parse_raw(
+///     "OpenShadingLanguage 1.12\nsurface s\nparam\tfloat[2]\tw\t1 2\ncode ___main___\n",
+/// )?;
+/// assert_eq!(query.param_count(), 1);
+/// assert_eq!(raw[0].fdefault, [1.0, 2.0]);
+/// # Ok::<(), oslquery_petite::parser::ParseError>(())
+/// ```
+pub fn parse_raw(content: &str) -> Result<(OslQuery, Vec<ParsedParameter>), ParseError> {
+    OsoReader::new().parse_string_raw(content)
+}
+
+/// Parse a stream of concatenated OSO files, calling `callback` with each
+/// shader as soon as it is complete.
+///
+/// A new shader starts at each version line, e.g. `OpenShadingLanguage
+/// 1.12`. Only the text of the current shader is held in memory, and each
+/// query is dropped by the callback before the next is read, so memory
+/// stays flat however large the stream is.
+///
+/// Stops at the first shader that fails to parse. Line numbers in its
+/// error count from the start of the stream; token offsets from the start
+/// of its shader. Returns the number of shaders passed to `callback`.
+///
+/// ```
+/// use oslquery_petite::parser::parse_stream;
+///
+/// let dump = "OpenShadingLanguage 1.12\nsurface a\ncode ___main___\n\
+///             OpenShadingLanguage 1.12\nsurface b\ncode ___main___\n";
+/// let mut names = Vec::new();
+/// let count = parse_stream(dump.as_bytes(), |query| {
+///     names.push(query.shader_name().to_string())
+/// })?;
+/// assert_eq!((count, names), (2, vec!["a".to_string(), "b".to_string()]));
+/// # Ok::<(), oslquery_petite::parser::ParseError>(())
+/// ```
+pub fn parse_stream<R: BufRead, F: FnMut(OslQuery)>(
+    mut reader: R,
+    mut callback: F,
+) -> Result<usize, ParseError> {
+    let mut shader = String::new();
+    // Line number of the first line in `shader`
+    let mut first_line = 1;
+    let mut line_no = 1;
+    let mut has_content = false;
+    let mut count = 0;
+
+    let mut flush = |shader: &mut String, first_line: usize| {
+        let query = OsoReader::new()
+            .parse_string(shader)
+            .map_err(|error| match error {
+                ParseError::ParseError {
+                    line,
+                    message,
+                    token_info,
+                } => ParseError::ParseError {
+                    line: line + first_line - 1,
+                    message,
+                    token_info,
+                },
+                error => error,
+            })?;
+        shader.clear();
+        callback(query);
+        Ok::<_, ParseError>(())
+    };
+
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader
+            .read_line(&mut line)
+            .map_err(|e| ParseError::Io(e.to_string()))?
+            == 0
         {
-            param.fields = fields;
+            break;
         }
-        Ok(())
-    }
 
-    /// Parse struct name hint
-    fn parse_struct_name(&mut self, hint_str: &str) -> Result<(), ParseError> {
-        if let Some(ref mut param) = self.current_param {
-            param.structname = hint::parse_struct_hint(hint_str);
+        let is_header = oso::parse_version(line.trim_end()).is_ok();
+        if is_header && has_content {
+            flush(&mut shader, first_line)?;
+            count += 1;
+            first_line = line_no;
+            has_content = false;
         }
-        Ok(())
+        let trimmed = line.trim();
+        has_content |= !trimmed.is_empty() && !trimmed.starts_with('#');
+        shader.push_str(&line);
+        line_no += 1;
     }
 
-    /// Parse space hint for geometric types
-    fn parse_space_hint(&mut self, hint_str: &str) -> Result<(), ParseError> {
-        if let Some(ref mut param) = self.current_param
-            && let Some(space) = hint::parse_space_hint(hint_str)
-        {
-            param.spacename.push(space);
+    if has_content {
+        flush(&mut shader, first_line)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Parse a single parameter declaration of an OSO file, e.g.
+/// `param color c 1 0 0 %meta{string,label,"Tint"}`, into a [`Parameter`].
+///
+/// The line is parsed as [`OsoReader`] with the default configuration parses
+/// it, but on its own: defaults continued on the following lines and hints on
+/// lines of their own are not seen. `line_no` is reported in errors, whose
+/// token offsets are relative to `line`. Defaults of closures are ignored.
+///
+/// Lines declaring other symbols, and struct parameters, which only group the
+/// parameters of their fields, are errors.
+///
+/// ```
+/// use oslquery_petite::parser::parse_param_line;
+///
+/// let param = parse_param_line("param\tfloat\tKd\t0.5\t%meta{float,min,0}", 12)?;
+/// assert_eq!(param.name, "Kd");
+/// assert!(param.find_metadata("min").is_some());
+/// # Ok::<(), oslquery_petite::parser::ParseError>(())
+/// ```
+pub fn parse_param_line(line: &str, line_no: usize) -> Result<Parameter, ParseError> {
+    let error = |message: String, token: Option<&str>| ParseError::ParseError {
+        line: line_no,
+        message,
+        token_info: token.map(|token| token_info(line, 0, token)),
+    };
+
+    let not_param = || {
+        error(
+            "Not a parameter declaration".to_string(),
+            oso::tokens(line).next(),
+        )
+    };
+
+    let Some(header) = parse_symbol_header(line, line_no, 0, false)? else {
+        return Err(not_param());
+    };
+    let Some(mut param) = header.new_param() else {
+        return Err(not_param());
+    };
+    if param.is_struct {
+        return Err(error(
+            format!(
+                "Struct parameter {} has no value; its fields are separate parameters",
+                param.name
+            ),
+            Some(header.name),
+        ));
+    }
+
+    apply_defaults_and_hints(&mut param, header.rest, &OsoReaderConfig::new());
+    param.try_into().map_err(ParseError::Conversion)
+}
+
+/// The start of a symbol line, e.g. `param float Kd` or
+/// `oparam closure color bsdf`.
+struct SymbolHeader<'a> {
+    symtype: SymType,
+    typespec: TypeSpec,
+    name: &'a str,
+    /// The struct name of a `struct Name` type
+    struct_name: Option<&'a str>,
+    /// The tokens after the name: defaults and hints
+    rest: oso::Tokens<'a>,
+}
+
+impl SymbolHeader<'_> {
+    /// A parameter for `param` and `oparam` symbols, without defaults.
+    fn new_param(&self) -> Option<ParsedParameter> {
+        if !matches!(self.symtype, SymType::Param | SymType::OutputParam) {
+            return None;
         }
-        Ok(())
+        let mut param = ParsedParameter::new(self.name, self.typespec.simpletype);
+        param.is_output = self.symtype == SymType::OutputParam;
+        param.is_struct = self.typespec.is_structure();
+        param.varlen_array = self.typespec.is_unsized_array();
+        // The struct name from the typespec, in case there is no `%struct` hint
+        param.structname = self.struct_name.map(Ustr::from);
+        Some(param)
     }
+}
 
-    /// Parse default hint (alternative default value format)
-    fn parse_default_hint(&mut self, hint_str: &str) -> Result<(), ParseError> {
-        if let Some(ref mut param) = self.current_param
-            && let Some(values) = hint::parse_default_hint(hint_str)
-        {
-            match param.type_desc.basetype {
-                BaseType::Int => {
-                    param
-                        .idefault
-                        .extend(values.iter().filter_map(|v| v.parse::<i32>().ok()));
-                }
-                BaseType::Float
-                | BaseType::Color
-                | BaseType::Point
-                | BaseType::Vector
-                | BaseType::Normal
-                | BaseType::Matrix => {
-                    param
-                        .fdefault
-                        .extend(values.iter().filter_map(|v| v.parse::<f32>().ok()));
-                }
-                BaseType::String => {
-                    param.sdefault.extend(values);
-                }
-                _ => {}
+/// Read the symbol type, type and name at the start of `line`; `None` if it
+/// is not a symbol line.
+///
+/// `line_start`, the byte offset of `line` in the source, and `line_no`
+/// locate errors. With `ignore_case`, type keywords match in any case.
+fn parse_symbol_header(
+    line: &str,
+    line_no: usize,
+    line_start: usize,
+    ignore_case: bool,
+) -> Result<Option<SymbolHeader<'_>>, ParseError> {
+    let mut tokens = oso::tokens(line);
+
+    // Check if first token is a valid symtype
+    let symtype = match tokens.next().map(oso::parse_symtype) {
+        Some(Ok((_, st))) => st,
+        _ => return Ok(None),
+    };
+
+    // Need at least 3 tokens: symtype, typespec, identifier
+    let (Some(type_token), Some(next_token)) = (tokens.next(), tokens.next()) else {
+        return Ok(None);
+    };
+
+    let error = |message: String| ParseError::ParseError {
+        line: line_no,
+        message,
+        token_info: Some(token_info(line, line_start, type_token)),
+    };
+
+    let is_keyword = |keyword: &str| {
+        type_token == keyword || (ignore_case && type_token.eq_ignore_ascii_case(keyword))
+    };
+    let parse_typespec = if ignore_case {
+        oso::parse_typespec_ignore_case
+    } else {
+        oso::parse_typespec
+    };
+
+    // Parse typespec from second token(s)
+    // Handle "closure color" and "struct Name" as two tokens
+    let (typespec, name) = if is_keyword("closure") || is_keyword("struct") {
+        // Need at least 4 tokens: symtype, keyword, typename, identifier
+        let Some(name) = tokens.next() else {
+            return Err(error(format!(
+                "Incomplete {} type specification",
+                type_token
+            )));
+        };
+        // Parse "keyword typename" as a single typespec
+        let compound_spec = format!("{} {}", type_token, next_token);
+        match parse_typespec(&compound_spec) {
+            Ok((_, ts)) => (ts, name),
+            _ => {
+                return Err(error(format!(
+                    "Invalid {} type: {}",
+                    type_token, next_token
+                )));
             }
+        }
+    } else {
+        // Regular single-token typespec
+        match parse_typespec(type_token) {
+            Ok((_, ts)) => (ts, next_token),
+            _ => {
+                return Err(error(format!("Invalid type specification: {}", type_token)));
+            }
+        }
+    };
 
-            param.valid_default = true;
+    let struct_name =
+        is_keyword("struct").then(|| next_token.split('[').next().unwrap_or(next_token));
+
+    Ok(Some(SymbolHeader {
+        symtype,
+        typespec,
+        name,
+        struct_name,
+        rest: tokens,
+    }))
+}
+
+/// Add the defaults and apply the hints among `tokens` to `param`.
+///
+/// Closures cannot have defaults; the first one found is returned and all
+/// are ignored.
+fn apply_defaults_and_hints<'a>(
+    param: &mut ParsedParameter,
+    tokens: impl Iterator<Item = &'a str>,
+    config: &OsoReaderConfig,
+) -> Option<&'a str> {
+    // `oslc` writes all defaults before the hints, but classify each token
+    // on its own so defaults after a hint are not lost.
+    let mut closure_default = None;
+    for token in tokens {
+        if token.starts_with('%') {
+            apply_param_hint(param, token, config);
+        } else if let Some(default) = oso::parse_default_token(token) {
+            if param.type_desc.is_closure {
+                closure_default.get_or_insert(token);
+            } else {
+                param.push_default(default);
+            }
         }
-        Ok(())
     }
+    closure_default
+}
 
-    /// Finish processing the current parameter and add it to the query
-    fn finish_current_param(&mut self, query: &mut OslQuery) {
-        if let Some(parsed_param) = self.current_param.take() {
-            // Convert ParsedParameter to final Parameter type
-            match parsed_param.try_into() {
-                Ok(param) => query.add_parameter(param),
-                Err(e) => eprintln!("Failed to convert parameter: {}", e),
+/// Apply a hint of a parameter's symbol line to `param`.
+fn apply_param_hint(param: &mut ParsedParameter, hint_str: &str, config: &OsoReaderConfig) {
+    if hint_str.starts_with("%meta{") {
+        if config.parse_metadata {
+            if config.lazy_metadata {
+                // Hints never span lines, so they can be joined with one.
+                if !param.raw_metadata.is_empty() {
+                    param.raw_metadata.push('\n');
+                }
+                param.raw_metadata.push_str(hint_str);
+            } else if let Ok((_, meta)) = hint::parse_metadata_hint(hint_str) {
+                param.metadata.push(meta);
             }
         }
-        self.reading_param = false;
+    } else if hint_str.starts_with("%structfields{") {
+        if let Some(fields) = hint::parse_structfields_hint(hint_str) {
+            param.fields = fields;
+        }
+    } else if hint_str.starts_with("%struct{") {
+        param.structname = hint::parse_struct_hint(hint_str);
+    } else if hint_str.starts_with("%mystruct{") {
+        param.mystruct = hint::parse_struct_hint(hint_str);
+    } else if hint_str.starts_with("%space{") {
+        // Space of geometric types
+        if let Some(space) = hint::parse_space_hint(hint_str) {
+            param.spacename.push(Ustr::from(space));
+        }
+    } else if hint_str.starts_with("%default{") {
+        // Alternative default value format
+        if let Some(values) = hint::parse_default_hint(hint_str) {
+            let basetype = param.type_desc.basetype;
+            if basetype.is_int_backed() {
+                param
+                    .idefault
+                    .extend(values.iter().filter_map(|v| v.parse::<i32>().ok()));
+            } else if basetype.is_float_backed() {
+                param
+                    .fdefault
+                    .extend(values.iter().filter_map(|v| v.parse::<f32>().ok()));
+            } else if basetype.is_string_backed() {
+                param.sdefault.extend(values);
+            }
+            param.valid_default = true;
+        }
+    } else if hint_str == "%initexpr" {
+        param.valid_default = false;
     }
+    // Ignore other hints like %read{...} %write{...} which are bytecode related
+}
+
+/// `token`, a slice of `line`, and its byte offset in the source, given the
+/// offset `line_start` of `line`
+fn token_info(line: &str, line_start: usize, token: &str) -> (String, usize) {
+    (token.to_string(), line_start + oso::offset_in(line, token))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_stream() {
+        let shader = |name: &str| {
+            format!(
+                "OpenShadingLanguage 1.12\n\
+                 # Compiled by oslc\n\
+                 surface {}\n\
+                 param\tfloat\tKd\t0.5\n\
+                 code ___main___\n\
+                 \tassign\tCout\tKd\n\
+                 \tend\n",
+                name
+            )
+        };
+        let dump = format!("\n{}{}\n{}", shader("a"), shader("b"), shader("c"));
+
+        let mut queries = Vec::new();
+        // A small buffer, so lines span reads.
+        let reader = std::io::BufReader::with_capacity(8, dump.as_bytes());
+        let count = parse_stream(reader, |query| queries.push(query)).unwrap();
+        assert_eq!(count, 3);
+        let names: Vec<&str> = queries.iter().map(|q| q.shader_name()).collect();
+        assert_eq!(names, ["a", "b", "c"]);
+        // The same apart from where the lines are, which differs by the
+        // blank lines that are part of the shaders in the stream.
+        for query in &queries {
+            let alone = OsoReader::new()
+                .parse_string(&shader(query.shader_name()))
+                .unwrap();
+            assert_eq!(query.canonical_form(), alone.canonical_form());
+        }
+
+        assert_eq!(parse_stream("".as_bytes(), |_| panic!()), Ok(0));
+        assert_eq!(
+            parse_stream("# nothing\n\n".as_bytes(), |_| panic!()),
+            Ok(0)
+        );
+    }
+
+    #[test]
+    fn test_parse_stream_error() {
+        let dump = "OpenShadingLanguage 1.12\n\
+                    surface a\n\
+                    code ___main___\n\
+                    OpenShadingLanguage 1.12\n\
+                    surface b\n\
+                    param\tFloat\tKd\t0.5\n\
+                    code ___main___\n\
+                    OpenShadingLanguage 1.12\n\
+                    surface c\n";
+        let mut names = Vec::new();
+        let error = parse_stream(dump.as_bytes(), |query| {
+            names.push(query.shader_name().to_string())
+        })
+        .unwrap_err();
+        assert_eq!(names, ["a"]);
+        assert!(
+            matches!(error, ParseError::ParseError { line: 6, .. }),
+            "{:?}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_parse_raw() {
+        use crate::types::TypedParameter;
+
+        let content = "OpenShadingLanguage 1.12\n\
+                       surface test\n\
+                       param\tcolor\tfull\t1 0.5 0\n\
+                       param\tcolor\tbroadcast\t0.5\n\
+                       param\tstruct S\ts\t\t%struct{\"S\"} %structfields{a}\n\
+                       param\tint\ts.a\t3\n\
+                       code ___main___\n";
+        let (query, raw) = parse_raw(content).unwrap();
+        assert_eq!(query, OsoReader::new().parse_string(content).unwrap());
+
+        let names: Vec<&str> = raw.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["full", "broadcast", "s", "s.a"]);
+        assert_eq!(raw[0].fdefault, [1.0, 0.5, 0.0]);
+        assert!(raw[0].valid_default);
+        assert_eq!(
+            query.param_by_name("full").unwrap().typed_param(),
+            &TypedParameter::Color {
+                default: Some([1.0, 0.5, 0.0]),
+                space: None,
+            }
+        );
+
+        // A single value is read, but a color needs three to convert.
+        assert_eq!(raw[1].fdefault, [0.5]);
+        assert!(raw[1].valid_default);
+        assert!(!query.param_by_name("broadcast").unwrap().has_default());
+
+        assert!(raw[2].is_struct);
+        assert_eq!(raw[3].idefault, [3]);
+    }
+
+    #[test]
+    fn test_parse_param_line() {
+        use crate::types::{MetadataValue, TypedParameter};
+
+        let param = parse_param_line(
+            "param\tcolor\tc\t1 0 0\t%meta{string,label,\"Tint\"} %meta{float,max,2}",
+            7,
+        )
+        .unwrap();
+        assert_eq!(param.name, "c");
+        assert!(!param.is_output());
+        assert_eq!(
+            param.typed_param(),
+            &TypedParameter::Color {
+                default: Some([1.0, 0.0, 0.0]),
+                space: None,
+            }
+        );
+        assert_eq!(
+            param.find_metadata("label").unwrap().value,
+            MetadataValue::String("Tint".into())
+        );
+        assert_eq!(
+            param.find_metadata("max").unwrap().value,
+            MetadataValue::Float(2.0)
+        );
+
+        // The same as in a whole file.
+        let source = "OpenShadingLanguage 1.12\nsurface s\n\
+                      oparam\tpoint\tP\t0 0 1\t%space{\"world\"} %read{0,0}\n";
+        let query = OsoReader::new().parse_string(source).unwrap();
+        assert_eq!(
+            parse_param_line(source.lines().nth(2).unwrap(), 3).unwrap(),
+            query.params()[0]
+        );
+
+        let error = parse_param_line("local\tfloat\ttmp", 9).unwrap_err();
+        assert!(matches!(error, ParseError::ParseError { line: 9, .. }));
+        assert_eq!(error.token_span("local\tfloat\ttmp"), Some(0..5));
+        assert!(parse_param_line("code ___main___", 1).is_err());
+        assert!(parse_param_line("param\tstruct Layer\tbase", 1).is_err());
+
+        let line = "param\tbogus\tx";
+        let error = parse_param_line(line, 4).unwrap_err();
+        assert_eq!(error.token_span(line), Some(6..11));
+    }
+
     #[test]
     fn test_parse_simple_shader() {
         let oso_content = r#"
@@ -418,6 +995,70 @@ code ___main___
         }
     }
 
+    #[test]
+    fn test_array_metadata() {
+        use crate::types::MetadataValue;
+
+        let param = parse_param_line(
+            "param\tint\tmode\t0\t%meta{int[],options,0 1 2 3} \
+             %meta{string[],enum,\"foo\" \"bar\" \"baz\"} %meta{float[2],range,0 0.5}",
+            3,
+        )
+        .unwrap();
+        assert_eq!(
+            param.find_metadata("options").unwrap().value,
+            MetadataValue::IntArray(vec![0, 1, 2, 3])
+        );
+        assert_eq!(
+            param.find_metadata("enum").unwrap().value,
+            MetadataValue::StringArray(vec!["foo".into(), "bar".into(), "baz".into()])
+        );
+        assert_eq!(
+            param.find_metadata("range").unwrap().value,
+            MetadataValue::FloatArray(vec![0.0, 0.5])
+        );
+    }
+
+    #[test]
+    fn test_ignore_type_case() {
+        use crate::types::TypedParameter;
+
+        let source = "OpenShadingLanguage 1.12\n\
+                      surface s\n\
+                      param\tColor\tc\t1 0 0\n\
+                      param\tFLOAT[2]\tw\t0.5 1\n\
+                      oparam\tClosure Color\tbsdf\n\
+                      code ___main___\n";
+
+        // Strict by default, as OSL is.
+        let error = OsoReader::new().parse_string(source).unwrap_err();
+        assert!(
+            matches!(&error, ParseError::ParseError { line: 3, message, .. }
+                if message == "Invalid type specification: Color"),
+            "{:?}",
+            error
+        );
+
+        let query = OsoReader::with_config(OsoReaderConfig::new().ignore_type_case(true))
+            .parse_string(source)
+            .unwrap();
+        assert_eq!(
+            query.param_by_name("c").unwrap().typed_param(),
+            &TypedParameter::Color {
+                default: Some([1.0, 0.0, 0.0]),
+                space: None,
+            }
+        );
+        assert_eq!(
+            query.param_by_name("w").unwrap().typed_param(),
+            &TypedParameter::FloatArray {
+                size: 2,
+                default: Some(vec![0.5, 1.0]),
+            }
+        );
+        assert!(query.param_by_name("bsdf").unwrap().is_closure());
+    }
+
     #[test]
     fn test_parse_shader_with_tabs() {
         let oso_content = r#"
@@ -460,4 +1101,386 @@ code ___main___
             _ => panic!("Expected Color parameter with default"),
         }
     }
+
+    #[test]
+    fn test_code_section_line_range() {
+        let oso_content = "OpenShadingLanguage 1.12
+shader test
+param float Kd 0.5
+code ___main___
+\tassign a b %line{15}
+\tend
+";
+
+        let query = OsoReader::new().parse_string(oso_content).unwrap();
+        assert_eq!(query.code_section_line_range(), Some((4, 7)));
+
+        // No code section at all
+        let query = OsoReader::new()
+            .parse_string("OpenShadingLanguage 1.12\nshader test\n")
+            .unwrap();
+        assert_eq!(query.code_section_line_range(), None);
+    }
+
+    #[test]
+    fn test_default_hint_color_constructor() {
+        let oso_content = r#"
+OpenShadingLanguage 1.12
+surface test
+param color tint %default{color(0.5,0.5,0.5)}
+code ___main___
+"#;
+
+        let query = OsoReader::new().parse_string(oso_content).unwrap();
+
+        let param = query.param_by_name("tint").unwrap();
+        use crate::TypedParameter;
+        match param.typed_param() {
+            TypedParameter::Color {
+                default: Some(rgb), ..
+            } => {
+                assert_eq!(*rgb, [0.5, 0.5, 0.5]);
+            }
+            _ => panic!("Expected Color parameter with default"),
+        }
+    }
+
+    #[test]
+    fn test_parse_without_metadata() {
+        let oso_content = r#"
+OpenShadingLanguage 1.12
+surface test %meta{string,help,"A shader"}
+param	int	coating_on	0	%meta{string,page,"Coating"} %meta{string,label,"On"}
+param	color	coating_color	1 1 1	%meta{string,label,"Color"}
+code ___main___
+"#;
+
+        let config = OsoReaderConfig::new().parse_metadata(false);
+        let query = OsoReader::with_config(config)
+            .parse_string(oso_content)
+            .unwrap();
+
+        assert!(query.metadata().is_empty());
+        assert_eq!(query.param_count(), 2);
+        assert!(query.params().iter().all(|p| p.metadata().is_empty()));
+
+        // Defaults are still parsed
+        use crate::TypedParameter;
+        assert!(matches!(
+            query.param_by_name("coating_on").unwrap().typed_param(),
+            TypedParameter::Int { default: Some(0) }
+        ));
+    }
+
+    #[test]
+    fn test_lazy_metadata() {
+        use crate::types::MetadataValue;
+
+        let sources = [
+            include_str!("../../tests/metadata.oso"),
+            include_str!("../../tests/ui.oso"),
+            include_str!("../../tests/renderman.oso"),
+            include_str!("../../tests/all_types.oso"),
+            include_str!("../../tests/struct.oso"),
+            r#"
+OpenShadingLanguage 1.12
+surface test %meta{string,help,"A shader"}
+param	int	coating_on	0	%meta{string,page,"Coating"} %meta{string,label,"On {x}"}
+param	float	Kd	0.5	%meta{float,min,0} %meta{float,max,1} %meta{int,slider,1}
+param	color	coating_color	1 1 1
+code ___main___
+"#,
+        ];
+
+        for source in sources {
+            let eager = OsoReader::new().parse_string(source).unwrap();
+            let lazy = OsoReader::with_config(OsoReaderConfig::new().lazy_metadata(true))
+                .parse_string(source)
+                .unwrap();
+
+            assert_eq!(lazy.metadata(), eager.metadata());
+            assert_eq!(lazy.param_count(), eager.param_count());
+            for (lazy, eager) in lazy.params().iter().zip(eager.params()) {
+                assert_eq!(lazy.metadata(), eager.metadata());
+                assert_eq!(lazy, eager);
+            }
+
+            // Serializing parses the hints.
+            #[cfg(feature = "json")]
+            assert_eq!(
+                serde_json::to_string(&lazy).unwrap(),
+                serde_json::to_string(&eager).unwrap()
+            );
+        }
+
+        // Parsing on access needs no mutable borrow, and happens only once.
+        let query = OsoReader::with_config(OsoReaderConfig::new().lazy_metadata(true))
+            .parse_string(sources[5])
+            .unwrap();
+        let kd = query.param_by_name("Kd").unwrap();
+        assert_eq!(kd.metadata().len(), 3);
+        assert!(std::ptr::eq(kd.metadata(), kd.metadata()));
+        assert_eq!(
+            query
+                .param_by_name("coating_on")
+                .unwrap()
+                .find_metadata("label")
+                .map(|m| &m.value),
+            Some(&MetadataValue::String("On {x}".into()))
+        );
+
+        let mut query = query.clone();
+        let kd = query.param_by_name_mut("Kd").unwrap();
+        kd.metadata_mut().pop();
+        assert_eq!(kd.metadata().len(), 2);
+    }
+
+    #[test]
+    fn test_defaults_after_hints() {
+        use crate::types::{MetadataValue, TypedParameter};
+
+        let query = OsoReader::new()
+            .parse_string(
+                "OpenShadingLanguage 1.12\n\
+                 surface test\n\
+                 param\tfloat[3]\tweights\t0.25 %meta{string,label,\"Weights\"} 0.5 0.75\n\
+                 code ___main___\n",
+            )
+            .unwrap();
+
+        let weights = query.param_by_name("weights").unwrap();
+        assert_eq!(
+            weights.typed_param(),
+            &TypedParameter::FloatArray {
+                size: 3,
+                default: Some(vec![0.25, 0.5, 0.75]),
+            }
+        );
+        assert_eq!(
+            weights.find_metadata("label").unwrap().value,
+            MetadataValue::String("Weights".into())
+        );
+    }
+
+    #[test]
+    fn test_multiline_defaults() {
+        use crate::types::TypedParameter;
+
+        let oso_content = "OpenShadingLanguage 1.12\n\
+                           surface wrapped\n\
+                           param\tfloat[6]\tweights\t1 2 3\n\
+                           \t4 5 6\t%meta{string,label,\"Weights\"}\n\
+                           param\tfloat\tKd\t0.5\n\
+                           code ___main___\n";
+
+        let query = OsoReader::with_config(OsoReaderConfig::new().multiline_defaults(true))
+            .parse_string(oso_content)
+            .unwrap();
+        let weights = query.param_by_name("weights").unwrap();
+        assert_eq!(
+            weights.typed_param(),
+            &TypedParameter::FloatArray {
+                size: 6,
+                default: Some(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]),
+            }
+        );
+        assert!(weights.find_metadata("label").is_some());
+        assert_eq!(query.param_count(), 2);
+
+        // Off by default: the continuation line is ignored.
+        let query = OsoReader::new().parse_string(oso_content).unwrap();
+        assert_eq!(
+            query.param_by_name("weights").unwrap().typed_param(),
+            &TypedParameter::FloatArray {
+                size: 6,
+                default: Some(vec![1.0, 2.0, 3.0]),
+            }
+        );
+    }
+
+    #[test]
+    fn test_format_identifier() {
+        let query = OsoReader::new()
+            .parse_string("OpenShadingLanguage 1.12\nsurface simple\ncode ___main___\n")
+            .unwrap();
+        assert_eq!(query.format_identifier(), "OpenShadingLanguage");
+
+        assert_eq!(
+            OsoReader::new().parse_string("OpenShadingLanguage 0.9\nsurface simple\n"),
+            Err(ParseError::UnsupportedVersion { major: 0, minor: 9 })
+        );
+
+        // Other formats are not subject to the OSL version check.
+        let query = OsoReader::new()
+            .parse_string("CustomShadingFormat 0.9\nsurface simple\ncode ___main___\n")
+            .unwrap();
+        assert_eq!(query.format_identifier(), "CustomShadingFormat");
+        assert_eq!(query.shader_name(), "simple");
+    }
+
+    #[test]
+    fn test_closure_defaults() {
+        let source = "OpenShadingLanguage 1.12\n\
+                      surface test\n\
+                      param\tfloat\tKd\t0.5\n\
+                      oparam\tfloat\tx\t0.5\n\
+                      oparam\tclosure color\tbsdf\t0.5 1\t%read{0,0}\n\
+                      code ___main___\n";
+
+        // Defaults of other outputs are their initial values.
+        let query = OsoReader::new()
+            .parse_string(&source.replace("0.5 1\t", ""))
+            .unwrap();
+        assert!(query.warnings().is_empty());
+
+        let query = OsoReader::new().parse_string(source).unwrap();
+        assert_eq!(
+            query.warnings(),
+            ["Closure parameter bsdf has default values on line 5; ignoring them"]
+        );
+        assert!(query.param_by_name("bsdf").unwrap().is_closure());
+
+        let error = OsoReader::with_config(OsoReaderConfig::new().strict_defaults(true))
+            .parse_string(source)
+            .unwrap_err();
+        assert_eq!(
+            error,
+            ParseError::ParseError {
+                line: 5,
+                message: "Closure parameter bsdf has default values".to_string(),
+                token_info: Some(("0.5".to_string(), source.find("0.5 1").unwrap())),
+            }
+        );
+        assert_eq!(error.line_column(source), Some((5, 27)));
+    }
+
+    #[test]
+    fn test_version_range() {
+        let source = |version: &str| format!("OpenShadingLanguage {version}\nsurface simple\n");
+
+        // Too old is always an error.
+        for config in [
+            OsoReaderConfig::new(),
+            OsoReaderConfig::new().strict_version(true),
+        ] {
+            assert_eq!(
+                OsoReader::with_config(config).parse_string(&source("0.5")),
+                Err(ParseError::UnsupportedVersion { major: 0, minor: 5 })
+            );
+        }
+
+        // Known versions parse without warnings.
+        for version in ["1.00", "1.11"] {
+            let query = OsoReader::new().parse_string(&source(version)).unwrap();
+            assert!(query.warnings().is_empty());
+        }
+
+        // Far-future versions parse with a warning, unless strict.
+        let query = OsoReader::new().parse_string(&source("9.02")).unwrap();
+        assert_eq!(query.shader_name(), "simple");
+        assert_eq!(query.warnings().len(), 1);
+        assert!(query.warnings()[0].contains("9.02"));
+
+        assert_eq!(
+            OsoReader::with_config(OsoReaderConfig::new().strict_version(true))
+                .parse_string(&source("9.02")),
+            Err(ParseError::UnsupportedVersion { major: 9, minor: 2 })
+        );
+
+        // The newest known version is configurable.
+        let config = OsoReaderConfig::new()
+            .max_version(1, 10)
+            .strict_version(true);
+        assert!(
+            OsoReader::with_config(config)
+                .parse_string(&source("1.10"))
+                .is_ok()
+        );
+        assert!(
+            OsoReader::with_config(config)
+                .parse_string(&source("1.11"))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_intern_strings() {
+        use crate::types::{MetadataValue, StringValue, TypedParameter};
+
+        let oso_content = r#"
+OpenShadingLanguage 1.12
+surface test
+param	string	texture	"none"	%meta{string,page,"Texture"}
+param	string	mask	"none"	%meta{string,page,"Texture"}
+param	string[2]	layers	"none" "base"
+code ___main___
+"#;
+
+        let page = |query: &OslQuery, name: &str| -> StringValue {
+            match &query.param_by_name(name).unwrap().metadata()[0].value {
+                MetadataValue::String(s) => s.clone(),
+                other => panic!("unexpected metadata {:?}", other),
+            }
+        };
+        let default = |query: &OslQuery, name: &str| -> StringValue {
+            match query.param_by_name(name).unwrap().typed_param() {
+                TypedParameter::String { default: Some(s) } => s.clone(),
+                other => panic!("unexpected parameter {:?}", other),
+            }
+        };
+
+        // Interned by default
+        let query = OsoReader::new().parse_string(oso_content).unwrap();
+        let (a, b) = (page(&query, "texture"), page(&query, "mask"));
+        assert!(a.is_interned());
+        assert_eq!(a, "Texture");
+        assert_eq!(a.as_ptr(), b.as_ptr());
+        let (a, b) = (default(&query, "texture"), default(&query, "mask"));
+        assert_eq!(a.as_ptr(), b.as_ptr());
+
+        // Arrays too
+        let layers = query.param_by_name("layers").unwrap();
+        let TypedParameter::StringArray {
+            default: Some(defaults),
+            ..
+        } = layers.typed_param()
+        else {
+            panic!("unexpected parameter {:?}", layers);
+        };
+        assert!(defaults.iter().all(StringValue::is_interned));
+        assert_eq!(defaults[0].as_ptr(), a.as_ptr());
+        let mut options = MetadataValue::StringArray(vec!["none".into(), "base".into()]);
+        options.intern_strings();
+        let MetadataValue::StringArray(options) = options else {
+            unreachable!()
+        };
+        assert_eq!(&options, defaults);
+        assert_eq!(options[1].as_ptr(), defaults[1].as_ptr());
+
+        // Lazily read metadata is interned when parsed
+        let config = OsoReaderConfig::new().lazy_metadata(true);
+        let query = OsoReader::with_config(config)
+            .parse_string(oso_content)
+            .unwrap();
+        let (a, b) = (page(&query, "texture"), page(&query, "mask"));
+        assert!(a.is_interned());
+        assert_eq!(a.as_ptr(), b.as_ptr());
+
+        // Owned if turned off
+        let config = OsoReaderConfig::new().intern_strings(false);
+        let query = OsoReader::with_config(config)
+            .parse_string(oso_content)
+            .unwrap();
+        let (a, b) = (page(&query, "texture"), page(&query, "mask"));
+        assert!(!a.is_interned());
+        assert_eq!(a, b);
+        assert_ne!(a.as_ptr(), b.as_ptr());
+
+        // Interned values can still be mutated
+        let mut value = default(&query, "texture");
+        value.intern();
+        value.to_mut().push_str("_2");
+        assert!(!value.is_interned());
+        assert_eq!(value, "none_2");
+    }
 }
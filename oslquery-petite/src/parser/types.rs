@@ -5,6 +5,8 @@
 
 use ustr::Ustr;
 
+use super::oso::DefaultValue;
+
 /// Base type enumeration matching OSL's type system.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BaseType {
@@ -29,6 +31,30 @@ impl BaseType {
         }
     }
 
+    /// Whether defaults of this type are stored as floats: `float` and the
+    /// aggregates built from floats.
+    pub fn is_float_backed(&self) -> bool {
+        matches!(
+            self,
+            BaseType::Float
+                | BaseType::Color
+                | BaseType::Point
+                | BaseType::Vector
+                | BaseType::Normal
+                | BaseType::Matrix
+        )
+    }
+
+    /// Whether defaults of this type are stored as integers.
+    pub fn is_int_backed(&self) -> bool {
+        matches!(self, BaseType::Int)
+    }
+
+    /// Whether defaults of this type are stored as strings.
+    pub fn is_string_backed(&self) -> bool {
+        matches!(self, BaseType::String)
+    }
+
     /// Convert to string representation
     pub fn as_str(&self) -> &'static str {
         match self {
@@ -137,6 +163,10 @@ impl TypeSpec {
 }
 
 /// Intermediate parameter structure for parsing.
+///
+/// Defaults are kept as read, in `idefault`, `fdefault` or `sdefault`
+/// depending on the base type, before they are checked against the type.
+/// [`parse_raw`](super::parse_raw) returns these alongside the query.
 #[derive(Debug, Clone)]
 pub struct ParsedParameter {
     pub name: Ustr,
@@ -150,10 +180,14 @@ pub struct ParsedParameter {
     pub fdefault: Vec<f32>,
     pub sdefault: Vec<String>,
 
-    pub spacename: Vec<String>,
+    pub spacename: Vec<Ustr>,
     pub structname: Option<Ustr>,
     pub fields: Vec<Ustr>,
+    /// The struct parameter this is a field of, from `%mystruct{...}`
+    pub mystruct: Option<Ustr>,
     pub metadata: Vec<ParsedParameter>,
+    /// Newline-separated `%meta{...}` hints, when metadata is read lazily
+    pub raw_metadata: String,
 }
 
 impl ParsedParameter {
@@ -171,11 +205,79 @@ impl ParsedParameter {
             spacename: Vec::new(),
             structname: None,
             fields: Vec::new(),
+            mystruct: None,
             metadata: Vec::new(),
+            raw_metadata: String::new(),
         }
     }
 
     pub fn find_metadata(&self, name: &str) -> Option<&ParsedParameter> {
         self.metadata.iter().find(|m| m.name.as_str() == name)
     }
+
+    /// Append a default value, storing integers as floats for float-based
+    /// types (float, color, point, vector, normal, matrix).
+    pub(crate) fn push_default(&mut self, value: DefaultValue) {
+        match value {
+            DefaultValue::Int(i) if self.type_desc.basetype.is_float_backed() => {
+                self.fdefault.push(i as f32)
+            }
+            DefaultValue::Int(i) => self.idefault.push(i),
+            DefaultValue::Float(f) => self.fdefault.push(f),
+            DefaultValue::String(s) => self.sdefault.push(s),
+        }
+        self.valid_default = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [BaseType; 9] = [
+        BaseType::None,
+        BaseType::Int,
+        BaseType::Float,
+        BaseType::String,
+        BaseType::Color,
+        BaseType::Point,
+        BaseType::Vector,
+        BaseType::Normal,
+        BaseType::Matrix,
+    ];
+
+    #[test]
+    fn test_backing() {
+        for basetype in ALL {
+            let backings = [
+                basetype.is_float_backed(),
+                basetype.is_int_backed(),
+                basetype.is_string_backed(),
+            ];
+            // Only `None` has no defaults.
+            let expected = usize::from(basetype != BaseType::None);
+            assert_eq!(
+                backings.iter().filter(|&&b| b).count(),
+                expected,
+                "{:?}",
+                basetype
+            );
+
+            // Only floats make up aggregates.
+            if basetype.components() > 1 {
+                assert!(basetype.is_float_backed(), "{:?}", basetype);
+            }
+        }
+
+        let float_backed: Vec<&str> = ALL
+            .iter()
+            .filter(|basetype| basetype.is_float_backed())
+            .map(BaseType::as_str)
+            .collect();
+        assert_eq!(
+            float_backed,
+            ["float", "color", "point", "vector", "normal", "matrix"]
+        );
+        assert!(BaseType::Int.is_int_backed() && BaseType::String.is_string_backed());
+    }
 }
@@ -1,11 +1,28 @@
 //! Query API using the fully type-safe parameter system.
 
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::Path;
 
+use ustr::Ustr;
+
+use crate::group::ShaderGroup;
 use crate::parser::ParseError;
-use crate::types::{Metadata, Parameter};
+use crate::searchpath::SearchPaths;
+use crate::source::ShaderSource;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use crate::source::StdFs;
+use crate::trace;
+use crate::types::{
+    CollapsedParam, Metadata, Parameter, ParameterDirection, ParameterKind, StructDecl,
+    StructParam, TypeFilter,
+};
 
 /// Main structure for querying OSL shader information.
+///
+/// A query is not changed after parsing, except through the `_mut`
+/// accessors. To use one from several threads, share it in an `Arc` rather
+/// than cloning it; [`QueryCache`](crate::QueryCache) hands out queries that
+/// way.
 #[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct OslQuery {
@@ -13,10 +30,37 @@ pub struct OslQuery {
     shader_name: String,
     /// Shader type (surface, displacement, volume, etc.)
     shader_type: String,
+    /// Keyword of the version directive, e.g. `OpenShadingLanguage`
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "String::is_empty")
+    )]
+    format_identifier: String,
     /// List of shader parameters
     parameters: Vec<Parameter>,
+    /// Struct parameters, whose fields are in `parameters`
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    structs: Vec<StructDecl>,
     /// Global shader metadata
     metadata: Vec<Metadata>,
+    /// Line range `[start, end)` of the code section in the source
+    #[cfg_attr(feature = "serde", serde(skip))]
+    code_section: Option<(usize, usize)>,
+    /// Problems that did not stop parsing
+    #[cfg_attr(feature = "serde", serde(skip))]
+    warnings: Vec<String>,
+    /// The files the shader was found in when opened, the parsed one first
+    #[cfg_attr(feature = "serde", serde(skip))]
+    resolution_candidates: Vec<std::path::PathBuf>,
+    /// Layers and connections if the source was a shader group
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    group: Option<ShaderGroup>,
 }
 
 impl OslQuery {
@@ -25,57 +69,189 @@ impl OslQuery {
         OslQuery {
             shader_name: String::new(),
             shader_type: String::new(),
+            format_identifier: String::new(),
             parameters: Vec::new(),
+            structs: Vec::new(),
             metadata: Vec::new(),
+            code_section: None,
+            warnings: Vec::new(),
+            resolution_candidates: Vec::new(),
+            group: None,
+        }
+    }
+
+    /// Create a query describing a shader group.
+    ///
+    /// The shader type is `shadergroup` and the shader name is the name of
+    /// the root (last) layer.
+    pub fn from_group(group: ShaderGroup) -> Self {
+        OslQuery {
+            shader_name: group.root().map(|l| l.layer.clone()).unwrap_or_default(),
+            shader_type: "shadergroup".to_string(),
+            group: Some(group),
+            ..Self::new()
         }
     }
 
+    /// Parse a shader group with [`parse_group`](crate::parse_group) and
+    /// describe it as a query, see [`from_group`](Self::from_group).
+    pub fn from_group_string(content: &str) -> Result<Self, ParseError> {
+        crate::group::parse_group(content).map(Self::from_group)
+    }
+
+    /// Read a shader group file, e.g. an `.oslgroup`, like
+    /// [`from_group_string`](Self::from_group_string).
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn open_group<P: AsRef<Path>>(path: P) -> Result<Self, ParseError> {
+        Self::from_group_string(&std::fs::read_to_string(path)?)
+    }
+
     /// Open and parse an OSO file from disk.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ParseError> {
-        Self::open_with_searchpath(path, "")
+        Self::open_with_search_paths(path, &SearchPaths::default())
     }
 
     /// Open and parse an OSO file with search path support.
+    ///
+    /// See [`SearchPaths`] for the syntax of `searchpath`. If it is empty,
+    /// the search path is taken from the environment.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
     pub fn open_with_searchpath<P: AsRef<Path>>(
         path: P,
         searchpath: &str,
     ) -> Result<Self, ParseError> {
-        let path = path.as_ref();
+        Self::open_with_search_paths(path, &SearchPaths::parse_or_env(searchpath))
+    }
 
-        // Check if file has .oso extension
-        if path.extension().and_then(|s| s.to_str()) != Some("oso") {
-            // Append .oso extension
-            let mut path_with_ext = path.to_path_buf();
-            path_with_ext.set_extension("oso");
+    /// Open and parse an OSO file, looking for it in `search_paths` if it is
+    /// not found directly.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn open_with_search_paths<P: AsRef<Path>>(
+        path: P,
+        search_paths: &SearchPaths,
+    ) -> Result<Self, ParseError> {
+        Self::resolve_and_open(path.as_ref(), search_paths).1
+    }
 
-            if path_with_ext.exists() {
-                return crate::parser::OsoReader::new().parse_file(path_with_ext);
-            }
+    /// Open and parse the shader called `name`, resolving it the way
+    /// renderers resolve shader names.
+    ///
+    /// The directories of `search_paths` are tried in order. In each, the
+    /// file `name` with `.oso` appended is tried first, then `name` exactly.
+    /// A name already ending in `.oso` is only tried exactly. The first file
+    /// found is parsed, so a shader shadows those of the same name in later
+    /// directories. Unlike [`open_with_search_paths`](Self::open_with_search_paths),
+    /// neither the current directory nor the environment is looked at.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ParseError::Io`] if `name` is empty or contains a `/` or `\`,
+    /// or if no file is found, listing every path tried.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn open_by_name(name: &str, search_paths: &SearchPaths) -> Result<Self, ParseError> {
+        trace::enter_span!("open_by_name", name);
+        let candidates = resolve_name(name, search_paths, &StdFs)?;
+        let mut query = crate::parser::OsoReader::new().parse_file(&candidates[0])?;
+        if search_paths.records_candidates() {
+            query.resolution_candidates = candidates;
         }
+        Ok(query)
+    }
 
-        // Try direct path first
-        if path.exists() {
-            return crate::parser::OsoReader::new().parse_file(path);
-        }
+    /// Find the file `path` refers to and parse it, returning the file found
+    /// along with the result.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub(crate) fn resolve_and_open(
+        path: &Path,
+        search_paths: &SearchPaths,
+    ) -> (Option<std::path::PathBuf>, Result<Self, ParseError>) {
+        Self::resolve_and_open_with_source(path, search_paths, &StdFs)
+    }
 
-        // Try searchpath if provided
-        if !searchpath.is_empty() {
-            for search_dir in searchpath.split(':') {
-                let search_path = Path::new(search_dir).join(path);
-                if search_path.exists() {
-                    return crate::parser::OsoReader::new().parse_file(search_path);
-                }
+    /// Like [`resolve_and_open`](Self::resolve_and_open), reading from
+    /// `source`.
+    pub(crate) fn resolve_and_open_with_source(
+        path: &Path,
+        search_paths: &SearchPaths,
+        source: &dyn ShaderSource,
+    ) -> (Option<std::path::PathBuf>, Result<Self, ParseError>) {
+        trace::enter_span!("resolve", path = %path.display());
 
-                // Also try with .oso extension
-                let mut search_path_with_ext = search_path.clone();
-                search_path_with_ext.set_extension("oso");
-                if search_path_with_ext.exists() {
-                    return crate::parser::OsoReader::new().parse_file(search_path_with_ext);
-                }
+        if let Some(found) = resolve_in(path, search_paths, source) {
+            let mut result = crate::parser::OsoReader::new().parse_source(&found, source);
+            if let Ok(query) = &mut result
+                && search_paths.records_candidates()
+            {
+                let shadowed = search_paths
+                    .resolve_all_in(path, source)
+                    .into_iter()
+                    .filter(|candidate| *candidate != found);
+                query.resolution_candidates =
+                    std::iter::once(found.clone()).chain(shadowed).collect();
             }
+            return (Some(found), result);
         }
 
-        Err(ParseError::Io(format!("Shader file not found: {:?}", path)))
+        trace::error!(path = %path.display(), "shader file not found");
+        (
+            None,
+            Err(ParseError::Io(format!("Shader file not found: {:?}", path))),
+        )
+    }
+
+    /// Open and parse several shaders by name, e.g. the nodes of a shader
+    /// graph.
+    ///
+    /// Each name is tried as a path, with and without an `.oso` extension.
+    /// The results are in the order of `names`. With the `rayon` feature,
+    /// the shaders are parsed in parallel.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn open_multiple(names: &[&str]) -> Vec<Result<Self, ParseError>> {
+        Self::open_multiple_with_search_paths(names, &SearchPaths::default())
+    }
+
+    /// Like [`open_multiple`](Self::open_multiple), also looking in the
+    /// default search path from the environment.
+    ///
+    /// See [`SearchPaths::from_env`].
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn open_multiple_with_environment(names: &[&str]) -> Vec<Result<Self, ParseError>> {
+        Self::open_multiple_with_search_paths(names, &SearchPaths::from_env())
+    }
+
+    /// Like [`open_multiple`](Self::open_multiple), also looking in
+    /// `searchpath`, which is parsed once for all names.
+    ///
+    /// See [`open_with_searchpath`](Self::open_with_searchpath).
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn open_multiple_from_searchpath(
+        names: &[&str],
+        searchpath: &str,
+    ) -> Vec<Result<Self, ParseError>> {
+        Self::open_multiple_with_search_paths(names, &SearchPaths::parse_or_env(searchpath))
+    }
+
+    /// Like [`open_multiple`](Self::open_multiple), also looking in
+    /// `search_paths`.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn open_multiple_with_search_paths(
+        names: &[&str],
+        search_paths: &SearchPaths,
+    ) -> Vec<Result<Self, ParseError>> {
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+            names
+                .par_iter()
+                .map(|name| Self::open_with_search_paths(name, search_paths))
+                .collect()
+        }
+        #[cfg(not(feature = "rayon"))]
+        names
+            .iter()
+            .map(|name| Self::open_with_search_paths(name, search_paths))
+            .collect()
     }
 
     /// Parse OSO content from a string.
@@ -83,6 +259,13 @@ impl OslQuery {
         crate::parser::OsoReader::new().parse_string(content)
     }
 
+    /// Parse OSO content from UTF-8 bytes, e.g. a file read by the caller.
+    pub fn from_bytes(content: &[u8]) -> Result<Self, ParseError> {
+        let content = std::str::from_utf8(content)
+            .map_err(|e| ParseError::InvalidFormat(format!("Not valid UTF-8: {}", e)))?;
+        Self::from_string(content)
+    }
+
     // Internal methods for the parser
 
     pub(crate) fn set_shader_info(&mut self, shader_type: &str, shader_name: String) {
@@ -90,14 +273,44 @@ impl OslQuery {
         self.shader_name = shader_name;
     }
 
+    pub(crate) fn set_format_identifier(&mut self, identifier: &str) {
+        self.format_identifier = identifier.to_string();
+    }
+
     pub(crate) fn add_parameter(&mut self, param: Parameter) {
         self.parameters.push(param);
     }
 
+    pub(crate) fn add_struct(&mut self, decl: StructDecl) {
+        self.structs.push(decl);
+    }
+
     pub(crate) fn add_metadata(&mut self, meta: Metadata) {
         self.metadata.push(meta);
     }
 
+    pub(crate) fn add_warning(&mut self, warning: String) {
+        trace::debug!(%warning, "warning");
+        self.warnings.push(warning);
+    }
+
+    pub(crate) fn set_code_section(&mut self, start: usize, end: usize) {
+        self.code_section = Some((start, end));
+    }
+
+    /// Sort the shader and parameter metadata by name, keeping the order of
+    /// entries with the same name.
+    #[cfg(feature = "json")]
+    pub(crate) fn sort_metadata(&mut self) {
+        self.metadata
+            .sort_by(|a, b| a.name.as_str().cmp(b.name.as_str()));
+        for param in &mut self.parameters {
+            param
+                .metadata_mut()
+                .sort_by(|a, b| a.name.as_str().cmp(b.name.as_str()));
+        }
+    }
+
     /// Get the shader name.
     pub fn shader_name(&self) -> &str {
         &self.shader_name
@@ -108,6 +321,14 @@ impl OslQuery {
         &self.shader_type
     }
 
+    /// Get the keyword of the file's version directive, e.g.
+    /// `OpenShadingLanguage`.
+    ///
+    /// Empty if the source had no version directive, as for shader groups.
+    pub fn format_identifier(&self) -> &str {
+        &self.format_identifier
+    }
+
     /// Get the number of parameters.
     pub fn param_count(&self) -> usize {
         self.parameters.len()
@@ -123,11 +344,78 @@ impl OslQuery {
         self.parameters.iter().find(|p| p.name.as_str() == name)
     }
 
+    #[cfg(any(feature = "json", test))]
+    pub(crate) fn param_by_name_mut(&mut self, name: &str) -> Option<&mut Parameter> {
+        self.parameters.iter_mut().find(|p| p.name.as_str() == name)
+    }
+
     /// Get all parameters.
     pub fn params(&self) -> &[Parameter] {
         &self.parameters
     }
 
+    /// The files the shader was found in when it was opened with
+    /// [`SearchPaths::record_candidates`] set, in priority order: the parsed
+    /// file first, then the ones it shadows.
+    ///
+    /// Empty otherwise, and for shaders not opened from a file.
+    pub fn resolution_candidates(&self) -> &[std::path::PathBuf] {
+        &self.resolution_candidates
+    }
+
+    /// Problems found while parsing that did not make it fail, e.g. a file
+    /// version newer than this parser knows.
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// Get the struct parameters.
+    ///
+    /// Their fields are regular parameters in [`params`](Self::params).
+    pub fn structs(&self) -> &[StructDecl] {
+        &self.structs
+    }
+
+    /// Find the struct parameter `param` is a field of, see
+    /// [`Parameter::struct_parent`].
+    ///
+    /// `None` if `param` is not a struct field, or the file did not declare
+    /// the struct parameter with a `%struct` hint.
+    pub fn struct_of(&self, param: &Parameter) -> Option<&StructDecl> {
+        let parent = param.struct_parent()?;
+        self.structs.iter().find(|decl| decl.name == parent)
+    }
+
+    /// Get the parameters with the fields of each struct parameter collapsed
+    /// into a single entry.
+    ///
+    /// Struct entries take the position of their first field. Other
+    /// parameters are returned as they are in [`params`](Self::params).
+    pub fn params_collapsed(&self) -> Vec<CollapsedParam<'_>> {
+        let mut collapsed = Vec::with_capacity(self.parameters.len());
+        let mut seen = vec![false; self.structs.len()];
+
+        for param in &self.parameters {
+            match self.structs.iter().position(|decl| decl.is_member(param)) {
+                Some(index) if !seen[index] => {
+                    seen[index] = true;
+                    let decl = &self.structs[index];
+                    collapsed.push(CollapsedParam::Struct(StructParam {
+                        decl,
+                        fields: self
+                            .parameters
+                            .iter()
+                            .filter(|param| decl.is_member(param))
+                            .collect(),
+                    }));
+                }
+                Some(_) => {}
+                None => collapsed.push(CollapsedParam::Scalar(param)),
+            }
+        }
+        collapsed
+    }
+
     /// Get input parameters only.
     pub fn input_params(&self) -> impl Iterator<Item = &Parameter> {
         self.parameters.iter().filter(|p| !p.is_output())
@@ -138,6 +426,205 @@ impl OslQuery {
         self.parameters.iter().filter(|p| p.is_output())
     }
 
+    /// Get the parameters of the type and direction given, in declaration
+    /// order.
+    pub fn params_of_type(
+        &self,
+        filter: TypeFilter,
+        direction: ParameterDirection,
+    ) -> impl Iterator<Item = &Parameter> {
+        self.parameters
+            .iter()
+            .filter(move |p| direction.matches(p) && filter.matches(p.typed_param()))
+    }
+
+    /// Get the `index`-th parameter of the type and direction given, e.g.
+    /// the second `float` input or the first `color` output.
+    pub fn find_param_by_type_at_index(
+        &self,
+        filter: TypeFilter,
+        direction: ParameterDirection,
+        index: usize,
+    ) -> Option<&Parameter> {
+        self.params_of_type(filter, direction).nth(index)
+    }
+
+    /// Count the parameters of the type and direction given.
+    pub fn count_params_of_type(&self, filter: TypeFilter, direction: ParameterDirection) -> usize {
+        self.params_of_type(filter, direction).count()
+    }
+
+    /// Count the parameters per
+    /// [`TypedParameter::type_name`](crate::types::TypedParameter::type_name),
+    /// e.g. for estimating the cost of binding them. The same as
+    /// [`type_histogram`](Self::type_histogram).
+    pub fn parameter_count_by_type(&self) -> BTreeMap<&'static str, usize> {
+        self.type_histogram()
+    }
+
+    /// Like [`parameter_count_by_type`](Self::parameter_count_by_type), for
+    /// the input parameters only.
+    pub fn input_parameter_count_by_type(&self) -> BTreeMap<&'static str, usize> {
+        count_by_type(self.input_params())
+    }
+
+    /// Like [`parameter_count_by_type`](Self::parameter_count_by_type), for
+    /// the output parameters only.
+    pub fn output_parameter_count_by_type(&self) -> BTreeMap<&'static str, usize> {
+        count_by_type(self.output_params())
+    }
+
+    /// Count the floats of all float-based input parameters, i.e.
+    /// [`component_count`](crate::types::TypedParameter::component_count)
+    /// times the number of array elements, e.g. to estimate the size of a
+    /// shader's data block.
+    ///
+    /// Dynamic arrays count with the length of their default, or not at all
+    /// without one.
+    pub fn total_float_component_count(&self) -> usize {
+        self.input_params()
+            .map(Parameter::typed_param)
+            .filter(|typed| typed.is_float_type())
+            .map(|typed| {
+                let elements = if typed.is_array() {
+                    typed.resolved_length().unwrap_or(0)
+                } else {
+                    1
+                };
+                typed.component_count() * elements
+            })
+            .sum()
+    }
+
+    /// Concatenate the float defaults of all parameters into one buffer,
+    /// e.g. to upload them in one copy, with the range of each parameter's
+    /// floats in it.
+    ///
+    /// Floats are laid out as by
+    /// [`TypedParameter::default_floats`](crate::types::TypedParameter::default_floats),
+    /// parameters in declaration order. Parameters without float defaults
+    /// take no space and are listed with an empty range.
+    pub fn pack_float_defaults(&self) -> (Vec<f32>, Vec<(Ustr, std::ops::Range<usize>)>) {
+        let mut buffer = Vec::new();
+        let ranges = self
+            .parameters
+            .iter()
+            .map(|param| {
+                let start = buffer.len();
+                buffer.extend_from_slice(param.typed_param().default_floats().unwrap_or_default());
+                (param.name, start..buffer.len())
+            })
+            .collect();
+        (buffer, ranges)
+    }
+
+    /// Get the parameters sorted by name.
+    pub fn params_sorted_by_name(&self) -> Vec<&Parameter> {
+        let mut params: Vec<&Parameter> = self.parameters.iter().collect();
+        params.sort_by(|a, b| a.name.as_str().cmp(b.name.as_str()));
+        params
+    }
+
+    /// Get the parameters sorted by
+    /// [`TypedParameter::type_name`](crate::types::TypedParameter::type_name),
+    /// then by name.
+    pub fn params_sorted_by_type(&self) -> Vec<&Parameter> {
+        let mut params: Vec<&Parameter> = self.parameters.iter().collect();
+        params.sort_by(|a, b| {
+            (a.typed_param().type_name(), a.name.as_str())
+                .cmp(&(b.typed_param().type_name(), b.name.as_str()))
+        });
+        params
+    }
+
+    /// Get the parameters sorted by [`Parameter::ui_page`], then by name.
+    /// Parameters without a page come first.
+    pub fn params_sorted_by_page_and_name(&self) -> Vec<&Parameter> {
+        let mut params: Vec<&Parameter> = self.parameters.iter().collect();
+        params.sort_by(|a, b| {
+            (a.ui_page().unwrap_or(""), a.name.as_str())
+                .cmp(&(b.ui_page().unwrap_or(""), b.name.as_str()))
+        });
+        params
+    }
+
+    /// Get the parameters that are global symbols, see
+    /// [`Parameter::is_global_symbol`].
+    pub fn global_symbols(&self) -> impl Iterator<Item = &Parameter> {
+        self.parameters.iter().filter(|p| p.is_global_symbol())
+    }
+
+    /// Group the closure parameters by closure type.
+    pub fn group_by_closure_type(&self) -> HashMap<Ustr, Vec<&Parameter>> {
+        let mut groups: HashMap<Ustr, Vec<&Parameter>> = HashMap::new();
+        for param in &self.parameters {
+            if let Some(closure_type) = param.typed_param().closure_type() {
+                groups.entry(closure_type).or_default().push(param);
+            }
+        }
+        groups
+    }
+
+    /// Get the closure types of the closure parameters, without duplicates,
+    /// in the order they first appear.
+    pub fn closure_types(&self) -> impl Iterator<Item = Ustr> + '_ {
+        let mut seen = HashSet::new();
+        self.parameters
+            .iter()
+            .filter_map(|p| p.typed_param().closure_type())
+            .filter(move |closure_type| seen.insert(*closure_type))
+    }
+
+    /// Get the closure parameters of type `closure_type`.
+    pub fn closures_of_type<'a>(
+        &'a self,
+        closure_type: &'a str,
+    ) -> impl Iterator<Item = &'a Parameter> {
+        self.parameters.iter().filter(move |p| {
+            p.typed_param()
+                .closure_type()
+                .is_some_and(|t| t == closure_type)
+        })
+    }
+
+    /// Count parameters per [`TypedParameter::type_name`](crate::types::TypedParameter::type_name).
+    ///
+    /// Fixed-size and dynamic arrays of the same element type are counted
+    /// together (e.g. under `float[]`).
+    pub fn type_histogram(&self) -> BTreeMap<&'static str, usize> {
+        count_by_type(self.parameters.iter())
+    }
+
+    /// The types and names of all parameters, without their defaults, e.g.
+    /// `"float Kd; color[3] baseColor; output color result"`.
+    ///
+    /// Types are written as by
+    /// [`TypedParameter::oslquery_typestring`](crate::types::TypedParameter::oslquery_typestring).
+    /// Two shaders with the same signature have the same interface, so it
+    /// can serve as a cache key that survives changes to defaults.
+    pub fn parameter_type_signature(&self) -> String {
+        self.parameters
+            .iter()
+            .map(|param| {
+                format!(
+                    "{}{} {}",
+                    if param.is_output() { "output " } else { "" },
+                    param.typed_param().oslquery_typestring(),
+                    param.name
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+
+    /// A hash of [`parameter_type_signature`](Self::parameter_type_signature).
+    ///
+    /// Uses FNV-1a, so the value is the same across runs, platforms and Rust
+    /// versions and can be stored.
+    pub fn type_signature_hash(&self) -> u64 {
+        fnv1a(self.parameter_type_signature().as_bytes())
+    }
+
     /// Get global metadata.
     pub fn metadata(&self) -> &[Metadata] {
         &self.metadata
@@ -148,22 +635,418 @@ impl OslQuery {
         self.metadata.iter().find(|m| m.name.as_str() == name)
     }
 
+    /// Get the line range `[start, end)` of the code section in the source.
+    ///
+    /// Line numbers are 1-based. Returns `None` if the source had no code
+    /// section.
+    pub fn code_section_line_range(&self) -> Option<(usize, usize)> {
+        self.code_section
+    }
+
+    /// Intern all string defaults and string metadata values.
+    ///
+    /// See [`OsoReaderConfig::intern_strings`](crate::parser::OsoReaderConfig::intern_strings).
+    pub fn intern_strings(&mut self) {
+        for param in &mut self.parameters {
+            param.intern_strings();
+        }
+        for meta in &mut self.metadata {
+            meta.value.intern_strings();
+        }
+    }
+
+    /// Get the spaces of geometric parameters, without duplicates, in the
+    /// order they first appear.
+    pub fn spaces(&self) -> impl Iterator<Item = Ustr> + '_ {
+        let mut seen = HashSet::new();
+        self.parameters
+            .iter()
+            .filter_map(|p| p.typed_param().space())
+            .filter(move |space| seen.insert(*space))
+    }
+
+    /// Get the geometric parameters tagged with `space`.
+    pub fn params_in_space(&self, space: &str) -> Vec<&Parameter> {
+        self.parameters
+            .iter()
+            .filter(|p| p.typed_param().space().is_some_and(|s| s == space))
+            .collect()
+    }
+
+    /// Rename the spaces of geometric parameters according to `map`.
+    ///
+    /// Spaces not in `map` are left alone. Only the labels change; defaults
+    /// are not converted. See [`TypedParameter::with_space`](crate::types::TypedParameter::with_space).
+    pub fn retag_spaces(&mut self, map: &HashMap<Ustr, Ustr>) {
+        for param in &mut self.parameters {
+            let (ParameterKind::Input(typed_param) | ParameterKind::Output(typed_param)) =
+                &mut param.kind;
+            if let Some(space) = typed_param.space_mut()
+                && let Some(new) = space.and_then(|s| map.get(&s))
+            {
+                *space = Some(*new);
+            }
+        }
+    }
+
+    /// Get a copy with only the interface: parameter names, types and
+    /// directions, without any metadata or defaults.
+    ///
+    /// Serialized, this makes a compact shader "header", e.g. for
+    /// documentation.
+    pub fn interface_only(&self) -> OslQuery {
+        let mut query = self.clone();
+        query.metadata.clear();
+        for param in &mut query.parameters {
+            param.clear_metadata();
+            let (ParameterKind::Input(typed_param) | ParameterKind::Output(typed_param)) =
+                &mut param.kind;
+            typed_param.clear_default();
+        }
+        query
+    }
+
+    /// Snap float defaults within `epsilon` of zero to `0.0`, see
+    /// [`TypedParameter::normalize_defaults`].
+    pub fn normalize_defaults(&mut self, epsilon: f32) {
+        for param in &mut self.parameters {
+            let (ParameterKind::Input(typed_param) | ParameterKind::Output(typed_param)) =
+                &mut param.kind;
+            typed_param.normalize_defaults(epsilon);
+        }
+    }
+
+    /// Get a copy normalized for comparison, so two queries of the same
+    /// interface compare equal with `==` whatever the compiler did.
+    ///
+    /// Parameters and struct declarations are sorted by name, shader and
+    /// parameter metadata by name (keeping the order of entries with the
+    /// same name), and empty metadata arrays are dropped. Float defaults
+    /// are normalized with [`normalize_defaults(1e-7)`](Self::normalize_defaults).
+    /// The code section line range and the warnings, which refer to source
+    /// lines, and the resolution candidates are removed.
+    pub fn canonical_form(&self) -> OslQuery {
+        let mut query = self.clone();
+        query.code_section = None;
+        query.warnings.clear();
+        query.resolution_candidates.clear();
+        query.metadata.retain(|meta| !meta.value.is_empty_array());
+        query
+            .metadata
+            .sort_by(|a, b| a.name.as_str().cmp(b.name.as_str()));
+        for param in &mut query.parameters {
+            param.canonicalize(CANONICAL_EPSILON);
+        }
+        query
+            .parameters
+            .sort_by(|a, b| a.name.as_str().cmp(b.name.as_str()));
+        query
+            .structs
+            .sort_by(|a, b| a.name.as_str().cmp(b.name.as_str()));
+        query
+    }
+
+    /// Check if the source was a shader group rather than a leaf shader.
+    pub fn is_group(&self) -> bool {
+        self.group.is_some()
+    }
+
+    /// Get the shader group, if the source was one.
+    pub fn group(&self) -> Option<&ShaderGroup> {
+        self.group.as_ref()
+    }
+
     /// Check if the query is valid (has been successfully parsed).
     pub fn is_valid(&self) -> bool {
         !self.shader_name.is_empty() && !self.shader_type.is_empty()
     }
 }
 
+/// Float defaults closer than this to zero are zero in
+/// [`OslQuery::canonical_form`].
+pub(crate) const CANONICAL_EPSILON: f32 = 1e-7;
+
 impl Default for OslQuery {
     fn default() -> Self {
         Self::new()
     }
 }
 
+/// Find the file `path` refers to: `path` with an `.oso` extension added,
+/// `path` itself, or either in `search_paths`.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub(crate) fn resolve(path: &Path, search_paths: &SearchPaths) -> Option<std::path::PathBuf> {
+    resolve_in(path, search_paths, &StdFs)
+}
+
+/// Like [`resolve`], looking in `source`.
+pub(crate) fn resolve_in(
+    path: &Path,
+    search_paths: &SearchPaths,
+    source: &dyn ShaderSource,
+) -> Option<std::path::PathBuf> {
+    // Check if file has .oso extension
+    if path.extension().and_then(|s| s.to_str()) != Some("oso") {
+        // Append .oso extension
+        let mut path_with_ext = path.to_path_buf();
+        path_with_ext.set_extension("oso");
+
+        trace::debug!(candidate = %path_with_ext.display(), "trying");
+        if source.exists(&path_with_ext) {
+            trace::debug!(found = %path_with_ext.display(), "resolved");
+            return Some(path_with_ext);
+        }
+    }
+
+    // Try direct path first
+    trace::debug!(candidate = %path.display(), "trying");
+    if source.exists(path) {
+        trace::debug!(found = %path.display(), "resolved");
+        return Some(path.to_path_buf());
+    }
+
+    let found = search_paths.find_in(path, source)?;
+    trace::debug!(found = %found.display(), "resolved");
+    Some(found)
+}
+
+fn count_by_type<'a>(params: impl Iterator<Item = &'a Parameter>) -> BTreeMap<&'static str, usize> {
+    let mut counts = BTreeMap::new();
+    for param in params {
+        *counts.entry(param.typed_param().type_name()).or_default() += 1;
+    }
+    counts
+}
+
+/// Find the shader called `name` in `search_paths`, see
+/// [`OslQuery::open_by_name`].
+///
+/// Returns the file to parse, followed by the files it shadows if
+/// `search_paths` records candidates.
+pub(crate) fn resolve_name(
+    name: &str,
+    search_paths: &SearchPaths,
+    source: &dyn ShaderSource,
+) -> Result<Vec<std::path::PathBuf>, ParseError> {
+    if name.is_empty() || name.contains(['/', '\\']) {
+        return Err(ParseError::Io(format!(
+            "Invalid shader name {:?}: expected a name without path separators",
+            name
+        )));
+    }
+
+    let mut found = Vec::new();
+    let mut tried = Vec::new();
+    for dir in search_paths {
+        let mut candidates = Vec::with_capacity(2);
+        if !name.ends_with(".oso") {
+            candidates.push(dir.join(format!("{}.oso", name)));
+        }
+        candidates.push(dir.join(name));
+
+        for candidate in candidates {
+            trace::debug!(candidate = %candidate.display(), "trying");
+            // A directory called like the shader is not the shader.
+            if source.exists(&candidate) && !source.is_dir(&candidate) {
+                trace::debug!(found = %candidate.display(), "resolved");
+                found.push(candidate);
+                if !search_paths.records_candidates() {
+                    return Ok(found);
+                }
+            } else {
+                tried.push(candidate);
+            }
+        }
+    }
+    if !found.is_empty() {
+        return Ok(found);
+    }
+
+    trace::error!(name, "shader not found");
+    if tried.is_empty() {
+        return Err(ParseError::Io(format!(
+            "Shader {:?} not found: the search path is empty",
+            name
+        )));
+    }
+    let tried: Vec<String> = tried
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect();
+    Err(ParseError::Io(format!(
+        "Shader {:?} not found, tried: {}",
+        name,
+        tried.join(", ")
+    )))
+}
+
+/// FNV-1a, for hashes that stay the same across Rust versions.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::TypedParameter;
+    use crate::types::{MetadataValue, TypedParameter};
+
+    #[test]
+    fn test_open_multiple_from_searchpath() {
+        let searchpath = concat!(env!("CARGO_MANIFEST_DIR"), "/tests");
+        let names = ["ui", "missing", "test.oso", "ui"];
+
+        let results = OslQuery::open_multiple_from_searchpath(&names, searchpath);
+        assert_eq!(results.len(), names.len());
+        assert_eq!(results[0].as_ref().unwrap().shader_name(), "uiTest");
+        assert!(matches!(results[1], Err(ParseError::Io(_))));
+        assert_eq!(
+            results[2],
+            OslQuery::open_with_searchpath("test.oso", searchpath)
+        );
+        assert_eq!(results[3], results[0]);
+
+        // Without a search path, names are only tried as paths.
+        let results = OslQuery::open_multiple(&["ui", &format!("{}/ui", searchpath)]);
+        assert!(results[0].is_err());
+        assert_eq!(results[1].as_ref().unwrap().shader_name(), "uiTest");
+    }
+
+    #[test]
+    fn test_global_symbols() {
+        let query = OslQuery::from_string(
+            "OpenShadingLanguage 1.12\n\
+             surface s\n\
+             param\tfloat\t@time\t0\n\
+             param\tfloat\tKd\t0.5\n\
+             param\tpoint\t@P\t0 0 0\n\
+             code ___main___\n",
+        )
+        .unwrap();
+
+        let names: Vec<&str> = query.global_symbols().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["@time", "@P"]);
+        assert!(!query.param_by_name("Kd").unwrap().is_global_symbol());
+    }
+
+    #[test]
+    fn test_closure_types() {
+        let mut query = OslQuery::from_string(
+            "OpenShadingLanguage 1.12\n\
+             surface layered\n\
+             oparam\tclosure color\tbsdf\t\t%read{0,0} %write{0,0}\n\
+             oparam\tcolor\tCout\t0 0 0\n\
+             oparam\tclosure color\tcoat\n\
+             code ___main___\n",
+        )
+        .unwrap();
+        assert_eq!(
+            query.param_by_name("bsdf").unwrap().typed_param(),
+            &TypedParameter::Closure {
+                closure_type: "color".into()
+            }
+        );
+
+        query.add_parameter(Parameter::new_output(
+            "emission",
+            TypedParameter::Closure {
+                closure_type: "edf".into(),
+            },
+        ));
+
+        let closure_types: Vec<Ustr> = query.closure_types().collect();
+        assert_eq!(closure_types, ["color", "edf"]);
+
+        let groups = query.group_by_closure_type();
+        assert_eq!(groups.len(), 2);
+        let names = |params: &[&Parameter]| -> Vec<String> {
+            params.iter().map(|p| p.name.to_string()).collect()
+        };
+        assert_eq!(names(&groups[&Ustr::from("color")]), ["bsdf", "coat"]);
+        assert_eq!(names(&groups[&Ustr::from("edf")]), ["emission"]);
+
+        let color_closures: Vec<&Parameter> = query.closures_of_type("color").collect();
+        assert_eq!(names(&color_closures), ["bsdf", "coat"]);
+        assert_eq!(query.closures_of_type("vdf").count(), 0);
+    }
+
+    #[test]
+    fn test_params_collapsed() {
+        let query = OslQuery::from_string(include_str!("../tests/struct.oso")).unwrap();
+
+        // The raw view has the fields as separate parameters.
+        let names: Vec<&str> = query.params().iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["p.Kd", "p.tint", "gain", "Cout"]);
+
+        let collapsed = query.params_collapsed();
+        assert_eq!(collapsed.len(), 3);
+
+        let CollapsedParam::Struct(p) = &collapsed[0] else {
+            panic!("Expected struct parameter, got {:?}", collapsed[0]);
+        };
+        assert_eq!(p.name(), "p");
+        assert_eq!(p.struct_name(), "MyParams");
+        assert_eq!(p.decl.fields, ["Kd", "tint"]);
+        assert_eq!(p.fields.len(), 2);
+        assert_eq!(
+            p.field("Kd").unwrap().typed_param(),
+            &TypedParameter::Float { default: Some(0.5) }
+        );
+        assert!(p.field("tint").is_some());
+        assert!(p.field("gain").is_none());
+
+        assert!(matches!(collapsed[1], CollapsedParam::Scalar(param) if param.name == "gain"));
+        assert!(matches!(collapsed[2], CollapsedParam::Scalar(param) if param.name == "Cout"));
+    }
+
+    #[test]
+    fn test_struct_of() {
+        let query = OslQuery::from_string(include_str!("../tests/struct.oso")).unwrap();
+
+        let tint = query.param_by_name("p.tint").unwrap();
+        assert_eq!(tint.struct_parent(), Some("p"));
+        let decl = query.struct_of(tint).unwrap();
+        assert_eq!(decl.struct_name, "MyParams");
+        assert!(decl.is_member(tint));
+
+        let gain = query.param_by_name("gain").unwrap();
+        assert_eq!(gain.struct_parent(), None);
+        assert!(query.struct_of(gain).is_none());
+
+        // Without the struct parameter, the field is not matched to a struct.
+        let query = OslQuery::from_string(
+            "OpenShadingLanguage 1.12\n\
+             surface test\n\
+             param\tfloat\tp.Kd\t0.5\n\
+             code ___main___\n",
+        )
+        .unwrap();
+        let kd = query.param_by_name("p.Kd").unwrap();
+        assert_eq!(kd.struct_parent(), Some("p"));
+        assert!(query.struct_of(kd).is_none());
+    }
+
+    #[test]
+    fn test_is_struct() {
+        let query = OslQuery::from_string(include_str!("../tests/struct.oso")).unwrap();
+        assert!(query.param_by_name("p.Kd").unwrap().is_struct());
+        assert!(!query.param_by_name("gain").unwrap().is_struct());
+
+        // Fields are known from `%structfields` without `%mystruct` hints,
+        // and a dotted name alone is no field.
+        let query = OslQuery::from_string(
+            "OpenShadingLanguage 1.12\n\
+             surface test\n\
+             param\tstruct S\tp\t\t%struct{\"S\"} %structfields{Kd}\n\
+             param\tfloat\tp.Kd\t0.5\n\
+             param\tfloat\tq.Kd\t0.5\n\
+             code ___main___\n",
+        )
+        .unwrap();
+        assert!(query.param_by_name("p.Kd").unwrap().is_struct());
+        assert!(!query.param_by_name("q.Kd").unwrap().is_struct());
+    }
 
     #[test]
     fn test_empty_query() {
@@ -281,4 +1164,333 @@ code ___main___
             _ => panic!("Expected Color output parameter"),
         }
     }
+
+    #[test]
+    fn test_type_histogram() {
+        let query = OslQuery::from_string(
+            r#"
+OpenShadingLanguage 1.12
+surface test
+param	color	base	1 1 1
+param	int	count	1
+param	float[3]	weights	0 0 0
+oparam	color	result	0 0 0
+code ___main___
+"#,
+        )
+        .unwrap();
+
+        let histogram = query.type_histogram();
+        assert_eq!(
+            histogram.into_iter().collect::<Vec<_>>(),
+            vec![("color", 2), ("float[]", 1), ("int", 1)]
+        );
+    }
+
+    #[test]
+    fn test_retag_spaces() {
+        let mut query = OslQuery::new();
+        for (name, space) in [
+            ("base", Some("rgb")),
+            ("tint", Some("hsv")),
+            ("plain", None),
+        ] {
+            query.add_parameter(Parameter::new_input(
+                name,
+                TypedParameter::Color {
+                    default: Some([1.0, 0.0, 0.0]),
+                    space: space.map(Ustr::from),
+                },
+            ));
+        }
+        query.add_parameter(Parameter::new_input(
+            "Kd",
+            TypedParameter::Float { default: Some(0.5) },
+        ));
+
+        let map = HashMap::from([(Ustr::from("rgb"), Ustr::from("lin_srgb"))]);
+        query.retag_spaces(&map);
+
+        let space = |name| match query.param_by_name(name).unwrap().typed_param() {
+            TypedParameter::Color { space, .. } => space.map(|s| s.as_str()),
+            other => panic!("unexpected parameter {:?}", other),
+        };
+        assert_eq!(space("base"), Some("lin_srgb"));
+        assert_eq!(space("tint"), Some("hsv"));
+        assert_eq!(space("plain"), None);
+        assert_eq!(
+            query.param_by_name("Kd").unwrap().typed_param(),
+            &TypedParameter::Float { default: Some(0.5) }
+        );
+    }
+
+    #[test]
+    fn test_params_in_space() {
+        let query = OslQuery::from_string(
+            "OpenShadingLanguage 1.12\n\
+             surface spaces\n\
+             param\tpoint\tcenter\t0 0 0\t%space{\"world\"}\n\
+             param\tcolor\ttint\t0 1 1\t%space{\"hsv\"}\n\
+             param\tfloat\tKd\t0.5\n\
+             param\tvector\tup\t0 1 0\t%space{\"world\"}\n\
+             param\tnormal\tNn\t0 0 1\n\
+             code ___main___\n",
+        )
+        .unwrap();
+
+        let names = |params: Vec<&Parameter>| -> Vec<String> {
+            params.iter().map(|p| p.name.to_string()).collect()
+        };
+        assert_eq!(names(query.params_in_space("world")), ["center", "up"]);
+        assert_eq!(names(query.params_in_space("hsv")), ["tint"]);
+        assert!(query.params_in_space("object").is_empty());
+
+        let spaces: Vec<Ustr> = query.spaces().collect();
+        assert_eq!(spaces, ["world", "hsv"]);
+    }
+
+    #[test]
+    fn test_interface_only() {
+        let query = OslQuery::from_string(include_str!("../tests/ui.oso")).unwrap();
+        assert!(query.params().iter().any(Parameter::has_default));
+        assert!(query.params().iter().any(|p| !p.metadata().is_empty()));
+
+        let interface = query.interface_only();
+        assert!(interface.metadata().is_empty());
+        assert_eq!(interface.param_count(), query.param_count());
+        for (stripped, param) in interface.params().iter().zip(query.params()) {
+            assert_eq!(stripped.name, param.name);
+            assert_eq!(stripped.is_output(), param.is_output());
+            assert_eq!(
+                stripped.typed_param().oslquery_typestring(),
+                param.typed_param().oslquery_typestring()
+            );
+            assert!(stripped.metadata().is_empty());
+            assert!(!stripped.has_default());
+        }
+    }
+
+    #[test]
+    fn test_canonical_form() {
+        let a = OslQuery::from_string(
+            "OpenShadingLanguage 1.12\n\
+             surface canon\t%meta{int,version,1} %meta{string,help,\"Canonical\"}\n\
+             param\tfloat\tKd\t0.5\t%meta{string,label,\"Diffuse\"} %meta{float,min,0}\n\
+             param\tcolor\tbase\t1 0 0.0000001\n\
+             oparam\tcolor\tCout\t0 0 0\n\
+             code ___main___\n",
+        )
+        .unwrap();
+        let mut b = OslQuery::from_string(
+            "OpenShadingLanguage 1.12\n\
+             surface canon\t%meta{string,help,\"Canonical\"} %meta{int,version,1}\n\
+             oparam\tcolor\tCout\t0 0 -0.\n\
+             param\tcolor\tbase\t1 0 0.\n\
+             param\tfloat\tKd\t0.5\t%meta{float,min,0} %meta{string,label,\"Diffuse\"}\n\
+             code ___main___\n\
+             \tend\n",
+        )
+        .unwrap();
+        b.param_by_name_mut("Kd")
+            .unwrap()
+            .add_metadata("tags", MetadataValue::StringArray(Vec::new()));
+        assert_ne!(a, b);
+
+        let canonical = a.canonical_form();
+        assert_eq!(canonical, b.canonical_form());
+        assert_eq!(canonical.canonical_form(), canonical);
+        assert_eq!(canonical.code_section_line_range(), None);
+
+        let names: Vec<&str> = canonical.params().iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["Cout", "Kd", "base"]);
+        assert_eq!(canonical.metadata()[0].name, "help");
+
+        // A real difference survives.
+        b.param_by_name_mut("Kd")
+            .unwrap()
+            .add_metadata("max", MetadataValue::Float(1.0));
+        assert_ne!(canonical, b.canonical_form());
+    }
+
+    #[test]
+    fn test_find_param_by_type_at_index() {
+        let query = OslQuery::from_string(
+            "OpenShadingLanguage 1.12\n\
+             surface typed\n\
+             param\tfloat\tKd\t0.8\n\
+             param\tcolor\tbase\t1 1 1\n\
+             param\tfloat[2]\tweights\t0 1\n\
+             param\tfloat\tKs\t0.2\n\
+             oparam\tfloat\tmask\t0\n\
+             oparam\tcolor\tCout\t0 0 0\n\
+             code ___main___\n",
+        )
+        .unwrap();
+
+        let name = |filter, direction, index| {
+            query
+                .find_param_by_type_at_index(filter, direction, index)
+                .map(|p| p.name.as_str())
+        };
+        use ParameterDirection::*;
+        assert_eq!(name(TypeFilter::Float, Input, 0), Some("Kd"));
+        assert_eq!(name(TypeFilter::Float, Input, 1), Some("Ks"));
+        assert_eq!(name(TypeFilter::Float, Input, 2), None);
+        assert_eq!(name(TypeFilter::Float, Both, 2), Some("mask"));
+        assert_eq!(name(TypeFilter::Color, Output, 0), Some("Cout"));
+        assert_eq!(name(TypeFilter::Array, Input, 0), Some("weights"));
+        assert_eq!(name(TypeFilter::Any, Output, 1), Some("Cout"));
+
+        assert_eq!(query.count_params_of_type(TypeFilter::Float, Input), 2);
+        assert_eq!(query.count_params_of_type(TypeFilter::Color, Both), 2);
+        assert_eq!(query.count_params_of_type(TypeFilter::Closure, Both), 0);
+        assert_eq!(
+            query.count_params_of_type(TypeFilter::Any, Both),
+            query.param_count()
+        );
+    }
+
+    #[test]
+    fn test_parameter_count_by_type() {
+        let query = OslQuery::from_string(
+            "OpenShadingLanguage 1.12\n\
+             surface counts\n\
+             param\tfloat\tKd\t0.8\n\
+             param\tfloat\tKs\t0.2\n\
+             param\tcolor\tbase\t1 1 1\n\
+             param\tfloat[2]\tweights\t0 1\n\
+             param\tfloat[]\tramp\t0 0.5 1\n\
+             param\tpoint[]\tpoints\n\
+             param\tmatrix\txform\t1 0 0 0 0 1 0 0 0 0 1 0 0 0 0 1\n\
+             param\tint\tseed\t1\n\
+             param\tstring\tmap\t\"\"\n\
+             oparam\tfloat\tmask\t0\n\
+             oparam\tcolor\tCout\t0 0 0\n\
+             oparam\tclosure color\tbsdf\n\
+             code ___main___\n",
+        )
+        .unwrap();
+
+        let counts = query.parameter_count_by_type();
+        assert_eq!(
+            counts.into_iter().collect::<Vec<_>>(),
+            [
+                ("closure", 1),
+                ("color", 2),
+                ("float", 3),
+                ("float[]", 2),
+                ("int", 1),
+                ("matrix", 1),
+                ("point[]", 1),
+                ("string", 1),
+            ]
+        );
+        let inputs = query.input_parameter_count_by_type();
+        assert_eq!(inputs["float"], 2);
+        assert_eq!(inputs["color"], 1);
+        assert!(!inputs.contains_key("closure"));
+        let outputs = query.output_parameter_count_by_type();
+        assert_eq!(outputs.values().sum::<usize>(), 3);
+        assert_eq!(outputs["float"], 1);
+
+        // Kd, Ks, base, weights, ramp, an empty point[], xform.
+        assert_eq!(query.total_float_component_count(), 1 + 1 + 3 + 2 + 3 + 16);
+    }
+
+    #[test]
+    fn test_pack_float_defaults() {
+        let query = OslQuery::from_string(
+            "OpenShadingLanguage 1.12\n\
+             surface packed\n\
+             param\tfloat\tKd\t0.8\n\
+             param\tint\tseed\t3\n\
+             param\tcolor\tbase\t0.1 0.2 0.3\n\
+             param\tfloat\tnone\n\
+             param\tfloat[2]\tweights\t4 5\n\
+             oparam\tcolor\tCout\t0 0 0\n\
+             code ___main___\n",
+        )
+        .unwrap();
+
+        let (buffer, ranges) = query.pack_float_defaults();
+        assert_eq!(buffer, [0.8, 0.1, 0.2, 0.3, 4.0, 5.0]);
+        let ranges: Vec<(&str, std::ops::Range<usize>)> = ranges
+            .iter()
+            .map(|(name, range)| (name.as_str(), range.clone()))
+            .collect();
+        assert_eq!(
+            ranges,
+            [
+                ("Kd", 0..1),
+                ("seed", 1..1),
+                ("base", 1..4),
+                ("none", 4..4),
+                ("weights", 4..6),
+                ("Cout", 6..6),
+            ]
+        );
+        assert_eq!(&buffer[ranges[2].1.clone()], [0.1, 0.2, 0.3]);
+    }
+
+    #[test]
+    fn test_params_sorted() {
+        let query = OslQuery::from_string(
+            "OpenShadingLanguage 1.12\n\
+             surface sorted\n\
+             param\tfloat\troughness\t0.5\t%meta{string,page,\"Specular\"}\n\
+             param\tcolor\tbase\t1 1 1\t%meta{string,page,\"Base\"}\n\
+             param\tfloat\tKd\t0.8\t%meta{string,page,\"Base\"}\n\
+             param\tint\tseed\t0\n\
+             param\tcolor\tspecular\t1 1 1\t%meta{string,page,\"Specular\"}\n\
+             code ___main___\n",
+        )
+        .unwrap();
+
+        let names = |params: Vec<&Parameter>| -> Vec<String> {
+            params.iter().map(|p| p.name.to_string()).collect()
+        };
+        assert_eq!(
+            names(query.params_sorted_by_name()),
+            ["Kd", "base", "roughness", "seed", "specular"]
+        );
+        assert_eq!(
+            names(query.params_sorted_by_type()),
+            ["base", "specular", "Kd", "roughness", "seed"]
+        );
+        assert_eq!(
+            names(query.params_sorted_by_page_and_name()),
+            ["seed", "Kd", "base", "roughness", "specular"]
+        );
+        assert_eq!(query.param_by_name("Kd").unwrap().ui_page(), Some("Base"));
+        assert_eq!(query.param_by_name("seed").unwrap().ui_page(), None);
+
+        // Declaration order is unchanged.
+        assert_eq!(query.params()[0].name, "roughness");
+    }
+
+    #[test]
+    fn test_parameter_type_signature() {
+        let source = "OpenShadingLanguage 1.12\n\
+             surface test\n\
+             param\tfloat\tKd\t0.8\n\
+             param\tcolor[3]\tbaseColor\t1 1 1 0 0 0 1 1 1\n\
+             oparam\tcolor\tresult\t0 0 0\n\
+             code ___main___\n";
+        let query = OslQuery::from_string(source).unwrap();
+
+        assert_eq!(
+            query.parameter_type_signature(),
+            "float Kd; color[3] baseColor; output color result"
+        );
+
+        // Defaults don't change the signature or its hash.
+        let other = OslQuery::from_string(&source.replace("0.8", "0.5")).unwrap();
+        assert_eq!(other.type_signature_hash(), query.type_signature_hash());
+
+        let renamed = OslQuery::from_string(&source.replace("Kd", "Ks")).unwrap();
+        assert_ne!(renamed.type_signature_hash(), query.type_signature_hash());
+
+        // FNV-1a offset basis for no parameters.
+        assert_eq!(OslQuery::new().type_signature_hash(), 0xcbf29ce484222325);
+    }
 }
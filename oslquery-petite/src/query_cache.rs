@@ -0,0 +1,216 @@
+//! In-memory cache of parsed shaders, shared between threads.
+//!
+//! [`QueryCache`] keys shaders by the canonical path of their file and hands
+//! out `Arc<OslQuery>`, so a shader is parsed once however many threads ask
+//! for it. Unlike `ShaderCache` it keeps nothing on disk and does not
+//! notice changed files; call [`QueryCache::invalidate`] for those.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+
+use crate::parser::{OsoReader, ParseError};
+use crate::query::{OslQuery, resolve};
+use crate::searchpath::SearchPaths;
+
+type Slot = Arc<OnceLock<Result<Arc<OslQuery>, ParseError>>>;
+
+#[derive(Debug)]
+struct Entry {
+    slot: Slot,
+    /// Value of `State::clock` when the entry was last used
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    entries: HashMap<PathBuf, Entry>,
+    clock: u64,
+}
+
+/// A thread-safe cache of parsed shaders holding at most `max_entries`.
+///
+/// When full, the least recently used shader is dropped. Threads asking for
+/// a shader that is being parsed wait for that parse instead of starting
+/// their own. Failed parses are not cached.
+#[derive(Debug)]
+pub struct QueryCache {
+    max_entries: usize,
+    state: Mutex<State>,
+    parses: AtomicUsize,
+}
+
+impl QueryCache {
+    /// Create a cache holding at most `max_entries` shaders (at least one).
+    pub fn new(max_entries: usize) -> Self {
+        QueryCache {
+            max_entries: max_entries.max(1),
+            state: Mutex::default(),
+            parses: AtomicUsize::new(0),
+        }
+    }
+
+    /// Get the shader at `path`, parsing it if it is not cached.
+    ///
+    /// `path` is resolved like in
+    /// [`OslQuery::open_with_searchpath`](crate::OslQuery::open_with_searchpath).
+    pub fn get_or_open(
+        &self,
+        path: impl AsRef<Path>,
+        searchpath: &str,
+    ) -> Result<Arc<OslQuery>, ParseError> {
+        self.get_or_open_with_search_paths(path, &SearchPaths::parse_or_env(searchpath))
+    }
+
+    /// Like [`get_or_open`](Self::get_or_open), with an already parsed
+    /// search path.
+    pub fn get_or_open_with_search_paths(
+        &self,
+        path: impl AsRef<Path>,
+        search_paths: &SearchPaths,
+    ) -> Result<Arc<OslQuery>, ParseError> {
+        let path = path.as_ref();
+        let file = resolve(path, search_paths)
+            .ok_or_else(|| ParseError::Io(format!("Shader file not found: {:?}", path)))?;
+        let key = canonical(&file);
+
+        let slot = self.slot(&key);
+        let result = slot
+            .get_or_init(|| {
+                self.parses.fetch_add(1, Ordering::Relaxed);
+                OsoReader::new().parse_file(&file).map(Arc::new)
+            })
+            .clone();
+
+        if result.is_err() {
+            // Let the next caller try again, unless the slot was replaced.
+            let mut state = self.lock();
+            if state
+                .entries
+                .get(&key)
+                .is_some_and(|entry| Arc::ptr_eq(&entry.slot, &slot))
+            {
+                state.entries.remove(&key);
+            }
+        }
+        result
+    }
+
+    /// Drop the cached shader at `path`, which is not resolved against a
+    /// search path. Returns whether it was cached.
+    pub fn invalidate(&self, path: impl AsRef<Path>) -> bool {
+        let key = canonical(path.as_ref());
+        self.lock().entries.remove(&key).is_some()
+    }
+
+    /// Drop all cached shaders.
+    pub fn clear(&self) {
+        self.lock().entries.clear();
+    }
+
+    /// Number of cached shaders, including ones being parsed.
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    /// Check if no shaders are cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of files parsed so far, including failed parses.
+    pub fn parse_count(&self) -> usize {
+        self.parses.load(Ordering::Relaxed)
+    }
+
+    /// Get the slot for `key`, creating it and evicting the least recently
+    /// used entry if needed.
+    fn slot(&self, key: &Path) -> Slot {
+        let mut state = self.lock();
+        state.clock += 1;
+        let clock = state.clock;
+
+        if let Some(entry) = state.entries.get_mut(key) {
+            entry.last_used = clock;
+            return Arc::clone(&entry.slot);
+        }
+
+        if state.entries.len() >= self.max_entries
+            && let Some(oldest) = state
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(path, _)| path.clone())
+        {
+            state.entries.remove(&oldest);
+        }
+
+        let slot = Slot::default();
+        state.entries.insert(
+            key.to_path_buf(),
+            Entry {
+                slot: Arc::clone(&slot),
+                last_used: clock,
+            },
+        );
+        slot
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    crate::searchpath::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_lru_eviction() {
+        let tests_dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests");
+        let cache = QueryCache::new(2);
+
+        let ui = cache.get_or_open("ui", tests_dir).unwrap();
+        cache.get_or_open("test", tests_dir).unwrap();
+        // Use `ui` again, so `test` is the least recently used.
+        assert!(Arc::ptr_eq(
+            &ui,
+            &cache.get_or_open("ui.oso", tests_dir).unwrap()
+        ));
+        cache.get_or_open("array", tests_dir).unwrap();
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.parse_count(), 3);
+        assert!(!cache.invalidate(format!("{}/test.oso", tests_dir)));
+        assert!(cache.invalidate(format!("{}/ui.oso", tests_dir)));
+
+        // Parsed again after invalidation.
+        let reparsed = cache.get_or_open("ui", tests_dir).unwrap();
+        assert!(!Arc::ptr_eq(&ui, &reparsed));
+        assert_eq!(cache.parse_count(), 4);
+    }
+
+    #[test]
+    fn test_errors_are_not_cached() {
+        let cache = QueryCache::new(4);
+        assert!(cache.get_or_open("/nonexistent/shader", "").is_err());
+
+        let source = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/tabs.oso");
+        let bad =
+            std::env::temp_dir().join(format!("oslquery-petite-bad-{}.oso", std::process::id()));
+        fs::write(&bad, "OpenShadingLanguage 0.9\nsurface s\n").unwrap();
+        assert!(cache.get_or_open(&bad, "").is_err());
+        assert!(cache.is_empty());
+
+        fs::copy(source, &bad).unwrap();
+        assert!(cache.get_or_open(&bad, "").is_ok());
+        assert_eq!(cache.parse_count(), 2);
+        let _ = fs::remove_file(&bad);
+    }
+}
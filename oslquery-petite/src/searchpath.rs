@@ -0,0 +1,500 @@
+//! Shader search paths.
+//!
+//! A search path is a list of directories in one string, as passed to
+//! `oslc -I` or renderers. Entries are separated by `;` or `:`; a `:` after
+//! a leading drive letter (`C:\shaders`, also `\\?\C:\shaders`) is kept. An
+//! entry may start with an environment variable (`$VAR`, `${VAR}` or
+//! `%VAR%`), and the entry `&` stands for the default search path from the
+//! environment (see [`SearchPaths::from_env`]).
+//!
+//! Windows paths, including UNC paths like `\\server\share\osl`, work on
+//! all platforms: shader names like `sub/shader` are joined to them with
+//! backslashes. This is done on the path strings, so it behaves the same on
+//! any platform.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::source::{ShaderSource, StdFs};
+use crate::trace;
+
+/// Environment variables holding the default search path, in order of
+/// precedence.
+pub const SEARCHPATH_ENV_VARS: [&str; 2] = ["OSLQUERY_SEARCHPATH", "OSL_SHADERS"];
+
+/// An ordered list of directories to look for shaders in.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchPaths {
+    dirs: Vec<PathBuf>,
+    /// Whether opening a shader records every file it was found in
+    record_candidates: bool,
+}
+
+impl SearchPaths {
+    /// Parse a search path, expanding variables from the process
+    /// environment.
+    pub fn parse(searchpath: &str) -> Self {
+        Self::parse_with(searchpath, |name| std::env::var(name).ok())
+    }
+
+    /// Parse a search path, or use [`from_env`](Self::from_env) if it is
+    /// empty.
+    pub fn parse_or_env(searchpath: &str) -> Self {
+        if searchpath.trim().is_empty() {
+            Self::from_env()
+        } else {
+            Self::parse(searchpath)
+        }
+    }
+
+    /// The default search path, from the first of [`SEARCHPATH_ENV_VARS`]
+    /// that is set.
+    pub fn from_env() -> Self {
+        Self::default_with(&|name| std::env::var(name).ok())
+    }
+
+    /// Parse a search path, looking up variables with `lookup` rather than
+    /// in the process environment.
+    pub fn parse_with(searchpath: &str, lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let mut dirs = Vec::new();
+        for entry in split(searchpath) {
+            if entry == "&" {
+                dirs.extend(Self::default_with(&lookup).dirs);
+            } else {
+                dirs.push(PathBuf::from(expand(entry, &lookup)));
+            }
+        }
+        SearchPaths {
+            dirs,
+            record_candidates: false,
+        }
+    }
+
+    fn default_with(lookup: &impl Fn(&str) -> Option<String>) -> Self {
+        let searchpath = SEARCHPATH_ENV_VARS
+            .iter()
+            .find_map(|name| lookup(name))
+            .unwrap_or_default();
+        // `&` in the default itself would refer to itself.
+        let dirs = split(&searchpath)
+            .filter(|entry| *entry != "&")
+            .map(|entry| PathBuf::from(expand(entry, lookup)))
+            .collect();
+        SearchPaths {
+            dirs,
+            record_candidates: false,
+        }
+    }
+
+    /// Set whether shaders opened with these search paths record every file
+    /// they were found in (default: `false`).
+    ///
+    /// See [`OslQuery::resolution_candidates`](crate::OslQuery::resolution_candidates).
+    /// This checks every directory instead of stopping at the first match.
+    pub fn record_candidates(mut self, enabled: bool) -> Self {
+        self.record_candidates = enabled;
+        self
+    }
+
+    /// Check if shaders opened with these search paths record every file
+    /// they were found in.
+    pub fn records_candidates(&self) -> bool {
+        self.record_candidates
+    }
+
+    /// The directories, in order.
+    pub fn dirs(&self) -> &[PathBuf] {
+        &self.dirs
+    }
+
+    /// Iterate over the directories.
+    pub fn iter(&self) -> std::slice::Iter<'_, PathBuf> {
+        self.dirs.iter()
+    }
+
+    /// Check if there are no directories.
+    pub fn is_empty(&self) -> bool {
+        self.dirs.is_empty()
+    }
+
+    /// Find `name` in the first directory containing it, trying `name.oso`
+    /// as well.
+    pub fn find(&self, name: impl AsRef<Path>) -> Option<PathBuf> {
+        self.find_in(name, &StdFs)
+    }
+
+    /// Like [`find`](Self::find), looking in `source` instead of the file
+    /// system.
+    pub fn find_in(&self, name: impl AsRef<Path>, source: &dyn ShaderSource) -> Option<PathBuf> {
+        self.candidates(name.as_ref()).find(|path| {
+            trace::debug!(candidate = %path.display(), "trying");
+            source.exists(path)
+        })
+    }
+
+    /// Find `name` in every directory containing it, in the order of
+    /// [`find`](Self::find): the first path is the one it returns, the
+    /// others are shadowed by it.
+    ///
+    /// Useful for finding out why the wrong version of a shader is loaded.
+    pub fn resolve_all(&self, name: impl AsRef<Path>) -> Vec<PathBuf> {
+        self.resolve_all_in(name, &StdFs)
+    }
+
+    /// Like [`resolve_all`](Self::resolve_all), looking in `source` instead
+    /// of the file system.
+    pub fn resolve_all_in(
+        &self,
+        name: impl AsRef<Path>,
+        source: &dyn ShaderSource,
+    ) -> Vec<PathBuf> {
+        self.candidates(name.as_ref())
+            .filter(|path| source.exists(path))
+            .collect()
+    }
+
+    /// The paths `name` may be at, in the order they are tried: in each
+    /// directory `name` itself, then with an `.oso` extension.
+    fn candidates<'a>(&'a self, name: &'a Path) -> impl Iterator<Item = PathBuf> + 'a {
+        self.dirs.iter().flat_map(move |dir| {
+            let path = join(dir, name);
+            let mut path_with_ext = path.clone();
+            path_with_ext.set_extension("oso");
+            let with_ext = (path_with_ext != path).then_some(path_with_ext);
+            std::iter::once(path).chain(with_ext)
+        })
+    }
+}
+
+impl<'a> IntoIterator for &'a SearchPaths {
+    type Item = &'a PathBuf;
+    type IntoIter = std::slice::Iter<'a, PathBuf>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl FromIterator<PathBuf> for SearchPaths {
+    fn from_iter<I: IntoIterator<Item = PathBuf>>(iter: I) -> Self {
+        SearchPaths {
+            dirs: iter.into_iter().collect(),
+            record_candidates: false,
+        }
+    }
+}
+
+/// Split a search path into its non-empty, trimmed entries.
+fn split(searchpath: &str) -> impl Iterator<Item = &str> {
+    let mut entries = Vec::new();
+    let mut start = 0;
+    for (i, c) in searchpath.char_indices() {
+        let is_separator = match c {
+            ';' => true,
+            ':' => !is_drive_letter(&searchpath[start..i], &searchpath[i + 1..]),
+            _ => false,
+        };
+        if is_separator {
+            entries.push(&searchpath[start..i]);
+            start = i + 1;
+        }
+    }
+    entries.push(&searchpath[start..]);
+
+    entries
+        .into_iter()
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+}
+
+/// Check if the `:` between `before` and `after` follows a drive letter,
+/// as in `C:\shaders`, `C:/shaders` or `\\?\C:\shaders`.
+fn is_drive_letter(before: &str, after: &str) -> bool {
+    let before = before.trim_start();
+    let before = VERBATIM_PREFIXES
+        .iter()
+        .find_map(|prefix| before.strip_prefix(prefix))
+        .unwrap_or(before);
+    before.len() == 1
+        && before.chars().all(|c| c.is_ascii_alphabetic())
+        && after.starts_with(['\\', '/'])
+}
+
+/// Prefixes of Windows verbatim and device paths, before a drive letter.
+const VERBATIM_PREFIXES: [&str; 4] = ["\\\\?\\", "\\\\.\\", "//?/", "//./"];
+
+/// Check if `path` is a Windows path: one starting with a drive letter or
+/// a UNC or verbatim path.
+fn is_windows_path(path: &str) -> bool {
+    let mut chars = path.chars();
+    let drive = matches!(
+        (chars.next(), chars.next(), chars.next()),
+        (Some(letter), Some(':'), None | Some('\\' | '/')) if letter.is_ascii_alphabetic()
+    );
+    drive || path.starts_with("\\\\")
+}
+
+/// Join the relative `name` to `dir`. In a Windows path, the `/` of `name`
+/// become `\`, which verbatim paths need.
+fn join_str(dir: &str, name: &str) -> String {
+    if dir.is_empty() {
+        return name.to_string();
+    }
+    let (separator, name) = if is_windows_path(dir) {
+        ('\\', name.replace('/', "\\"))
+    } else {
+        ('/', name.to_string())
+    };
+    if dir.ends_with(['/', '\\']) {
+        format!("{}{}", dir, name)
+    } else {
+        format!("{}{}{}", dir, separator, name)
+    }
+}
+
+/// Join `name` to `dir` as [`join_str`] does, or with [`Path::join`] if
+/// either is not UTF-8 or `name` is absolute.
+fn join(dir: &Path, name: &Path) -> PathBuf {
+    match (dir.to_str(), name.to_str()) {
+        (Some(dir), Some(name_str)) if !name.has_root() && !name.is_absolute() => {
+            PathBuf::from(join_str(dir, name_str))
+        }
+        _ => dir.join(name),
+    }
+}
+
+/// Canonicalize `path` like [`std::fs::canonicalize`], but without the
+/// `\\?\` prefix Windows adds where the path works without it, so that
+/// paths shown to users and passed to other tools look as expected.
+pub fn canonicalize(path: impl AsRef<Path>) -> io::Result<PathBuf> {
+    let path = std::fs::canonicalize(path)?;
+    if cfg!(windows)
+        && let Some(simplified) = path.to_str().and_then(simplify_verbatim)
+    {
+        return Ok(PathBuf::from(simplified));
+    }
+    Ok(path)
+}
+
+/// Remove the verbatim prefix of `\\?\C:\dir` or `\\?\UNC\server\share`,
+/// unless the path needs it: when it is too long for other paths, has `/`,
+/// `.` or `..` components, reserved device names like `CON`, or components
+/// ending in a dot or space, which Windows would otherwise change.
+fn simplify_verbatim(path: &str) -> Option<String> {
+    let (prefix, rest) = if let Some(unc) = path.strip_prefix("\\\\?\\UNC\\") {
+        ("\\\\", unc)
+    } else {
+        let rest = path.strip_prefix("\\\\?\\")?;
+        if !is_windows_path(rest) || rest.starts_with("\\\\") {
+            return None;
+        }
+        ("", rest)
+    };
+
+    const MAX_PATH: usize = 260;
+    const RESERVED: [&str; 22] = [
+        "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+        "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+    ];
+    let simplified = format!("{}{}", prefix, rest);
+    let components = rest.split('\\').skip(if prefix.is_empty() { 1 } else { 0 });
+    let safe = simplified.len() < MAX_PATH
+        && !rest.contains('/')
+        && components.filter(|c| !c.is_empty()).all(|component| {
+            let stem = component.split('.').next().unwrap_or_default().trim_end();
+            component != "."
+                && component != ".."
+                && !component.ends_with(['.', ' '])
+                && !RESERVED.iter().any(|name| stem.eq_ignore_ascii_case(name))
+        });
+    safe.then_some(simplified)
+}
+
+/// Expand a leading `$VAR`, `${VAR}` or `%VAR%`. Unset variables are left
+/// as they are.
+fn expand(entry: &str, lookup: &impl Fn(&str) -> Option<String>) -> String {
+    let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '_';
+
+    let (name, rest) = if let Some(braced) = entry.strip_prefix("${") {
+        match braced.split_once('}') {
+            Some(split) => split,
+            None => return entry.to_string(),
+        }
+    } else if let Some(percent) = entry.strip_prefix('%') {
+        match percent.split_once('%') {
+            Some(split) => split,
+            None => return entry.to_string(),
+        }
+    } else if let Some(dollar) = entry.strip_prefix('$') {
+        let end = dollar.find(|c| !is_name_char(c)).unwrap_or(dollar.len());
+        dollar.split_at(end)
+    } else {
+        return entry.to_string();
+    };
+
+    if name.is_empty() || !name.chars().all(is_name_char) {
+        return entry.to_string();
+    }
+    match lookup(name) {
+        Some(value) => format!("{}{}", value, rest),
+        None => entry.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(name: &str) -> Option<String> {
+        match name {
+            "HOME" => Some("/home/me".to_string()),
+            "SHADERS" => Some("D:\\shaders".to_string()),
+            "OSL_SHADERS" => Some("/opt/osl/shaders:&:/usr/share/osl".to_string()),
+            _ => None,
+        }
+    }
+
+    fn dirs(searchpath: &str) -> Vec<String> {
+        SearchPaths::parse_with(searchpath, env)
+            .iter()
+            .map(|dir| dir.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_separators() {
+        assert_eq!(dirs("/a:/b"), vec!["/a", "/b"]);
+        assert_eq!(dirs("/a;/b"), vec!["/a", "/b"]);
+        assert_eq!(dirs("/a;/b:/c"), vec!["/a", "/b", "/c"]);
+        assert_eq!(dirs(" /a : :/b;"), vec!["/a", "/b"]);
+        assert!(dirs("").is_empty());
+    }
+
+    #[test]
+    fn test_windows_paths() {
+        assert_eq!(
+            dirs("C:\\shaders;D:/more shaders"),
+            vec!["C:\\shaders", "D:/more shaders"]
+        );
+        assert_eq!(
+            dirs("C:\\shaders:/usr/shaders:x:y"),
+            vec!["C:\\shaders", "/usr/shaders", "x", "y"]
+        );
+    }
+
+    #[test]
+    fn test_unc_and_verbatim_paths() {
+        assert_eq!(
+            dirs("\\\\server\\share\\osl;/usr/shaders"),
+            vec!["\\\\server\\share\\osl", "/usr/shaders"]
+        );
+        assert_eq!(
+            dirs("\\\\?\\C:\\shaders:\\\\?\\UNC\\server\\share:/a"),
+            vec!["\\\\?\\C:\\shaders", "\\\\?\\UNC\\server\\share", "/a"]
+        );
+        assert_eq!(dirs("//?/D:/osl"), vec!["//?/D:/osl"]);
+    }
+
+    #[test]
+    fn test_join() {
+        assert_eq!(
+            join_str("/usr/shaders", "sub/ui.oso"),
+            "/usr/shaders/sub/ui.oso"
+        );
+        assert_eq!(join_str("/usr/shaders/", "ui"), "/usr/shaders/ui");
+        assert_eq!(
+            join_str("C:\\shaders", "sub/ui.oso"),
+            "C:\\shaders\\sub\\ui.oso"
+        );
+        assert_eq!(join_str("C:/shaders", "sub/ui"), "C:/shaders\\sub\\ui");
+        assert_eq!(join_str("C:\\", "ui"), "C:\\ui");
+        assert_eq!(
+            join_str("\\\\?\\C:\\shaders", "a/b"),
+            "\\\\?\\C:\\shaders\\a\\b"
+        );
+        assert_eq!(join_str("\\\\server\\share", "ui"), "\\\\server\\share\\ui");
+        assert_eq!(join_str("", "ui"), "ui");
+
+        // Absolute names replace the directory, as with `Path::join`.
+        assert_eq!(
+            join(Path::new("/usr/shaders"), Path::new("/abs/ui")),
+            PathBuf::from("/abs/ui")
+        );
+        assert_eq!(
+            join(Path::new("/usr/shaders"), Path::new("ui")),
+            PathBuf::from("/usr/shaders/ui")
+        );
+    }
+
+    #[test]
+    fn test_simplify_verbatim() {
+        let simplify = |path: &str| simplify_verbatim(path);
+        assert_eq!(
+            simplify("\\\\?\\C:\\shaders\\ui.oso").as_deref(),
+            Some("C:\\shaders\\ui.oso")
+        );
+        assert_eq!(
+            simplify("\\\\?\\UNC\\server\\share\\ui.oso").as_deref(),
+            Some("\\\\server\\share\\ui.oso")
+        );
+        assert_eq!(simplify("\\\\?\\C:\\").as_deref(), Some("C:\\"));
+
+        // Already simple, or needing the prefix.
+        assert_eq!(simplify("C:\\shaders"), None);
+        assert_eq!(simplify("/usr/shaders"), None);
+        assert_eq!(simplify("\\\\?\\Volume{1234}\\osl"), None);
+        assert_eq!(simplify("\\\\?\\C:\\shaders\\con.oso"), None);
+        assert_eq!(simplify("\\\\?\\C:\\shaders\\LPT1"), None);
+        assert_eq!(simplify("\\\\?\\C:\\trailing.\\ui.oso"), None);
+        assert_eq!(simplify("\\\\?\\C:\\a\\..\\ui.oso"), None);
+        assert_eq!(simplify("\\\\?\\C:\\a/b"), None);
+        let long = format!("\\\\?\\C:\\{}", "x".repeat(300));
+        assert_eq!(simplify(&long), None);
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        let manifest = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let path = canonicalize(manifest.join("tests/../tests/ui.oso")).unwrap();
+        assert!(path.ends_with("tests/ui.oso"));
+        assert!(!path.to_string_lossy().starts_with("\\\\?\\"));
+        assert!(canonicalize(manifest.join("no such file")).is_err());
+    }
+
+    #[test]
+    fn test_variables() {
+        assert_eq!(dirs("$HOME/shaders"), vec!["/home/me/shaders"]);
+        assert_eq!(dirs("${HOME}/shaders"), vec!["/home/me/shaders"]);
+        assert_eq!(dirs("%SHADERS%\\osl"), vec!["D:\\shaders\\osl"]);
+        assert_eq!(dirs("%SHADERS%;$HOME"), vec!["D:\\shaders", "/home/me"]);
+        // Unset or malformed variables are kept verbatim.
+        assert_eq!(dirs("$UNSET/shaders"), vec!["$UNSET/shaders"]);
+        assert_eq!(dirs("${HOME/shaders"), vec!["${HOME/shaders"]);
+        // Only a leading variable is expanded.
+        assert_eq!(dirs("/a/$HOME"), vec!["/a/$HOME"]);
+    }
+
+    #[test]
+    fn test_default_token() {
+        assert_eq!(
+            dirs("/first:&:/last"),
+            vec!["/first", "/opt/osl/shaders", "/usr/share/osl", "/last"]
+        );
+        assert_eq!(
+            SearchPaths::parse_with("&", |_| None),
+            SearchPaths::default()
+        );
+    }
+
+    #[test]
+    fn test_env_precedence() {
+        let lookup = |name: &str| match name {
+            "OSLQUERY_SEARCHPATH" => Some("/preferred".to_string()),
+            _ => env(name),
+        };
+        assert_eq!(
+            SearchPaths::parse_with("&", lookup).dirs(),
+            &[PathBuf::from("/preferred")]
+        );
+    }
+}
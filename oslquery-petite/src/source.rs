@@ -0,0 +1,151 @@
+//! Where shader files are read from.
+//!
+//! Opening a shader by name looks for it with an `.oso` extension added, as
+//! given and in each directory of a search path. All of this goes through a
+//! [`ShaderSource`], so shaders can live in pack files or asset databases
+//! instead of loose on disk. [`StdFs`], the file system, is used unless
+//! another source is given, e.g. to [`OslQuery::open_with_source`] or
+//! [`ShaderLibrary::scan_with_source`](crate::ShaderLibrary::scan_with_source).
+
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::parser::{OsoReader, ParseError};
+use crate::query::OslQuery;
+use crate::searchpath::SearchPaths;
+
+/// A file system that shaders are read from.
+///
+/// Only [`exists`](Self::exists) and [`read`](Self::read) are needed to
+/// open shaders. [`ShaderLibrary`](crate::ShaderLibrary) also lists
+/// directories, and uses modification times to skip unchanged files on
+/// refresh.
+pub trait ShaderSource: Send + Sync {
+    /// Check if there is a file or directory at `path`.
+    fn exists(&self, path: &Path) -> bool;
+
+    /// Read the whole file at `path`.
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// Read the whole file at `path` as UTF-8 text.
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        String::from_utf8(self.read(path)?)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    /// Open the file at `path` for reading a part of it, e.g. the header.
+    ///
+    /// Reads the whole file by default.
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + '_>> {
+        Ok(Box::new(io::Cursor::new(self.read(path)?)))
+    }
+
+    /// Check if `path` is a directory.
+    fn is_dir(&self, _path: &Path) -> bool {
+        false
+    }
+
+    /// Check if `path` is a symbolic link. [`ShaderLibrary`](crate::ShaderLibrary)
+    /// does not descend into linked directories.
+    fn is_symlink(&self, _path: &Path) -> bool {
+        false
+    }
+
+    /// The paths of the entries of directory `dir`, in any order.
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("Cannot list {}", dir.display()),
+        ))
+    }
+
+    /// The modification time and size of the file at `path`, `(None, 0)` if
+    /// they are unknown.
+    fn modified_and_len(&self, _path: &Path) -> (Option<SystemTime>, u64) {
+        (None, 0)
+    }
+}
+
+impl fmt::Debug for dyn ShaderSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ShaderSource")
+    }
+}
+
+/// The file system of the operating system.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StdFs;
+
+impl ShaderSource for StdFs {
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + '_>> {
+        Ok(Box::new(fs::File::open(path)?))
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn is_symlink(&self, path: &Path) -> bool {
+        path.is_symlink()
+    }
+
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        fs::read_dir(dir)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect()
+    }
+
+    fn modified_and_len(&self, path: &Path) -> (Option<SystemTime>, u64) {
+        fs::metadata(path)
+            .map(|m| (m.modified().ok(), m.len()))
+            .unwrap_or((None, 0))
+    }
+}
+
+impl OslQuery {
+    /// Open and parse a shader from `source`, looking for it in `searchpath`
+    /// if it is not found directly.
+    ///
+    /// This is [`open_with_searchpath`](Self::open_with_searchpath) with the
+    /// files read from `source` instead of the file system.
+    pub fn open_with_source<P: AsRef<Path>>(
+        path: P,
+        searchpath: &str,
+        source: &impl ShaderSource,
+    ) -> Result<Self, ParseError> {
+        Self::resolve_and_open_with_source(
+            path.as_ref(),
+            &SearchPaths::parse_or_env(searchpath),
+            source,
+        )
+        .1
+    }
+}
+
+impl OsoReader {
+    /// Parse the OSO file at `path` in `source`.
+    pub(crate) fn parse_source(
+        self,
+        path: &Path,
+        source: &dyn ShaderSource,
+    ) -> Result<OslQuery, ParseError> {
+        crate::trace::enter_span!("read", path = %path.display());
+        let content = source.read_to_string(path)?;
+        self.parse_string(&content)
+    }
+}
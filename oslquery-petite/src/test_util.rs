@@ -0,0 +1,334 @@
+//! [`proptest`](https://docs.rs/proptest) strategies and round-trip
+//! assertions for downstream tests.
+//!
+//! Enabled by the `test-util` feature. The strategies generate queries that
+//! are valid by construction: outputs never have defaults, fixed-size array
+//! defaults always have as many elements as the array, and parameter names
+//! are unique. These invariants hold while shrinking, too.
+//!
+//! Generated strings avoid quotes, backslashes and braces, and metadata is
+//! scalar, so everything survives being written and parsed again. Struct
+//! parameters are not generated.
+//!
+//! A downstream test that checks queries survive a trip through your own
+//! code:
+//!
+//! ```
+//! use oslquery_petite::{OslQuery, test_util};
+//! use proptest::prelude::*;
+//!
+//! // Stand-in for code that stores and restores queries.
+//! fn store_and_restore(query: &OslQuery) -> OslQuery {
+//!     OslQuery::from_string(&query.to_oso()).unwrap()
+//! }
+//!
+//! proptest!(|(query in test_util::arb_query())| {
+//!     let restored = store_and_restore(&query);
+//!     prop_assert_eq!(restored.params(), query.params());
+//!     test_util::assert_roundtrip(&restored);
+//! });
+//! ```
+
+use proptest::collection::vec;
+use proptest::option;
+use proptest::prelude::*;
+use ustr::Ustr;
+
+use crate::query::OslQuery;
+use crate::types::{Metadata, MetadataValue, Parameter, StringValue, TypedParameter};
+
+/// Floats that print and parse back exactly.
+fn arb_float() -> impl Strategy<Value = f32> {
+    -1000.0f32..1000.0
+}
+
+fn arb_triple() -> impl Strategy<Value = [f32; 3]> {
+    prop::array::uniform3(arb_float())
+}
+
+fn arb_matrix() -> impl Strategy<Value = [f32; 16]> {
+    prop::array::uniform16(arb_float())
+}
+
+fn arb_string() -> impl Strategy<Value = StringValue> {
+    "[a-zA-Z0-9 _.,:/-]{0,12}".prop_map(StringValue::from)
+}
+
+fn arb_identifier() -> impl Strategy<Value = String> {
+    "[a-zA-Z][a-zA-Z0-9_]{0,8}"
+}
+
+/// A fixed-size array type, with or without a default of matching length.
+fn arb_fixed<T: Clone + std::fmt::Debug + 'static>(
+    element: impl Strategy<Value = T> + 'static,
+    make: fn(usize, Option<Vec<T>>) -> TypedParameter,
+) -> BoxedStrategy<TypedParameter> {
+    prop_oneof![
+        (1usize..5).prop_map(move |size| make(size, None)),
+        vec(element, 1..5).prop_map(move |default| make(default.len(), Some(default))),
+    ]
+    .boxed()
+}
+
+/// A dynamic array type, with or without a non-empty default.
+fn arb_dynamic<T: Clone + std::fmt::Debug + 'static>(
+    element: impl Strategy<Value = T> + 'static,
+    make: fn(Option<Vec<T>>) -> TypedParameter,
+) -> BoxedStrategy<TypedParameter> {
+    option::of(vec(element, 1..5)).prop_map(make).boxed()
+}
+
+fn set_space(typed_param: &mut TypedParameter, new_space: Option<Ustr>) {
+    match typed_param {
+        TypedParameter::Color { space, .. }
+        | TypedParameter::Point { space, .. }
+        | TypedParameter::Vector { space, .. }
+        | TypedParameter::Normal { space, .. }
+        | TypedParameter::ColorArray { space, .. }
+        | TypedParameter::PointArray { space, .. }
+        | TypedParameter::VectorArray { space, .. }
+        | TypedParameter::NormalArray { space, .. }
+        | TypedParameter::ColorDynamicArray { space, .. }
+        | TypedParameter::PointDynamicArray { space, .. }
+        | TypedParameter::VectorDynamicArray { space, .. }
+        | TypedParameter::NormalDynamicArray { space, .. } => *space = new_space,
+        _ => {}
+    }
+}
+
+fn arb_scalar() -> BoxedStrategy<TypedParameter> {
+    prop_oneof![
+        option::of(any::<i32>()).prop_map(|default| TypedParameter::Int { default }),
+        option::of(arb_float()).prop_map(|default| TypedParameter::Float { default }),
+        option::of(arb_string()).prop_map(|default| TypedParameter::String { default }),
+        option::of(arb_triple()).prop_map(|default| TypedParameter::Color {
+            default,
+            space: None
+        }),
+        option::of(arb_triple()).prop_map(|default| TypedParameter::Point {
+            default,
+            space: None
+        }),
+        option::of(arb_triple()).prop_map(|default| TypedParameter::Vector {
+            default,
+            space: None
+        }),
+        option::of(arb_triple()).prop_map(|default| TypedParameter::Normal {
+            default,
+            space: None
+        }),
+        option::of(arb_matrix()).prop_map(|default| TypedParameter::Matrix {
+            default: default.map(Box::new)
+        }),
+        Just(TypedParameter::Closure {
+            closure_type: Ustr::from("color")
+        }),
+    ]
+    .boxed()
+}
+
+fn arb_fixed_array() -> BoxedStrategy<TypedParameter> {
+    prop_oneof![
+        arb_fixed(any::<i32>(), |size, default| TypedParameter::IntArray {
+            size,
+            default
+        }),
+        arb_fixed(arb_float(), |size, default| TypedParameter::FloatArray {
+            size,
+            default
+        }),
+        arb_fixed(arb_string(), |size, default| TypedParameter::StringArray {
+            size,
+            default
+        }),
+        arb_fixed(arb_triple(), |size, default| TypedParameter::ColorArray {
+            size,
+            default,
+            space: None
+        }),
+        arb_fixed(arb_triple(), |size, default| TypedParameter::PointArray {
+            size,
+            default,
+            space: None
+        }),
+        arb_fixed(arb_triple(), |size, default| TypedParameter::VectorArray {
+            size,
+            default,
+            space: None
+        }),
+        arb_fixed(arb_triple(), |size, default| TypedParameter::NormalArray {
+            size,
+            default,
+            space: None
+        }),
+        arb_fixed(arb_matrix(), |size, default| TypedParameter::MatrixArray {
+            size,
+            default
+        }),
+    ]
+    .boxed()
+}
+
+fn arb_dynamic_array() -> BoxedStrategy<TypedParameter> {
+    prop_oneof![
+        arb_dynamic(any::<i32>(), |default| TypedParameter::IntDynamicArray {
+            default
+        }),
+        arb_dynamic(arb_float(), |default| TypedParameter::FloatDynamicArray {
+            default
+        }),
+        arb_dynamic(arb_string(), |default| {
+            TypedParameter::StringDynamicArray { default }
+        }),
+        arb_dynamic(arb_triple(), |default| {
+            TypedParameter::ColorDynamicArray {
+                default,
+                space: None,
+            }
+        }),
+        arb_dynamic(arb_triple(), |default| {
+            TypedParameter::PointDynamicArray {
+                default,
+                space: None,
+            }
+        }),
+        arb_dynamic(arb_triple(), |default| {
+            TypedParameter::VectorDynamicArray {
+                default,
+                space: None,
+            }
+        }),
+        arb_dynamic(arb_triple(), |default| {
+            TypedParameter::NormalDynamicArray {
+                default,
+                space: None,
+            }
+        }),
+        arb_dynamic(arb_matrix(), |default| {
+            TypedParameter::MatrixDynamicArray { default }
+        }),
+    ]
+    .boxed()
+}
+
+/// Any [`TypedParameter`], with an optional space on the types that have
+/// one.
+pub fn arb_typed_parameter() -> impl Strategy<Value = TypedParameter> {
+    let space = option::of(prop_oneof![
+        Just(Ustr::from("world")),
+        Just(Ustr::from("object")),
+        Just(Ustr::from("camera")),
+        Just(Ustr::from("rgb")),
+    ]);
+    (
+        prop_oneof![arb_scalar(), arb_fixed_array(), arb_dynamic_array()],
+        space,
+    )
+        .prop_map(|(mut typed_param, space)| {
+            set_space(&mut typed_param, space);
+            typed_param
+        })
+}
+
+/// Scalar [`Metadata`]: an int, a float or a string without commas.
+pub fn arb_metadata() -> impl Strategy<Value = Metadata> {
+    (
+        arb_identifier(),
+        prop_oneof![
+            any::<i32>().prop_map(MetadataValue::Int),
+            arb_float().prop_map(MetadataValue::Float),
+            "[a-zA-Z0-9 _./:-]{1,12}".prop_map(|s| MetadataValue::String(s.into())),
+        ],
+    )
+        .prop_map(|(name, value)| Metadata {
+            name: name.into(),
+            value,
+        })
+}
+
+/// An input or output [`Parameter`] with up to three metadata entries.
+///
+/// Outputs are created with [`Parameter::new_output`], which strips the
+/// default.
+pub fn arb_parameter() -> impl Strategy<Value = Parameter> {
+    (
+        arb_identifier(),
+        any::<bool>(),
+        arb_typed_parameter(),
+        vec(arb_metadata(), 0..3),
+    )
+        .prop_map(|(name, is_output, typed_param, metadata)| {
+            let mut param = if is_output {
+                Parameter::new_output(name, typed_param)
+            } else {
+                Parameter::new_input(name, typed_param)
+            };
+            *param.metadata_mut() = metadata;
+            param
+        })
+}
+
+/// An [`OslQuery`] with up to eight parameters and some shader metadata.
+///
+/// Parameter names get their index as a suffix so they are unique.
+pub fn arb_query() -> impl Strategy<Value = OslQuery> {
+    (
+        prop_oneof![
+            Just("surface"),
+            Just("displacement"),
+            Just("volume"),
+            Just("shader"),
+        ],
+        arb_identifier(),
+        vec(arb_parameter(), 0..8),
+        vec(arb_metadata(), 0..3),
+    )
+        .prop_map(|(shader_type, shader_name, params, metadata)| {
+            let mut query = OslQuery::new();
+            query.set_shader_info(shader_type, shader_name);
+            for (index, mut param) in params.into_iter().enumerate() {
+                param.name = format!("{}_{}", param.name, index).into();
+                query.add_parameter(param);
+            }
+            for meta in metadata {
+                query.add_metadata(meta);
+            }
+            query
+        })
+}
+
+fn assert_same_interface(actual: &OslQuery, expected: &OslQuery) {
+    assert_eq!(actual.shader_type(), expected.shader_type());
+    assert_eq!(actual.shader_name(), expected.shader_name());
+    assert_eq!(actual.params(), expected.params());
+    assert_eq!(actual.structs(), expected.structs());
+    assert_eq!(actual.metadata(), expected.metadata());
+}
+
+/// Write `query` with [`OslQuery::to_oso`], parse it again and assert the
+/// shader type, name, parameters, struct parameters and metadata are
+/// unchanged.
+///
+/// # Panics
+///
+/// If the written OSO fails to parse or anything differs.
+pub fn assert_roundtrip(query: &OslQuery) {
+    let oso = query.to_oso();
+    let parsed = OslQuery::from_string(&oso)
+        .unwrap_or_else(|error| panic!("written OSO does not parse: {}\n{}", error, oso));
+    assert_same_interface(&parsed, query);
+}
+
+/// Serialize `query` to JSON, deserialize it again and assert the shader
+/// type, name, parameters, struct parameters and metadata are unchanged.
+///
+/// # Panics
+///
+/// If the JSON fails to deserialize or anything differs.
+#[cfg(feature = "json")]
+pub fn assert_json_roundtrip(query: &OslQuery) {
+    let json = serde_json::to_string(query).expect("queries always serialize");
+    let parsed: OslQuery = serde_json::from_str(&json)
+        .unwrap_or_else(|error| panic!("written JSON does not parse: {}\n{}", error, json));
+    assert_same_interface(&parsed, query);
+}
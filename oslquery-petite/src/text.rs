@@ -0,0 +1,467 @@
+//! Plain-text listing of a shader interface in the style of `oslinfo`.
+//!
+//! [`OslQuery::to_text`] renders the listing as a string. Front-ends that
+//! want to highlight it use [`OslQuery::to_text_spans`] instead, which yields
+//! the same text split into [`TextSpan`]s tagged with a [`TextRole`].
+
+use crate::query::OslQuery;
+use crate::types::{Metadata, MetadataValue, Parameter, TypedParameter};
+
+/// Options for [`OslQuery::to_text`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextOptions {
+    /// List each parameter as a block with its default and metadata on
+    /// separate lines (`oslinfo -v`).
+    pub verbose: bool,
+    /// Include shader metadata, and parameter metadata in verbose mode.
+    pub metadata: bool,
+    /// Pad names and types to line up in columns.
+    pub align: bool,
+    /// Elide array elements beyond this count.
+    pub max_array_elements: Option<usize>,
+    /// Only list the parameter with this name.
+    pub param: Option<String>,
+}
+
+impl Default for TextOptions {
+    fn default() -> Self {
+        TextOptions {
+            verbose: false,
+            metadata: true,
+            align: true,
+            max_array_elements: None,
+            param: None,
+        }
+    }
+}
+
+/// What a [`TextSpan`] represents, for highlighting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextRole {
+    /// Whitespace, padding and metadata lines
+    Plain,
+    /// Shader type and the `output` qualifier
+    Keyword,
+    /// Parameter type
+    Type,
+    /// Shader and parameter names
+    Identifier,
+    /// Default values
+    Value,
+    /// Brackets and quotes around values
+    Delimiter,
+}
+
+/// A piece of a text listing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextSpan {
+    pub text: String,
+    pub role: TextRole,
+}
+
+impl OslQuery {
+    /// Render the shader interface as text.
+    pub fn to_text(&self, options: &TextOptions) -> String {
+        self.to_text_spans(options)
+            .into_iter()
+            .map(|span| span.text)
+            .collect()
+    }
+
+    /// Render the shader interface as a sequence of tagged spans.
+    ///
+    /// Concatenating the spans' text gives the result of [`to_text`](Self::to_text).
+    pub fn to_text_spans(&self, options: &TextOptions) -> Vec<TextSpan> {
+        let mut out = Spans::default();
+
+        out.push(TextRole::Keyword, self.shader_type());
+        out.plain(" ");
+        out.push(TextRole::Identifier, self.shader_name());
+        out.plain(format!(" \"{}\"\n", self.shader_name()));
+
+        if options.metadata {
+            for meta in self.metadata() {
+                write_metadata(&mut out, meta, "\t");
+            }
+        }
+
+        let params: Vec<&Parameter> = self
+            .params()
+            .iter()
+            .filter(|param| {
+                options
+                    .param
+                    .as_deref()
+                    .is_none_or(|name| param.name.as_str() == name)
+            })
+            .collect();
+
+        let (name_width, type_width) = if options.align {
+            params
+                .iter()
+                .fold((0, 0), |(name_width, type_width), param| {
+                    (
+                        name_width.max(param.name.len()),
+                        type_width.max(param.typed_param().to_string().len()),
+                    )
+                })
+        } else {
+            (0, 0)
+        };
+
+        for param in params {
+            let name = param.name.as_str();
+            let typestring = param.typed_param().to_string();
+            let name_padding = " ".repeat(name_width.saturating_sub(name.len()) + 1);
+
+            if options.verbose {
+                out.plain("    \"");
+                out.push(TextRole::Identifier, name);
+                out.plain(format!("\"{} \"", name_padding));
+                if param.is_output() {
+                    out.push(TextRole::Keyword, "output");
+                    out.plain(" ");
+                }
+                out.push(TextRole::Type, typestring);
+                out.plain("\"\n");
+            } else {
+                out.push(TextRole::Identifier, name);
+                out.plain(name_padding);
+                // Inputs are padded to where the type of outputs ends
+                let type_padding = type_width.saturating_sub(typestring.len());
+                if param.is_output() {
+                    out.push(TextRole::Keyword, "output");
+                    out.plain(" ");
+                    out.push(TextRole::Type, typestring);
+                    out.plain(" ".repeat(type_padding + 1));
+                } else {
+                    out.push(TextRole::Type, typestring);
+                    let output_width = if options.align { 7 } else { 0 };
+                    out.plain(" ".repeat(type_padding + output_width + 1));
+                }
+            }
+
+            write_default(&mut out, param, options);
+
+            if options.verbose && options.metadata {
+                for meta in param.metadata() {
+                    write_metadata(&mut out, meta, "\t\t");
+                }
+            }
+        }
+
+        out.0
+    }
+}
+
+impl Parameter {
+    /// The default value as [`OslQuery::to_text`] lists it, e.g. `0.5`,
+    /// `"name"`, `[1 0 0]` or `<no default>`.
+    ///
+    /// With `verbose`, this is the form of `oslinfo -v`, which puts a space
+    /// before quoted and missing defaults. Column padding is not included.
+    pub fn default_display(&self, verbose: bool) -> String {
+        let mut out = Spans::default();
+        write_default_value(&mut out, &param_default_text(self, None), verbose);
+        out.0.into_iter().map(|span| span.text).collect()
+    }
+}
+
+/// Collects spans.
+#[derive(Default)]
+struct Spans(Vec<TextSpan>);
+
+impl Spans {
+    fn push(&mut self, role: TextRole, text: impl Into<String>) {
+        let text = text.into();
+        if !text.is_empty() {
+            self.0.push(TextSpan { text, role });
+        }
+    }
+
+    fn plain(&mut self, text: impl Into<String>) {
+        self.push(TextRole::Plain, text);
+    }
+}
+
+/// The textual form of a default value.
+enum DefaultText {
+    Missing,
+    Scalar(String),
+    Quoted(String),
+    /// Bracketed list; `nested` if the elements are bracketed themselves.
+    List {
+        items: String,
+        nested: bool,
+    },
+}
+
+fn write_default(out: &mut Spans, param: &Parameter, options: &TextOptions) {
+    let default = param_default_text(param, options.max_array_elements);
+
+    if options.verbose {
+        out.plain("\t\tDefault value: ");
+    } else {
+        out.plain(match default {
+            DefaultText::Scalar(_) => "   ",
+            DefaultText::List { nested: true, .. } => " ",
+            _ => "  ",
+        });
+    }
+    write_default_value(out, &default, options.verbose);
+    out.plain("\n");
+}
+
+/// Write a default without padding.
+fn write_default_value(out: &mut Spans, default: &DefaultText, verbose: bool) {
+    match default {
+        DefaultText::Missing => {
+            out.push(TextRole::Delimiter, if verbose { " <" } else { "<" });
+            out.push(TextRole::Value, "no default");
+            out.push(TextRole::Delimiter, ">");
+        }
+        DefaultText::Scalar(value) => {
+            out.push(TextRole::Value, value.as_str());
+        }
+        DefaultText::Quoted(value) => {
+            out.push(TextRole::Delimiter, if verbose { " \"" } else { "\"" });
+            out.push(TextRole::Value, value.as_str());
+            out.push(TextRole::Delimiter, "\"");
+        }
+        DefaultText::List { items, .. } => {
+            out.push(TextRole::Delimiter, "[");
+            out.push(TextRole::Value, items.as_str());
+            out.push(TextRole::Delimiter, "]");
+        }
+    }
+}
+
+fn param_default_text(param: &Parameter, max_elements: Option<usize>) -> DefaultText {
+    if param.is_output() {
+        DefaultText::Missing
+    } else {
+        default_text(param.typed_param(), max_elements)
+    }
+}
+
+fn default_text(typed_param: &TypedParameter, max_elements: Option<usize>) -> DefaultText {
+    let flat = |items: String| DefaultText::List {
+        items,
+        nested: false,
+    };
+    let nested = |items: String| DefaultText::List {
+        items,
+        nested: true,
+    };
+
+    match typed_param {
+        TypedParameter::Int { default: Some(v) } => DefaultText::Scalar(v.to_string()),
+        TypedParameter::Float { default: Some(v) } => DefaultText::Scalar(v.to_string()),
+        TypedParameter::String { default: Some(s) } => DefaultText::Quoted(escape_string(s)),
+        TypedParameter::Color {
+            default: Some(v), ..
+        }
+        | TypedParameter::Point {
+            default: Some(v), ..
+        }
+        | TypedParameter::Vector {
+            default: Some(v), ..
+        }
+        | TypedParameter::Normal {
+            default: Some(v), ..
+        } => flat(join(v, None)),
+        TypedParameter::Matrix { default: Some(m) } => flat(join(&m[..], None)),
+        TypedParameter::IntArray {
+            default: Some(v), ..
+        }
+        | TypedParameter::IntDynamicArray { default: Some(v) } => flat(join(v, max_elements)),
+        TypedParameter::FloatArray {
+            default: Some(v), ..
+        }
+        | TypedParameter::FloatDynamicArray { default: Some(v) } => flat(join(v, max_elements)),
+        TypedParameter::StringArray {
+            default: Some(v), ..
+        }
+        | TypedParameter::StringDynamicArray { default: Some(v) } => {
+            let quoted: Vec<String> = v
+                .iter()
+                .map(|s| format!("\"{}\"", escape_string(s)))
+                .collect();
+            flat(join(&quoted, max_elements))
+        }
+        TypedParameter::ColorArray {
+            default: Some(v), ..
+        }
+        | TypedParameter::PointArray {
+            default: Some(v), ..
+        }
+        | TypedParameter::VectorArray {
+            default: Some(v), ..
+        }
+        | TypedParameter::NormalArray {
+            default: Some(v), ..
+        }
+        | TypedParameter::ColorDynamicArray {
+            default: Some(v), ..
+        }
+        | TypedParameter::PointDynamicArray {
+            default: Some(v), ..
+        }
+        | TypedParameter::VectorDynamicArray {
+            default: Some(v), ..
+        }
+        | TypedParameter::NormalDynamicArray {
+            default: Some(v), ..
+        } => {
+            let items: Vec<String> = v.iter().map(|t| format!("[{}]", join(t, None))).collect();
+            nested(join(&items, max_elements))
+        }
+        TypedParameter::MatrixArray {
+            default: Some(v), ..
+        }
+        | TypedParameter::MatrixDynamicArray { default: Some(v) } => {
+            let items: Vec<String> = v.iter().map(|m| format!("[{}]", join(m, None))).collect();
+            nested(join(&items, max_elements))
+        }
+        _ => DefaultText::Missing,
+    }
+}
+
+/// Join values with spaces, eliding those beyond `max` with `...`.
+fn join<T: ToString>(values: &[T], max: Option<usize>) -> String {
+    let shown = max.unwrap_or(values.len()).min(values.len());
+    let mut items: Vec<String> = values[..shown].iter().map(T::to_string).collect();
+    if shown < values.len() {
+        items.push("...".to_string());
+    }
+    items.join(" ")
+}
+
+fn write_metadata(out: &mut Spans, meta: &Metadata, indent: &str) {
+    let line = match &meta.value {
+        MetadataValue::Int(v) => format!("int {} = {}", meta.name, v),
+        MetadataValue::Float(v) => format!("float {} = {}", meta.name, v),
+        MetadataValue::String(v) => format!("string {} = \"{}\"", meta.name, escape_string(v)),
+        MetadataValue::IntArray(v) => {
+            let values: String = v.iter().map(|v| format!(" {}", v)).collect();
+            format!("int[] {} ={}", meta.name, values)
+        }
+        MetadataValue::FloatArray(v) => {
+            let values: String = v.iter().map(|v| format!(" {}", v)).collect();
+            format!("float[] {} ={}", meta.name, values)
+        }
+        MetadataValue::StringArray(v) => {
+            let values: String = v
+                .iter()
+                .map(|v| format!(" \"{}\"", escape_string(v)))
+                .collect();
+            format!("string[] {} ={}", meta.name, values)
+        }
+    };
+    out.plain(format!("{}metadata: {}\n", indent, line));
+}
+
+/// Escape a string for a double-quoted OSL string literal.
+pub(crate) fn escape_string(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+        .replace('\t', "\\t")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query() -> OslQuery {
+        OslQuery::from_string(
+            "OpenShadingLanguage 1.12\n\
+             surface test\n\
+             param\tfloat[4]\tweights\t1 2 3 4\n\
+             oparam\tfloat\tout\t0\n\
+             code ___main___\n",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_max_array_elements() {
+        let options = TextOptions {
+            max_array_elements: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(
+            query().to_text(&options),
+            "surface test \"test\"\n\
+             weights float[4]          [1 2 ...]\n\
+             out     output float      <no default>\n"
+        );
+    }
+
+    #[test]
+    fn test_unaligned() {
+        let options = TextOptions {
+            align: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            query().to_text(&options),
+            "surface test \"test\"\n\
+             weights float[4]   [1 2 3 4]\n\
+             out output float   <no default>\n"
+        );
+    }
+
+    #[test]
+    fn test_spans_concatenate_to_text() {
+        let options = TextOptions {
+            verbose: true,
+            ..Default::default()
+        };
+        let query = query();
+        let spans = query.to_text_spans(&options);
+        assert!(
+            spans
+                .iter()
+                .any(|span| span.role == TextRole::Keyword && span.text == "output")
+        );
+        let text: String = spans.iter().map(|span| span.text.as_str()).collect();
+        assert_eq!(text, query.to_text(&options));
+    }
+
+    #[test]
+    fn test_default_display() {
+        let query = OslQuery::from_string(
+            "OpenShadingLanguage 1.12\n\
+             surface test\n\
+             param\tfloat\tKd\t0.5\n\
+             param\tstring\tname\t\"a\tb\"\n\
+             param\tint[3]\tids\t1 2 3\n\
+             param\tcolor[2]\tramp\t0 0 0 1 1 1\n\
+             param\tmatrix\txform\t1 0 0 0 0 1 0 0 0 0 1 0 0 0 0 1\n\
+             param\tfloat[]\tweights\n\
+             oparam\tcolor\tCout\t0 0 0\n\
+             code ___main___\n",
+        )
+        .unwrap();
+        let display =
+            |name: &str, verbose: bool| query.param_by_name(name).unwrap().default_display(verbose);
+
+        assert_eq!(display("Kd", false), "0.5");
+        assert_eq!(display("name", false), "\"a\\tb\"");
+        assert_eq!(display("name", true), " \"a\\tb\"");
+        assert_eq!(display("ids", false), "[1 2 3]");
+        assert_eq!(display("ramp", false), "[[0 0 0] [1 1 1]]");
+        assert_eq!(display("xform", true), "[1 0 0 0 0 1 0 0 0 0 1 0 0 0 0 1]");
+        assert_eq!(display("weights", false), "<no default>");
+        assert_eq!(display("Cout", false), "<no default>");
+        assert_eq!(display("Cout", true), " <no default>");
+
+        // The listing contains the same text.
+        let text = query.to_text(&TextOptions::default());
+        for param in query.params() {
+            assert!(text.contains(&param.default_display(false)));
+        }
+    }
+}
@@ -0,0 +1,30 @@
+//! Optional [`tracing`](https://docs.rs/tracing) instrumentation.
+//!
+//! With the `tracing` feature these macros forward to `tracing`; without it
+//! they expand to nothing, so their arguments are not even evaluated.
+
+/// Emit a `tracing::debug!` event.
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::debug!($($arg)*);
+    };
+}
+
+/// Emit a `tracing::error!` event.
+macro_rules! error {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::error!($($arg)*);
+    };
+}
+
+/// Enter a debug-level span until the end of the enclosing block.
+macro_rules! enter_span {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!($($arg)*).entered();
+    };
+}
+
+pub(crate) use {debug, enter_span, error};
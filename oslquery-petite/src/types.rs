@@ -4,6 +4,7 @@
 //! to have a mismatch between a parameter's type and its default value.
 
 use std::fmt;
+use std::sync::OnceLock;
 use ustr::Ustr;
 
 /// A typed parameter that unifies type information with its potential value.
@@ -19,7 +20,7 @@ pub enum TypedParameter {
     /// Float parameter
     Float { default: Option<f32> },
     /// String parameter
-    String { default: Option<String> },
+    String { default: Option<StringValue> },
 
     // ============= Geometric Types =============
     // These are always 3 floats in OSL
@@ -44,7 +45,9 @@ pub enum TypedParameter {
         space: Option<Ustr>,
     },
     /// 4x4 transformation matrix (16 floats)
-    Matrix { default: Option<[f32; 16]> },
+    ///
+    /// The default is boxed so it doesn't make every variant 64 bytes larger.
+    Matrix { default: Option<Box<[f32; 16]>> },
 
     // ============= Fixed-Size Array Types =============
     /// Fixed-size array of integers
@@ -60,7 +63,7 @@ pub enum TypedParameter {
     /// Fixed-size array of strings
     StringArray {
         size: usize,
-        default: Option<Vec<String>>,
+        default: Option<Vec<StringValue>>,
     },
     /// Fixed-size array of colors
     ColorArray {
@@ -98,7 +101,7 @@ pub enum TypedParameter {
     /// Dynamic array of floats
     FloatDynamicArray { default: Option<Vec<f32>> },
     /// Dynamic array of strings
-    StringDynamicArray { default: Option<Vec<String>> },
+    StringDynamicArray { default: Option<Vec<StringValue>> },
     /// Dynamic array of colors
     ColorDynamicArray {
         default: Option<Vec<[f32; 3]>>,
@@ -162,6 +165,121 @@ impl TypedParameter {
         }
     }
 
+    /// Get the number of values the default holds, or 0 without one.
+    ///
+    /// Single colors, points, vectors and normals hold 3 values and single
+    /// matrices 16; arrays hold one value per element. Closures have no
+    /// default.
+    pub fn default_count(&self) -> usize {
+        match self {
+            TypedParameter::Int { default } => default.map_or(0, |_| 1),
+            TypedParameter::Float { default } => default.map_or(0, |_| 1),
+            TypedParameter::String { default } => default.as_ref().map_or(0, |_| 1),
+            TypedParameter::Color { default, .. }
+            | TypedParameter::Point { default, .. }
+            | TypedParameter::Vector { default, .. }
+            | TypedParameter::Normal { default, .. } => default.map_or(0, |v| v.len()),
+            TypedParameter::Matrix { default } => default.as_ref().map_or(0, |m| m.len()),
+
+            TypedParameter::IntArray { default, .. }
+            | TypedParameter::IntDynamicArray { default } => default.as_ref().map_or(0, Vec::len),
+            TypedParameter::FloatArray { default, .. }
+            | TypedParameter::FloatDynamicArray { default } => default.as_ref().map_or(0, Vec::len),
+            TypedParameter::StringArray { default, .. }
+            | TypedParameter::StringDynamicArray { default } => {
+                default.as_ref().map_or(0, Vec::len)
+            }
+            TypedParameter::ColorArray { default, .. }
+            | TypedParameter::PointArray { default, .. }
+            | TypedParameter::VectorArray { default, .. }
+            | TypedParameter::NormalArray { default, .. }
+            | TypedParameter::ColorDynamicArray { default, .. }
+            | TypedParameter::PointDynamicArray { default, .. }
+            | TypedParameter::VectorDynamicArray { default, .. }
+            | TypedParameter::NormalDynamicArray { default, .. } => {
+                default.as_ref().map_or(0, Vec::len)
+            }
+            TypedParameter::MatrixArray { default, .. }
+            | TypedParameter::MatrixDynamicArray { default } => {
+                default.as_ref().map_or(0, Vec::len)
+            }
+
+            TypedParameter::Closure { .. } => 0,
+        }
+    }
+
+    /// Get the number of values a complete default holds, counted as in
+    /// [`default_count`](Self::default_count).
+    ///
+    /// `None` for dynamic arrays, which can have any length, and closures.
+    pub fn expected_default_count(&self) -> Option<usize> {
+        match self {
+            TypedParameter::Int { .. }
+            | TypedParameter::Float { .. }
+            | TypedParameter::String { .. } => Some(1),
+            TypedParameter::Color { .. }
+            | TypedParameter::Point { .. }
+            | TypedParameter::Vector { .. }
+            | TypedParameter::Normal { .. } => Some(3),
+            TypedParameter::Matrix { .. } => Some(16),
+
+            TypedParameter::IntArray { size, .. }
+            | TypedParameter::FloatArray { size, .. }
+            | TypedParameter::StringArray { size, .. }
+            | TypedParameter::ColorArray { size, .. }
+            | TypedParameter::PointArray { size, .. }
+            | TypedParameter::VectorArray { size, .. }
+            | TypedParameter::NormalArray { size, .. }
+            | TypedParameter::MatrixArray { size, .. } => Some(*size),
+
+            _ => None,
+        }
+    }
+
+    /// Get the number of values of one element, counted as in
+    /// [`default_count`](Self::default_count): 3 for colors, points, vectors
+    /// and normals, 16 for matrices, 0 for closures and 1 otherwise.
+    pub fn component_count(&self) -> usize {
+        match self {
+            TypedParameter::Color { .. }
+            | TypedParameter::Point { .. }
+            | TypedParameter::Vector { .. }
+            | TypedParameter::Normal { .. }
+            | TypedParameter::ColorArray { .. }
+            | TypedParameter::PointArray { .. }
+            | TypedParameter::VectorArray { .. }
+            | TypedParameter::NormalArray { .. }
+            | TypedParameter::ColorDynamicArray { .. }
+            | TypedParameter::PointDynamicArray { .. }
+            | TypedParameter::VectorDynamicArray { .. }
+            | TypedParameter::NormalDynamicArray { .. } => 3,
+            TypedParameter::Matrix { .. }
+            | TypedParameter::MatrixArray { .. }
+            | TypedParameter::MatrixDynamicArray { .. } => 16,
+            TypedParameter::Closure { .. } => 0,
+            _ => 1,
+        }
+    }
+
+    /// Get the number of array elements: the size of a fixed-size array, or
+    /// the length of the default of a dynamic one.
+    ///
+    /// `None` for dynamic arrays without a default and for non-arrays.
+    pub fn resolved_length(&self) -> Option<usize> {
+        match self {
+            TypedParameter::IntArray { size, .. }
+            | TypedParameter::FloatArray { size, .. }
+            | TypedParameter::StringArray { size, .. }
+            | TypedParameter::ColorArray { size, .. }
+            | TypedParameter::PointArray { size, .. }
+            | TypedParameter::VectorArray { size, .. }
+            | TypedParameter::NormalArray { size, .. }
+            | TypedParameter::MatrixArray { size, .. } => Some(*size),
+            _ if self.is_dynamic_array() && self.has_default() => Some(self.default_count()),
+            _ => None,
+        }
+    }
+
     /// Check if this is an array type.
     pub fn is_array(&self) -> bool {
         !matches!(
@@ -198,6 +316,70 @@ impl TypedParameter {
         matches!(self, TypedParameter::Closure { .. })
     }
 
+    /// Get the closure type, e.g. `color` for `closure color`.
+    pub fn closure_type(&self) -> Option<Ustr> {
+        match self {
+            TypedParameter::Closure { closure_type } => Some(*closure_type),
+            _ => None,
+        }
+    }
+
+    /// Check if the element type is `point`, `vector` or `normal`.
+    pub fn is_geometric(&self) -> bool {
+        matches!(
+            self,
+            TypedParameter::Point { .. }
+                | TypedParameter::Vector { .. }
+                | TypedParameter::Normal { .. }
+                | TypedParameter::PointArray { .. }
+                | TypedParameter::VectorArray { .. }
+                | TypedParameter::NormalArray { .. }
+                | TypedParameter::PointDynamicArray { .. }
+                | TypedParameter::VectorDynamicArray { .. }
+                | TypedParameter::NormalDynamicArray { .. }
+        )
+    }
+
+    /// Check if this is a single `int` or `float`.
+    pub fn is_scalar(&self) -> bool {
+        matches!(
+            self,
+            TypedParameter::Int { .. } | TypedParameter::Float { .. }
+        )
+    }
+
+    /// Check if the values are ints: `int` and arrays of it.
+    pub fn is_integer_type(&self) -> bool {
+        matches!(
+            self,
+            TypedParameter::Int { .. }
+                | TypedParameter::IntArray { .. }
+                | TypedParameter::IntDynamicArray { .. }
+        )
+    }
+
+    /// Check if the values are floats: `float`, `color`, `point`, `vector`,
+    /// `normal` and `matrix`, and arrays of them.
+    pub fn is_float_type(&self) -> bool {
+        !self.is_integer_type() && !self.is_string_type() && !self.is_closure()
+    }
+
+    /// Check if the values are strings: `string` and arrays of it.
+    pub fn is_string_type(&self) -> bool {
+        matches!(
+            self,
+            TypedParameter::String { .. }
+                | TypedParameter::StringArray { .. }
+                | TypedParameter::StringDynamicArray { .. }
+        )
+    }
+
+    /// Check if the element type has three float components: `color`,
+    /// `point`, `vector` and `normal`, and arrays of them.
+    pub fn is_aggregate(&self) -> bool {
+        self.is_float_type() && self.component_count() == 3
+    }
+
     /// Get the type name as a string.
     pub fn type_name(&self) -> &'static str {
         match self {
@@ -231,155 +413,1538 @@ impl TypedParameter {
             TypedParameter::Closure { .. } => "closure",
         }
     }
-}
 
-impl fmt::Display for TypedParameter {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    /// The OpenImageIO `TypeDesc` of the (element) type, as a string that
+    /// `TypeDesc::fromstring()` accepts: `float`, `int`, `string`, `color`,
+    /// `point`, `vector`, `normal` or `matrix`.
+    ///
+    /// Arrays give their element type; the length is up to the caller.
+    /// Closures have no `TypeDesc` and give `unknown`.
+    pub fn as_oiio_typedesc_string(&self) -> &'static str {
+        match self {
+            TypedParameter::Int { .. }
+            | TypedParameter::IntArray { .. }
+            | TypedParameter::IntDynamicArray { .. } => "int",
+            TypedParameter::Float { .. }
+            | TypedParameter::FloatArray { .. }
+            | TypedParameter::FloatDynamicArray { .. } => "float",
+            TypedParameter::String { .. }
+            | TypedParameter::StringArray { .. }
+            | TypedParameter::StringDynamicArray { .. } => "string",
+            TypedParameter::Color { .. }
+            | TypedParameter::ColorArray { .. }
+            | TypedParameter::ColorDynamicArray { .. } => "color",
+            TypedParameter::Point { .. }
+            | TypedParameter::PointArray { .. }
+            | TypedParameter::PointDynamicArray { .. } => "point",
+            TypedParameter::Vector { .. }
+            | TypedParameter::VectorArray { .. }
+            | TypedParameter::VectorDynamicArray { .. } => "vector",
+            TypedParameter::Normal { .. }
+            | TypedParameter::NormalArray { .. }
+            | TypedParameter::NormalDynamicArray { .. } => "normal",
+            TypedParameter::Matrix { .. }
+            | TypedParameter::MatrixArray { .. }
+            | TypedParameter::MatrixDynamicArray { .. } => "matrix",
+            TypedParameter::Closure { .. } => "unknown",
+        }
+    }
+
+    /// The type as written by OSL's `OSLQuery`, i.e. `Parameter::type.c_str()`
+    /// with `closure ` prepended for closures, as `oslinfo` prints it:
+    ///
+    /// - Scalars and aggregates use their OSL keyword: `int`, `float`,
+    ///   `string`, `color`, `point`, `vector`, `normal`, `matrix`.
+    /// - Fixed-size arrays append the length: `color[4]`.
+    /// - Unsized arrays append empty brackets: `float[]`.
+    /// - Closures are `closure` followed by the closure type: `closure color`.
+    ///
+    /// This is also the [`Display`](fmt::Display) form. Unlike
+    /// [`type_name`](Self::type_name), it keeps array lengths.
+    pub fn oslquery_typestring(&self) -> String {
+        match self {
+            TypedParameter::IntArray { size, .. }
+            | TypedParameter::FloatArray { size, .. }
+            | TypedParameter::StringArray { size, .. }
+            | TypedParameter::ColorArray { size, .. }
+            | TypedParameter::PointArray { size, .. }
+            | TypedParameter::VectorArray { size, .. }
+            | TypedParameter::NormalArray { size, .. }
+            | TypedParameter::MatrixArray { size, .. } => {
+                let name = self.type_name();
+                format!("{}[{}]", &name[..name.len() - 2], size)
+            }
+            TypedParameter::Closure { closure_type } => format!("closure {}", closure_type),
+            other => other.type_name().to_string(),
+        }
+    }
+
+    /// Convert integer types to their float counterparts.
+    ///
+    /// `Int`, `IntArray` and `IntDynamicArray` become `Float`, `FloatArray`
+    /// and `FloatDynamicArray` with their defaults converted. All other types
+    /// are returned unchanged.
+    pub fn promote_int_to_float(&self) -> TypedParameter {
+        match self {
+            TypedParameter::Int { default } => TypedParameter::Float {
+                default: default.map(|v| v as f32),
+            },
+            TypedParameter::IntArray { size, default } => TypedParameter::FloatArray {
+                size: *size,
+                default: default
+                    .as_ref()
+                    .map(|v| v.iter().map(|&i| i as f32).collect()),
+            },
+            TypedParameter::IntDynamicArray { default } => TypedParameter::FloatDynamicArray {
+                default: default
+                    .as_ref()
+                    .map(|v| v.iter().map(|&i| i as f32).collect()),
+            },
+            other => other.clone(),
+        }
+    }
+
+    /// Convert float types to their integer counterparts, truncating defaults.
+    ///
+    /// The inverse of [`promote_int_to_float`](Self::promote_int_to_float).
+    /// All other types are returned unchanged.
+    pub fn demote_float_to_int(&self) -> TypedParameter {
+        match self {
+            TypedParameter::Float { default } => TypedParameter::Int {
+                default: default.map(|v| v as i32),
+            },
+            TypedParameter::FloatArray { size, default } => TypedParameter::IntArray {
+                size: *size,
+                default: default
+                    .as_ref()
+                    .map(|v| v.iter().map(|&f| f as i32).collect()),
+            },
+            TypedParameter::FloatDynamicArray { default } => TypedParameter::IntDynamicArray {
+                default: default
+                    .as_ref()
+                    .map(|v| v.iter().map(|&f| f as i32).collect()),
+            },
+            other => other.clone(),
+        }
+    }
+
+    /// Convert a scalar `Float` into a `FloatDynamicArray` holding its
+    /// default as the only element.
+    ///
+    /// All other types are returned unchanged.
+    pub fn widen_to_float_array(&self) -> TypedParameter {
+        match self {
+            TypedParameter::Float { default } => TypedParameter::FloatDynamicArray {
+                default: default.map(|v| vec![v]),
+            },
+            other => other.clone(),
+        }
+    }
+
+    /// Return a copy with the space set to `space`.
+    ///
+    /// The default is not converted; only the label changes. Types without
+    /// a space are returned unchanged.
+    pub fn with_space(&self, space: Ustr) -> TypedParameter {
+        let mut typed_param = self.clone();
+        if let Some(old) = typed_param.space_mut() {
+            *old = Some(space);
+        }
+        typed_param
+    }
+
+    /// Get the space of a color, point, vector or normal (array) default,
+    /// e.g. `world`.
+    pub fn space(&self) -> Option<Ustr> {
+        match self {
+            TypedParameter::Color { space, .. }
+            | TypedParameter::Point { space, .. }
+            | TypedParameter::Vector { space, .. }
+            | TypedParameter::Normal { space, .. }
+            | TypedParameter::ColorArray { space, .. }
+            | TypedParameter::PointArray { space, .. }
+            | TypedParameter::VectorArray { space, .. }
+            | TypedParameter::NormalArray { space, .. }
+            | TypedParameter::ColorDynamicArray { space, .. }
+            | TypedParameter::PointDynamicArray { space, .. }
+            | TypedParameter::VectorDynamicArray { space, .. }
+            | TypedParameter::NormalDynamicArray { space, .. } => *space,
+            _ => None,
+        }
+    }
+
+    /// Remove the default value, if any.
+    pub fn clear_default(&mut self) {
+        match self {
+            TypedParameter::Int { default } => *default = None,
+            TypedParameter::Float { default } => *default = None,
+            TypedParameter::String { default } => *default = None,
+            TypedParameter::Color { default, .. } => *default = None,
+            TypedParameter::Point { default, .. } => *default = None,
+            TypedParameter::Vector { default, .. } => *default = None,
+            TypedParameter::Normal { default, .. } => *default = None,
+            TypedParameter::Matrix { default } => *default = None,
+
+            TypedParameter::IntArray { default, .. } => *default = None,
+            TypedParameter::FloatArray { default, .. } => *default = None,
+            TypedParameter::StringArray { default, .. } => *default = None,
+            TypedParameter::ColorArray { default, .. } => *default = None,
+            TypedParameter::PointArray { default, .. } => *default = None,
+            TypedParameter::VectorArray { default, .. } => *default = None,
+            TypedParameter::NormalArray { default, .. } => *default = None,
+            TypedParameter::MatrixArray { default, .. } => *default = None,
+
+            TypedParameter::IntDynamicArray { default } => *default = None,
+            TypedParameter::FloatDynamicArray { default } => *default = None,
+            TypedParameter::StringDynamicArray { default } => *default = None,
+            TypedParameter::ColorDynamicArray { default, .. } => *default = None,
+            TypedParameter::PointDynamicArray { default, .. } => *default = None,
+            TypedParameter::VectorDynamicArray { default, .. } => *default = None,
+            TypedParameter::NormalDynamicArray { default, .. } => *default = None,
+            TypedParameter::MatrixDynamicArray { default } => *default = None,
+
+            TypedParameter::Closure { .. } => {} // Already has no defaults
+        }
+    }
+
+    /// Snap float defaults that are within `epsilon` of zero to `0.0`, so
+    /// noise such as `1e-8` and negative zero compare equal to zero.
+    pub fn normalize_defaults(&mut self, epsilon: f32) {
+        for value in self.default_floats_mut().unwrap_or_default() {
+            if value.abs() <= epsilon {
+                *value = 0.0;
+            }
+        }
+    }
+
+    /// Get the default of a float-based parameter as flat floats: the
+    /// components of colors, points, vectors, normals and matrices one after
+    /// the other, and of array elements in order.
+    ///
+    /// `None` without a default and for other types.
+    pub fn default_floats(&self) -> Option<&[f32]> {
+        Some(match self {
+            TypedParameter::Float { default: Some(v) } => std::slice::from_ref(v),
+            TypedParameter::Color {
+                default: Some(v), ..
+            }
+            | TypedParameter::Point {
+                default: Some(v), ..
+            }
+            | TypedParameter::Vector {
+                default: Some(v), ..
+            }
+            | TypedParameter::Normal {
+                default: Some(v), ..
+            } => v,
+            TypedParameter::Matrix { default: Some(v) } => &v[..],
+            TypedParameter::FloatArray {
+                default: Some(v), ..
+            }
+            | TypedParameter::FloatDynamicArray { default: Some(v) } => v,
+            TypedParameter::ColorArray {
+                default: Some(v), ..
+            }
+            | TypedParameter::PointArray {
+                default: Some(v), ..
+            }
+            | TypedParameter::VectorArray {
+                default: Some(v), ..
+            }
+            | TypedParameter::NormalArray {
+                default: Some(v), ..
+            }
+            | TypedParameter::ColorDynamicArray {
+                default: Some(v), ..
+            }
+            | TypedParameter::PointDynamicArray {
+                default: Some(v), ..
+            }
+            | TypedParameter::VectorDynamicArray {
+                default: Some(v), ..
+            }
+            | TypedParameter::NormalDynamicArray {
+                default: Some(v), ..
+            } => v.as_flattened(),
+            TypedParameter::MatrixArray {
+                default: Some(v), ..
+            }
+            | TypedParameter::MatrixDynamicArray { default: Some(v) } => v.as_flattened(),
+            _ => return None,
+        })
+    }
+
+    /// Get the default as flat floats, whatever the type: floats as in
+    /// [`default_floats`](Self::default_floats), ints converted `as f32`.
+    ///
+    /// Empty for strings, closures and parameters without a default. This
+    /// is the form renderer APIs taking `float*` defaults want.
+    pub fn default_f32_slice_lossy(&self) -> Vec<f32> {
+        match self.default_floats() {
+            Some(floats) => floats.to_vec(),
+            None => self.default_ints().iter().map(|&v| v as f32).collect(),
+        }
+    }
+
+    /// Get the default as flat ints, whatever the type: ints as they are,
+    /// floats converted `as i32`, which truncates toward zero, saturates and
+    /// turns NaN into 0.
+    ///
+    /// Empty for strings, closures and parameters without a default.
+    pub fn default_i32_slice_lossy(&self) -> Vec<i32> {
+        match self.default_floats() {
+            Some(floats) => floats.iter().map(|&v| v as i32).collect(),
+            None => self.default_ints().to_vec(),
+        }
+    }
+
+    /// The default of an `int` or `int` array; empty for other types.
+    fn default_ints(&self) -> &[i32] {
+        match self {
+            TypedParameter::Int { default: Some(v) } => std::slice::from_ref(v),
+            TypedParameter::IntArray {
+                default: Some(v), ..
+            }
+            | TypedParameter::IntDynamicArray { default: Some(v) } => v,
+            _ => &[],
+        }
+    }
+
+    fn default_floats_mut(&mut self) -> Option<&mut [f32]> {
+        Some(match self {
+            TypedParameter::Float { default: Some(v) } => std::slice::from_mut(v),
+            TypedParameter::Color {
+                default: Some(v), ..
+            }
+            | TypedParameter::Point {
+                default: Some(v), ..
+            }
+            | TypedParameter::Vector {
+                default: Some(v), ..
+            }
+            | TypedParameter::Normal {
+                default: Some(v), ..
+            } => v,
+            TypedParameter::Matrix { default: Some(v) } => &mut v[..],
+            TypedParameter::FloatArray {
+                default: Some(v), ..
+            }
+            | TypedParameter::FloatDynamicArray { default: Some(v) } => v,
+            TypedParameter::ColorArray {
+                default: Some(v), ..
+            }
+            | TypedParameter::PointArray {
+                default: Some(v), ..
+            }
+            | TypedParameter::VectorArray {
+                default: Some(v), ..
+            }
+            | TypedParameter::NormalArray {
+                default: Some(v), ..
+            }
+            | TypedParameter::ColorDynamicArray {
+                default: Some(v), ..
+            }
+            | TypedParameter::PointDynamicArray {
+                default: Some(v), ..
+            }
+            | TypedParameter::VectorDynamicArray {
+                default: Some(v), ..
+            }
+            | TypedParameter::NormalDynamicArray {
+                default: Some(v), ..
+            } => v.as_flattened_mut(),
+            TypedParameter::MatrixArray {
+                default: Some(v), ..
+            }
+            | TypedParameter::MatrixDynamicArray { default: Some(v) } => v.as_flattened_mut(),
+            _ => return None,
+        })
+    }
+
+    /// Check if `other` is the same apart from the [`space`](Self::space),
+    /// e.g. a color with the same default in another color space.
+    pub fn eq_ignoring_space(&self, other: &Self) -> bool {
+        if self == other {
+            return true;
+        }
+        let without_space = |typed_param: &Self| {
+            let mut typed_param = typed_param.clone();
+            if let Some(space) = typed_param.space_mut() {
+                *space = None;
+            }
+            typed_param
+        };
+        without_space(self) == without_space(other)
+    }
+
+    /// The space of colors, points, vectors and normals and arrays thereof.
+    pub(crate) fn space_mut(&mut self) -> Option<&mut Option<Ustr>> {
+        match self {
+            TypedParameter::Color { space, .. }
+            | TypedParameter::Point { space, .. }
+            | TypedParameter::Vector { space, .. }
+            | TypedParameter::Normal { space, .. }
+            | TypedParameter::ColorArray { space, .. }
+            | TypedParameter::PointArray { space, .. }
+            | TypedParameter::VectorArray { space, .. }
+            | TypedParameter::NormalArray { space, .. }
+            | TypedParameter::ColorDynamicArray { space, .. }
+            | TypedParameter::PointDynamicArray { space, .. }
+            | TypedParameter::VectorDynamicArray { space, .. }
+            | TypedParameter::NormalDynamicArray { space, .. } => Some(space),
+            _ => None,
+        }
+    }
+
+    /// Check whether a value of this type can be connected to `target`.
+    ///
+    /// Element types are compatible if they have the same number of
+    /// components (`int` and `float`; `color`, `point`, `vector` and
+    /// `normal`), or if a single `int`/`float` is broadcast to a triple, as
+    /// OSL does when assigning a float to a color. Arrays are compatible with
+    /// arrays of the same length or with unsized arrays; scalars only with
+    /// scalars. Closures are compatible with closures of the same type.
+    pub fn is_compatible_with(&self, target: &TypedParameter) -> bool {
+        if let (
+            TypedParameter::Closure { closure_type: a },
+            TypedParameter::Closure { closure_type: b },
+        ) = (self, target)
+        {
+            return a == b;
+        }
+
+        let shapes = match (self.shape(), target.shape()) {
+            (Shape::Scalar, Shape::Scalar)
+            | (Shape::Fixed(_), Shape::Dynamic)
+            | (Shape::Dynamic, Shape::Dynamic) => true,
+            (Shape::Fixed(a), Shape::Fixed(b)) => a == b,
+            _ => false,
+        };
+
+        let elements = match (self.element_type(), target.element_type()) {
+            (ElementType::Closure, _) | (_, ElementType::Closure) => false,
+            (a, b) if a == b => true,
+            (ElementType::Int | ElementType::Float, ElementType::Int | ElementType::Float) => true,
+            (ElementType::Int | ElementType::Float, ElementType::Triple) => true,
+            _ => false,
+        };
+
+        shapes && elements
+    }
+
+    /// Convert this parameter to the type of `target`, keeping its default.
+    ///
+    /// The result is `target` (including its array size and space) with the
+    /// default values of `self` converted to the target's element type:
+    /// floats are truncated to ints and ints/floats are broadcast to
+    /// triples. Returns `None` if the types are not
+    /// [compatible](Self::is_compatible_with).
+    pub fn coerce_to(&self, target: &TypedParameter) -> Option<TypedParameter> {
+        if !self.is_compatible_with(target) {
+            return None;
+        }
+
+        let elements = self
+            .default_elements()
+            .map(|elements| elements.convert(target.element_type()));
+        Some(target.with_default_elements(elements))
+    }
+
+    fn shape(&self) -> Shape {
+        match self {
+            TypedParameter::IntArray { size, .. }
+            | TypedParameter::FloatArray { size, .. }
+            | TypedParameter::StringArray { size, .. }
+            | TypedParameter::ColorArray { size, .. }
+            | TypedParameter::PointArray { size, .. }
+            | TypedParameter::VectorArray { size, .. }
+            | TypedParameter::NormalArray { size, .. }
+            | TypedParameter::MatrixArray { size, .. } => Shape::Fixed(*size),
+            _ if self.is_dynamic_array() => Shape::Dynamic,
+            _ => Shape::Scalar,
+        }
+    }
+
+    fn element_type(&self) -> ElementType {
         match self {
-            TypedParameter::IntArray { size, .. } => write!(f, "int[{}]", size),
-            TypedParameter::FloatArray { size, .. } => write!(f, "float[{}]", size),
-            TypedParameter::StringArray { size, .. } => write!(f, "string[{}]", size),
-            TypedParameter::ColorArray { size, .. } => write!(f, "color[{}]", size),
-            TypedParameter::PointArray { size, .. } => write!(f, "point[{}]", size),
-            TypedParameter::VectorArray { size, .. } => write!(f, "vector[{}]", size),
-            TypedParameter::NormalArray { size, .. } => write!(f, "normal[{}]", size),
-            TypedParameter::MatrixArray { size, .. } => write!(f, "matrix[{}]", size),
+            TypedParameter::Int { .. }
+            | TypedParameter::IntArray { .. }
+            | TypedParameter::IntDynamicArray { .. } => ElementType::Int,
+            TypedParameter::Float { .. }
+            | TypedParameter::FloatArray { .. }
+            | TypedParameter::FloatDynamicArray { .. } => ElementType::Float,
+            TypedParameter::String { .. }
+            | TypedParameter::StringArray { .. }
+            | TypedParameter::StringDynamicArray { .. } => ElementType::String,
+            TypedParameter::Matrix { .. }
+            | TypedParameter::MatrixArray { .. }
+            | TypedParameter::MatrixDynamicArray { .. } => ElementType::Matrix,
+            TypedParameter::Closure { .. } => ElementType::Closure,
+            _ => ElementType::Triple,
+        }
+    }
 
-            TypedParameter::Closure { closure_type } => write!(f, "closure {}", closure_type),
+    /// The default as a list of elements (one for scalars).
+    fn default_elements(&self) -> Option<Elements> {
+        Some(match self {
+            TypedParameter::Int { default } => Elements::Int(vec![(*default)?]),
+            TypedParameter::Float { default } => Elements::Float(vec![(*default)?]),
+            TypedParameter::String { default } => Elements::String(vec![default.clone()?]),
+            TypedParameter::Color { default, .. }
+            | TypedParameter::Point { default, .. }
+            | TypedParameter::Vector { default, .. }
+            | TypedParameter::Normal { default, .. } => Elements::Triple(vec![(*default)?]),
+            TypedParameter::Matrix { default } => Elements::Matrix(vec![**default.as_ref()?]),
+            TypedParameter::IntArray { default, .. }
+            | TypedParameter::IntDynamicArray { default } => Elements::Int(default.clone()?),
+            TypedParameter::FloatArray { default, .. }
+            | TypedParameter::FloatDynamicArray { default } => Elements::Float(default.clone()?),
+            TypedParameter::StringArray { default, .. }
+            | TypedParameter::StringDynamicArray { default } => Elements::String(default.clone()?),
+            TypedParameter::ColorArray { default, .. }
+            | TypedParameter::PointArray { default, .. }
+            | TypedParameter::VectorArray { default, .. }
+            | TypedParameter::NormalArray { default, .. }
+            | TypedParameter::ColorDynamicArray { default, .. }
+            | TypedParameter::PointDynamicArray { default, .. }
+            | TypedParameter::VectorDynamicArray { default, .. }
+            | TypedParameter::NormalDynamicArray { default, .. } => {
+                Elements::Triple(default.clone()?)
+            }
+            TypedParameter::MatrixArray { default, .. }
+            | TypedParameter::MatrixDynamicArray { default } => Elements::Matrix(default.clone()?),
+            TypedParameter::Closure { .. } => return None,
+        })
+    }
 
-            other => write!(f, "{}", other.type_name()),
+    /// A copy of `self` with the default replaced by `elements`, which must
+    /// match the element type.
+    fn with_default_elements(&self, elements: Option<Elements>) -> TypedParameter {
+        let mut result = self.clone();
+        match &mut result {
+            TypedParameter::Int { default } => {
+                *default = elements.and_then(Elements::into_ints).and_then(first)
+            }
+            TypedParameter::Float { default } => {
+                *default = elements.and_then(Elements::into_floats).and_then(first)
+            }
+            TypedParameter::String { default } => {
+                *default = elements.and_then(Elements::into_strings).and_then(first)
+            }
+            TypedParameter::Color { default, .. }
+            | TypedParameter::Point { default, .. }
+            | TypedParameter::Vector { default, .. }
+            | TypedParameter::Normal { default, .. } => {
+                *default = elements.and_then(Elements::into_triples).and_then(first)
+            }
+            TypedParameter::Matrix { default } => {
+                *default = elements
+                    .and_then(Elements::into_matrices)
+                    .and_then(first)
+                    .map(Box::new)
+            }
+            TypedParameter::IntArray { default, .. }
+            | TypedParameter::IntDynamicArray { default } => {
+                *default = elements.and_then(Elements::into_ints)
+            }
+            TypedParameter::FloatArray { default, .. }
+            | TypedParameter::FloatDynamicArray { default } => {
+                *default = elements.and_then(Elements::into_floats)
+            }
+            TypedParameter::StringArray { default, .. }
+            | TypedParameter::StringDynamicArray { default } => {
+                *default = elements.and_then(Elements::into_strings)
+            }
+            TypedParameter::ColorArray { default, .. }
+            | TypedParameter::PointArray { default, .. }
+            | TypedParameter::VectorArray { default, .. }
+            | TypedParameter::NormalArray { default, .. }
+            | TypedParameter::ColorDynamicArray { default, .. }
+            | TypedParameter::PointDynamicArray { default, .. }
+            | TypedParameter::VectorDynamicArray { default, .. }
+            | TypedParameter::NormalDynamicArray { default, .. } => {
+                *default = elements.and_then(Elements::into_triples)
+            }
+            TypedParameter::MatrixArray { default, .. }
+            | TypedParameter::MatrixDynamicArray { default } => {
+                *default = elements.and_then(Elements::into_matrices)
+            }
+            TypedParameter::Closure { .. } => {}
         }
+        result
     }
 }
 
-/// Metadata attached to parameters.
-#[derive(Debug, Clone, PartialEq)]
-#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-pub struct Metadata {
-    pub name: Ustr,
-    pub value: MetadataValue,
+/// Widen `value` via its shortest decimal representation, so `0.1f32`
+/// becomes `0.1` rather than `0.10000000149011612`.
+///
+/// For handing defaults and metadata to consumers that only know `f64`,
+/// like JSON or Python.
+pub fn widen_f32(value: f32) -> f64 {
+    value.to_string().parse().unwrap_or(f64::NAN)
 }
 
-/// Metadata values are simpler - they're always scalar or string arrays.
-#[derive(Debug, Clone, PartialEq)]
-#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-pub enum MetadataValue {
-    Int(i32),
-    Float(f32),
-    String(String),
-    IntArray(Vec<i32>),
-    FloatArray(Vec<f32>),
-    StringArray(Vec<String>),
+fn first<T>(values: Vec<T>) -> Option<T> {
+    values.into_iter().next()
 }
 
-/// A parameter with its direction (input/output).
-#[derive(Debug, Clone, PartialEq)]
-#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-pub enum ParameterKind {
-    /// Input parameter with potential default value
-    Input(TypedParameter),
-    /// Output parameter (never has defaults)
-    Output(TypedParameter),
+/// Array-ness of a parameter type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Shape {
+    Scalar,
+    Fixed(usize),
+    Dynamic,
 }
 
-impl ParameterKind {
-    /// Check if this is an output parameter.
-    pub fn is_output(&self) -> bool {
-        matches!(self, ParameterKind::Output(_))
+/// Element type of a parameter, grouping types with the same components.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ElementType {
+    Int,
+    Float,
+    String,
+    /// Color, point, vector or normal
+    Triple,
+    Matrix,
+    Closure,
+}
+
+/// Default values as a flat list of elements.
+enum Elements {
+    Int(Vec<i32>),
+    Float(Vec<f32>),
+    String(Vec<StringValue>),
+    Triple(Vec<[f32; 3]>),
+    Matrix(Vec<[f32; 16]>),
+}
+
+impl Elements {
+    /// Convert to `element_type`; only called for compatible types.
+    fn convert(self, element_type: ElementType) -> Elements {
+        match (self, element_type) {
+            (Elements::Int(v), ElementType::Float) => {
+                Elements::Float(v.into_iter().map(|i| i as f32).collect())
+            }
+            (Elements::Float(v), ElementType::Int) => {
+                Elements::Int(v.into_iter().map(|f| f as i32).collect())
+            }
+            (Elements::Int(v), ElementType::Triple) => {
+                Elements::Triple(v.into_iter().map(|i| [i as f32; 3]).collect())
+            }
+            (Elements::Float(v), ElementType::Triple) => {
+                Elements::Triple(v.into_iter().map(|f| [f; 3]).collect())
+            }
+            (elements, _) => elements,
+        }
     }
 
-    /// Get the inner typed parameter.
-    pub fn typed_param(&self) -> &TypedParameter {
+    fn into_ints(self) -> Option<Vec<i32>> {
         match self {
-            ParameterKind::Input(p) | ParameterKind::Output(p) => p,
+            Elements::Int(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    fn into_floats(self) -> Option<Vec<f32>> {
+        match self {
+            Elements::Float(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    fn into_strings(self) -> Option<Vec<StringValue>> {
+        match self {
+            Elements::String(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    fn into_triples(self) -> Option<Vec<[f32; 3]>> {
+        match self {
+            Elements::Triple(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    fn into_matrices(self) -> Option<Vec<[f32; 16]>> {
+        match self {
+            Elements::Matrix(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TypedParameter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.oslquery_typestring())
+    }
+}
+
+/// A string value that is either owned or interned.
+///
+/// The reader interns strings unless configured not to (see
+/// [`OsoReaderConfig::intern_strings`](crate::parser::OsoReaderConfig::intern_strings)),
+/// so identical strings share storage; values built in code are owned.
+/// Either way values compare, hash and serialize by their content.
+#[derive(Clone)]
+pub enum StringValue {
+    Owned(String),
+    Interned(Ustr),
+}
+
+impl StringValue {
+    /// Get the string.
+    pub fn as_str(&self) -> &str {
+        match self {
+            StringValue::Owned(s) => s,
+            StringValue::Interned(s) => s.as_str(),
+        }
+    }
+
+    /// Check if the string is interned.
+    pub fn is_interned(&self) -> bool {
+        matches!(self, StringValue::Interned(_))
+    }
+
+    /// Intern the string if it is owned.
+    pub fn intern(&mut self) {
+        if let StringValue::Owned(s) = self {
+            *self = StringValue::Interned(Ustr::from(s.as_str()));
+        }
+    }
+
+    /// Get a mutable `String`, converting an interned value to an owned one.
+    pub fn to_mut(&mut self) -> &mut String {
+        if let StringValue::Interned(s) = self {
+            *self = StringValue::Owned(s.to_string());
+        }
+        match self {
+            StringValue::Owned(s) => s,
+            StringValue::Interned(_) => unreachable!(),
+        }
+    }
+
+    /// Convert into an owned `String`.
+    pub fn into_string(self) -> String {
+        match self {
+            StringValue::Owned(s) => s,
+            StringValue::Interned(s) => s.to_string(),
+        }
+    }
+}
+
+impl std::ops::Deref for StringValue {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for StringValue {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Debug for StringValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for StringValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl PartialEq for StringValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for StringValue {}
+
+impl PartialEq<str> for StringValue {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for StringValue {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<String> for StringValue {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl std::hash::Hash for StringValue {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state);
+    }
+}
+
+impl From<String> for StringValue {
+    fn from(s: String) -> Self {
+        StringValue::Owned(s)
+    }
+}
+
+impl From<&str> for StringValue {
+    fn from(s: &str) -> Self {
+        StringValue::Owned(s.to_string())
+    }
+}
+
+impl From<Ustr> for StringValue {
+    fn from(s: Ustr) -> Self {
+        StringValue::Interned(s)
+    }
+}
+
+impl From<StringValue> for String {
+    fn from(s: StringValue) -> Self {
+        s.into_string()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for StringValue {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for StringValue {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(StringValue::Owned)
+    }
+}
+
+#[cfg(feature = "json")]
+impl From<StringValue> for serde_json::Value {
+    fn from(s: StringValue) -> Self {
+        serde_json::Value::String(s.into_string())
+    }
+}
+
+/// Metadata attached to parameters.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Metadata {
+    pub name: Ustr,
+    pub value: MetadataValue,
+}
+
+impl Metadata {
+    /// Check if `other` has the same name.
+    pub fn same_name_as(&self, other: &Metadata) -> bool {
+        self.name == other.name
+    }
+
+    /// Check if `other` has a value of the same type, regardless of the
+    /// values themselves. Array lengths are not compared.
+    pub fn same_type_as(&self, other: &Metadata) -> bool {
+        std::mem::discriminant(&self.value) == std::mem::discriminant(&other.value)
+    }
+
+    /// Check if `other` has the same name and type, i.e. only its value may
+    /// differ.
+    pub fn is_same_entry(&self, other: &Metadata) -> bool {
+        self.same_name_as(other) && self.same_type_as(other)
+    }
+
+    /// Convert a parsed `%meta{...}` hint; `None` if it has no value.
+    pub(crate) fn from_parsed(meta: crate::parser::types::ParsedParameter) -> Option<Metadata> {
+        let is_array = meta.type_desc.is_array();
+        let value = if !meta.idefault.is_empty() {
+            if meta.idefault.len() == 1 && !is_array {
+                MetadataValue::Int(meta.idefault[0])
+            } else {
+                MetadataValue::IntArray(meta.idefault)
+            }
+        } else if !meta.fdefault.is_empty() {
+            if meta.fdefault.len() == 1 && !is_array {
+                MetadataValue::Float(meta.fdefault[0])
+            } else {
+                MetadataValue::FloatArray(meta.fdefault)
+            }
+        } else if !meta.sdefault.is_empty() {
+            if meta.sdefault.len() == 1 && !is_array {
+                MetadataValue::String(meta.sdefault[0].clone().into())
+            } else {
+                MetadataValue::StringArray(
+                    meta.sdefault.into_iter().map(StringValue::from).collect(),
+                )
+            }
+        } else {
+            return None;
+        };
+        Some(Metadata {
+            name: meta.name,
+            value,
+        })
+    }
+}
+
+/// Metadata values are simpler - they're always scalar or string arrays.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MetadataValue {
+    Int(i32),
+    Float(f32),
+    String(StringValue),
+    IntArray(Vec<i32>),
+    FloatArray(Vec<f32>),
+    StringArray(Vec<StringValue>),
+}
+
+impl MetadataValue {
+    /// The name of the variant, e.g. `"Float"` or `"StringArray"`.
+    pub fn variant_name(&self) -> &'static str {
+        match self {
+            MetadataValue::Int(_) => "Int",
+            MetadataValue::Float(_) => "Float",
+            MetadataValue::String(_) => "String",
+            MetadataValue::IntArray(_) => "IntArray",
+            MetadataValue::FloatArray(_) => "FloatArray",
+            MetadataValue::StringArray(_) => "StringArray",
+        }
+    }
+
+    /// Check if this is an array without elements.
+    pub fn is_empty_array(&self) -> bool {
+        match self {
+            MetadataValue::IntArray(v) => v.is_empty(),
+            MetadataValue::FloatArray(v) => v.is_empty(),
+            MetadataValue::StringArray(v) => v.is_empty(),
+            _ => false,
+        }
+    }
+
+    /// Intern the value if it is a string or string array.
+    pub fn intern_strings(&mut self) {
+        match self {
+            MetadataValue::String(s) => s.intern(),
+            MetadataValue::StringArray(v) => v.iter_mut().for_each(StringValue::intern),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+impl MetadataValue {
+    /// Convert to a bare JSON value, moving strings rather than cloning them.
+    pub fn into_json_value(self) -> serde_json::Value {
+        use serde_json::Value;
+
+        match self {
+            MetadataValue::Int(v) => Value::from(v),
+            MetadataValue::Float(v) => crate::json::float(v),
+            MetadataValue::String(v) => Value::String(v.into_string()),
+            MetadataValue::IntArray(v) => Value::from(v),
+            MetadataValue::FloatArray(v) => {
+                Value::Array(v.into_iter().map(crate::json::float).collect())
+            }
+            MetadataValue::StringArray(v) => Value::from(v),
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+impl Metadata {
+    /// Convert to a name and bare JSON value, e.g. for inserting into a
+    /// [`serde_json::Map`].
+    pub fn into_json_kv(self) -> (String, serde_json::Value) {
+        (self.name.to_string(), self.value.into_json_value())
+    }
+}
+
+/// A parameter with its direction (input/output).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ParameterKind {
+    /// Input parameter with potential default value
+    Input(TypedParameter),
+    /// Output parameter (never has defaults)
+    Output(TypedParameter),
+}
+
+impl ParameterKind {
+    /// Check if this is an output parameter.
+    pub fn is_output(&self) -> bool {
+        matches!(self, ParameterKind::Output(_))
+    }
+
+    /// Get the inner typed parameter.
+    pub fn typed_param(&self) -> &TypedParameter {
+        match self {
+            ParameterKind::Input(p) | ParameterKind::Output(p) => p,
+        }
+    }
+}
+
+/// Which parameters to look at by direction, see
+/// [`OslQuery::find_param_by_type_at_index`](crate::OslQuery::find_param_by_type_at_index).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParameterDirection {
+    /// Input parameters
+    Input,
+    /// Output parameters
+    Output,
+    /// All parameters
+    Both,
+}
+
+impl ParameterDirection {
+    /// Check if `param` has this direction.
+    pub fn matches(self, param: &Parameter) -> bool {
+        match self {
+            ParameterDirection::Input => !param.is_output(),
+            ParameterDirection::Output => param.is_output(),
+            ParameterDirection::Both => true,
+        }
+    }
+}
+
+/// Which parameters to look at by type, see
+/// [`OslQuery::find_param_by_type_at_index`](crate::OslQuery::find_param_by_type_at_index).
+///
+/// The element types match single values only, e.g. [`Float`](Self::Float)
+/// matches `float` but not `float[4]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TypeFilter {
+    /// `int`
+    Int,
+    /// `float`
+    Float,
+    /// `string`
+    String,
+    /// `color`
+    Color,
+    /// `point`
+    Point,
+    /// `vector`
+    Vector,
+    /// `normal`
+    Normal,
+    /// `matrix`
+    Matrix,
+    /// Arrays of any type, fixed-size or dynamic
+    Array,
+    /// Closures of any type
+    Closure,
+    /// Any type
+    Any,
+}
+
+impl TypeFilter {
+    /// Check if `typed_param` is of this type.
+    pub fn matches(self, typed_param: &TypedParameter) -> bool {
+        match self {
+            TypeFilter::Int => matches!(typed_param, TypedParameter::Int { .. }),
+            TypeFilter::Float => matches!(typed_param, TypedParameter::Float { .. }),
+            TypeFilter::String => matches!(typed_param, TypedParameter::String { .. }),
+            TypeFilter::Color => matches!(typed_param, TypedParameter::Color { .. }),
+            TypeFilter::Point => matches!(typed_param, TypedParameter::Point { .. }),
+            TypeFilter::Vector => matches!(typed_param, TypedParameter::Vector { .. }),
+            TypeFilter::Normal => matches!(typed_param, TypedParameter::Normal { .. }),
+            TypeFilter::Matrix => matches!(typed_param, TypedParameter::Matrix { .. }),
+            TypeFilter::Array => typed_param.is_array(),
+            TypeFilter::Closure => typed_param.is_closure(),
+            TypeFilter::Any => true,
+        }
+    }
+}
+
+/// Complete parameter with name and metadata.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Parameter {
+    /// Parameter name
+    pub name: Ustr,
+    /// Parameter kind and type
+    pub kind: ParameterKind,
+    /// Associated metadata, see [`metadata`](Self::metadata)
+    metadata: ParamMetadata,
+    /// The struct parameter this is a field of, see [`is_struct`](Self::is_struct)
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    struct_field_of: Option<Ustr>,
+}
+
+/// Metadata of a [`Parameter`], either parsed or as the raw `%meta{...}`
+/// hints of the OSO source, which are parsed on first access.
+///
+/// See [`OsoReaderConfig::lazy_metadata`](crate::parser::OsoReaderConfig::lazy_metadata).
+#[derive(Clone)]
+enum ParamMetadata {
+    Parsed(Vec<Metadata>),
+    Raw(Box<RawMetadata>),
+}
+
+#[derive(Clone)]
+struct RawMetadata {
+    /// Newline-separated hints
+    hints: Box<str>,
+    /// Whether string values are interned when parsed
+    intern: bool,
+    parsed: OnceLock<Vec<Metadata>>,
+}
+
+impl RawMetadata {
+    fn parse(&self) -> Vec<Metadata> {
+        self.hints
+            .lines()
+            .filter_map(crate::parser::hint::parse_metadata)
+            .map(|mut meta| {
+                if self.intern {
+                    meta.value.intern_strings();
+                }
+                meta
+            })
+            .collect()
+    }
+}
+
+impl ParamMetadata {
+    fn get(&self) -> &[Metadata] {
+        match self {
+            ParamMetadata::Parsed(metadata) => metadata,
+            ParamMetadata::Raw(raw) => raw.parsed.get_or_init(|| raw.parse()),
+        }
+    }
+
+    fn get_mut(&mut self) -> &mut Vec<Metadata> {
+        if let ParamMetadata::Raw(raw) = self {
+            let metadata = match raw.parsed.take() {
+                Some(metadata) => metadata,
+                None => raw.parse(),
+            };
+            *self = ParamMetadata::Parsed(metadata);
+        }
+        match self {
+            ParamMetadata::Parsed(metadata) => metadata,
+            ParamMetadata::Raw(_) => unreachable!(),
+        }
+    }
+}
+
+impl Default for ParamMetadata {
+    fn default() -> Self {
+        ParamMetadata::Parsed(Vec::new())
+    }
+}
+
+impl fmt::Debug for ParamMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.get(), f)
+    }
+}
+
+impl PartialEq for ParamMetadata {
+    fn eq(&self, other: &Self) -> bool {
+        self.get() == other.get()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for ParamMetadata {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.get().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ParamMetadata {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::deserialize(deserializer).map(ParamMetadata::Parsed)
+    }
+}
+
+impl Parameter {
+    /// Create a new input parameter.
+    pub fn new_input(name: impl Into<Ustr>, typed_param: TypedParameter) -> Self {
+        Parameter {
+            name: name.into(),
+            kind: ParameterKind::Input(typed_param),
+            metadata: ParamMetadata::default(),
+            struct_field_of: None,
+        }
+    }
+
+    /// Create a new output parameter (strips any default values).
+    pub fn new_output(name: impl Into<Ustr>, mut typed_param: TypedParameter) -> Self {
+        // Output parameters can't have defaults, so strip them
+        typed_param.clear_default();
+
+        Parameter {
+            name: name.into(),
+            kind: ParameterKind::Output(typed_param),
+            metadata: ParamMetadata::default(),
+            struct_field_of: None,
+        }
+    }
+
+    /// Check if this is an output parameter.
+    pub fn is_output(&self) -> bool {
+        self.kind.is_output()
+    }
+
+    /// Get the typed parameter.
+    pub fn typed_param(&self) -> &TypedParameter {
+        self.kind.typed_param()
+    }
+
+    /// Check if this parameter has a default value.
+    pub fn has_default(&self) -> bool {
+        self.typed_param().has_default()
+    }
+
+    /// Check if this is an array type.
+    pub fn is_array(&self) -> bool {
+        self.typed_param().is_array()
+    }
+
+    /// Check if this is a dynamic (unsized) array.
+    pub fn is_dynamic_array(&self) -> bool {
+        self.typed_param().is_dynamic_array()
+    }
+
+    /// Check if this is a closure type.
+    pub fn is_closure(&self) -> bool {
+        self.typed_param().is_closure()
+    }
+
+    /// Check if this is a field of a struct parameter.
+    ///
+    /// Struct parameters themselves are [`StructDecl`]s. The parser marks
+    /// their fields from the `%mystruct` hint, or the `%structfields` of the
+    /// struct parameter in files without it; a `.` in the name alone does
+    /// not make a field.
+    pub fn is_struct(&self) -> bool {
+        self.struct_field_of.is_some()
+    }
+
+    /// The struct parameter the parser found this to be a field of.
+    pub(crate) fn struct_field_of(&self) -> Option<Ustr> {
+        self.struct_field_of
+    }
+
+    /// Get the name of the struct parameter this is a field of, e.g. `p` for
+    /// `p.Kd`, or `None` if it is not a struct field.
+    ///
+    /// Fields of nested structs name the innermost struct parameter, e.g.
+    /// `p.base` for `p.base.Kd`. [`OslQuery::struct_of`](crate::OslQuery::struct_of)
+    /// finds its declaration.
+    pub fn struct_parent(&self) -> Option<&str> {
+        self.name.rsplit_once('.').map(|(parent, _)| parent)
+    }
+
+    /// Check if the element type is `point`, `vector` or `normal`.
+    pub fn is_geometric(&self) -> bool {
+        self.typed_param().is_geometric()
+    }
+
+    /// Check if this is a global symbol, which some compilers write with an
+    /// `@` prefix, e.g. `@time` or `@P`.
+    pub fn is_global_symbol(&self) -> bool {
+        self.name.starts_with('@')
+    }
+
+    /// Check if this is a single `int` or `float`.
+    pub fn is_scalar(&self) -> bool {
+        self.typed_param().is_scalar()
+    }
+
+    /// Get the metadata, parsing it first if it was read lazily.
+    pub fn metadata(&self) -> &[Metadata] {
+        self.metadata.get()
+    }
+
+    /// Get the metadata mutably, parsing it first if it was read lazily.
+    pub fn metadata_mut(&mut self) -> &mut Vec<Metadata> {
+        self.metadata.get_mut()
+    }
+
+    /// The heap bytes held by lazily read hints, and the metadata if it was
+    /// parsed.
+    pub(crate) fn metadata_storage(&self) -> (usize, Option<&Vec<Metadata>>) {
+        match &self.metadata {
+            ParamMetadata::Parsed(metadata) => (0, Some(metadata)),
+            ParamMetadata::Raw(raw) => (
+                std::mem::size_of::<RawMetadata>() + raw.hints.len(),
+                raw.parsed.get(),
+            ),
+        }
+    }
+
+    /// Sort the metadata by name, keeping the order of entries with the
+    /// same name, drop empty metadata arrays and normalize float defaults,
+    /// see [`OslQuery::canonical_form`](crate::OslQuery::canonical_form).
+    pub(crate) fn canonicalize(&mut self, epsilon: f32) {
+        let metadata = self.metadata_mut();
+        metadata.retain(|meta| !meta.value.is_empty_array());
+        metadata.sort_by(|a, b| a.name.as_str().cmp(b.name.as_str()));
+        let (ParameterKind::Input(typed_param) | ParameterKind::Output(typed_param)) =
+            &mut self.kind;
+        typed_param.normalize_defaults(epsilon);
+    }
+
+    /// Remove all metadata, without parsing it first.
+    pub(crate) fn clear_metadata(&mut self) {
+        self.metadata = ParamMetadata::default();
+    }
+
+    /// Keep the raw `%meta{...}` hints, to be parsed on first access.
+    pub(crate) fn set_raw_metadata(&mut self, hints: String) {
+        self.metadata = ParamMetadata::Raw(Box::new(RawMetadata {
+            hints: hints.into_boxed_str(),
+            intern: false,
+            parsed: OnceLock::new(),
+        }));
+    }
+
+    /// Find metadata by name.
+    pub fn find_metadata(&self, name: &str) -> Option<&Metadata> {
+        self.metadata().iter().find(|m| m.name.as_str() == name)
+    }
+
+    /// Get the UI page, i.e. the `page` string metadata.
+    pub fn ui_page(&self) -> Option<&str> {
+        self.string_metadata("page")
+    }
+
+    /// Get the display label, i.e. the `label` string metadata.
+    pub fn label(&self) -> Option<&str> {
+        self.string_metadata("label")
+    }
+
+    /// Get the tooltip text, i.e. the `help` string metadata.
+    pub fn help(&self) -> Option<&str> {
+        self.string_metadata("help")
+    }
+
+    /// Get the UI widget, i.e. the `widget` string metadata, e.g.
+    /// `"checkBox"`.
+    pub fn widget(&self) -> Option<&str> {
+        self.string_metadata("widget")
+    }
+
+    /// Get the units of the value, i.e. the `units` string metadata, e.g.
+    /// `"cm"`.
+    pub fn units(&self) -> Option<&str> {
+        self.string_metadata("units")
+    }
+
+    /// Get the value of `string` metadata `name`.
+    ///
+    /// `None` if there is no such metadata or it is not a single string.
+    pub fn string_metadata(&self, name: &str) -> Option<&str> {
+        match self.find_metadata(name).map(|m| &m.value) {
+            Some(MetadataValue::String(value)) => Some(value.as_str()),
+            _ => None,
+        }
+    }
+
+    /// Get the value of `int` or `float` metadata `name` as `f32`, e.g. for
+    /// `min`/`max`.
+    ///
+    /// `None` if there is no such metadata or it is not a single number.
+    pub fn float_metadata(&self, name: &str) -> Option<f32> {
+        match self.find_metadata(name).map(|m| &m.value) {
+            Some(MetadataValue::Int(value)) => Some(*value as f32),
+            Some(MetadataValue::Float(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Pair the `options` labels with the `enumvalues` values, the usual
+    /// metadata for an enum widget.
+    ///
+    /// `options` is a `|`-separated string, e.g. `"Off|Low|High"`, or a
+    /// string array; `enumvalues` is an `int` or `int[]`. Returns `None` if
+    /// either is missing or they differ in length.
+    pub fn enum_options(&self) -> Option<Vec<(String, i32)>> {
+        let labels: Vec<&str> = match &self.find_metadata("options")?.value {
+            MetadataValue::String(options) => crate::export::parse_options(options)
+                .into_iter()
+                .map(|(label, _)| label)
+                .collect(),
+            MetadataValue::StringArray(options) => options.iter().map(|s| s.as_str()).collect(),
+            _ => return None,
+        };
+        let values = match &self.find_metadata("enumvalues")?.value {
+            MetadataValue::Int(value) => std::slice::from_ref(value),
+            MetadataValue::IntArray(values) => values.as_slice(),
+            _ => return None,
+        };
+
+        (labels.len() == values.len()).then(|| {
+            labels
+                .into_iter()
+                .map(String::from)
+                .zip(values.iter().copied())
+                .collect()
+        })
+    }
+
+    /// Add metadata to this parameter.
+    pub fn add_metadata(&mut self, name: impl Into<Ustr>, value: MetadataValue) {
+        self.metadata_mut().push(Metadata {
+            name: name.into(),
+            value,
+        });
+    }
+
+    /// Add `int` metadata to this parameter.
+    pub fn add_metadata_int(&mut self, name: impl Into<Ustr>, value: i32) {
+        self.add_metadata(name, MetadataValue::Int(value));
+    }
+
+    /// Add `float` metadata to this parameter.
+    pub fn add_metadata_float(&mut self, name: impl Into<Ustr>, value: f32) {
+        self.add_metadata(name, MetadataValue::Float(value));
+    }
+
+    /// Add `string` metadata to this parameter.
+    pub fn add_metadata_string(&mut self, name: impl Into<Ustr>, value: impl Into<StringValue>) {
+        self.add_metadata(name, MetadataValue::String(value.into()));
+    }
+
+    /// Add `string[]` metadata to this parameter.
+    pub fn add_metadata_string_array<S: Into<StringValue>>(
+        &mut self,
+        name: impl Into<Ustr>,
+        values: impl IntoIterator<Item = S>,
+    ) {
+        let values = values.into_iter().map(Into::into).collect();
+        self.add_metadata(name, MetadataValue::StringArray(values));
+    }
+
+    /// Add metadata and return the parameter, for building parameters in one
+    /// expression:
+    ///
+    /// ```
+    /// use oslquery_petite::{MetadataValue, Parameter, TypedParameter};
+    ///
+    /// let roughness = Parameter::new_input("roughness", TypedParameter::Float { default: Some(0.5) })
+    ///     .with_metadata("label", MetadataValue::String("Roughness".into()))
+    ///     .with_metadata("min", MetadataValue::Float(0.0));
+    /// assert_eq!(roughness.metadata().len(), 2);
+    /// ```
+    pub fn with_metadata(mut self, name: impl Into<Ustr>, value: MetadataValue) -> Self {
+        self.add_metadata(name, value);
+        self
+    }
+
+    /// Intern the string defaults and string metadata values.
+    ///
+    /// Metadata that was read lazily stays unparsed and is interned when it
+    /// is parsed.
+    pub fn intern_strings(&mut self) {
+        match &mut self.kind {
+            ParameterKind::Input(TypedParameter::String {
+                default: Some(default),
+            }) => default.intern(),
+            ParameterKind::Input(
+                TypedParameter::StringArray {
+                    default: Some(default),
+                    ..
+                }
+                | TypedParameter::StringDynamicArray {
+                    default: Some(default),
+                },
+            ) => default.iter_mut().for_each(StringValue::intern),
+            _ => {}
+        }
+        let metadata = match &mut self.metadata {
+            ParamMetadata::Parsed(metadata) => Some(metadata),
+            ParamMetadata::Raw(raw) => {
+                raw.intern = true;
+                raw.parsed.get_mut()
+            }
+        };
+        for meta in metadata.into_iter().flatten() {
+            meta.value.intern_strings();
         }
     }
 }
 
-/// Complete parameter with name and metadata.
-#[derive(Debug, Clone, PartialEq)]
+/// A struct parameter.
+///
+/// OSO files declare struct parameters followed by one parameter per field,
+/// named `<struct parameter>.<field>`. Only the fields are kept as
+/// [`Parameter`]s; this records the struct they belong to.
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-pub struct Parameter {
+pub struct StructDecl {
     /// Parameter name
     pub name: Ustr,
-    /// Parameter kind and type
-    pub kind: ParameterKind,
-    /// Associated metadata
-    pub metadata: Vec<Metadata>,
+    /// Name of the struct type
+    pub struct_name: Ustr,
+    /// Field names, in declaration order
+    pub fields: Vec<Ustr>,
+    /// Whether this is an output parameter
+    pub is_output: bool,
 }
 
-impl Parameter {
-    /// Create a new input parameter.
-    pub fn new_input(name: impl Into<Ustr>, typed_param: TypedParameter) -> Self {
-        Parameter {
-            name: name.into(),
-            kind: ParameterKind::Input(typed_param),
-            metadata: Vec::new(),
-        }
+impl StructDecl {
+    /// Whether `param` is a field of this struct parameter.
+    pub fn is_member(&self, param: &Parameter) -> bool {
+        param
+            .name
+            .strip_prefix(self.name.as_str())
+            .is_some_and(|rest| rest.starts_with('.'))
     }
+}
 
-    /// Create a new output parameter (strips any default values).
-    pub fn new_output(name: impl Into<Ustr>, mut typed_param: TypedParameter) -> Self {
-        // Output parameters can't have defaults, so strip them
-        match &mut typed_param {
-            TypedParameter::Int { default } => *default = None,
-            TypedParameter::Float { default } => *default = None,
-            TypedParameter::String { default } => *default = None,
-            TypedParameter::Color { default, .. } => *default = None,
-            TypedParameter::Point { default, .. } => *default = None,
-            TypedParameter::Vector { default, .. } => *default = None,
-            TypedParameter::Normal { default, .. } => *default = None,
-            TypedParameter::Matrix { default } => *default = None,
-
-            TypedParameter::IntArray { default, .. } => *default = None,
-            TypedParameter::FloatArray { default, .. } => *default = None,
-            TypedParameter::StringArray { default, .. } => *default = None,
-            TypedParameter::ColorArray { default, .. } => *default = None,
-            TypedParameter::PointArray { default, .. } => *default = None,
-            TypedParameter::VectorArray { default, .. } => *default = None,
-            TypedParameter::NormalArray { default, .. } => *default = None,
-            TypedParameter::MatrixArray { default, .. } => *default = None,
-
-            TypedParameter::IntDynamicArray { default } => *default = None,
-            TypedParameter::FloatDynamicArray { default } => *default = None,
-            TypedParameter::StringDynamicArray { default } => *default = None,
-            TypedParameter::ColorDynamicArray { default, .. } => *default = None,
-            TypedParameter::PointDynamicArray { default, .. } => *default = None,
-            TypedParameter::VectorDynamicArray { default, .. } => *default = None,
-            TypedParameter::NormalDynamicArray { default, .. } => *default = None,
-            TypedParameter::MatrixDynamicArray { default } => *default = None,
-
-            TypedParameter::Closure { .. } => {} // Already has no defaults
-        }
-
-        Parameter {
-            name: name.into(),
-            kind: ParameterKind::Output(typed_param),
-            metadata: Vec::new(),
-        }
-    }
+/// A parameter as returned by
+/// [`OslQuery::params_collapsed`](crate::OslQuery::params_collapsed).
+#[derive(Debug, Clone, PartialEq)]
+pub enum CollapsedParam<'a> {
+    /// A parameter that is not a struct field
+    Scalar(&'a Parameter),
+    /// A struct parameter with its fields
+    Struct(StructParam<'a>),
+}
 
-    /// Check if this is an output parameter.
-    pub fn is_output(&self) -> bool {
-        self.kind.is_output()
-    }
+/// A struct parameter with its field parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructParam<'a> {
+    /// The struct declaration
+    pub decl: &'a StructDecl,
+    /// Field parameters, in declaration order
+    pub fields: Vec<&'a Parameter>,
+}
 
-    /// Get the typed parameter.
-    pub fn typed_param(&self) -> &TypedParameter {
-        self.kind.typed_param()
+impl StructParam<'_> {
+    /// Parameter name.
+    pub fn name(&self) -> &str {
+        &self.decl.name
     }
 
-    /// Find metadata by name.
-    pub fn find_metadata(&self, name: &str) -> Option<&Metadata> {
-        self.metadata.iter().find(|m| m.name.as_str() == name)
+    /// Name of the struct type.
+    pub fn struct_name(&self) -> &str {
+        &self.decl.struct_name
     }
 
-    /// Add metadata to this parameter.
-    pub fn add_metadata(&mut self, name: impl Into<Ustr>, value: MetadataValue) {
-        self.metadata.push(Metadata {
-            name: name.into(),
-            value,
-        });
+    /// Find a field parameter by field name, e.g. `Kd` for `p.Kd`.
+    pub fn field(&self, name: &str) -> Option<&Parameter> {
+        self.fields
+            .iter()
+            .copied()
+            .find(|param| param.name[self.decl.name.len() + 1..] == *name)
     }
 }
 
@@ -392,6 +1957,12 @@ impl TryFrom<crate::parser::types::ParsedParameter> for Parameter {
 
         // Convert the type and value together
         let typed_param = match old.type_desc.basetype {
+            // `closure color`; closures have no defaults
+            basetype if old.type_desc.is_closure && basetype != BaseType::None => {
+                TypedParameter::Closure {
+                    closure_type: basetype.as_str().into(),
+                }
+            }
             BaseType::Int => {
                 if old.type_desc.is_array() {
                     if old.type_desc.arraylen == -1 {
@@ -457,7 +2028,7 @@ impl TryFrom<crate::parser::types::ParsedParameter> for Parameter {
                     if old.type_desc.arraylen == -1 {
                         TypedParameter::StringDynamicArray {
                             default: if old.valid_default && !old.sdefault.is_empty() {
-                                Some(old.sdefault)
+                                Some(old.sdefault.into_iter().map(StringValue::from).collect())
                             } else {
                                 None
                             },
@@ -466,7 +2037,7 @@ impl TryFrom<crate::parser::types::ParsedParameter> for Parameter {
                         TypedParameter::StringArray {
                             size: old.type_desc.arraylen as usize,
                             default: if old.valid_default && !old.sdefault.is_empty() {
-                                Some(old.sdefault)
+                                Some(old.sdefault.into_iter().map(StringValue::from).collect())
                             } else {
                                 None
                             },
@@ -475,7 +2046,7 @@ impl TryFrom<crate::parser::types::ParsedParameter> for Parameter {
                 } else {
                     TypedParameter::String {
                         default: if old.valid_default && !old.sdefault.is_empty() {
-                            Some(old.sdefault[0].clone())
+                            Some(old.sdefault[0].clone().into())
                         } else {
                             None
                         },
@@ -483,7 +2054,7 @@ impl TryFrom<crate::parser::types::ParsedParameter> for Parameter {
                 }
             }
             BaseType::Color => {
-                let space = old.spacename.first().map(|s| Ustr::from(s.as_str()));
+                let space = old.spacename.first().copied();
                 if old.type_desc.is_array() {
                     // Convert flat array to array of [f32; 3]
                     let arrays = if old.valid_default && !old.fdefault.is_empty() {
@@ -521,7 +2092,7 @@ impl TryFrom<crate::parser::types::ParsedParameter> for Parameter {
                 }
             }
             BaseType::Point => {
-                let space = old.spacename.first().map(|s| Ustr::from(s.as_str()));
+                let space = old.spacename.first().copied();
                 if old.type_desc.is_array() {
                     let arrays = if old.valid_default && !old.fdefault.is_empty() {
                         Some(
@@ -558,7 +2129,7 @@ impl TryFrom<crate::parser::types::ParsedParameter> for Parameter {
                 }
             }
             BaseType::Vector => {
-                let space = old.spacename.first().map(|s| Ustr::from(s.as_str()));
+                let space = old.spacename.first().copied();
                 if old.type_desc.is_array() {
                     let arrays = if old.valid_default && !old.fdefault.is_empty() {
                         Some(
@@ -595,7 +2166,7 @@ impl TryFrom<crate::parser::types::ParsedParameter> for Parameter {
                 }
             }
             BaseType::Normal => {
-                let space = old.spacename.first().map(|s| Ustr::from(s.as_str()));
+                let space = old.spacename.first().copied();
                 if old.type_desc.is_array() {
                     let arrays = if old.valid_default && !old.fdefault.is_empty() {
                         Some(
@@ -661,7 +2232,7 @@ impl TryFrom<crate::parser::types::ParsedParameter> for Parameter {
                         default: if old.valid_default && old.fdefault.len() >= 16 {
                             let mut arr = [0.0; 16];
                             arr.copy_from_slice(&old.fdefault[..16]);
-                            Some(arr)
+                            Some(Box::new(arr))
                         } else {
                             None
                         },
@@ -686,30 +2257,17 @@ impl TryFrom<crate::parser::types::ParsedParameter> for Parameter {
             Parameter::new_input(old.name, typed_param)
         };
 
+        param.struct_field_of = old.mystruct;
+
         // Convert metadata
-        for meta in old.metadata {
-            let meta_value = if !meta.idefault.is_empty() {
-                if meta.idefault.len() == 1 {
-                    MetadataValue::Int(meta.idefault[0])
-                } else {
-                    MetadataValue::IntArray(meta.idefault)
-                }
-            } else if !meta.fdefault.is_empty() {
-                if meta.fdefault.len() == 1 {
-                    MetadataValue::Float(meta.fdefault[0])
-                } else {
-                    MetadataValue::FloatArray(meta.fdefault)
-                }
-            } else if !meta.sdefault.is_empty() {
-                if meta.sdefault.len() == 1 {
-                    MetadataValue::String(meta.sdefault[0].clone())
-                } else {
-                    MetadataValue::StringArray(meta.sdefault)
-                }
-            } else {
-                continue;
-            };
-            param.add_metadata(meta.name, meta_value);
+        if !old.raw_metadata.is_empty() {
+            param.set_raw_metadata(old.raw_metadata);
+        } else {
+            // Not `collect()`, which would keep the much larger allocation of
+            // `old.metadata`.
+            for meta in old.metadata.into_iter().filter_map(Metadata::from_parsed) {
+                param.metadata_mut().push(meta);
+            }
         }
 
         Ok(param)
@@ -720,6 +2278,252 @@ impl TryFrom<crate::parser::types::ParsedParameter> for Parameter {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_eq_ignoring_space() {
+        let color = |space: Option<&str>, default: [f32; 3]| TypedParameter::Color {
+            default: Some(default),
+            space: space.map(Ustr::from),
+        };
+
+        let linear = color(Some("linear"), [0.5, 0.5, 0.5]);
+        let srgb = color(Some("sRGB"), [0.5, 0.5, 0.5]);
+        assert_ne!(linear, srgb);
+        assert!(linear.eq_ignoring_space(&srgb));
+        assert!(linear.eq_ignoring_space(&color(None, [0.5, 0.5, 0.5])));
+
+        // A value change is still a change.
+        assert!(!linear.eq_ignoring_space(&color(Some("linear"), [1.0, 0.5, 0.5])));
+        assert!(!linear.eq_ignoring_space(&TypedParameter::Vector {
+            default: Some([0.5, 0.5, 0.5]),
+            space: None,
+        }));
+    }
+
+    #[test]
+    fn test_widen_f32() {
+        assert_eq!(widen_f32(0.1), 0.1);
+        assert_eq!(widen_f32(-2.5), -2.5);
+        assert_eq!(widen_f32(f32::INFINITY), f64::INFINITY);
+        assert!(widen_f32(f32::NAN).is_nan());
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn test_sizes() {
+        // The largest variants hold a size, a `Vec` and a space.
+        assert_eq!(std::mem::size_of::<TypedParameter>(), 48);
+        // Name, kind, metadata and the struct parameter of a field.
+        assert_eq!(std::mem::size_of::<Parameter>(), 96);
+    }
+
+    #[test]
+    fn test_resolved_length() {
+        let fixed = TypedParameter::FloatArray {
+            size: 3,
+            default: None,
+        };
+        assert_eq!(fixed.resolved_length(), Some(3));
+
+        let dynamic = TypedParameter::FloatDynamicArray {
+            default: Some(vec![0.0, 1.0, 2.0, 3.0]),
+        };
+        assert_eq!(dynamic.resolved_length(), Some(4));
+
+        let matrices = TypedParameter::MatrixDynamicArray {
+            default: Some(vec![[0.0; 16]; 2]),
+        };
+        assert_eq!(matrices.resolved_length(), Some(2));
+
+        let without_default = TypedParameter::FloatDynamicArray { default: None };
+        assert_eq!(without_default.resolved_length(), None);
+
+        let scalar = TypedParameter::Float { default: Some(1.0) };
+        assert_eq!(scalar.resolved_length(), None);
+    }
+
+    #[test]
+    fn test_type_groups() {
+        // (type, (integer, float, string, aggregate), components)
+        let cases = [
+            (
+                TypedParameter::Int { default: None },
+                (true, false, false, false),
+                1,
+            ),
+            (
+                TypedParameter::IntDynamicArray { default: None },
+                (true, false, false, false),
+                1,
+            ),
+            (
+                TypedParameter::Float { default: None },
+                (false, true, false, false),
+                1,
+            ),
+            (
+                TypedParameter::FloatArray {
+                    size: 2,
+                    default: None,
+                },
+                (false, true, false, false),
+                1,
+            ),
+            (
+                TypedParameter::Color {
+                    default: None,
+                    space: None,
+                },
+                (false, true, false, true),
+                3,
+            ),
+            (
+                TypedParameter::NormalDynamicArray {
+                    default: None,
+                    space: None,
+                },
+                (false, true, false, true),
+                3,
+            ),
+            (
+                TypedParameter::Matrix { default: None },
+                (false, true, false, false),
+                16,
+            ),
+            (
+                TypedParameter::StringArray {
+                    size: 2,
+                    default: None,
+                },
+                (false, false, true, false),
+                1,
+            ),
+            (
+                TypedParameter::Closure {
+                    closure_type: Ustr::from("color"),
+                },
+                (false, false, false, false),
+                0,
+            ),
+        ];
+        for (typed, groups, components) in cases {
+            assert_eq!(
+                (
+                    typed.is_integer_type(),
+                    typed.is_float_type(),
+                    typed.is_string_type(),
+                    typed.is_aggregate()
+                ),
+                groups,
+                "{:?}",
+                typed
+            );
+            assert_eq!(typed.component_count(), components, "{:?}", typed);
+        }
+    }
+
+    #[test]
+    fn test_default_count() {
+        let complete = [
+            TypedParameter::Int { default: Some(1) },
+            TypedParameter::String {
+                default: Some("a".into()),
+            },
+            TypedParameter::Normal {
+                default: Some([0.0, 0.0, 1.0]),
+                space: None,
+            },
+            TypedParameter::Matrix {
+                default: Some(Box::new([0.0; 16])),
+            },
+            TypedParameter::FloatArray {
+                size: 5,
+                default: Some(vec![0.0; 5]),
+            },
+            TypedParameter::ColorArray {
+                size: 2,
+                default: Some(vec![[1.0; 3]; 2]),
+                space: None,
+            },
+        ];
+        for param in &complete {
+            debug_assert_eq!(
+                Some(param.default_count()),
+                param.expected_default_count(),
+                "{param:?}"
+            );
+        }
+        assert_eq!(complete[3].default_count(), 16);
+        assert_eq!(complete[5].default_count(), 2);
+
+        let dynamic = TypedParameter::StringDynamicArray {
+            default: Some(vec!["a".into(), "b".into(), "c".into()]),
+        };
+        assert_eq!(dynamic.default_count(), 3);
+        assert_eq!(dynamic.expected_default_count(), None);
+
+        let without_default = TypedParameter::IntArray {
+            size: 4,
+            default: None,
+        };
+        assert_eq!(without_default.default_count(), 0);
+        assert_eq!(without_default.expected_default_count(), Some(4));
+        assert_eq!(TypedParameter::Float { default: None }.default_count(), 0);
+        assert_eq!(
+            TypedParameter::FloatDynamicArray { default: None }.default_count(),
+            0
+        );
+
+        let closure = TypedParameter::Closure {
+            closure_type: "color".into(),
+        };
+        assert_eq!(closure.default_count(), 0);
+        assert_eq!(closure.expected_default_count(), None);
+    }
+
+    #[test]
+    fn test_default_slices_lossy() {
+        let color = TypedParameter::Color {
+            default: Some([1.0, 0.5, -2.75]),
+            space: None,
+        };
+        assert_eq!(color.default_f32_slice_lossy(), [1.0, 0.5, -2.75]);
+        assert_eq!(color.default_i32_slice_lossy(), [1, 0, -2]);
+
+        let ints = TypedParameter::IntArray {
+            size: 3,
+            default: Some(vec![1, -2, 3]),
+        };
+        assert_eq!(ints.default_f32_slice_lossy(), [1.0, -2.0, 3.0]);
+        assert_eq!(ints.default_i32_slice_lossy(), [1, -2, 3]);
+        let int = TypedParameter::Int { default: Some(7) };
+        assert_eq!(int.default_f32_slice_lossy(), [7.0]);
+
+        let matrices = TypedParameter::MatrixDynamicArray {
+            default: Some(vec![[1.0; 16]; 2]),
+        };
+        assert_eq!(matrices.default_f32_slice_lossy(), [1.0; 32]);
+
+        let extremes = TypedParameter::FloatArray {
+            size: 3,
+            default: Some(vec![f32::NAN, 1e20, f32::NEG_INFINITY]),
+        };
+        assert_eq!(extremes.default_i32_slice_lossy(), [0, i32::MAX, i32::MIN]);
+
+        for empty in [
+            TypedParameter::String {
+                default: Some("a".into()),
+            },
+            TypedParameter::Closure {
+                closure_type: "color".into(),
+            },
+            TypedParameter::Float { default: None },
+            TypedParameter::IntDynamicArray { default: None },
+        ] {
+            assert!(empty.default_f32_slice_lossy().is_empty(), "{empty:?}");
+            assert!(empty.default_i32_slice_lossy().is_empty(), "{empty:?}");
+        }
+    }
+
     #[test]
     fn test_typed_parameter_creation() {
         // Simple float with default
@@ -749,7 +2553,7 @@ mod tests {
 
         // Dynamic array
         let param = TypedParameter::StringDynamicArray {
-            default: Some(vec!["hello".to_string(), "world".to_string()]),
+            default: Some(vec!["hello".into(), "world".into()]),
         };
         assert!(param.has_default());
         assert!(param.is_array());
@@ -804,4 +2608,561 @@ mod tests {
             _ => {}
         }
     }
+
+    #[test]
+    fn test_int_float_conversion() {
+        let param = TypedParameter::Int { default: Some(3) };
+        assert_eq!(
+            param.promote_int_to_float(),
+            TypedParameter::Float { default: Some(3.0) }
+        );
+
+        let param = TypedParameter::IntArray {
+            size: 2,
+            default: Some(vec![1, -2]),
+        };
+        assert_eq!(
+            param.promote_int_to_float(),
+            TypedParameter::FloatArray {
+                size: 2,
+                default: Some(vec![1.0, -2.0]),
+            }
+        );
+
+        // Demotion truncates towards zero
+        let param = TypedParameter::FloatDynamicArray {
+            default: Some(vec![1.9, -2.7]),
+        };
+        assert_eq!(
+            param.demote_float_to_int(),
+            TypedParameter::IntDynamicArray {
+                default: Some(vec![1, -2]),
+            }
+        );
+
+        let param = TypedParameter::Float { default: Some(0.5) };
+        assert_eq!(
+            param.widen_to_float_array(),
+            TypedParameter::FloatDynamicArray {
+                default: Some(vec![0.5]),
+            }
+        );
+
+        // Other types pass through unchanged
+        let param = TypedParameter::String {
+            default: Some("a".into()),
+        };
+        assert_eq!(param.promote_int_to_float(), param);
+        assert_eq!(param.demote_float_to_int(), param);
+        assert_eq!(param.widen_to_float_array(), param);
+    }
+
+    fn triples(default: Option<[f32; 3]>) -> [TypedParameter; 4] {
+        [
+            TypedParameter::Color {
+                default,
+                space: None,
+            },
+            TypedParameter::Point {
+                default,
+                space: None,
+            },
+            TypedParameter::Vector {
+                default,
+                space: None,
+            },
+            TypedParameter::Normal {
+                default,
+                space: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_coerce_triples() {
+        let values = [0.1, 0.2, 0.3];
+        for source in triples(Some(values)) {
+            for (target, expected) in triples(None).iter().zip(triples(Some(values))) {
+                assert!(source.is_compatible_with(target));
+                assert_eq!(source.coerce_to(target), Some(expected));
+            }
+        }
+
+        // The target's space is kept
+        let target = TypedParameter::Point {
+            default: None,
+            space: Some(Ustr::from("object")),
+        };
+        assert_eq!(
+            triples(Some(values))[0].coerce_to(&target),
+            Some(TypedParameter::Point {
+                default: Some(values),
+                space: Some(Ustr::from("object")),
+            })
+        );
+    }
+
+    #[test]
+    fn test_coerce_triple_arrays() {
+        let values = vec![[1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+        let fixed = |default: Option<Vec<[f32; 3]>>| {
+            [
+                TypedParameter::ColorArray {
+                    size: 2,
+                    default: default.clone(),
+                    space: None,
+                },
+                TypedParameter::PointArray {
+                    size: 2,
+                    default: default.clone(),
+                    space: None,
+                },
+                TypedParameter::VectorArray {
+                    size: 2,
+                    default: default.clone(),
+                    space: None,
+                },
+                TypedParameter::NormalArray {
+                    size: 2,
+                    default,
+                    space: None,
+                },
+            ]
+        };
+        let dynamic = |default: Option<Vec<[f32; 3]>>| {
+            [
+                TypedParameter::ColorDynamicArray {
+                    default: default.clone(),
+                    space: None,
+                },
+                TypedParameter::PointDynamicArray {
+                    default: default.clone(),
+                    space: None,
+                },
+                TypedParameter::VectorDynamicArray {
+                    default: default.clone(),
+                    space: None,
+                },
+                TypedParameter::NormalDynamicArray {
+                    default,
+                    space: None,
+                },
+            ]
+        };
+
+        for source in fixed(Some(values.clone())) {
+            for (target, expected) in fixed(None).iter().zip(fixed(Some(values.clone()))) {
+                assert_eq!(source.coerce_to(target), Some(expected));
+            }
+            for (target, expected) in dynamic(None).iter().zip(dynamic(Some(values.clone()))) {
+                assert_eq!(source.coerce_to(target), Some(expected));
+            }
+        }
+        for source in dynamic(Some(values.clone())) {
+            for (target, expected) in dynamic(None).iter().zip(dynamic(Some(values.clone()))) {
+                assert_eq!(source.coerce_to(target), Some(expected));
+            }
+            // The length of an unsized array is not known statically
+            for target in fixed(None) {
+                assert_eq!(source.coerce_to(&target), None);
+            }
+        }
+    }
+
+    #[test]
+    fn test_coerce_numbers() {
+        let int = TypedParameter::Int { default: Some(2) };
+        let float = TypedParameter::Float { default: Some(2.5) };
+
+        assert_eq!(
+            int.coerce_to(&TypedParameter::Float { default: None }),
+            Some(TypedParameter::Float { default: Some(2.0) })
+        );
+        assert_eq!(
+            float.coerce_to(&TypedParameter::Int { default: None }),
+            Some(TypedParameter::Int { default: Some(2) })
+        );
+
+        // Broadcast to triples
+        for (target, expected) in triples(None).iter().zip(triples(Some([2.5; 3]))) {
+            assert_eq!(float.coerce_to(target), Some(expected));
+        }
+        for (target, expected) in triples(None).iter().zip(triples(Some([2.0; 3]))) {
+            assert_eq!(int.coerce_to(target), Some(expected));
+        }
+
+        assert_eq!(
+            TypedParameter::IntArray {
+                size: 2,
+                default: Some(vec![1, 2]),
+            }
+            .coerce_to(&TypedParameter::FloatDynamicArray { default: None }),
+            Some(TypedParameter::FloatDynamicArray {
+                default: Some(vec![1.0, 2.0]),
+            })
+        );
+    }
+
+    #[test]
+    fn test_coerce_float_array_to_color_array() {
+        let source = TypedParameter::FloatArray {
+            size: 3,
+            default: Some(vec![0.0, 0.5, 1.0]),
+        };
+        let target = TypedParameter::ColorArray {
+            size: 3,
+            default: None,
+            space: None,
+        };
+
+        assert_eq!(
+            source.coerce_to(&target),
+            Some(TypedParameter::ColorArray {
+                size: 3,
+                default: Some(vec![[0.0; 3], [0.5; 3], [1.0; 3]]),
+                space: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_coerce_same_type() {
+        let params = [
+            TypedParameter::String {
+                default: Some("a".into()),
+            },
+            TypedParameter::Matrix {
+                default: Some(Box::new([1.0; 16])),
+            },
+            TypedParameter::StringDynamicArray {
+                default: Some(vec!["a".into()]),
+            },
+            TypedParameter::MatrixArray {
+                size: 1,
+                default: Some(vec![[0.0; 16]]),
+            },
+        ];
+        for param in params {
+            assert_eq!(param.coerce_to(&param), Some(param.clone()));
+        }
+
+        let closure = TypedParameter::Closure {
+            closure_type: Ustr::from("color"),
+        };
+        assert_eq!(closure.coerce_to(&closure), Some(closure.clone()));
+    }
+
+    #[test]
+    fn test_coerce_incompatible() {
+        let color = TypedParameter::Color {
+            default: Some([1.0; 3]),
+            space: None,
+        };
+        let matrix = TypedParameter::Matrix {
+            default: Some(Box::new([1.0; 16])),
+        };
+        let float = TypedParameter::Float { default: Some(1.0) };
+        let string = TypedParameter::String { default: None };
+
+        assert_eq!(matrix.coerce_to(&color), None);
+        assert_eq!(color.coerce_to(&matrix), None);
+        assert_eq!(color.coerce_to(&float), None);
+        assert_eq!(string.coerce_to(&float), None);
+        assert_eq!(float.coerce_to(&string), None);
+        assert_eq!(
+            float.coerce_to(&TypedParameter::FloatDynamicArray { default: None }),
+            None
+        );
+        assert_eq!(
+            TypedParameter::FloatArray {
+                size: 2,
+                default: None,
+            }
+            .coerce_to(&TypedParameter::FloatArray {
+                size: 3,
+                default: None,
+            }),
+            None
+        );
+        assert_eq!(
+            TypedParameter::Closure {
+                closure_type: Ustr::from("color"),
+            }
+            .coerce_to(&color),
+            None
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_metadata_into_json() {
+        use serde_json::{Map, json};
+
+        let metadata = vec![
+            Metadata {
+                name: "label".into(),
+                value: MetadataValue::String("Diffuse".into()),
+            },
+            Metadata {
+                name: "range".into(),
+                value: MetadataValue::FloatArray(vec![0.1, 1.0]),
+            },
+            Metadata {
+                name: "options".into(),
+                value: MetadataValue::StringArray(vec!["a".into(), "b".into()]),
+            },
+            Metadata {
+                name: "digits".into(),
+                value: MetadataValue::Int(3),
+            },
+        ];
+
+        let object: Map<_, _> = metadata.into_iter().map(Metadata::into_json_kv).collect();
+        assert_eq!(
+            serde_json::Value::Object(object),
+            json!({
+                "label": "Diffuse",
+                "range": [0.1, 1.0],
+                "options": ["a", "b"],
+                "digits": 3,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parameter_predicates() {
+        let kd = Parameter::new_input("Kd", TypedParameter::Float { default: Some(0.5) });
+        assert!(kd.is_scalar() && kd.has_default());
+        assert!(!kd.is_array() && !kd.is_geometric() && !kd.is_struct());
+
+        let mut points = Parameter::new_input(
+            "p.points",
+            TypedParameter::PointDynamicArray {
+                default: None,
+                space: None,
+            },
+        );
+        assert!(!points.is_struct());
+        points.struct_field_of = Some("p".into());
+        assert!(points.is_array() && points.is_dynamic_array());
+        assert!(points.is_geometric() && points.is_struct());
+        assert_eq!(kd.struct_parent(), None);
+        assert_eq!(points.struct_parent(), Some("p"));
+        let nested = Parameter::new_input("p.base.Kd", TypedParameter::Float { default: None });
+        assert_eq!(nested.struct_parent(), Some("p.base"));
+        assert!(!points.is_scalar() && !points.has_default());
+
+        let bsdf = Parameter::new_output(
+            "bsdf",
+            TypedParameter::Closure {
+                closure_type: "color".into(),
+            },
+        );
+        assert!(bsdf.is_closure() && !bsdf.is_scalar());
+    }
+
+    #[test]
+    fn test_enum_options() {
+        let query = crate::OslQuery::from_string(
+            "OpenShadingLanguage 1.12\n\
+             surface test\n\
+             param\tint\tmode\t0\t%meta{string,options,\"Off|Low|High\"} \
+             %meta{int[],enumvalues,0 1 4}\n\
+             param\tint\tsingle\t0\t%meta{string[1],options,\"On\"} \
+             %meta{int[1],enumvalues,7}\n\
+             param\tint\tshort\t0\t%meta{string,options,\"Off|On\"} \
+             %meta{int[3],enumvalues,0,1,2}\n\
+             param\tint\tplain\t0\t%meta{string,options,\"Off|On\"}\n\
+             code ___main___\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            query.param_by_name("mode").unwrap().enum_options(),
+            Some(vec![
+                ("Off".to_string(), 0),
+                ("Low".to_string(), 1),
+                ("High".to_string(), 4)
+            ])
+        );
+        assert_eq!(
+            query.param_by_name("single").unwrap().enum_options(),
+            Some(vec![("On".to_string(), 7)])
+        );
+        assert_eq!(query.param_by_name("short").unwrap().enum_options(), None);
+        assert_eq!(query.param_by_name("plain").unwrap().enum_options(), None);
+    }
+
+    #[test]
+    fn test_metadata_builder() {
+        let mut param = Parameter::new_input("Kd", TypedParameter::Float { default: Some(0.5) })
+            .with_metadata("label", MetadataValue::String("Diffuse".into()))
+            .with_metadata("min", MetadataValue::Float(0.0))
+            .with_metadata("max", MetadataValue::Float(1.0));
+        param.add_metadata_int("slider", 1);
+        param.add_metadata_float("slidermax", 0.8);
+        param.add_metadata_string("page", "Base");
+        param.add_metadata_string_array("tags", ["diffuse", "base"]);
+
+        let names: Vec<&str> = param.metadata().iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["label", "min", "max", "slider", "slidermax", "page", "tags"]
+        );
+        assert_eq!(
+            param.find_metadata("label").unwrap().value,
+            MetadataValue::String("Diffuse".into())
+        );
+        assert_eq!(
+            param.find_metadata("max").unwrap().value,
+            MetadataValue::Float(1.0)
+        );
+        assert_eq!(
+            param.find_metadata("slider").unwrap().value,
+            MetadataValue::Int(1)
+        );
+        assert_eq!(param.ui_page(), Some("Base"));
+        assert_eq!(
+            param.find_metadata("tags").unwrap().value,
+            MetadataValue::StringArray(vec!["diffuse".into(), "base".into()])
+        );
+    }
+
+    #[test]
+    fn test_units() {
+        let param = crate::parser::parse_param_line(
+            "param\tfloat\tradius\t1\t%meta{string,units,\"cm\"} %meta{float,min,0}",
+            3,
+        )
+        .unwrap();
+        assert_eq!(param.units(), Some("cm"));
+
+        // Only string metadata counts.
+        let param = Parameter::new_input("radius", TypedParameter::Float { default: Some(1.0) })
+            .with_metadata("units", MetadataValue::Int(2));
+        assert_eq!(param.units(), None);
+    }
+
+    #[test]
+    fn test_with_space() {
+        let color = TypedParameter::Color {
+            default: Some([1.0, 0.5, 0.0]),
+            space: Some(Ustr::from("rgb")),
+        };
+        assert_eq!(
+            color.with_space(Ustr::from("lin_srgb")),
+            TypedParameter::Color {
+                default: Some([1.0, 0.5, 0.0]),
+                space: Some(Ustr::from("lin_srgb")),
+            }
+        );
+
+        let points = TypedParameter::PointDynamicArray {
+            default: None,
+            space: None,
+        };
+        assert_eq!(
+            points.with_space(Ustr::from("world")),
+            TypedParameter::PointDynamicArray {
+                default: None,
+                space: Some(Ustr::from("world")),
+            }
+        );
+
+        let float = TypedParameter::Float { default: Some(1.0) };
+        assert_eq!(float.with_space(Ustr::from("world")), float);
+    }
+
+    #[test]
+    fn test_oslquery_typestring() {
+        // As printed by `oslinfo` for the same declarations.
+        let cases = [
+            (TypedParameter::Int { default: None }, "int"),
+            (TypedParameter::Float { default: None }, "float"),
+            (TypedParameter::String { default: None }, "string"),
+            (
+                TypedParameter::Normal {
+                    default: None,
+                    space: None,
+                },
+                "normal",
+            ),
+            (TypedParameter::Matrix { default: None }, "matrix"),
+            (
+                TypedParameter::ColorArray {
+                    size: 4,
+                    default: None,
+                    space: None,
+                },
+                "color[4]",
+            ),
+            (
+                TypedParameter::StringArray {
+                    size: 2,
+                    default: None,
+                },
+                "string[2]",
+            ),
+            (
+                TypedParameter::FloatDynamicArray { default: None },
+                "float[]",
+            ),
+            (
+                TypedParameter::PointDynamicArray {
+                    default: None,
+                    space: None,
+                },
+                "point[]",
+            ),
+            (
+                TypedParameter::Closure {
+                    closure_type: Ustr::from("color"),
+                },
+                "closure color",
+            ),
+        ];
+
+        for (typed_param, expected) in cases {
+            assert_eq!(typed_param.oslquery_typestring(), expected);
+            assert_eq!(typed_param.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_as_oiio_typedesc_string() {
+        let cases = [
+            (TypedParameter::Int { default: None }, "int"),
+            (TypedParameter::Float { default: Some(1.0) }, "float"),
+            (
+                TypedParameter::Vector {
+                    default: None,
+                    space: None,
+                },
+                "vector",
+            ),
+            (TypedParameter::Matrix { default: None }, "matrix"),
+            (
+                TypedParameter::ColorArray {
+                    size: 4,
+                    default: None,
+                    space: None,
+                },
+                "color",
+            ),
+            (
+                TypedParameter::StringDynamicArray { default: None },
+                "string",
+            ),
+            (
+                TypedParameter::Closure {
+                    closure_type: Ustr::from("color"),
+                },
+                "unknown",
+            ),
+        ];
+
+        for (typed_param, expected) in cases {
+            assert_eq!(typed_param.as_oiio_typedesc_string(), expected);
+        }
+    }
 }
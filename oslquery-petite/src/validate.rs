@@ -0,0 +1,835 @@
+//! Checking a parsed shader for internal consistency.
+//!
+//! The parser accepts anything `oslc` can write. [`OslQuery::validate`]
+//! looks for interfaces that parse fine but contradict themselves, e.g. a
+//! default outside the range its own metadata declares.
+
+use std::collections::HashMap;
+
+use thiserror::Error;
+use ustr::Ustr;
+
+use crate::export::parse_options;
+use crate::metadata_schema::{MetadataIssueKind, check_metadata};
+use crate::query::OslQuery;
+use crate::types::{MetadataValue, Parameter, TypedParameter};
+
+/// The check a [`ValidationIssue`] comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RuleId {
+    /// A default is below the `min` or above the `max` metadata.
+    DefaultOutOfRange,
+    /// A fixed-size array's default has a different number of elements than
+    /// the array.
+    ArrayLengthMismatch,
+    /// `min`, `max`, `slidermin`, `slidermax` or `sensitivity` of a float
+    /// parameter is an `int`.
+    IntMetadataForFloat,
+    /// Conventional metadata has a value of the wrong type, e.g. an `int`
+    /// label or a `float` minimum of an `int` parameter, see
+    /// [`metadata_schema`](crate::metadata_schema).
+    MetadataType,
+    /// Metadata has an unknown name close to a conventional one, e.g.
+    /// `lable`.
+    MetadataTypo,
+    /// The default is not one of the values in the `options` metadata.
+    DefaultNotInOptions,
+    /// `min` is above `max`, or `slidermin` above `slidermax`.
+    InvertedRange,
+    /// `slidermin` is below `min`, or `slidermax` above `max`.
+    SliderOutsideRange,
+    /// The same metadata appears more than once with different values.
+    DuplicateMetadata,
+    /// A parameter has an empty name.
+    EmptyName,
+    /// An output parameter has a default.
+    OutputDefault,
+}
+
+/// How much a [`ValidationIssue`] matters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// The interface is usable, but a host will likely show or use it
+    /// differently than intended.
+    Warning,
+    /// The interface cannot be what `oslc` wrote.
+    Error,
+}
+
+/// A finding of [`OslQuery::validate`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationIssue {
+    /// The parameter the issue is about, `None` if it has no name
+    pub param: Option<Ustr>,
+    /// The check that found the issue
+    pub rule: RuleId,
+    /// How much the issue matters
+    pub severity: Severity,
+    /// A description of the issue
+    pub message: String,
+}
+
+impl OslQuery {
+    /// Check the parameters for defaults and metadata that contradict each
+    /// other, see [`RuleId`].
+    ///
+    /// Issues are reported in parameter order. An empty list means the
+    /// shader passed every check.
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+        for (index, param) in self.params().iter().enumerate() {
+            validate_param(index, param, &mut issues);
+        }
+        issues
+    }
+}
+
+/// A struct parameter whose `%structfields{...}` hint lists another number
+/// of fields than expected, see [`OslQuery::validate_struct_fields`].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("struct parameter {param_name} lists {actual_fields} fields, expected {expected_fields}")]
+pub struct StructFieldError {
+    /// Name of the struct parameter
+    pub param_name: Ustr,
+    /// The number of fields the struct type has
+    pub expected_fields: usize,
+    /// The number of fields in the `%structfields{...}` hint
+    pub actual_fields: usize,
+}
+
+/// The number of fields of known struct types, for
+/// [`OslQuery::validate_struct_fields_with_registry`].
+///
+/// ```
+/// use oslquery_petite::StructRegistry;
+///
+/// let mut registry = StructRegistry::new();
+/// registry.register_struct("Layer", 3);
+/// assert_eq!(registry.field_count("Layer"), Some(3));
+/// assert_eq!(registry.field_count("Coat"), None);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StructRegistry {
+    field_counts: HashMap<Ustr, usize>,
+}
+
+impl StructRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the struct type `name` with `field_count` fields, replacing
+    /// an earlier registration of the same name.
+    pub fn register_struct(&mut self, name: &str, field_count: usize) {
+        self.field_counts.insert(Ustr::from(name), field_count);
+    }
+
+    /// Get the number of fields of the struct type `name`, if registered.
+    pub fn field_count(&self, name: &str) -> Option<usize> {
+        self.field_counts.get(&Ustr::from(name)).copied()
+    }
+}
+
+impl OslQuery {
+    /// Check that the `%structfields{...}` hint of each struct parameter
+    /// lists as many fields as the shader has field parameters for it.
+    ///
+    /// Fields that are structs themselves count once. Reported in the order
+    /// of [`structs`](Self::structs).
+    pub fn validate_struct_fields(&self) -> Vec<StructFieldError> {
+        self.validate_struct_fields_with_registry(&StructRegistry::new())
+    }
+
+    /// Like [`validate_struct_fields`](Self::validate_struct_fields), but
+    /// struct parameters of a type in `registry` are checked against the
+    /// number of fields registered for it instead.
+    pub fn validate_struct_fields_with_registry(
+        &self,
+        registry: &StructRegistry,
+    ) -> Vec<StructFieldError> {
+        self.structs()
+            .iter()
+            .filter_map(|decl| {
+                let expected_fields = registry
+                    .field_count(&decl.struct_name)
+                    .unwrap_or_else(|| self.direct_field_count(&decl.name));
+                (decl.fields.len() != expected_fields).then_some(StructFieldError {
+                    param_name: decl.name,
+                    expected_fields,
+                    actual_fields: decl.fields.len(),
+                })
+            })
+            .collect()
+    }
+
+    /// The number of parameters and struct parameters that are fields of
+    /// the struct parameter `name` itself, not of a struct inside it.
+    fn direct_field_count(&self, name: &str) -> usize {
+        let is_field = |field: &str| {
+            field
+                .rsplit_once('.')
+                .is_some_and(|(parent, _)| parent == name)
+        };
+        let params = self.params().iter().filter(|p| is_field(&p.name));
+        let structs = self.structs().iter().filter(|s| is_field(&s.name));
+        params.count() + structs.count()
+    }
+}
+
+fn validate_param(index: usize, param: &Parameter, issues: &mut Vec<ValidationIssue>) {
+    let typed = param.typed_param();
+    let mut issue = |rule, severity, message| {
+        issues.push(ValidationIssue {
+            param: (!param.name.is_empty()).then_some(param.name),
+            rule,
+            severity,
+            message,
+        })
+    };
+
+    if param.name.is_empty() {
+        issue(
+            RuleId::EmptyName,
+            Severity::Error,
+            format!("parameter {} has an empty name", index),
+        );
+    }
+
+    if param.is_output() && typed.has_default() {
+        issue(
+            RuleId::OutputDefault,
+            Severity::Error,
+            format!("output parameter \"{}\" has a default", param.name),
+        );
+    }
+
+    if typed.has_default()
+        && let Some(expected) = typed.expected_default_count()
+        && typed.default_count() != expected
+    {
+        issue(
+            RuleId::ArrayLengthMismatch,
+            Severity::Error,
+            format!(
+                "default of \"{}\" has {} values, the type holds {}",
+                param.name,
+                typed.default_count(),
+                expected
+            ),
+        );
+    }
+
+    for metadata_issue in check_metadata(param) {
+        let rule = match metadata_issue.kind {
+            MetadataIssueKind::RangeTypeMismatch if typed.is_float_type() => {
+                RuleId::IntMetadataForFloat
+            }
+            MetadataIssueKind::RangeTypeMismatch | MetadataIssueKind::WrongType => {
+                RuleId::MetadataType
+            }
+            MetadataIssueKind::PossibleTypo { .. } => RuleId::MetadataTypo,
+        };
+        issue(rule, Severity::Warning, metadata_issue.message);
+    }
+
+    let metadata = param.metadata();
+    for (index, first) in metadata.iter().enumerate() {
+        let earlier = metadata[..index].iter().any(|m| m.name == first.name);
+        for later in metadata[index + 1..]
+            .iter()
+            .filter(|m| m.name == first.name)
+        {
+            if !earlier && later.value != first.value {
+                issue(
+                    RuleId::DuplicateMetadata,
+                    Severity::Warning,
+                    format!(
+                        "\"{}\" has \"{}\" metadata {} and {}",
+                        param.name,
+                        first.name,
+                        format_value(&first.value),
+                        format_value(&later.value)
+                    ),
+                );
+            }
+        }
+    }
+
+    // Each entry is a conflict when its metadata are in the given relation.
+    let conflicts = [
+        (
+            "min",
+            Relation::Above,
+            "max",
+            RuleId::InvertedRange,
+            Severity::Error,
+        ),
+        (
+            "slidermin",
+            Relation::Above,
+            "slidermax",
+            RuleId::InvertedRange,
+            Severity::Error,
+        ),
+        (
+            "slidermin",
+            Relation::Below,
+            "min",
+            RuleId::SliderOutsideRange,
+            Severity::Warning,
+        ),
+        (
+            "slidermax",
+            Relation::Above,
+            "max",
+            RuleId::SliderOutsideRange,
+            Severity::Warning,
+        ),
+    ];
+    for (name, relation, other, rule, severity) in conflicts {
+        if let (Some(value), Some(other_value)) =
+            (param.float_metadata(name), param.float_metadata(other))
+            && relation.holds(value, other_value)
+        {
+            issue(
+                rule,
+                severity,
+                format!(
+                    "\"{}\" has {} {} {} {} {}",
+                    param.name,
+                    name,
+                    value,
+                    relation.as_str(),
+                    other,
+                    other_value
+                ),
+            );
+        }
+    }
+
+    let values = typed.default_f32_slice_lossy();
+    let below = param
+        .float_metadata("min")
+        .and_then(|min| Some((*values.iter().find(|&&v| v < min)?, min)));
+    let above = param
+        .float_metadata("max")
+        .and_then(|max| Some((*values.iter().find(|&&v| v > max)?, max)));
+    if let Some((value, min)) = below {
+        issue(
+            RuleId::DefaultOutOfRange,
+            Severity::Warning,
+            format!(
+                "default {} of \"{}\" is below min {}",
+                value, param.name, min
+            ),
+        );
+    } else if let Some((value, max)) = above {
+        issue(
+            RuleId::DefaultOutOfRange,
+            Severity::Warning,
+            format!(
+                "default {} of \"{}\" is above max {}",
+                value, param.name, max
+            ),
+        );
+    }
+
+    if let Some(default) = scalar_default(typed)
+        && let Some(options) = option_values(param)
+        && !options.contains(&default)
+    {
+        issue(
+            RuleId::DefaultNotInOptions,
+            Severity::Warning,
+            format!(
+                "default {} of \"{}\" is not one of its options {}",
+                default,
+                param.name,
+                options.join(", ")
+            ),
+        );
+    }
+}
+
+/// How two range metadata compare in a conflict.
+#[derive(Clone, Copy)]
+enum Relation {
+    Above,
+    Below,
+}
+
+impl Relation {
+    fn holds(self, value: f32, other: f32) -> bool {
+        match self {
+            Relation::Above => value > other,
+            Relation::Below => value < other,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Relation::Above => "above",
+            Relation::Below => "below",
+        }
+    }
+}
+
+/// A metadata value as in OSO metadata, with strings quoted and arrays in
+/// braces, e.g. `{1, 2}`.
+fn format_value(value: &MetadataValue) -> String {
+    fn list<T: ToString>(values: impl Iterator<Item = T>) -> String {
+        let values: Vec<String> = values.map(|v| v.to_string()).collect();
+        format!("{{{}}}", values.join(", "))
+    }
+
+    match value {
+        MetadataValue::Int(v) => v.to_string(),
+        MetadataValue::Float(v) => v.to_string(),
+        MetadataValue::String(v) => format!("\"{}\"", v),
+        MetadataValue::IntArray(v) => list(v.iter()),
+        MetadataValue::FloatArray(v) => list(v.iter()),
+        MetadataValue::StringArray(v) => list(v.iter().map(|v| format!("\"{}\"", v))),
+    }
+}
+
+/// The default of a single `int` or `string`, formatted to compare with
+/// [`option_values`].
+fn scalar_default(typed: &TypedParameter) -> Option<String> {
+    match typed {
+        TypedParameter::Int { default } => default.map(|v| v.to_string()),
+        TypedParameter::String { default } => default.as_ref().map(|v| v.as_str().to_string()),
+        _ => None,
+    }
+}
+
+/// The values the `options` metadata allows, formatted as in
+/// [`scalar_default`].
+///
+/// For strings these are the entries themselves, or their explicit values
+/// (`"Linear:linear|Smooth:smooth"`). Ints take the `enumvalues`, explicit
+/// values, or else the positions of the entries.
+fn option_values(param: &Parameter) -> Option<Vec<String>> {
+    let is_int = matches!(param.typed_param(), TypedParameter::Int { .. });
+    if is_int && let Some(options) = param.enum_options() {
+        return Some(options.into_iter().map(|(_, v)| v.to_string()).collect());
+    }
+
+    Some(match &param.find_metadata("options")?.value {
+        MetadataValue::String(options) => parse_options(options)
+            .into_iter()
+            .enumerate()
+            .map(|(index, (label, value))| match value {
+                Some(value) => value.to_string(),
+                None if is_int => index.to_string(),
+                None => label.to_string(),
+            })
+            .collect(),
+        MetadataValue::StringArray(options) if is_int => {
+            (0..options.len()).map(|index| index.to_string()).collect()
+        }
+        MetadataValue::StringArray(options) => {
+            options.iter().map(|v| v.as_str().to_string()).collect()
+        }
+        MetadataValue::IntArray(options) => options.iter().map(|v| v.to_string()).collect(),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ParameterKind;
+
+    fn query(params: impl IntoIterator<Item = Parameter>) -> OslQuery {
+        let mut query = OslQuery::new();
+        for param in params {
+            query.add_parameter(param);
+        }
+        query
+    }
+
+    /// Check that `param` has exactly one issue, from `rule`.
+    fn assert_fires(param: Parameter, rule: RuleId) -> ValidationIssue {
+        let mut issues = query([param]).validate();
+        assert_eq!(issues.len(), 1, "{:?}", issues);
+        let issue = issues.pop().unwrap();
+        assert_eq!(issue.rule, rule);
+        issue
+    }
+
+    fn float(name: &str, default: f32) -> Parameter {
+        Parameter::new_input(
+            name,
+            TypedParameter::Float {
+                default: Some(default),
+            },
+        )
+    }
+
+    fn int(name: &str, default: i32) -> Parameter {
+        Parameter::new_input(
+            name,
+            TypedParameter::Int {
+                default: Some(default),
+            },
+        )
+    }
+
+    fn string(name: &str, default: &str) -> Parameter {
+        Parameter::new_input(
+            name,
+            TypedParameter::String {
+                default: Some(default.into()),
+            },
+        )
+    }
+
+    #[test]
+    fn test_valid() {
+        let query = query([
+            float("Kd", 0.5)
+                .with_metadata("min", MetadataValue::Float(0.0))
+                .with_metadata("max", MetadataValue::Float(1.0)),
+            int("mode", 1)
+                .with_metadata("options", MetadataValue::String("Off|Low|High".into()))
+                .with_metadata("min", MetadataValue::Int(0)),
+            string("wrap", "clamp")
+                .with_metadata("options", MetadataValue::String("periodic|clamp".into())),
+            Parameter::new_input(
+                "weights",
+                TypedParameter::FloatArray {
+                    size: 2,
+                    default: Some(vec![0.25, 0.75]),
+                },
+            )
+            .with_metadata("max", MetadataValue::Float(1.0)),
+            Parameter::new_output(
+                "out",
+                TypedParameter::Color {
+                    default: Some([1.0; 3]),
+                    space: None,
+                },
+            ),
+        ]);
+        assert_eq!(query.validate(), []);
+        assert_eq!(OslQuery::new().validate(), []);
+    }
+
+    #[test]
+    fn test_default_out_of_range() {
+        let issue = assert_fires(
+            float("Kd", 1.5)
+                .with_metadata("min", MetadataValue::Float(0.0))
+                .with_metadata("max", MetadataValue::Float(1.0)),
+            RuleId::DefaultOutOfRange,
+        );
+        assert_eq!(issue.param, Some("Kd".into()));
+        assert_eq!(issue.severity, Severity::Warning);
+        assert_eq!(issue.message, "default 1.5 of \"Kd\" is above max 1");
+
+        let issue = assert_fires(
+            Parameter::new_input(
+                "tint",
+                TypedParameter::Color {
+                    default: Some([0.5, -0.1, 0.5]),
+                    space: None,
+                },
+            )
+            .with_metadata("min", MetadataValue::Float(0.0)),
+            RuleId::DefaultOutOfRange,
+        );
+        assert!(issue.message.contains("below min 0"), "{}", issue.message);
+
+        assert_fires(
+            int("samples", 0).with_metadata("min", MetadataValue::Int(1)),
+            RuleId::DefaultOutOfRange,
+        );
+    }
+
+    #[test]
+    fn test_array_length_mismatch() {
+        let issue = assert_fires(
+            Parameter::new_input(
+                "weights",
+                TypedParameter::FloatArray {
+                    size: 3,
+                    default: Some(vec![0.25, 0.75]),
+                },
+            ),
+            RuleId::ArrayLengthMismatch,
+        );
+        assert_eq!(issue.severity, Severity::Error);
+        assert!(issue.message.contains("2 values"), "{}", issue.message);
+
+        // Dynamic arrays can have any length.
+        let query = query([Parameter::new_input(
+            "weights",
+            TypedParameter::FloatDynamicArray {
+                default: Some(vec![0.25, 0.75]),
+            },
+        )]);
+        assert_eq!(query.validate(), []);
+    }
+
+    #[test]
+    fn test_int_metadata_for_float() {
+        let issue = assert_fires(
+            float("Kd", 0.5)
+                .with_metadata("min", MetadataValue::Float(0.0))
+                .with_metadata("slidermax", MetadataValue::Int(1)),
+            RuleId::IntMetadataForFloat,
+        );
+        assert!(issue.message.contains("\"slidermax\""), "{}", issue.message);
+
+        // Ints are expected for int parameters and other metadata.
+        let query = query([
+            int("samples", 4).with_metadata("max", MetadataValue::Int(16)),
+            float("Kd", 0.5).with_metadata("digits", MetadataValue::Int(2)),
+        ]);
+        assert_eq!(query.validate(), []);
+    }
+
+    #[test]
+    fn test_default_not_in_options() {
+        let issue = assert_fires(
+            string("wrap", "mirror")
+                .with_metadata("options", MetadataValue::String("periodic|clamp".into())),
+            RuleId::DefaultNotInOptions,
+        );
+        assert_eq!(issue.param, Some("wrap".into()));
+        assert_eq!(
+            issue.message,
+            "default mirror of \"wrap\" is not one of its options periodic, clamp"
+        );
+
+        assert_fires(
+            int("mode", 3).with_metadata("options", MetadataValue::String("Off|Low|High".into())),
+            RuleId::DefaultNotInOptions,
+        );
+        assert_fires(
+            int("mode", 0).with_metadata("options", MetadataValue::IntArray(vec![1, 2, 4])),
+            RuleId::DefaultNotInOptions,
+        );
+        assert_fires(
+            int("mode", 1)
+                .with_metadata("options", MetadataValue::String("Linear:0|Smooth:2".into())),
+            RuleId::DefaultNotInOptions,
+        );
+
+        // `enumvalues` give the values of the labels.
+        let query = query([int("mode", 4)
+            .with_metadata(
+                "options",
+                MetadataValue::StringArray(vec!["Low".into(), "High".into()]),
+            )
+            .with_metadata("enumvalues", MetadataValue::IntArray(vec![2, 4]))]);
+        assert_eq!(query.validate(), []);
+    }
+
+    #[test]
+    fn test_metadata_type() {
+        let issue = assert_fires(
+            float("Kd", 0.5).with_metadata("label", MetadataValue::Int(1)),
+            RuleId::MetadataType,
+        );
+        assert_eq!(issue.severity, Severity::Warning);
+
+        let issue = assert_fires(
+            int("samples", 4).with_metadata("min", MetadataValue::Float(0.5)),
+            RuleId::MetadataType,
+        );
+        assert!(issue.message.contains("is a float"), "{}", issue.message);
+    }
+
+    #[test]
+    fn test_metadata_typo() {
+        let issue = assert_fires(
+            float("Kd", 0.5).with_metadata("lable", MetadataValue::String("Diffuse".into())),
+            RuleId::MetadataTypo,
+        );
+        assert_eq!(issue.param, Some("Kd".into()));
+        assert!(issue.message.contains("\"label\"?"), "{}", issue.message);
+    }
+
+    #[test]
+    fn test_validate_struct_fields() {
+        let query = OslQuery::from_string(include_str!("fixtures/struct.oso")).unwrap();
+        assert_eq!(query.validate_struct_fields(), []);
+
+        let mut registry = StructRegistry::new();
+        registry.register_struct("Layer", 3);
+        registry.register_struct("Other", 1);
+        assert_eq!(query.validate_struct_fields_with_registry(&registry), []);
+
+        // The type grew a field the shader was not recompiled for.
+        registry.register_struct("Layer", 4);
+        let errors = query.validate_struct_fields_with_registry(&registry);
+        let error = |param_name: &str| StructFieldError {
+            param_name: param_name.into(),
+            expected_fields: 4,
+            actual_fields: 3,
+        };
+        assert_eq!(errors, [error("base"), error("coat")]);
+        assert_eq!(
+            errors[0].to_string(),
+            "struct parameter base lists 3 fields, expected 4"
+        );
+    }
+
+    #[test]
+    fn test_struct_field_hint_mismatch() {
+        // `%structfields` misses `tint`, and the nested struct counts once.
+        let query = OslQuery::from_string(
+            "OpenShadingLanguage 1.12\n\
+             surface test\n\
+             param\tstruct Outer\tp\t\t%struct{\"Outer\"} %structfields{Kd,inner}\n\
+             param\tfloat\tp.Kd\t0.5\n\
+             param\tcolor\tp.tint\t1 1 1\n\
+             param\tstruct Inner\tp.inner\t\t%struct{\"Inner\"} %structfields{a}\n\
+             param\tint\tp.inner.a\t1\n\
+             code ___main___\n",
+        )
+        .unwrap();
+        assert_eq!(
+            query.validate_struct_fields(),
+            [StructFieldError {
+                param_name: "p".into(),
+                expected_fields: 3,
+                actual_fields: 2,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_no_conflicts() {
+        let query = query([
+            float("Kd", 0.5)
+                .with_metadata("min", MetadataValue::Float(0.0))
+                .with_metadata("slidermin", MetadataValue::Float(0.0))
+                .with_metadata("slidermax", MetadataValue::Float(0.8))
+                .with_metadata("max", MetadataValue::Float(1.0))
+                .with_metadata("label", MetadataValue::String("Diffuse".into()))
+                .with_metadata("label", MetadataValue::String("Diffuse".into())),
+            // A range of a single value.
+            int("mode", 2)
+                .with_metadata("min", MetadataValue::Int(2))
+                .with_metadata("max", MetadataValue::Int(2))
+                .with_metadata("options", MetadataValue::IntArray(vec![2])),
+        ]);
+        assert_eq!(query.validate(), []);
+    }
+
+    #[test]
+    fn test_inverted_range() {
+        // Without a default, which would be out of range as well.
+        let issue = assert_fires(
+            Parameter::new_input("Kd", TypedParameter::Float { default: None })
+                .with_metadata("min", MetadataValue::Float(1.0))
+                .with_metadata("max", MetadataValue::Float(0.0)),
+            RuleId::InvertedRange,
+        );
+        assert_eq!(issue.severity, Severity::Error);
+        assert_eq!(issue.message, "\"Kd\" has min 1 above max 0");
+
+        let issue = assert_fires(
+            float("Kd", 0.5)
+                .with_metadata("slidermin", MetadataValue::Float(0.75))
+                .with_metadata("slidermax", MetadataValue::Float(0.25)),
+            RuleId::InvertedRange,
+        );
+        assert_eq!(
+            issue.message,
+            "\"Kd\" has slidermin 0.75 above slidermax 0.25"
+        );
+    }
+
+    #[test]
+    fn test_slider_outside_range() {
+        let issue = assert_fires(
+            float("Kd", 0.5)
+                .with_metadata("min", MetadataValue::Float(0.0))
+                .with_metadata("slidermin", MetadataValue::Float(-1.0)),
+            RuleId::SliderOutsideRange,
+        );
+        assert_eq!(issue.severity, Severity::Warning);
+        assert_eq!(issue.message, "\"Kd\" has slidermin -1 below min 0");
+
+        let issue = assert_fires(
+            int("samples", 4)
+                .with_metadata("max", MetadataValue::Int(16))
+                .with_metadata("slidermax", MetadataValue::Int(64)),
+            RuleId::SliderOutsideRange,
+        );
+        assert_eq!(issue.message, "\"samples\" has slidermax 64 above max 16");
+    }
+
+    #[test]
+    fn test_duplicate_metadata() {
+        let issue = assert_fires(
+            float("Kd", 0.5)
+                .with_metadata("label", MetadataValue::String("Diffuse".into()))
+                .with_metadata("help", MetadataValue::String("Scattering".into()))
+                .with_metadata("label", MetadataValue::String("Diffuse Weight".into())),
+            RuleId::DuplicateMetadata,
+        );
+        assert_eq!(
+            issue.message,
+            "\"Kd\" has \"label\" metadata \"Diffuse\" and \"Diffuse Weight\""
+        );
+
+        // Each differing repetition is compared with the first entry.
+        let query = query([int("ids", 0)
+            .with_metadata("options", MetadataValue::IntArray(vec![0, 1]))
+            .with_metadata("options", MetadataValue::IntArray(vec![0, 2]))
+            .with_metadata("options", MetadataValue::IntArray(vec![0, 1]))
+            .with_metadata("options", MetadataValue::IntArray(vec![0, 3]))]);
+        let messages: Vec<String> = query
+            .validate()
+            .into_iter()
+            .map(|issue| issue.message)
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "\"ids\" has \"options\" metadata {0, 1} and {0, 2}",
+                "\"ids\" has \"options\" metadata {0, 1} and {0, 3}"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_empty_name() {
+        let issue = assert_fires(float("", 0.5), RuleId::EmptyName);
+        assert_eq!(issue.param, None);
+        assert_eq!(issue.severity, Severity::Error);
+        assert_eq!(issue.message, "parameter 0 has an empty name");
+    }
+
+    #[test]
+    fn test_output_default() {
+        // Parsing and `new_output` strip output defaults, so set one directly.
+        let mut param = Parameter::new_output("out", TypedParameter::Float { default: None });
+        param.kind = ParameterKind::Output(TypedParameter::Float { default: Some(1.0) });
+        let issue = assert_fires(param, RuleId::OutputDefault);
+        assert_eq!(issue.param, Some("out".into()));
+        assert_eq!(issue.severity, Severity::Error);
+    }
+
+    #[test]
+    fn test_issue_order() {
+        let query = query([
+            float("", 0.5),
+            float("Kd", 2.0).with_metadata("max", MetadataValue::Int(1)),
+        ]);
+        let rules: Vec<RuleId> = query.validate().iter().map(|issue| issue.rule).collect();
+        assert_eq!(
+            rules,
+            [
+                RuleId::EmptyName,
+                RuleId::IntMetadataForFloat,
+                RuleId::DefaultOutOfRange
+            ]
+        );
+    }
+}
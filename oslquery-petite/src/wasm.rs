@@ -0,0 +1,59 @@
+//! JavaScript bindings, enabled by the `wasm` feature.
+//!
+//! Build with e.g. `wasm-pack build oslquery-petite --features wasm`, then:
+//!
+//! ```js
+//! import { parseOso } from "oslquery-petite";
+//!
+//! try {
+//!   const shader = parseOso(text);
+//!   console.log(shader.shader_name, shader.parameters.length);
+//! } catch (e) {
+//!   console.error(`${e.kind} at ${e.line}:${e.column}: ${e.message}`);
+//! }
+//! ```
+
+use js_sys::{Error, JSON, Reflect};
+use wasm_bindgen::prelude::*;
+
+use crate::parser::ParseError;
+use crate::query::OslQuery;
+
+/// Parse OSO source into the JSON representation also written by
+/// `oslq --json`.
+///
+/// Throws an `Error` with a `kind` property naming the [`ParseError`]
+/// variant and, where known, the 1-based `line` and `column` of the problem.
+#[wasm_bindgen(js_name = parseOso)]
+pub fn parse_oso(text: &str) -> Result<JsValue, JsValue> {
+    let query = OslQuery::from_string(text).map_err(|e| to_js_error(&e, text))?;
+    let json = serde_json::to_string(&query).expect("serializing a query cannot fail");
+    JSON::parse(&json)
+}
+
+fn to_js_error(error: &ParseError, source: &str) -> JsValue {
+    let js_error = Error::new(&error.to_string());
+    set(&js_error, "kind", &kind(error).into());
+    if let Some((line, column)) = error.line_column(source) {
+        set(&js_error, "line", &(line as f64).into());
+        set(&js_error, "column", &(column as f64).into());
+    }
+    js_error.into()
+}
+
+fn set(target: &Error, key: &str, value: &JsValue) {
+    Reflect::set(target, &key.into(), value).expect("setting a property on an Error cannot fail");
+}
+
+fn kind(error: &ParseError) -> &'static str {
+    match error {
+        ParseError::Io(_) => "Io",
+        ParseError::InvalidFormat(_) => "InvalidFormat",
+        ParseError::UnsupportedVersion { .. } => "UnsupportedVersion",
+        ParseError::ParseError { .. } => "ParseError",
+        ParseError::Incomplete(_) => "Incomplete",
+        ParseError::Conversion(_) => "Conversion",
+        #[cfg(feature = "compile-osl")]
+        ParseError::Compilation(_) => "Compilation",
+    }
+}
@@ -0,0 +1,115 @@
+//! Allocation and memory budgets for parsing.
+//!
+//! Counts the heap allocations of parsing the generated shader of the
+//! benches, and the memory kept by a library of them. A change that pushes a
+//! count over its budget should either be fixed or come with a new,
+//! justified budget.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use oslquery_petite::OslQuery;
+
+#[path = "../benches/fixtures/mod.rs"]
+mod fixtures;
+
+/// Counts the allocations and live bytes of the current thread, so tests
+/// running in parallel do not see each other's.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    /// Signed, as memory may be freed by another thread than allocated it.
+    static LIVE_BYTES: Cell<isize> = const { Cell::new(0) };
+}
+
+fn add_live_bytes(bytes: isize) {
+    LIVE_BYTES.with(|live| live.set(live.get() + bytes));
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        add_live_bytes(layout.size() as isize);
+        // SAFETY: forwarded unchanged.
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        add_live_bytes(-(layout.size() as isize));
+        // SAFETY: forwarded unchanged.
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        add_live_bytes(new_size as isize - layout.size() as isize);
+        // SAFETY: forwarded unchanged.
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Number of allocations (including reallocations) made by `f`.
+fn count_allocations<T>(f: impl FnOnce() -> T) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    let result = f();
+    let after = ALLOCATIONS.with(Cell::get);
+    drop(result);
+    after - before
+}
+
+/// Bytes still allocated after `f` returns, while its result is alive.
+fn retained_bytes<T>(f: impl FnOnce() -> T) -> isize {
+    let before = LIVE_BYTES.with(Cell::get);
+    let result = f();
+    let after = LIVE_BYTES.with(Cell::get);
+    drop(result);
+    after - before
+}
+
+/// About twice the 9086 allocations at the time of writing.
+const BUDGET_300_PARAMS: usize = 18_000;
+
+#[test]
+fn test_parse_allocation_budget() {
+    let source = fixtures::metadata_heavy_shader(300);
+    // Warm up thread locals and string interners.
+    OslQuery::from_string(&source).unwrap();
+
+    let allocations = count_allocations(|| OslQuery::from_string(&source).unwrap());
+    eprintln!("parsing 300 params: {allocations} allocations");
+    assert!(
+        allocations <= BUDGET_300_PARAMS,
+        "parsing 300 params made {allocations} allocations, budget is {BUDGET_300_PARAMS}"
+    );
+}
+
+/// About 1.25 times the 3.1 MB at the time of writing.
+const BUDGET_SCAN_BYTES: isize = 3_900_000;
+
+#[test]
+fn test_scan_memory_budget() {
+    let sources: Vec<String> = (0..20)
+        .map(|i| {
+            fixtures::metadata_heavy_shader(300)
+                .replace("\"metadataHeavy\"", &format!("\"metadataHeavy{i}\""))
+        })
+        .collect();
+    // Warm up thread locals and string interners.
+    OslQuery::from_string(&sources[0]).unwrap();
+
+    let bytes = retained_bytes(|| {
+        sources
+            .iter()
+            .map(|source| OslQuery::from_string(source).unwrap())
+            .collect::<Vec<_>>()
+    });
+    eprintln!("20 shaders with 300 params: {bytes} bytes");
+    assert!(
+        bytes <= BUDGET_SCAN_BYTES,
+        "20 shaders with 300 params keep {bytes} bytes, budget is {BUDGET_SCAN_BYTES}"
+    );
+}
@@ -0,0 +1,128 @@
+#![cfg(feature = "json")]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use oslquery_petite::cache::ShaderCache;
+
+/// A temporary directory with a copy of `ui.oso` and a cache directory,
+/// removed on drop.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let root = std::env::temp_dir().join(format!(
+            "oslquery-petite-cache-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        fs::copy(
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/ui.oso"),
+            root.join("ui.oso"),
+        )
+        .unwrap();
+        TempDir(root)
+    }
+
+    fn source(&self) -> PathBuf {
+        self.0.join("ui.oso")
+    }
+
+    fn cache(&self) -> ShaderCache {
+        ShaderCache::new(self.0.join("cache"))
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn set_modified(path: &Path, time: SystemTime) {
+    fs::File::options()
+        .write(true)
+        .open(path)
+        .unwrap()
+        .set_modified(time)
+        .unwrap();
+}
+
+#[test]
+fn test_served_from_cache() {
+    let dir = TempDir::new("hit");
+    let cache = dir.cache();
+    let source = dir.source();
+
+    let query = cache.get_or_parse(&source).unwrap();
+    assert_eq!(query.shader_name(), "uiTest");
+    assert!(cache.entry_path(&source).exists());
+
+    // Same size and mtime: the (now broken) source is not read again.
+    let modified = fs::metadata(&source).unwrap().modified().unwrap();
+    let len = fs::metadata(&source).unwrap().len() as usize;
+    fs::write(&source, "x".repeat(len)).unwrap();
+    set_modified(&source, modified);
+
+    let cached = cache.get_or_parse(&source).unwrap();
+    assert_eq!(cached.params(), query.params());
+}
+
+#[test]
+fn test_touch_causes_reparse() {
+    let dir = TempDir::new("touch");
+    let cache = dir.cache();
+    let source = dir.source();
+
+    assert_eq!(cache.get_or_parse(&source).unwrap().shader_name(), "uiTest");
+
+    let content = fs::read_to_string(&source).unwrap();
+    fs::write(&source, content.replace("uiTest", "uiTest2")).unwrap();
+    set_modified(&source, SystemTime::now() + Duration::from_secs(10));
+
+    assert_eq!(
+        cache.get_or_parse(&source).unwrap().shader_name(),
+        "uiTest2"
+    );
+}
+
+#[test]
+fn test_corrupt_entry_is_healed() {
+    let dir = TempDir::new("corrupt");
+    let cache = dir.cache();
+    let source = dir.source();
+
+    let query = cache.get_or_parse(&source).unwrap();
+    let entry = cache.entry_path(&source);
+    let intact = fs::read(&entry).unwrap();
+
+    fs::write(&entry, &intact[..intact.len() / 2]).unwrap();
+    let reparsed = cache.get_or_parse(&source).unwrap();
+    assert_eq!(reparsed.params(), query.params());
+    assert_eq!(fs::read(&entry).unwrap(), intact);
+}
+
+#[test]
+fn test_invalidate_and_prune() {
+    let dir = TempDir::new("prune");
+    let cache = dir.cache();
+    let source = dir.source();
+
+    cache.get_or_parse(&source).unwrap();
+    cache.invalidate(&source).unwrap();
+    assert!(!cache.entry_path(&source).exists());
+    // Nothing to invalidate is fine.
+    cache.invalidate(&source).unwrap();
+
+    cache.get_or_parse(&source).unwrap();
+    assert_eq!(cache.prune(Duration::from_secs(3600)).unwrap(), 0);
+    set_modified(
+        &cache.entry_path(&source),
+        SystemTime::now() - Duration::from_secs(7200),
+    );
+    assert_eq!(cache.prune(Duration::from_secs(3600)).unwrap(), 1);
+    assert!(!cache.entry_path(&source).exists());
+}
@@ -0,0 +1,38 @@
+#![cfg(feature = "compile-osl")]
+
+use oslquery_petite::parser::ParseError;
+use oslquery_petite::{OslQuery, TypedParameter};
+
+#[test]
+fn test_from_osl_source() {
+    let query = OslQuery::from_osl_source(
+        r#"
+surface plastic(
+    float Kd = 0.5 [[ string label = "Diffuse" ]],
+    color Cs = color(1, 0, 0),
+    output color Cout = 0)
+{
+    Cout = Kd * Cs;
+}
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(query.shader_type(), "surface");
+    assert_eq!(query.shader_name(), "plastic");
+    assert_eq!(query.format_identifier(), "OpenShadingLanguage");
+
+    let kd = query.param_by_name("Kd").unwrap();
+    assert_eq!(
+        kd.typed_param(),
+        &TypedParameter::Float { default: Some(0.5) }
+    );
+    assert!(kd.find_metadata("label").is_some());
+    assert!(query.param_by_name("Cout").unwrap().is_output());
+}
+
+#[test]
+fn test_from_osl_source_error() {
+    let error = OslQuery::from_osl_source("surface broken( { }").unwrap_err();
+    assert!(matches!(error, ParseError::Compilation(_)));
+}
@@ -1,49 +1,57 @@
-use oslquery_petite::{OslQuery, TypedParameter};
+use oslquery_petite::{OslQuery, TypedParameter, fixtures};
 
 #[test]
 fn test_parse_complex_oso() {
-    // Test with multiple real OSO files from 3Delight
+    let mut sources: Vec<(String, String)> = fixtures::all()
+        .iter()
+        .map(|(name, content)| (name.to_string(), content.to_string()))
+        .collect();
+
+    // Real OSO files from 3Delight, where installed
     let test_files = vec![
         "/usr/local/3delight-2.9/Linux-x86_64/osl/dlPrimitiveAttribute.oso",
         "/usr/local/3delight-2.9/Linux-x86_64/osl/colorVariation.oso",
         "/usr/local/3delight-2.9/Linux-x86_64/osl/dlRemap.oso",
         "/usr/local/3delight-2.9/Linux-x86_64/osl/material3DelightMetal.oso",
     ];
-
     for path in test_files {
         if let Ok(content) = std::fs::read_to_string(path) {
-            let result = OslQuery::from_string(&content);
+            sources.push((path.to_string(), content));
+        }
+    }
 
-            assert!(result.is_ok(), "Failed to parse {}: {:?}", path, result);
+    for (path, content) in sources {
+        let result = OslQuery::from_string(&content);
 
-            let query = result.unwrap();
+        assert!(result.is_ok(), "Failed to parse {}: {:?}", path, result);
 
-            // Basic sanity checks
-            assert!(
-                !query.shader_name().is_empty(),
-                "{}: Shader name should not be empty",
-                path
-            );
-            assert!(
-                !query.shader_type().is_empty(),
-                "{}: Shader type should not be empty",
-                path
-            );
+        let query = result.unwrap();
 
-            // Check that parameters are properly parsed
-            for param in query.params() {
-                // Just make sure we can access the typed parameter
-                let _ = param.typed_param();
-            }
+        // Basic sanity checks
+        assert!(
+            !query.shader_name().is_empty(),
+            "{}: Shader name should not be empty",
+            path
+        );
+        assert!(
+            !query.shader_type().is_empty(),
+            "{}: Shader type should not be empty",
+            path
+        );
 
-            println!(
-                "✓ Successfully parsed {}: {} shader '{}' with {} params",
-                path,
-                query.shader_type(),
-                query.shader_name(),
-                query.param_count()
-            );
+        // Check that parameters are properly parsed
+        for param in query.params() {
+            // Just make sure we can access the typed parameter
+            let _ = param.typed_param();
         }
+
+        println!(
+            "✓ Successfully parsed {}: {} shader '{}' with {} params",
+            path,
+            query.shader_type(),
+            query.shader_name(),
+            query.param_count()
+        );
     }
 }
 
@@ -0,0 +1,29 @@
+use oslquery_petite::OslQuery;
+use oslquery_petite::export::{CsvColumn, CsvOptions, to_csv};
+use pretty_assertions::assert_eq;
+
+#[test]
+fn test_csv_snapshot() {
+    let query = OslQuery::from_string(include_str!("ui.oso")).unwrap();
+
+    assert_eq!(
+        to_csv(&query, &CsvOptions::default()),
+        include_str!("snapshots/ui.csv")
+    );
+}
+
+#[test]
+fn test_tsv_without_outputs() {
+    let query = OslQuery::from_string(include_str!("ui.oso")).unwrap();
+    let options = CsvOptions {
+        columns: vec![CsvColumn::Name, CsvColumn::Type, CsvColumn::ArrayLen],
+        include_outputs: false,
+        ..CsvOptions::tsv()
+    };
+
+    let table = to_csv(&query, &options);
+    let mut rows = table.lines();
+    assert_eq!(rows.next(), Some("name\ttype\tarray_len"));
+    assert!(rows.any(|row| row == "weights\tfloat\t2"));
+    assert!(!table.contains("outColor"));
+}
@@ -0,0 +1,31 @@
+use oslquery_petite::OslQuery;
+use oslquery_petite::export::{MayaTemplateOptions, maya_ae_template};
+use pretty_assertions::assert_eq;
+
+#[test]
+fn test_maya_ae_template_snapshot() {
+    let query = OslQuery::from_string(include_str!("ui.oso")).unwrap();
+    let options = MayaTemplateOptions {
+        node_name: None,
+        strip_prefixes: vec!["i_".to_string()],
+    };
+
+    assert_eq!(
+        maya_ae_template(&query, &options),
+        include_str!("snapshots/maya_ae_template.mel")
+    );
+}
+
+#[test]
+fn test_maya_ae_template_node_name() {
+    let query = OslQuery::from_string(include_str!("ui.oso")).unwrap();
+    let options = MayaTemplateOptions {
+        node_name: Some("dlUiTest".to_string()),
+        ..Default::default()
+    };
+
+    let mel = maya_ae_template(&query, &options);
+    assert!(mel.contains("global proc AEdlUiTestTemplate(string $nodeName)"));
+    // Without prefix stripping the attribute keeps its OSL name
+    assert!(mel.contains("-addControl \"i_gain\";"));
+}
@@ -0,0 +1,107 @@
+use oslquery_petite::OslQuery;
+use oslquery_petite::export::renderman_args;
+use pretty_assertions::assert_eq;
+
+#[test]
+fn test_renderman_args_snapshot() {
+    let query = OslQuery::from_string(include_str!("renderman.oso")).unwrap();
+
+    assert_eq!(
+        renderman_args(&query),
+        include_str!("snapshots/renderman.args")
+    );
+}
+
+#[test]
+fn test_renderman_args_omits_missing_metadata() {
+    let query = OslQuery::from_string(
+        r#"
+OpenShadingLanguage 1.12
+shader plain
+param float Kd 0.5
+oparam color result
+code ___main___
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        renderman_args(&query),
+        r#"<args format="1.0">
+    <param name="Kd" type="float" default="0.5"/>
+    <output name="result"/>
+</args>
+"#
+    );
+}
+
+#[test]
+fn test_renderman_args_struct_fields_are_vstruct_members() {
+    let query = OslQuery::from_string(include_str!("struct.oso")).unwrap();
+
+    assert_eq!(
+        renderman_args(&query),
+        r#"<args format="1.0">
+    <param name="p" type="struct">
+        <tags>
+            <tag value="vstruct"/>
+        </tags>
+    </param>
+    <param name="p.Kd" type="float" default="0.5" vstructmember="p.Kd"/>
+    <param name="p.tint" type="color" default="1 1 1" vstructmember="p.tint"/>
+    <param name="gain" type="float" default="1"/>
+    <output name="Cout"/>
+</args>
+"#
+    );
+}
+
+#[test]
+fn test_renderman_args_skips_closures() {
+    let query = OslQuery::from_string(
+        r#"
+OpenShadingLanguage 1.12
+shader layer
+param closure color base
+oparam closure color out
+code ___main___
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        renderman_args(&query),
+        r#"<args format="1.0">
+    <!-- Skipped closure parameter "base": Args files have no closure type -->
+    <output name="out"/>
+</args>
+"#
+    );
+}
+
+#[test]
+fn test_renderman_args_connectable_forms() {
+    let query = OslQuery::from_string(
+        r#"
+OpenShadingLanguage 1.12
+shader plain
+param float a 0 %meta{int[1],connectable,0}
+param float b 0 %meta{string,connectable,"False"}
+param float c 0 %meta{int[2],connectable,1,0}
+param float d 0 %meta{string,connectable,"maybe"}
+code ___main___
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        renderman_args(&query),
+        r#"<args format="1.0">
+    <param name="a" type="float" default="0" connectable="False"/>
+    <param name="b" type="float" default="0" connectable="False"/>
+    <param name="c" type="float" default="0"/>
+    <param name="d" type="float" default="0"/>
+</args>
+"#
+    );
+}
@@ -0,0 +1,83 @@
+use oslquery_petite::OslQuery;
+use oslquery_petite::export::{UiControlKind, UiValue, ui_descriptor};
+
+#[test]
+fn test_ui_descriptor_controls() {
+    let query = OslQuery::from_string(include_str!("ui.oso")).unwrap();
+    let ui = ui_descriptor(&query);
+
+    assert_eq!(ui.shader, "uiTest");
+    assert_eq!(ui.help.as_deref(), Some("Shader exercising UI metadata"));
+
+    let names: Vec<_> = ui.groups.iter().map(|g| g.name.as_deref()).collect();
+    assert_eq!(
+        names,
+        vec![Some("Texture"), None, Some("Adjust"), Some("Placement")]
+    );
+
+    let controls: Vec<_> = ui.groups.iter().flat_map(|g| &g.controls).collect();
+
+    // Hidden and output parameters are left out
+    assert!(controls.iter().all(|c| c.param != "hidden"));
+    assert!(controls.iter().all(|c| c.param != "outColor"));
+
+    let filter = controls.iter().find(|c| c.param == "filter").unwrap();
+    match &filter.kind {
+        UiControlKind::Dropdown { options } => {
+            assert_eq!(options.len(), 3);
+            assert_eq!(options[2].label, "Lanczos");
+            assert_eq!(options[2].value, UiValue::Int(2));
+        }
+        other => panic!("Expected dropdown, got {:?}", other),
+    }
+
+    let roughness = controls.iter().find(|c| c.param == "roughness").unwrap();
+    assert_eq!(roughness.kind, UiControlKind::Slider);
+    assert_eq!(roughness.range.unwrap().soft_max, Some(1.0));
+    let visibility = roughness.visibility.as_ref().unwrap();
+    assert_eq!(visibility.param, "enable");
+    assert_eq!(visibility.op, "equalTo");
+    assert_eq!(visibility.value, "1");
+}
+
+#[test]
+fn test_ui_dropdown_enum_values() {
+    let query = OslQuery::from_string(
+        r#"
+OpenShadingLanguage 1.12
+shader enumTest
+param int quality 4 %meta{string,widget,"popup"} %meta{string,options,"Low|High"} %meta{int[2],enumvalues,1,4}
+code ___main___
+"#,
+    )
+    .unwrap();
+    let ui = ui_descriptor(&query);
+
+    match &ui.groups[0].controls[0].kind {
+        UiControlKind::Dropdown { options } => {
+            let options: Vec<_> = options
+                .iter()
+                .map(|o| (o.label.as_str(), &o.value))
+                .collect();
+            assert_eq!(
+                options,
+                [("Low", &UiValue::Int(1)), ("High", &UiValue::Int(4))]
+            );
+        }
+        other => panic!("Expected dropdown, got {:?}", other),
+    }
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_ui_json_snapshot() {
+    use oslquery_petite::export::to_ui_json;
+    use pretty_assertions::assert_eq;
+
+    let query = OslQuery::from_string(include_str!("ui.oso")).unwrap();
+
+    assert_eq!(
+        to_ui_json(&query) + "\n",
+        include_str!("snapshots/ui_descriptor.json")
+    );
+}
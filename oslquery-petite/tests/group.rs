@@ -0,0 +1,81 @@
+#[cfg(feature = "json")]
+use oslquery_petite::parser::ParseError;
+use oslquery_petite::{OslQuery, TypedParameter, parse_group};
+
+#[test]
+fn test_parse_group() {
+    let content = std::fs::read_to_string("tests/layers.oslgroup").unwrap();
+    let group = parse_group(&content).expect("Failed to parse shader group");
+
+    assert_eq!(
+        group.shader_names().collect::<Vec<_>>(),
+        vec!["texture", "plastic"]
+    );
+
+    let tex = group.layer("tex1").unwrap();
+    assert_eq!(tex.params.len(), 2);
+    assert_eq!(
+        tex.params[0].typed_param(),
+        &TypedParameter::Float { default: Some(4.0) }
+    );
+    assert_eq!(
+        tex.params[1].typed_param(),
+        &TypedParameter::String {
+            default: Some("grid.tx".into())
+        }
+    );
+
+    let surface = group.root().unwrap();
+    assert_eq!(surface.layer, "surface1");
+    assert_eq!(surface.params[0].name.as_str(), "Cs");
+    assert!(matches!(
+        surface.params[0].typed_param(),
+        TypedParameter::Color {
+            default: Some([0.5, 0.5, 0.5]),
+            ..
+        }
+    ));
+
+    assert_eq!(group.connections.len(), 2);
+    assert_eq!(group.connections[0].src_layer, "tex1");
+    assert_eq!(group.connections[0].src_param, "Cout");
+    assert_eq!(group.connections[0].dst_layer, "surface1");
+    assert_eq!(group.connections[0].dst_param, "Cs");
+}
+
+#[test]
+fn test_open_group() {
+    let query = OslQuery::open_group("tests/layers.oslgroup").expect("Failed to open shader group");
+    assert!(query.is_group());
+    assert_eq!(query.shader_type(), "shadergroup");
+    assert_eq!(query.shader_name(), "surface1");
+    assert_eq!(query.group().unwrap().layers.len(), 2);
+
+    let query = OslQuery::open("tests/test.oso").expect("Failed to parse OSO file");
+    assert!(!query.is_group());
+}
+
+#[test]
+fn test_oso_parser_ignores_groups() {
+    // The OSO parser never guesses that its input is a group.
+    let content = std::fs::read_to_string("tests/layers.oslgroup").unwrap();
+    assert!(!OslQuery::from_string(&content).is_ok_and(|query| query.is_group()));
+    assert!(!OslQuery::open("tests/layers.oslgroup").is_ok_and(|query| query.is_group()));
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_group_json() {
+    let content = std::fs::read_to_string("tests/layers.oslgroup").unwrap();
+    let group = parse_group(&content).unwrap();
+    let json = serde_json::to_string(&group).unwrap();
+
+    let query = OslQuery::from_group_string(&json).unwrap();
+    assert_eq!(query.group(), Some(&group));
+    assert_eq!(query.shader_name(), "surface1");
+
+    assert!(matches!(
+        parse_group("{\"layers\": 1}"),
+        Err(ParseError::InvalidFormat(_))
+    ));
+}
@@ -0,0 +1,337 @@
+use oslquery_petite::SearchPaths;
+use oslquery_petite::library::{LibraryChange, LibraryError, ScanOptions, ShaderLibrary};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A temporary directory tree, removed on drop.
+struct TempTree(PathBuf);
+
+impl TempTree {
+    /// Create:
+    ///
+    /// ```text
+    /// root/test.oso            dlConstant
+    /// root/notes.txt
+    /// root/a/ui.oso            uiTest
+    /// root/a/b/array.oso       arraytest
+    /// root/c/copy.oso          dlConstant
+    /// ```
+    fn new(name: &str) -> Self {
+        let root =
+            std::env::temp_dir().join(format!("oslquery-petite-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        fs::create_dir_all(root.join("c")).unwrap();
+
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+        for (fixture, dst) in [
+            ("test.oso", "test.oso"),
+            ("ui.oso", "a/ui.oso"),
+            ("array.oso", "a/b/array.oso"),
+            ("test.oso", "c/copy.oso"),
+        ] {
+            fs::copy(fixtures.join(fixture), root.join(dst)).unwrap();
+        }
+        fs::write(root.join("notes.txt"), "not a shader").unwrap();
+
+        TempTree(root)
+    }
+}
+
+impl Drop for TempTree {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[test]
+fn test_scan_non_recursive() {
+    let tree = TempTree::new("flat");
+    let library = ShaderLibrary::scan([&tree.0], &ScanOptions::default()).unwrap();
+
+    assert_eq!(library.names().collect::<Vec<_>>(), vec!["dlConstant"]);
+    assert!(library.collisions().is_empty());
+}
+
+#[test]
+fn test_scan_recursive() {
+    let tree = TempTree::new("recursive");
+    let library = ShaderLibrary::scan([&tree.0], &ScanOptions { recursive: true }).unwrap();
+
+    assert_eq!(library.len(), 4);
+    assert_eq!(
+        library.names().collect::<Vec<_>>(),
+        vec!["arraytest", "dlConstant", "uiTest"]
+    );
+
+    let entry = library.entry("uiTest").unwrap();
+    assert_eq!(entry.shader_type(), "shader");
+    assert_eq!(entry.path(), tree.0.join("a/ui.oso"));
+    assert!(entry.modified().is_some());
+
+    let paths: Vec<_> = library.iter().map(|e| e.path().to_path_buf()).collect();
+    assert_eq!(
+        paths,
+        vec![
+            tree.0.join("a/b/array.oso"),
+            tree.0.join("a/ui.oso"),
+            tree.0.join("c/copy.oso"),
+            tree.0.join("test.oso"),
+        ]
+    );
+}
+
+#[test]
+fn test_get() {
+    let tree = TempTree::new("get");
+    let library = ShaderLibrary::scan([&tree.0], &ScanOptions { recursive: true }).unwrap();
+
+    let query = library.get("arraytest").unwrap();
+    assert_eq!(query.shader_name(), "arraytest");
+    assert!(query.param_by_name("myarray").is_some());
+
+    // Parsed once, then shared.
+    assert!(std::sync::Arc::ptr_eq(
+        &query,
+        &library.get("arraytest").unwrap()
+    ));
+
+    assert_eq!(
+        library.get("missing").unwrap_err(),
+        LibraryError::NotFound("missing".to_string())
+    );
+}
+
+#[test]
+fn test_total_heap_size() {
+    let tree = TempTree::new("heap-size");
+    let library = ShaderLibrary::scan([&tree.0], &ScanOptions { recursive: true }).unwrap();
+
+    let index_only = library.total_heap_size();
+    assert!(index_only > 0);
+
+    // Parsed shaders are added once.
+    let query = library.get("arraytest").unwrap();
+    let parsed = library.total_heap_size();
+    assert!(parsed >= index_only + query.heap_size_estimate());
+    library.get("arraytest").unwrap();
+    assert_eq!(library.total_heap_size(), parsed);
+}
+
+#[test]
+fn test_collisions() {
+    let tree = TempTree::new("collisions");
+    let library = ShaderLibrary::scan([&tree.0], &ScanOptions { recursive: true }).unwrap();
+
+    let collisions = library.collisions();
+    assert_eq!(collisions.len(), 1);
+    let (name, paths) = &collisions[0];
+    assert_eq!(*name, "dlConstant");
+    assert_eq!(
+        paths,
+        &vec![tree.0.join("c/copy.oso"), tree.0.join("test.oso")]
+    );
+
+    // The first directory given wins.
+    let library =
+        ShaderLibrary::scan([tree.0.join("c"), tree.0.clone()], &ScanOptions::default()).unwrap();
+    assert_eq!(
+        library.entry("dlConstant").unwrap().path(),
+        tree.0.join("c/copy.oso")
+    );
+}
+
+#[test]
+fn test_scan_unreadable_file() {
+    let tree = TempTree::new("unreadable");
+    fs::write(
+        tree.0.join("latin1.oso"),
+        b"OpenShadingLanguage 1.00\nshader caf\xe9\n",
+    )
+    .unwrap();
+    let library = ShaderLibrary::scan([&tree.0], &ScanOptions::default()).unwrap();
+
+    assert_eq!(library.names().collect::<Vec<_>>(), vec!["dlConstant"]);
+    let failures = library.failures();
+    assert_eq!(failures.len(), 1);
+    let LibraryError::Parse { path, .. } = &failures[0] else {
+        panic!("unexpected failure {:?}", failures[0]);
+    };
+    assert_eq!(*path, tree.0.join("latin1.oso"));
+    assert!(failures[0].to_string().contains("latin1.oso"));
+
+    // The failure goes away with the file.
+    fs::remove_file(tree.0.join("latin1.oso")).unwrap();
+    library.refresh().unwrap();
+    assert!(library.failures().is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_scan_symlink_loop() {
+    let tree = TempTree::new("symlink");
+    std::os::unix::fs::symlink(&tree.0, tree.0.join("a/up")).unwrap();
+    let library = ShaderLibrary::scan([&tree.0], &ScanOptions { recursive: true }).unwrap();
+
+    assert_eq!(library.len(), 4);
+    assert_eq!(library.collisions().len(), 1);
+}
+
+#[test]
+fn test_find_by_metadata() {
+    let tree = TempTree::new("metadata");
+    let library = ShaderLibrary::scan([&tree.0], &ScanOptions { recursive: true }).unwrap();
+
+    let found = library.find_by_metadata("help", "Shader exercising UI metadata");
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].shader_name(), "uiTest");
+
+    assert!(library.find_by_metadata("help", "nothing").is_empty());
+}
+
+#[test]
+fn test_scan_missing_directory() {
+    assert!(ShaderLibrary::scan(["/nonexistent/shaders"], &ScanOptions::default()).is_err());
+}
+
+#[test]
+fn test_scan_search_paths() {
+    let tree = TempTree::new("searchpath");
+    let searchpath = format!(
+        "{};/nonexistent/shaders;{}",
+        tree.0.join("c").display(),
+        tree.0.join("a").display()
+    );
+    let library =
+        ShaderLibrary::scan_search_paths(&SearchPaths::parse(&searchpath), &ScanOptions::default())
+            .unwrap();
+
+    assert_eq!(
+        library.names().collect::<Vec<_>>(),
+        vec!["dlConstant", "uiTest"]
+    );
+}
+
+/// Write `source` to `path` so its modification time or size differs from
+/// the previous contents.
+fn rewrite(path: &Path, source: &str) {
+    let before = fs::metadata(path).and_then(|m| m.modified()).ok();
+    loop {
+        fs::write(path, source).unwrap();
+        if fs::metadata(path).and_then(|m| m.modified()).ok() != before {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn test_refresh() {
+    let tree = TempTree::new("refresh");
+    let library = ShaderLibrary::scan([&tree.0], &ScanOptions { recursive: true }).unwrap();
+    let before = library.get("uiTest").unwrap();
+    let constant = library.get("dlConstant").unwrap();
+
+    assert!(library.refresh().unwrap().is_empty());
+
+    // Rewrite ui.oso with one parameter removed, delete array.oso and add a
+    // new shader.
+    let ui_path = tree.0.join("a/ui.oso");
+    let ui = fs::read_to_string(&ui_path).unwrap();
+    let removed_param = ui
+        .lines()
+        .find(|line| line.starts_with("param"))
+        .unwrap()
+        .to_string();
+    rewrite(&ui_path, &ui.replace(&format!("{}\n", removed_param), ""));
+    fs::remove_file(tree.0.join("a/b/array.oso")).unwrap();
+    fs::copy(
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/struct.oso"),
+        tree.0.join("c/struct.oso"),
+    )
+    .unwrap();
+
+    let change = library.refresh().unwrap();
+    assert_eq!(
+        change,
+        LibraryChange {
+            added: vec!["structTest".to_string()],
+            updated: vec!["uiTest".to_string()],
+            removed: vec!["arraytest".to_string()],
+        }
+    );
+
+    let after = library.get("uiTest").unwrap();
+    assert_eq!(after.param_count(), before.param_count() - 1);
+    assert!(library.get("structTest").is_ok());
+    assert_eq!(
+        library.get("arraytest").unwrap_err(),
+        LibraryError::NotFound("arraytest".to_string())
+    );
+
+    // Unchanged files keep their parsed shader.
+    assert!(std::sync::Arc::ptr_eq(
+        &constant,
+        &library.get("dlConstant").unwrap()
+    ));
+}
+
+#[test]
+fn test_get_during_refresh() {
+    let tree = TempTree::new("concurrent");
+    let library = ShaderLibrary::scan([&tree.0], &ScanOptions { recursive: true }).unwrap();
+    let ui_path = tree.0.join("a/ui.oso");
+    let ui = fs::read_to_string(&ui_path).unwrap();
+
+    std::thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                for _ in 0..100 {
+                    assert_eq!(library.get("uiTest").unwrap().shader_name(), "uiTest");
+                }
+            });
+        }
+        // Replace the file atomically, so shaders parsed on first access
+        // never see it half-written.
+        let tmp_path = tree.0.join("ui.tmp");
+        for i in 0..20 {
+            fs::write(&tmp_path, format!("{}# {}\n", ui, i)).unwrap();
+            fs::rename(&tmp_path, &ui_path).unwrap();
+            library.refresh().unwrap();
+        }
+    });
+}
+
+#[cfg(feature = "watch")]
+#[test]
+fn test_watch() {
+    use std::sync::{Arc, mpsc};
+    use std::time::Duration;
+
+    let tree = TempTree::new("watch");
+    let library =
+        Arc::new(ShaderLibrary::scan([&tree.0], &ScanOptions { recursive: true }).unwrap());
+    let param_count = library.get("uiTest").unwrap().param_count();
+
+    let (sender, receiver) = mpsc::channel();
+    let _watcher = library
+        .watch(move |change| {
+            let _ = sender.send(change.clone());
+        })
+        .unwrap();
+
+    let ui_path = tree.0.join("a/ui.oso");
+    let ui = fs::read_to_string(&ui_path).unwrap();
+    let param = ui.lines().find(|line| line.starts_with("param")).unwrap();
+    rewrite(&ui_path, &ui.replace(&format!("{}\n", param), ""));
+
+    // The file is written in several steps, so the shader may briefly be
+    // missing; wait until the final contents were picked up.
+    let deadline = std::time::Instant::now() + Duration::from_secs(10);
+    while library.get("uiTest").map(|query| query.param_count()) != Ok(param_count - 1) {
+        let change = receiver
+            .recv_timeout(deadline.saturating_duration_since(std::time::Instant::now()))
+            .expect("no change reported");
+        assert!(!change.is_empty());
+    }
+}
@@ -0,0 +1,228 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use oslquery_petite::library::{ScanOptions, ShaderLibrary};
+use oslquery_petite::parser::ParseError;
+use oslquery_petite::{OslQuery, SearchPaths};
+
+/// A temporary search tree, removed on drop:
+///
+/// ```text
+/// root/first/lambert.oso     dlConstant
+/// root/first/raw             uiTest, without an extension
+/// root/second/lambert.oso    uiTest
+/// root/second/constant.oso   dlConstant
+/// root/second/plastic.oso    arraytest
+/// root/second/ui/            a directory
+/// ```
+struct TempTree(PathBuf);
+
+impl TempTree {
+    fn new(name: &str) -> Self {
+        let root = std::env::temp_dir().join(format!(
+            "oslquery-petite-by-name-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("first")).unwrap();
+        fs::create_dir_all(root.join("second/ui")).unwrap();
+
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+        for (fixture, dst) in [
+            ("test.oso", "first/lambert.oso"),
+            ("ui.oso", "first/raw"),
+            ("ui.oso", "second/lambert.oso"),
+            ("test.oso", "second/constant.oso"),
+            ("array.oso", "second/plastic.oso"),
+        ] {
+            fs::copy(fixtures.join(fixture), root.join(dst)).unwrap();
+        }
+        TempTree(root)
+    }
+
+    fn search_paths(&self) -> SearchPaths {
+        [self.0.join("first"), self.0.join("second")]
+            .into_iter()
+            .collect()
+    }
+}
+
+impl Drop for TempTree {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn shader_name(result: Result<OslQuery, ParseError>) -> String {
+    result.unwrap().shader_name().to_string()
+}
+
+#[test]
+fn test_open_by_name() {
+    let tree = TempTree::new("open");
+    let search_paths = tree.search_paths();
+
+    // The first directory shadows the second.
+    assert_eq!(
+        shader_name(OslQuery::open_by_name("lambert", &search_paths)),
+        "dlConstant"
+    );
+    let reversed: SearchPaths = search_paths.iter().rev().cloned().collect();
+    assert_eq!(
+        shader_name(OslQuery::open_by_name("lambert", &reversed)),
+        "uiTest"
+    );
+
+    assert_eq!(
+        shader_name(OslQuery::open_by_name("plastic", &search_paths)),
+        "arraytest"
+    );
+    assert_eq!(
+        shader_name(OslQuery::open_by_name("plastic.oso", &search_paths)),
+        "arraytest"
+    );
+    // The exact name is tried after the one with `.oso`.
+    assert_eq!(
+        shader_name(OslQuery::open_by_name("raw", &search_paths)),
+        "uiTest"
+    );
+}
+
+#[test]
+fn test_open_by_name_not_found() {
+    let tree = TempTree::new("missing");
+    let search_paths = tree.search_paths();
+
+    // Directories are skipped.
+    let ParseError::Io(message) = OslQuery::open_by_name("ui", &search_paths).unwrap_err() else {
+        panic!("expected an IO error");
+    };
+    let tried = [
+        tree.0.join("first/ui.oso"),
+        tree.0.join("first/ui"),
+        tree.0.join("second/ui.oso"),
+        tree.0.join("second/ui"),
+    ]
+    .map(|path| path.display().to_string());
+    assert_eq!(
+        message,
+        format!("Shader \"ui\" not found, tried: {}", tried.join(", "))
+    );
+
+    let error = OslQuery::open_by_name("lambert", &SearchPaths::default()).unwrap_err();
+    assert!(
+        error.to_string().contains("search path is empty"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn test_open_by_name_rejects_paths() {
+    let tree = TempTree::new("paths");
+    let search_paths = tree.search_paths();
+
+    for name in ["first/lambert", "../first/lambert", "first\\lambert", ""] {
+        let error = OslQuery::open_by_name(name, &search_paths).unwrap_err();
+        assert!(
+            error.to_string().contains("Invalid shader name"),
+            "{}: {}",
+            name,
+            error
+        );
+    }
+    // Even when the path exists.
+    let absolute = tree.0.join("first/lambert.oso");
+    assert!(OslQuery::open_by_name(absolute.to_str().unwrap(), &search_paths).is_err());
+}
+
+#[test]
+fn test_resolve_all() {
+    let tree = TempTree::new("all");
+    let search_paths = tree.search_paths();
+
+    assert_eq!(
+        search_paths.resolve_all("lambert"),
+        [
+            tree.0.join("first/lambert.oso"),
+            tree.0.join("second/lambert.oso")
+        ]
+    );
+    assert_eq!(
+        search_paths.resolve_all("lambert").first(),
+        search_paths.find("lambert").as_ref()
+    );
+    assert_eq!(
+        search_paths.resolve_all("plastic.oso"),
+        [tree.0.join("second/plastic.oso")]
+    );
+    assert!(search_paths.resolve_all("missing").is_empty());
+}
+
+#[test]
+fn test_resolution_candidates() {
+    let tree = TempTree::new("candidates");
+    let search_paths = tree.search_paths();
+    let shadowing = [
+        tree.0.join("first/lambert.oso"),
+        tree.0.join("second/lambert.oso"),
+    ];
+
+    // Not recorded by default.
+    let query = OslQuery::open_by_name("lambert", &search_paths).unwrap();
+    assert!(query.resolution_candidates().is_empty());
+    let query = OslQuery::open_with_search_paths("lambert", &search_paths).unwrap();
+    assert!(query.resolution_candidates().is_empty());
+
+    let recording = search_paths.clone().record_candidates(true);
+    assert!(recording.records_candidates());
+    let query = OslQuery::open_by_name("lambert", &recording).unwrap();
+    assert_eq!(query.shader_name(), "dlConstant");
+    assert_eq!(query.resolution_candidates(), shadowing);
+    let query = OslQuery::open_with_search_paths("lambert", &recording).unwrap();
+    assert_eq!(query.shader_name(), "dlConstant");
+    assert_eq!(query.resolution_candidates(), shadowing);
+
+    // A single match shadows nothing.
+    let query = OslQuery::open_by_name("plastic", &recording).unwrap();
+    assert_eq!(
+        query.resolution_candidates(),
+        [tree.0.join("second/plastic.oso")]
+    );
+    // Candidates are not part of the interface.
+    assert_eq!(
+        query.canonical_form(),
+        OslQuery::open_by_name("plastic", &search_paths)
+            .unwrap()
+            .canonical_form()
+    );
+}
+
+#[test]
+fn test_library_collisions() {
+    let tree = TempTree::new("library");
+    let library = ShaderLibrary::scan(
+        [tree.0.join("first"), tree.0.join("second")],
+        &ScanOptions::default(),
+    )
+    .unwrap();
+
+    // The library goes by the shader names in the files, so the two
+    // `lambert.oso` do not clash, but `dlConstant` is in both directories.
+    // The first one wins, as in a search path.
+    assert_eq!(
+        library.collisions(),
+        [(
+            "dlConstant".to_string(),
+            vec![
+                tree.0.join("first/lambert.oso"),
+                tree.0.join("second/constant.oso")
+            ]
+        )]
+    );
+    assert_eq!(
+        library.entry("dlConstant").unwrap().path(),
+        tree.0.join("first/lambert.oso")
+    );
+}
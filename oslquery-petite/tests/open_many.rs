@@ -0,0 +1,76 @@
+use std::path::{Path, PathBuf};
+
+use oslquery_petite::OslQuery;
+
+fn fixtures() -> Vec<PathBuf> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "oso"))
+        .collect();
+    paths.sort();
+    paths.push(dir.join("missing.oso"));
+    paths
+}
+
+#[test]
+fn test_open_many_preserves_order() {
+    let paths = fixtures();
+    let report = OslQuery::open_many(&paths, "");
+
+    assert_eq!(report.len(), paths.len());
+    for (file, expected_path) in report.files().iter().zip(&paths) {
+        assert_eq!(&file.path, expected_path);
+        assert_eq!(file.result, OslQuery::open(&file.path));
+    }
+    assert!(report.files().last().unwrap().result.is_err());
+}
+
+#[test]
+fn test_open_many_report() {
+    let paths = fixtures();
+    let missing = paths.last().unwrap();
+    let report = OslQuery::open_many(&paths, "");
+
+    assert!(!report.is_all_ok());
+    assert_eq!(report.ok_iter().count(), paths.len() - 1);
+    for ((path, query), expected_path) in report.ok_iter().zip(&paths) {
+        assert_eq!(path, expected_path);
+        assert!(query.is_valid());
+    }
+
+    let errors: Vec<&Path> = report.errors().map(|(path, _)| path).collect();
+    assert_eq!(errors, [missing.as_path()]);
+
+    for file in report.files() {
+        if file.result.is_ok() {
+            assert_eq!(file.resolved.as_ref(), Some(&file.path));
+        } else {
+            assert_eq!(file.resolved, None);
+        }
+    }
+}
+
+#[test]
+fn test_open_many_from_threads() {
+    let paths = fixtures();
+    let serial: Vec<_> = paths.iter().map(OslQuery::open).collect();
+
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..8)
+            .map(|_| scope.spawn(|| OslQuery::open_many(&paths, "")))
+            .collect();
+
+        for handle in handles {
+            let results: Vec<_> = handle
+                .join()
+                .unwrap()
+                .into_files()
+                .into_iter()
+                .map(|file| file.result)
+                .collect();
+            assert_eq!(results, serial);
+        }
+    });
+}
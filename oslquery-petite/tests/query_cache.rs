@@ -0,0 +1,44 @@
+use std::sync::{Arc, Barrier};
+
+use oslquery_petite::QueryCache;
+
+#[test]
+fn test_one_parse_per_file_across_threads() {
+    const THREADS: usize = 16;
+    let tests_dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests");
+    let names = ["ui", "test", "array.oso", "struct", "points", "all_types"];
+
+    let cache = QueryCache::new(names.len());
+    let barrier = Barrier::new(THREADS);
+
+    let results: Vec<Vec<_>> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..THREADS)
+            .map(|thread| {
+                let (cache, barrier) = (&cache, &barrier);
+                scope.spawn(move || {
+                    barrier.wait();
+                    // Start at different names so threads race on each file.
+                    (0..names.len() * 10)
+                        .map(|i| {
+                            let name = names[(thread + i) % names.len()];
+                            (name, cache.get_or_open(name, tests_dir).unwrap())
+                        })
+                        .collect()
+                })
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+
+    assert_eq!(cache.parse_count(), names.len());
+    assert_eq!(cache.len(), names.len());
+
+    // Every thread got the same instance of each shader.
+    for name in names {
+        let first = cache.get_or_open(name, tests_dir).unwrap();
+        for (_, query) in results.iter().flatten().filter(|(n, _)| *n == name) {
+            assert!(Arc::ptr_eq(&first, query), "{}", name);
+        }
+    }
+    assert_eq!(cache.parse_count(), names.len());
+}
@@ -1,12 +1,27 @@
-use oslquery_petite::{OslQuery, TypedParameter};
+use oslquery_petite::{OslQuery, TypedParameter, fixtures};
+
+/// A 3Delight installation, for extra coverage where one is present.
+const DELIGHT_OSL_DIR: &str = "/usr/local/3delight-2.9/Linux-x86_64/osl";
+
+/// Read a shader from the 3Delight installation, if there is one.
+fn read_delight(name: &str) -> Option<String> {
+    std::fs::read_to_string(format!("{}/{}", DELIGHT_OSL_DIR, name)).ok()
+}
 
 #[test]
 fn test_parse_lambert_oso() {
-    let oso_path = "/usr/local/3delight-2.9/Linux-x86_64/osl/lambert.oso";
+    check_lambert(fixtures::LAMBERT_OSO);
+}
 
-    let content = std::fs::read_to_string(oso_path).expect("Failed to read lambert.oso");
+#[test]
+fn test_parse_delight_lambert_oso() {
+    if let Some(content) = read_delight("lambert.oso") {
+        check_lambert(&content);
+    }
+}
 
-    let query = OslQuery::from_string(&content).expect("Failed to parse OSO file");
+fn check_lambert(content: &str) {
+    let query = OslQuery::from_string(content).expect("Failed to parse OSO file");
 
     // Basic shader info
     assert_eq!(query.shader_type(), "surface");
@@ -57,7 +72,7 @@ fn test_parse_lambert_oso() {
     match i_diffuse.typed_param() {
         TypedParameter::Float { default: Some(val) } => {
             assert!(
-                (val - 0.800000012).abs() < 0.0001,
+                (val - 0.8).abs() < 0.0001,
                 "i_diffuse default should be ~0.8"
             );
         }
@@ -88,12 +103,19 @@ fn test_parse_lambert_oso() {
 
 #[test]
 fn test_parse_with_initexpr() {
-    // normalCamera has %initexpr, so should not have valid default
-    let oso_path = "/usr/local/3delight-2.9/Linux-x86_64/osl/lambert.oso";
+    check_initexpr(fixtures::LAMBERT_OSO);
+}
 
-    let content = std::fs::read_to_string(oso_path).expect("Failed to read lambert.oso");
+#[test]
+fn test_parse_delight_with_initexpr() {
+    if let Some(content) = read_delight("lambert.oso") {
+        check_initexpr(&content);
+    }
+}
 
-    let query = OslQuery::from_string(&content).expect("Failed to parse OSO file");
+fn check_initexpr(content: &str) {
+    // normalCamera has %initexpr, so should not have valid default
+    let query = OslQuery::from_string(content).expect("Failed to parse OSO file");
 
     let normal_camera = query
         .param_by_name("normalCamera")
@@ -110,22 +132,26 @@ fn test_parse_with_initexpr() {
 
 #[test]
 fn test_parse_multiple_oso_files() {
-    let test_files = vec![
-        "/usr/local/3delight-2.9/Linux-x86_64/osl/dlPrimitiveAttribute.oso",
-        "/usr/local/3delight-2.9/Linux-x86_64/osl/colorVariation.oso",
-        "/usr/local/3delight-2.9/Linux-x86_64/osl/dlRemap.oso",
-    ];
-
-    for path in test_files {
-        if let Ok(content) = std::fs::read_to_string(path) {
+    for (name, content) in fixtures::all() {
+        let query = OslQuery::from_string(content)
+            .unwrap_or_else(|e| panic!("Failed to parse {}: {:?}", name, e));
+        assert_eq!(query.shader_name(), *name);
+    }
+
+    for name in [
+        "dlPrimitiveAttribute.oso",
+        "colorVariation.oso",
+        "dlRemap.oso",
+    ] {
+        if let Some(content) = read_delight(name) {
             let result = OslQuery::from_string(&content);
 
-            assert!(result.is_ok(), "Failed to parse {}: {:?}", path, result);
+            assert!(result.is_ok(), "Failed to parse {}: {:?}", name, result);
 
             let query = result.unwrap();
             println!(
                 "Successfully parsed {}: {} shader '{}'",
-                path,
+                name,
                 query.shader_type(),
                 query.shader_name()
             );
@@ -0,0 +1,25 @@
+use oslquery_petite::test_util::{arb_query, assert_roundtrip};
+use oslquery_petite::{OslQuery, fixtures};
+use proptest::prelude::*;
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(300))]
+
+    #[test]
+    fn test_oso_roundtrip(query in arb_query()) {
+        assert_roundtrip(&query);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_json_roundtrip(query in arb_query()) {
+        oslquery_petite::test_util::assert_json_roundtrip(&query);
+    }
+}
+
+#[test]
+fn test_fixtures_roundtrip() {
+    for (_, source) in fixtures::all() {
+        assert_roundtrip(&OslQuery::from_string(source).unwrap());
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use oslquery_petite::OslQuery;
+use oslquery_petite::library::{ScanOptions, ShaderLibrary};
+use oslquery_petite::source::ShaderSource;
+
+/// The fixtures in `tests/`, kept in memory under the same relative paths.
+struct MemorySource(HashMap<PathBuf, Vec<u8>>);
+
+impl MemorySource {
+    fn fixtures() -> Self {
+        let files = fs::read_dir("tests")
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.is_file())
+            .map(|path| {
+                let content = fs::read(&path).unwrap();
+                (path, content)
+            })
+            .collect();
+        MemorySource(files)
+    }
+}
+
+impl ShaderSource for MemorySource {
+    fn exists(&self, path: &Path) -> bool {
+        self.0.contains_key(path) || self.is_dir(path)
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.0
+            .get(path)
+            .cloned()
+            .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.0
+            .keys()
+            .any(|file| file != path && file.starts_with(path))
+    }
+
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        let mut entries: Vec<PathBuf> = self
+            .0
+            .keys()
+            .filter_map(|file| {
+                let rest = file.strip_prefix(dir).ok()?;
+                Some(dir.join(rest.components().next()?))
+            })
+            .collect();
+        entries.sort();
+        entries.dedup();
+        Ok(entries)
+    }
+}
+
+#[test]
+fn test_open_with_source() {
+    let source = MemorySource::fixtures();
+    for name in [
+        "ui",
+        "ui.oso",
+        "tests/struct.oso",
+        "tests/struct",
+        "missing",
+        "no/such/file.oso",
+    ] {
+        for searchpath in ["tests", "", "elsewhere:tests"] {
+            assert_eq!(
+                OslQuery::open_with_source(name, searchpath, &source),
+                OslQuery::open_with_searchpath(name, searchpath),
+                "{} in {:?}",
+                name,
+                searchpath
+            );
+        }
+    }
+    assert_eq!(
+        OslQuery::open_with_source("ui", "tests", &source)
+            .unwrap()
+            .shader_name(),
+        "uiTest"
+    );
+}
+
+#[test]
+fn test_library_with_source() {
+    let options = ScanOptions::default();
+    let library =
+        ShaderLibrary::scan_with_source(["tests"], &options, MemorySource::fixtures()).unwrap();
+    let disk = ShaderLibrary::scan(["tests"], &options).unwrap();
+
+    assert!(!library.is_empty());
+    assert_eq!(
+        library.names().collect::<Vec<_>>(),
+        disk.names().collect::<Vec<_>>()
+    );
+    assert_eq!(library.collisions(), disk.collisions());
+    for name in disk.names() {
+        assert_eq!(library.get(&name).unwrap(), disk.get(&name).unwrap());
+    }
+
+    // Without modification times, a refresh cannot tell what changed and
+    // parses everything again, but finds the same shaders.
+    library.refresh().unwrap();
+    assert_eq!(library.len(), disk.len());
+}
@@ -0,0 +1,91 @@
+use oslquery_petite::OslQuery;
+use oslquery_petite::text::TextOptions;
+use pretty_assertions::assert_eq;
+
+fn assert_text(file: &str, verbose: bool, expected: &str) {
+    let query = OslQuery::open(file).expect("Failed to parse OSO file");
+    let options = TextOptions {
+        verbose,
+        ..Default::default()
+    };
+    assert_eq!(query.to_text(&options), expected);
+}
+
+#[test]
+fn test_text_all_types() {
+    assert_text(
+        "tests/all_types.oso",
+        false,
+        include_str!("snapshots/text_all_types.txt"),
+    );
+    assert_text(
+        "tests/all_types.oso",
+        true,
+        include_str!("snapshots/text_all_types_verbose.txt"),
+    );
+}
+
+#[test]
+fn test_text_array() {
+    assert_text(
+        "tests/array.oso",
+        false,
+        include_str!("snapshots/text_array.txt"),
+    );
+    assert_text(
+        "tests/array.oso",
+        true,
+        include_str!("snapshots/text_array_verbose.txt"),
+    );
+}
+
+#[test]
+fn test_text_metadata() {
+    assert_text(
+        "tests/metadata.oso",
+        false,
+        include_str!("snapshots/text_metadata.txt"),
+    );
+    assert_text(
+        "tests/metadata.oso",
+        true,
+        include_str!("snapshots/text_metadata_verbose.txt"),
+    );
+}
+
+#[test]
+fn test_text_points() {
+    assert_text(
+        "tests/points.oso",
+        false,
+        include_str!("snapshots/text_points.txt"),
+    );
+    assert_text(
+        "tests/points.oso",
+        true,
+        include_str!("snapshots/text_points_verbose.txt"),
+    );
+}
+
+#[test]
+fn test_text_ui() {
+    assert_text("tests/ui.oso", false, include_str!("snapshots/text_ui.txt"));
+    assert_text(
+        "tests/ui.oso",
+        true,
+        include_str!("snapshots/text_ui_verbose.txt"),
+    );
+}
+
+#[test]
+fn test_text_single_param() {
+    let query = OslQuery::open("tests/array.oso").expect("Failed to parse OSO file");
+    let options = TextOptions {
+        param: Some("count".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(
+        query.to_text(&options),
+        "shader arraytest \"arraytest\"\ncount int           5\n"
+    );
+}
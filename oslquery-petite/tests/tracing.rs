@@ -0,0 +1,88 @@
+#![cfg(feature = "tracing")]
+
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use oslquery_petite::OslQuery;
+use tracing_subscriber::fmt::MakeWriter;
+
+/// Collects the formatted events for inspection.
+#[derive(Clone, Default)]
+struct TestWriter(Arc<Mutex<Vec<u8>>>);
+
+impl io::Write for TestWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for TestWriter {
+    type Writer = TestWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+impl TestWriter {
+    fn lines(&self) -> Vec<String> {
+        String::from_utf8(self.0.lock().unwrap().clone())
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+}
+
+#[test]
+fn test_searchpath_resolution_events() {
+    let tests_dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests");
+    let missing_dir = concat!(env!("CARGO_MANIFEST_DIR"), "/no-such-dir");
+    let searchpath = format!("{}:{}", missing_dir, tests_dir);
+
+    let writer = TestWriter::default();
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::DEBUG)
+        .with_writer(writer.clone())
+        .with_ansi(false)
+        .without_time()
+        .finish();
+
+    let query = tracing::subscriber::with_default(subscriber, || {
+        OslQuery::open_with_searchpath("ui", &searchpath)
+    })
+    .unwrap();
+    assert_eq!(query.shader_name(), "uiTest");
+
+    let resolution: Vec<String> = writer
+        .lines()
+        .iter()
+        .filter_map(|line| line.rsplit(": ").next())
+        .filter(|event| event.starts_with("trying ") || event.starts_with("resolved "))
+        .map(String::from)
+        .collect();
+
+    assert_eq!(
+        resolution,
+        [
+            "trying candidate=ui.oso".to_string(),
+            "trying candidate=ui".to_string(),
+            format!("trying candidate={}/ui", missing_dir),
+            format!("trying candidate={}/ui.oso", missing_dir),
+            format!("trying candidate={}/ui", tests_dir),
+            format!("trying candidate={}/ui.oso", tests_dir),
+            format!("resolved found={}/ui.oso", tests_dir),
+        ]
+    );
+
+    // The file is then read and parsed within the resolution span.
+    assert!(
+        writer.lines().iter().any(|line| line.contains("resolve")
+            && line.contains("read")
+            && line.contains("parse"))
+    );
+}
@@ -0,0 +1,34 @@
+//! Run with `wasm-pack test --node oslquery-petite --features wasm -- --test wasm`.
+#![cfg(all(target_arch = "wasm32", feature = "wasm"))]
+
+use js_sys::{Array, Reflect};
+use oslquery_petite::wasm::parse_oso;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+fn get(value: &JsValue, key: &str) -> JsValue {
+    Reflect::get(value, &key.into()).unwrap()
+}
+
+#[wasm_bindgen_test]
+fn test_parse_oso() {
+    let shader = parse_oso(include_str!("ui.oso")).unwrap();
+    let query = oslquery_petite::OslQuery::from_string(include_str!("ui.oso")).unwrap();
+
+    assert_eq!(get(&shader, "shader_name"), query.shader_name());
+    assert_eq!(get(&shader, "shader_type"), query.shader_type());
+    let parameters = Array::from(&get(&shader, "parameters"));
+    assert_eq!(parameters.length() as usize, query.param_count());
+    assert_eq!(
+        get(&parameters.get(0), "name"),
+        query.params()[0].name.as_str()
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_parse_oso_error() {
+    let error = parse_oso("OpenShadingLanguage 1.12\nsurface s\nparam\tbogus\tx\n").unwrap_err();
+    assert_eq!(get(&error, "kind"), "ParseError");
+    assert_eq!(get(&error, "line"), 3.0);
+    assert_eq!(get(&error, "column"), 7.0);
+}